@@ -31,6 +31,7 @@ fn main() {
     parent.set_size(&Size2::<f64>::new(200.0, 200.0));
 
     let b = Button::new();
+    b.set_text("&First");
     b.set_location(&Point2::<f64>::new(50.0, 50.0));
     b.set_size(&Size2::<f64>::new(75.0, 23.0));
     b.on_click_event(|_| {
@@ -39,6 +40,7 @@ fn main() {
     parent.children().borrow_mut().push(b.clone());
 
     let b = Button::new();
+    b.set_text("&Second");
     b.set_location(&Point2::<f64>::new(50.0, 90.0));
     b.set_size(&Size2::<f64>::new(75.0, 23.0));
     b.on_click_event(|_| {
@@ -0,0 +1,295 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ *
+ */
+
+use std::cell::{Cell, RefCell};
+use std::ops::Deref;
+use std::rc::{Rc, Weak};
+
+use zaffre::Image;
+
+use crate::control::{control_handle, Control};
+use crate::event_vec::{EventHandlerVec, EventRoute};
+use crate::keyboard::Shortcut;
+
+/// A command that can be run from several places, like a button, a menu item, a tool bar and a
+/// keyboard shortcut, which all show its text and icon and are disabled when it is.
+///
+/// Attach it with `Button::set_action()`, `MenuItem::set_action()` or `ToolBar::add_action()`.
+/// Its shortcut is only shown by those; to make pressing the shortcut trigger it, add it to a
+/// window with `WindowData::add_accelerator()`.
+#[derive(Clone)]
+pub struct Action(Rc<ActionData>);
+
+impl Action {
+    /// Creates an action with the text. An `&` before a character makes it the mnemonic of the
+    /// buttons and menu items the action is attached to.
+    pub fn new(text: &str) -> Self {
+        Action(Rc::new(ActionData {
+            text: RefCell::new(text.to_owned()),
+            icon: RefCell::new(None),
+            shortcut: Cell::new(None),
+            enabled: Cell::new(true),
+            checkable: Cell::new(false),
+            checked: Cell::new(false),
+            event_handlers: EventHandlerVec::new(),
+            subscribers: RefCell::new(vec![]),
+        }))
+    }
+
+    pub(crate) fn ptr_eq(&self, other: &Action) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Deref for Action {
+    type Target = Rc<ActionData>;
+    fn deref(&self) -> &Rc<ActionData> {
+        &self.0
+    }
+}
+
+pub struct ActionData {
+    text: RefCell<String>,
+    icon: RefCell<Option<Image>>,
+    shortcut: Cell<Option<Shortcut>>,
+    enabled: Cell<bool>,
+    checkable: Cell<bool>,
+    checked: Cell<bool>,
+    event_handlers: EventHandlerVec,
+    // Called when the action changes, for each `Subscription` that hasn't been dropped
+    subscribers: RefCell<Vec<Rc<Notify>>>,
+}
+
+type Notify = Box<dyn Fn()>;
+
+// Keeps a control or menu item that the action is attached to told when the action changes,
+// until it is dropped
+pub(crate) struct Subscription {
+    action: Weak<ActionData>,
+    notify: Rc<Notify>,
+}
+
+impl Subscription {
+    pub(crate) fn is_to(&self, action: &Action) -> bool {
+        self.action.ptr_eq(&Rc::downgrade(&action.0))
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        if let Some(action) = self.action.upgrade() {
+            action.subscribers.borrow_mut().retain(|n| !Rc::ptr_eq(n, &self.notify));
+        }
+    }
+}
+
+/// Sent to an action when it is triggered, by clicking something it is attached to, by its
+/// shortcut, or by `ActionData::trigger()`.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct TriggeredEvent;
+
+/// Sent to an action when its text, icon, shortcut, enabled or checked state changes, so that the
+/// controls it is attached to can update.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct ActionChangedEvent;
+
+impl ActionData {
+    /// Returns the text, including the `&` marking the mnemonic.
+    pub fn text(&self) -> String {
+        self.text.borrow().clone()
+    }
+
+    pub fn set_text(&self, text: &str) {
+        *self.text.borrow_mut() = text.to_owned();
+        self.changed();
+    }
+
+    pub fn icon(&self) -> Option<Image> {
+        self.icon.borrow().clone()
+    }
+
+    pub fn set_icon(&self, icon: Option<Image>) {
+        *self.icon.borrow_mut() = icon;
+        self.changed();
+    }
+
+    pub fn shortcut(&self) -> Option<Shortcut> {
+        self.shortcut.get()
+    }
+
+    pub fn set_shortcut(&self, shortcut: Option<Shortcut>) {
+        self.shortcut.set(shortcut);
+        self.changed();
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled.get()
+    }
+
+    /// Sets whether the action can be triggered. The controls it is attached to are disabled with
+    /// it.
+    pub fn set_enabled(&self, enabled: bool) {
+        if self.enabled.replace(enabled) != enabled {
+            self.changed();
+        }
+    }
+
+    pub fn checkable(&self) -> bool {
+        self.checkable.get()
+    }
+
+    /// Sets whether triggering the action toggles whether it is checked.
+    pub fn set_checkable(&self, checkable: bool) {
+        self.checkable.set(checkable);
+        self.changed();
+    }
+
+    pub fn checked(&self) -> bool {
+        self.checked.get()
+    }
+
+    pub fn set_checked(&self, checked: bool) {
+        if self.checked.replace(checked) != checked {
+            self.changed();
+        }
+    }
+
+    pub fn event_handlers(&self) -> &EventHandlerVec {
+        &self.event_handlers
+    }
+
+    /// Toggles whether the action is checked if it is checkable and sends a `TriggeredEvent`.
+    /// Nothing happens if the action is disabled.
+    pub fn trigger(&self) {
+        if !self.enabled.get() {
+            return;
+        }
+        if self.checkable.get() {
+            self.set_checked(!self.checked.get());
+        }
+        self.event_handlers.send(&mut TriggeredEvent);
+    }
+
+    fn changed(&self) {
+        self.event_handlers.send(&mut ActionChangedEvent);
+        // The subscribers are copied out, since a control may attach or detach actions while it
+        // updates.
+        let subscribers = self.subscribers.borrow().clone();
+        for notify in subscribers.iter() {
+            (notify.as_ref())();
+        }
+    }
+
+    // Convenience method to add an event handler that is called for `TriggeredEvent`s.
+    pub fn on_triggered_event<F>(&self, mut handler: F)
+        where F: for<'a> FnMut(&'a mut EventRoute) + 'static
+    {
+        self.event_handlers.add(move |mut route| {
+            if let Some(_) = route.event.downcast_mut::<TriggeredEvent>() {
+                handler(&mut route);
+            }
+        });
+    }
+
+    // Convenience method to add an event handler that is called for `ActionChangedEvent`s.
+    pub fn on_changed_event<F>(&self, mut handler: F)
+        where F: for<'a> FnMut(&'a mut EventRoute) + 'static
+    {
+        self.event_handlers.add(move |mut route| {
+            if let Some(_) = route.event.downcast_mut::<ActionChangedEvent>() {
+                handler(&mut route);
+            }
+        });
+    }
+}
+
+// Sent to a control that an action is attached to when the action changes
+pub(crate) struct AttachedActionChangedEvent {
+    pub(crate) action: Action,
+}
+
+// Calls `notify` whenever the action changes, until the returned subscription is dropped.
+pub(crate) fn subscribe<F>(action: &Action, notify: F) -> Subscription
+    where F: Fn() + 'static
+{
+    let notify: Rc<Notify> = Rc::new(Box::new(notify));
+    action.subscribers.borrow_mut().push(notify.clone());
+    Subscription { action: Rc::downgrade(&action.0), notify }
+}
+
+// Makes the action send `AttachedActionChangedEvent` to the control whenever it changes, until
+// the subscription is dropped. Returns `None` if the control's handle isn't registered yet.
+pub(crate) fn notify_when_changed(action: &Action, control: &dyn Control) -> Option<Subscription> {
+    let handle = Rc::downgrade(&control_handle(control)?);
+    let weak_action = Rc::downgrade(&action.0);
+    Some(subscribe(action, move || {
+        if let (Some(control), Some(action)) = (handle.upgrade(), weak_action.upgrade()) {
+            control.event_handlers().send(&mut AttachedActionChangedEvent {
+                action: Action(action),
+            });
+        }
+    }))
+}
+
+#[test]
+fn test_action_attached() {
+    use crate::button::Button;
+    use crate::menu::MenuItem;
+    use crate::tool_bar::ToolBar;
+
+    let action = Action::new("&Bold");
+    action.set_checkable(true);
+    let triggered = Rc::new(Cell::new(0));
+    let triggered2 = triggered.clone();
+    action.on_triggered_event(move |_| triggered2.set(triggered2.get() + 1));
+
+    let button = Button::new();
+    button.set_action(Some(&action));
+    let item = MenuItem::new("");
+    item.set_action(Some(&action));
+    assert_eq!(button.text(), "Bold");
+    assert_eq!(item.text(), "Bold");
+
+    button.click();
+    item.click();
+    assert_eq!(triggered.get(), 2);
+    assert!(!action.checked());
+    item.click();
+    assert!(item.checked());
+
+    // Changes to the action are shown by the controls it is attached to.
+    action.set_text("&Strong");
+    action.set_enabled(false);
+    assert_eq!(button.text(), "Strong");
+    assert!(!button.enabled());
+    assert!(!item.enabled());
+    button.click();
+    assert_eq!(triggered.get(), 3);
+
+    // A detached button is no longer updated.
+    button.set_action(None);
+    action.set_text("&Bold");
+    assert_eq!(button.text(), "Strong");
+
+    // Attaching again doesn't add another subscription, and detaching removes it.
+    let tool_bar = ToolBar::new();
+    tool_bar.add_action(&action);
+    tool_bar.add_action(&action);
+    item.set_action(Some(&action));
+    assert_eq!(action.subscribers.borrow().len(), 2);
+    tool_bar.clear();
+    item.set_action(None);
+    assert!(action.subscribers.borrow().is_empty());
+
+    // Dropping a menu item ends its subscription right away.
+    let item = MenuItem::with_action(&action);
+    assert_eq!(action.subscribers.borrow().len(), 1);
+    drop(item);
+    assert!(action.subscribers.borrow().is_empty());
+}
@@ -6,16 +6,29 @@
  */
 
 use std::any::Any;
+use std::cell::{Cell, RefCell};
 use std::ops::Deref;
+use std::ptr;
 use std::rc::Rc;
 use std::slice::SliceIndex;
+use std::time::Duration;
 
-use zaffre::text::{FormattedString, TextLayout, TextRectFramer};
-use zaffre::{font, Brush, Color, PathBuf, Point2, Rect, Size2, StrokeStyle};
+use zaffre::text::TextLayout;
+use zaffre::{Brush, Color, Image, Painter, Point2, Rect, Size2, StrokeStyle};
 
+use crate::action::{notify_when_changed, Action, AttachedActionChangedEvent, Subscription};
 use crate::control::{
-    Control, MouseUpEvent, PaintingEvent, SubControl, SubControlData, SubControlRef,
+    control_handle, Control, DetachedFromWindowEvent, EnabledChangedEvent, FocusGainedEvent,
+    FocusLostEvent, MouseButton, MouseCaptureLostEvent, MouseDownEvent, MouseEnteredEvent,
+    MouseLeftEvent, MouseUpEvent, PaintingEvent, set_focus, SubControl, SubControlData,
+    SubControlRef,
 };
+use crate::event_vec::{EventHandler, EventRoute};
+use crate::keyboard::{Key, KeyDownEvent, KeyUpEvent, MnemonicEvent, Modifiers};
+use crate::layout::{HorizontalAlignment, VerticalAlignment};
+use crate::mnemonic::MnemonicText;
+use crate::theme;
+use crate::timer::Timer;
 
 // TODO: generate with a proc macro
 // start proc macro generated
@@ -55,74 +68,579 @@ impl From<Button> for Rc<dyn EventHandler> {
 // end proc macro generated
 
 
+impl Button {
+    // Used to get a handle back from a weak reference to the data
+    pub(crate) fn from_rc(data: Rc<ButtonData>) -> Self {
+        Button(data)
+    }
+}
+
+/// Where a button's icon is placed relative to its text.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum IconPlacement {
+    Left,
+    Right,
+    Above,
+    Below,
+}
+
+// The space between the border and the content
+const PADDING_X: f64 = 10.0;
+const PADDING_Y: f64 = 4.0;
+// The space between the icon and the text
+const ICON_GAP: f64 = 4.0;
+// The standard size of a push button on Windows
+const MIN_WIDTH: f64 = 75.0;
+const MIN_HEIGHT: f64 = 23.0;
+
+// How long the button has to be held before auto-repeat starts, and how often it repeats after
+// that. These match the Windows defaults for scroll bar arrows.
+const REPEAT_DELAY: Duration = Duration::from_millis(400);
+const REPEAT_INTERVAL: Duration = Duration::from_millis(50);
+
+// What happened to a button-like control as a result of an event
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub(crate) enum ButtonAction {
+    // The left mouse button or Space was pressed on the control.
+    Pressed,
+    // The press ended. `clicked` is true if it ended by releasing over the control, and false if
+    // it was canceled.
+    Released { clicked: bool },
+    // Enter or the mnemonic was pressed, which clicks without pressing first.
+    Activated,
+}
+
+// The pressing, hovering and keyboard behavior shared by push buttons, check boxes and radio
+// buttons. A control feeds its events to `on_event()` and reacts to the actions returned.
+pub(crate) struct ButtonBehavior {
+    // Whether the mouse is over the control
+    hot: Cell<bool>,
+    // Whether the left mouse button was pressed on the control and hasn't been released
+    mouse_pressed: Cell<bool>,
+    // Whether Space was pressed while the control was focused and hasn't been released
+    key_pressed: Cell<bool>,
+    // Whether Enter activates the control. Check boxes and radio buttons leave it to the
+    // window's default button.
+    activate_on_enter: bool,
+    pub(crate) text: RefCell<MnemonicText>,
+}
+
+impl ButtonBehavior {
+    pub(crate) fn new(activate_on_enter: bool) -> Self {
+        ButtonBehavior {
+            hot: Cell::new(false),
+            mouse_pressed: Cell::new(false),
+            key_pressed: Cell::new(false),
+            activate_on_enter,
+            text: RefCell::new(MnemonicText::default()),
+        }
+    }
+
+    pub(crate) fn hot(&self) -> bool {
+        self.hot.get()
+    }
+
+    // Returns whether the control is painted pushed in.
+    pub(crate) fn pressed(&self) -> bool {
+        (self.mouse_pressed.get() && self.hot.get()) || self.key_pressed.get()
+    }
+
+    // Returns the text laid out on one line with the mnemonic underlined, or `None` if there is
+    // no text.
+    pub(crate) fn text_layout(&self, color: Color) -> Option<TextLayout> {
+        let text = self.text.borrow();
+        if text.text.is_empty() {
+            return None;
+        }
+        let mut string = theme::formatted_string(&text.text, color);
+        if let Some(range) = text.mnemonic_range() {
+            string.set_underline(range, true);
+        }
+        Some(theme::layout_line(string))
+    }
+
+    pub(crate) fn on_event(&self, control: &dyn Control, route: &mut EventRoute)
+        -> Option<ButtonAction>
+    {
+        let mut action = None;
+
+        if let Some(MouseEnteredEvent) = route.event.downcast_mut() {
+            self.hot.set(true);
+            control.repaint_later();
+        }
+
+        if let Some(MouseLeftEvent) = route.event.downcast_mut() {
+            self.hot.set(false);
+            control.repaint_later();
+        }
+
+        if let Some(MouseDownEvent { button: MouseButton::Left, .. }) = route.event.downcast_mut() {
+            if let Some(handle) = control_handle(control) {
+                set_focus(&handle);
+            }
+            self.mouse_pressed.set(true);
+            self.hot.set(true);
+            control.repaint_later();
+            route.handled = true;
+            action = Some(ButtonAction::Pressed);
+        }
+
+        if let Some(MouseUpEvent { button: MouseButton::Left, location }) =
+            route.event.downcast_mut()
+        {
+            if self.mouse_pressed.replace(false) {
+                let clicked = control.hit_test(location.x, location.y);
+                control.repaint_later();
+                action = Some(ButtonAction::Released { clicked });
+            }
+            route.handled = true;
+        }
+
+        if let Some(KeyDownEvent { key, modifiers, repeat }) = route.event.downcast_mut() {
+            if *modifiers == Modifiers::none() {
+                match key {
+                    Key::Space => {
+                        if !*repeat {
+                            self.key_pressed.set(true);
+                            control.repaint_later();
+                            action = Some(ButtonAction::Pressed);
+                        }
+                        route.handled = true;
+                    }
+                    Key::Enter if self.activate_on_enter => {
+                        if !*repeat {
+                            action = Some(ButtonAction::Activated);
+                        }
+                        route.handled = true;
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        if let Some(KeyUpEvent { key: Key::Space, .. }) = route.event.downcast_mut() {
+            if self.key_pressed.replace(false) {
+                control.repaint_later();
+                action = Some(ButtonAction::Released { clicked: true });
+            }
+            route.handled = true;
+        }
+
+        if let Some(MnemonicEvent { c }) = route.event.downcast_mut() {
+            if self.text.borrow().matches(*c) {
+                if let Some(handle) = control_handle(control) {
+                    set_focus(&handle);
+                }
+                route.handled = true;
+                action = Some(ButtonAction::Activated);
+            }
+        }
+
+        // Cancel the press if the control can no longer get the mouse up or key up event.
+        let cancel = route.event.is::<MouseCaptureLostEvent>() ||
+            route.event.is::<FocusLostEvent>() ||
+            route.event.is::<DetachedFromWindowEvent>() ||
+            route.event.downcast_ref::<EnabledChangedEvent>().map_or(false, |e| !e.enabled);
+        if cancel && (self.mouse_pressed.get() || self.key_pressed.get()) {
+            self.mouse_pressed.set(false);
+            self.key_pressed.set(false);
+            control.repaint_later();
+            action = Some(ButtonAction::Released { clicked: false });
+        }
+
+        if let Some(EnabledChangedEvent { .. }) = route.event.downcast_mut() {
+            control.repaint_later();
+        }
+        if let Some(FocusGainedEvent) = route.event.downcast_mut() {
+            control.repaint_later();
+        }
+        if let Some(FocusLostEvent) = route.event.downcast_mut() {
+            control.repaint_later();
+        }
+
+        action
+    }
+}
+
 //#[dynamin::control]
 pub struct ButtonData {
     sub_control: SubControlData,
+    behavior: ButtonBehavior,
+    auto_repeat: Cell<bool>,
+    auto_repeat_timer: AutoRepeat,
+    icon: RefCell<Option<Image>>,
+    icon_placement: Cell<IconPlacement>,
+    content_alignment: Cell<(HorizontalAlignment, VerticalAlignment)>,
+    action: RefCell<Option<Action>>,
+    action_subscription: RefCell<Option<Subscription>>,
 }
 
 impl SubControlRef for ButtonData {
     fn sub_control_ref(&self) -> &SubControlData { &self.sub_control }
+
+    fn best_size(&self) -> Size2<f64> {
+        let layout = self.behavior.text_layout(theme::text_color(true));
+        let content = self.content_size(layout.as_ref());
+        Size2::new(
+            (content.width + PADDING_X * 2.0).max(MIN_WIDTH).ceil(),
+            (content.height + PADDING_Y * 2.0).max(MIN_HEIGHT).ceil(),
+        )
+    }
 }
 
 #[derive(Debug)]
 #[non_exhaustive]
 pub struct ClickEvent;
 
+// Sent to a control by its `AutoRepeat` timer
+struct RepeatTimerEvent;
+
+// Repeats something while a button, arrow or track is held down, after a delay like native
+// controls. The control starts it when pressed, calls `is_repeat_event()` on its events, repeating
+// when it returns true, and stops it when released.
+pub(crate) struct AutoRepeat {
+    timer: RefCell<Option<Timer>>,
+}
+
+impl AutoRepeat {
+    pub(crate) fn new() -> Self {
+        AutoRepeat { timer: RefCell::new(None) }
+    }
+
+    // Starts sending `RepeatTimerEvent`s to the control, the first after `REPEAT_DELAY`. The
+    // control must be registered with a handle.
+    pub(crate) fn start(&self, control: &dyn Control) {
+        let mut timer = self.timer.borrow_mut();
+        if timer.is_none() {
+            let handle = match control_handle(control) {
+                Some(handle) => Rc::downgrade(&handle),
+                None => return,
+            };
+            *timer = Some(Timer::new(REPEAT_DELAY, move || {
+                if let Some(handle) = handle.upgrade() {
+                    handle.event_handlers().send(&mut RepeatTimerEvent);
+                }
+            }));
+        }
+        let timer = timer.as_ref().unwrap();
+        timer.set_interval(REPEAT_DELAY);
+        timer.start();
+    }
+
+    pub(crate) fn stop(&self) {
+        if let Some(timer) = self.timer.borrow().as_ref() {
+            timer.stop();
+        }
+    }
+
+    // Returns whether the event is from the timer. After the first one, they are sent every
+    // `REPEAT_INTERVAL`.
+    pub(crate) fn is_repeat_event(&self, route: &EventRoute) -> bool {
+        if !route.event.is::<RepeatTimerEvent>() {
+            return false;
+        }
+        if let Some(timer) = self.timer.borrow().as_ref() {
+            if timer.interval() != REPEAT_INTERVAL {
+                timer.set_interval(REPEAT_INTERVAL);
+            }
+        }
+        true
+    }
+}
+
 impl EventHandler for ButtonData {
     fn on_event(&self, route: &mut EventRoute) {
-        if let Some(MouseUpEvent { .. }) = route.event.downcast_mut() {
-            let mut event = ClickEvent;
-            self.event_handlers().send(&mut event);
+        match self.behavior.on_event(self, route) {
+            Some(ButtonAction::Pressed) => self.start_repeating(),
+            Some(ButtonAction::Released { clicked }) => {
+                self.stop_repeating();
+                // With auto-repeat, the click happened when the button was pressed.
+                if clicked && !self.auto_repeat.get() {
+                    self.click();
+                }
+            }
+            Some(ButtonAction::Activated) => self.click(),
+            None => {}
+        }
+
+        if self.auto_repeat_timer.is_repeat_event(route) {
+            // Like a scroll bar arrow, pause repeating while the mouse is dragged off the button.
+            if self.behavior.key_pressed.get() || self.behavior.hot() {
+                self.click();
+            }
+        }
+
+        if let Some(AttachedActionChangedEvent { action }) = route.event.downcast_mut() {
+            if self.action().map_or(false, |a| a.ptr_eq(action)) {
+                self.update_from_action();
+            }
         }
 
         if let Some(PaintingEvent { painter }) = route.event.downcast_mut() {
-            let size = self.sub_control.size();
-            let size = Size2::new(size.width as f32, size.height as f32);
+            self.paint(&mut **painter);
+        }
+    }
+}
+
+impl ButtonData {
+    pub fn new() -> Self {
+        let sub_control = SubControlData::new();
+        sub_control.set_focusable(true);
+        ButtonData {
+            sub_control,
+            behavior: ButtonBehavior::new(true),
+            auto_repeat: Cell::new(false),
+            auto_repeat_timer: AutoRepeat::new(),
+            icon: RefCell::new(None),
+            icon_placement: Cell::new(IconPlacement::Left),
+            content_alignment: Cell::new((HorizontalAlignment::Center, VerticalAlignment::Center)),
+            action: RefCell::new(None),
+            action_subscription: RefCell::new(None),
+        }
+    }
+
+    /// Returns the button's text, without the `&` marking the mnemonic.
+    pub fn text(&self) -> String {
+        self.behavior.text.borrow().text.clone()
+    }
+
+    /// Sets the text shown on the button. An `&` before a character makes it the mnemonic, which
+    /// is underlined and clicks the button when pressed with Alt. For example, `"&Save"` shows
+    /// "Save" and is clicked by Alt+S. Use `&&` for an ampersand.
+    pub fn set_text(&self, text: &str) {
+        *self.behavior.text.borrow_mut() = MnemonicText::parse(text);
+        self.sub_control.best_size_changed();
+        self.repaint_later();
+    }
+
+    pub fn icon(&self) -> Option<Image> {
+        self.icon.borrow().clone()
+    }
+
+    /// Sets the image shown next to the button's text.
+    pub fn set_icon(&self, icon: Option<Image>) {
+        *self.icon.borrow_mut() = icon;
+        self.sub_control.best_size_changed();
+        self.repaint_later();
+    }
+
+    pub fn icon_placement(&self) -> IconPlacement {
+        self.icon_placement.get()
+    }
+
+    pub fn set_icon_placement(&self, placement: IconPlacement) {
+        self.icon_placement.set(placement);
+        self.sub_control.best_size_changed();
+        self.repaint_later();
+    }
 
-            let mut path = PathBuf::new();
-            path.move_to(Point2::new(0.5f32, 0.5f32));
-            path.line_to(Point2::new(size.width - 0.5, 0.5f32));
-            path.line_to(Point2::new(size.width - 0.5, size.height - 0.5));
-            path.line_to(Point2::new(0.5f32, size.height - 0.5));
-            path.close();
+    /// Returns how the icon and text are positioned in the button. They are centered by default.
+    pub fn content_alignment(&self) -> (HorizontalAlignment, VerticalAlignment) {
+        self.content_alignment.get()
+    }
+
+    pub fn set_content_alignment(&self, horizontal: HorizontalAlignment,
+                                 vertical: VerticalAlignment) {
+        self.content_alignment.set((horizontal, vertical));
+        self.repaint_later();
+    }
+
+    fn icon_size(&self) -> Size2<f64> {
+        self.icon.borrow().as_ref()
+            .map_or(Size2::new(0.0, 0.0), |i| Size2::new(i.width() as f64, i.height() as f64))
+    }
 
+    // Returns the size of the icon and text together.
+    fn content_size(&self, layout: Option<&TextLayout>) -> Size2<f64> {
+        let text_size = layout.map_or(Size2::new(0.0, 0.0), theme::text_size);
+        let icon_size = self.icon_size();
+        let gap = if layout.is_some() && self.icon.borrow().is_some() { ICON_GAP } else { 0.0 };
+        match self.icon_placement.get() {
+            IconPlacement::Left | IconPlacement::Right => Size2::new(
+                icon_size.width + gap + text_size.width,
+                icon_size.height.max(text_size.height),
+            ),
+            IconPlacement::Above | IconPlacement::Below => Size2::new(
+                icon_size.width.max(text_size.width),
+                icon_size.height + gap + text_size.height,
+            ),
+        }
+    }
+
+    fn paint(&self, painter: &mut dyn Painter) {
+        let enabled = self.is_effectively_enabled();
+        let background_color = if !enabled {
+            Color::from_rgba(244, 244, 244, 255)
+        } else if self.pressed() {
+            Color::from_rgba(204, 228, 247, 255)
+        } else if self.behavior.hot() {
+            Color::from_rgba(229, 241, 251, 255)
+        } else {
+            Color::from_rgba(225, 225, 225, 255)
+        };
+        let border_color = if !enabled {
+            Color::from_rgba(191, 191, 191, 255)
+        } else if self.pressed() || self.behavior.hot() {
+            Color::from_rgba(0, 120, 215, 255)
+        } else {
+            Color::from_rgba(128, 128, 128, 255)
+        };
+
+        let size = self.sub_control.size();
+        let (width, height) = (size.width as f32, size.height as f32);
+
+        painter.fill_path(
+            &mut theme::rect_path(0.0, 0.0, width, height).path_iter(),
+            &Brush::Solid(background_color),
+        );
+
+        // The default button has a thicker border so that it's clear what Enter will do.
+        let border_width = if self.is_default() { 2.0 } else { 1.0 };
+        let inset = border_width / 2.0;
+        painter.stroke_path(
+            &mut theme::rect_path(inset, inset, width - inset, height - inset).path_iter(),
+            &Brush::Solid(border_color),
+            &StrokeStyle::with_width(border_width),
+        );
+
+        if self.focused() {
             painter.stroke_path(
-                &mut path.path_iter(),
-                &Brush::Solid(Color::from_rgba(128, 128, 128, 255)),
+                &mut theme::rect_path(3.5, 3.5, width - 3.5, height - 3.5).path_iter(),
+                &Brush::Solid(Color::from_rgba(0, 0, 0, 255)),
                 &StrokeStyle::with_width(1.0),
             );
+        }
+
+        let layout = self.behavior.text_layout(theme::text_color(enabled));
+        let content = self.content_size(layout.as_ref());
+        let (h_align, v_align) = self.content_alignment.get();
+        // Shift the content down and right a pixel when pressed, like Windows does.
+        let pressed_offset = if self.pressed() { 1.0 } else { 0.0 };
+        let x = PADDING_X + pressed_offset +
+            h_align.offset(size.width - PADDING_X * 2.0, content.width);
+        let y = PADDING_Y + pressed_offset +
+            v_align.offset(size.height - PADDING_Y * 2.0, content.height);
 
-            let font_family = font::get_family("DejaVu Sans")
-                .or_else(|| font::get_family("DejaVu Sans"))
-                .or_else(|| font::get_family("Helvetica"))
-                .or_else(|| font::get_family("Arial"))
-                .expect("couldn't find font");
-            let font = font_family.get_styles()[0].get_font(20.0);
+        let text_size = layout.as_ref().map_or(Size2::new(0.0, 0.0), theme::text_size);
+        let icon_size = self.icon_size();
+        let gap = if layout.is_some() && self.icon.borrow().is_some() { ICON_GAP } else { 0.0 };
+        // Each item is centered across the direction the items are arranged in.
+        let center_x = |w: f64| x + ((content.width - w) / 2.0).round();
+        let center_y = |h: f64| y + ((content.height - h) / 2.0).round();
+        let (icon_location, text_location) = match self.icon_placement.get() {
+            IconPlacement::Left => (
+                Point2::new(x, center_y(icon_size.height)),
+                Point2::new(x + icon_size.width + gap, center_y(text_size.height)),
+            ),
+            IconPlacement::Right => (
+                Point2::new(x + text_size.width + gap, center_y(icon_size.height)),
+                Point2::new(x, center_y(text_size.height)),
+            ),
+            IconPlacement::Above => (
+                Point2::new(center_x(icon_size.width), y),
+                Point2::new(center_x(text_size.width), y + icon_size.height + gap),
+            ),
+            IconPlacement::Below => (
+                Point2::new(center_x(icon_size.width), y + text_size.height + gap),
+                Point2::new(center_x(text_size.width), y),
+            ),
+        };
 
-            // painter.draw_glyphs(
-            //     &[41, 42], &[Point2::new(0.0, 0.0), Point2::new(10.0, 0.0)], Point2::new(5.0, 5.0),
-            //     &font, &Brush::Solid(Color::from_rgba(160, 50, 255, 255)));
+        if let Some(icon) = self.icon.borrow().as_ref() {
+            painter.draw_image(icon, Rect::new(
+                icon_location.x as f32, icon_location.y as f32,
+                icon_size.width as f32, icon_size.height as f32,
+            ));
+        }
+        if let Some(layout) = layout {
+            painter.save();
+            painter.translate(text_location.x, text_location.y);
+            layout.draw(painter);
+            painter.restore();
+        }
+    }
 
-            let mut text = FormattedString::new();
-            text.clear_and_set_text_from(&"First".to_owned());
-            text.set_initial_font(font);
-            let mut layout = TextLayout::new();
-            layout.set_text(text);
-            layout.layout(&mut TextRectFramer::new(Rect::new(
-                5.0, 5.0, 50_000.0, 30.0,
-            )));
-            layout.draw(&mut **painter);
+    /// Sends a `ClickEvent` to the button, as though the user clicked it, and triggers its action.
+    /// Nothing happens if the button is disabled.
+    pub fn click(&self) {
+        if self.is_effectively_enabled() {
+            self.event_handlers().send(&mut ClickEvent);
+            if let Some(action) = self.action() {
+                action.trigger();
+            }
         }
     }
-}
 
-impl ButtonData {
-    pub fn new() -> Self {
-        ButtonData {
-            sub_control: SubControlData::new(),
+    pub fn action(&self) -> Option<Action> {
+        self.action.borrow().clone()
+    }
+
+    /// Sets the action that clicking the button triggers. The button shows the action's text and
+    /// icon and is enabled with it, and it is updated when they change. After the action is
+    /// removed, the button keeps the text, icon and enabled state it had.
+    pub fn set_action(&self, action: Option<&Action>) {
+        self.action.replace(action.cloned());
+        let action = match action {
+            Some(action) => action,
+            None => {
+                self.action_subscription.replace(None);
+                return;
+            }
+        };
+        self.update_from_action();
+        self.action_subscription.replace(notify_when_changed(action, self));
+    }
+
+    fn update_from_action(&self) {
+        let action = match self.action() {
+            Some(action) => action,
+            None => return,
+        };
+        self.set_text(&action.text());
+        self.set_icon(action.icon());
+        self.set_enabled(action.enabled());
+    }
+
+    /// Returns whether the button is painted pushed in. This is the case while the mouse is
+    /// pressed on it and over it, or while Space is held down.
+    pub fn pressed(&self) -> bool {
+        self.behavior.pressed()
+    }
+
+    /// Returns whether holding the button down repeatedly sends `ClickEvent`s.
+    pub fn auto_repeat(&self) -> bool {
+        self.auto_repeat.get()
+    }
+
+    /// Sets whether holding the button down repeatedly sends `ClickEvent`s, like the arrow
+    /// buttons of a scroll bar. With auto-repeat, a click happens as soon as the button is
+    /// pressed instead of when it is released.
+    pub fn set_auto_repeat(&self, auto_repeat: bool) {
+        self.auto_repeat.set(auto_repeat);
+        if !auto_repeat {
+            self.stop_repeating();
         }
     }
 
+    /// Returns whether the button is the default button of its window.
+    pub fn is_default(&self) -> bool {
+        self.window()
+            .and_then(|w| w.default_button())
+            .map_or(false, |b| ptr::eq(&**b, self))
+    }
+
+    fn start_repeating(&self) {
+        if !self.auto_repeat.get() {
+            return;
+        }
+        self.click();
+        self.auto_repeat_timer.start(self);
+    }
+
+    fn stop_repeating(&self) {
+        self.auto_repeat_timer.stop();
+    }
+
     // Convenience method to add an event handler that is called for `ClickEvent`s.
     pub fn on_click_event<F>(&self, mut handler: F)
         where F: for<'a> FnMut(&'a mut EventRoute) + 'static
@@ -134,3 +652,65 @@ impl ButtonData {
         });
     }
 }
+
+#[test]
+fn test_button_click() {
+    use crate::control::{dispatch_mouse_down, dispatch_mouse_moved, dispatch_mouse_up};
+
+    let root = SubControl::new();
+    root.set_size(&Size2::new(200.0, 200.0));
+    let button = Button::new();
+    button.set_location(&Point2::new(10.0, 10.0));
+    button.set_size(&Size2::new(75.0, 23.0));
+    root.children().borrow_mut().push(button.clone());
+    let clicks = Rc::new(Cell::new(0));
+    let clicks2 = clicks.clone();
+    button.on_click_event(move |_| clicks2.set(clicks2.get() + 1));
+
+    dispatch_mouse_down(&**root, Point2::new(20.0, 20.0), MouseButton::Left, Modifiers::none());
+    assert!(button.pressed());
+    dispatch_mouse_up(&**root, Point2::new(20.0, 20.0), MouseButton::Left);
+    assert_eq!(clicks.get(), 1);
+
+    // Dragging off the button before releasing cancels the click.
+    dispatch_mouse_down(&**root, Point2::new(20.0, 20.0), MouseButton::Left, Modifiers::none());
+    dispatch_mouse_moved(&**root, Point2::new(150.0, 150.0));
+    assert!(!button.pressed());
+    dispatch_mouse_up(&**root, Point2::new(150.0, 150.0), MouseButton::Left);
+    assert_eq!(clicks.get(), 1);
+
+    // Pressing elsewhere and releasing over the button doesn't click it.
+    dispatch_mouse_down(&**root, Point2::new(150.0, 150.0), MouseButton::Left, Modifiers::none());
+    dispatch_mouse_up(&**root, Point2::new(20.0, 20.0), MouseButton::Left);
+    dispatch_mouse_down(&**root, Point2::new(20.0, 20.0), MouseButton::Right, Modifiers::none());
+    dispatch_mouse_up(&**root, Point2::new(20.0, 20.0), MouseButton::Right);
+    assert_eq!(clicks.get(), 1);
+
+    let modifiers = Modifiers::none();
+    button.event_handlers().send(&mut KeyDownEvent { key: Key::Space, modifiers, repeat: false });
+    assert!(button.pressed());
+    button.event_handlers().send(&mut KeyUpEvent { key: Key::Space, modifiers });
+    button.event_handlers().send(&mut KeyDownEvent { key: Key::Enter, modifiers, repeat: false });
+    assert_eq!(clicks.get(), 3);
+
+    button.set_enabled(false);
+    button.click();
+    assert_eq!(clicks.get(), 3);
+}
+
+#[test]
+fn test_button_text_changes_layout() {
+    use crate::layout::{Orientation, StackPanel};
+
+    let panel = StackPanel::new();
+    panel.set_orientation(Orientation::Horizontal);
+    let button = Button::new();
+    button.set_text("OK");
+    panel.children().borrow_mut().push(button.clone());
+    assert_eq!(button.size().width, MIN_WIDTH);
+
+    // The panel gives the button its new best size without being asked to lay out.
+    button.set_text("Save the document under a different name");
+    assert!(button.size().width > MIN_WIDTH);
+    assert_eq!(button.size().width, button.best_size().width);
+}
@@ -0,0 +1,324 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ *
+ */
+
+use std::cell::Cell;
+use std::ops::Deref;
+use std::rc::Rc;
+
+use zaffre::text::TextLayout;
+use zaffre::{Brush, Color, Painter, PathBuf, Point2, Size2, StrokeStyle};
+
+use crate::button::{ButtonAction, ButtonBehavior};
+use crate::control::{Control, PaintingEvent, SubControl, SubControlData, SubControlRef};
+use crate::event_vec::{EventHandler, EventRoute};
+use crate::mnemonic::MnemonicText;
+use crate::theme;
+
+// TODO: generate with a proc macro
+// start proc macro generated
+#[derive(Clone)]
+pub struct CheckBox(Rc<CheckBoxData>);
+
+impl CheckBox {
+    pub fn new() -> Self {
+        SubControl::register_handle(CheckBox(Rc::new(CheckBoxData::new())))
+    }
+}
+
+impl Default for CheckBox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deref for CheckBox {
+    type Target = Rc<CheckBoxData>;
+    fn deref(&self) -> &Rc<CheckBoxData> {
+        &self.0
+    }
+}
+
+impl From<CheckBox> for Rc<dyn Control> {
+    fn from(self_: CheckBox) -> Self {
+        self_.0 as Rc<dyn Control>
+    }
+}
+
+impl From<CheckBox> for Rc<dyn EventHandler> {
+    fn from(self_: CheckBox) -> Self {
+        self_.0 as Rc<dyn EventHandler>
+    }
+}
+// end proc macro generated
+
+/// The state of a check box or radio button.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum CheckState {
+    Unchecked,
+    Checked,
+    /// Neither checked nor unchecked, such as a check box for a group of items where only some
+    /// are checked. Only tri-state check boxes can get into this state by being clicked.
+    Indeterminate,
+}
+
+/// Sent to a check box or radio button after its state changes, whether by the user or by code.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct CheckedChangedEvent {
+    pub state: CheckState,
+}
+
+impl CheckedChangedEvent {
+    pub fn checked(&self) -> bool {
+        self.state == CheckState::Checked
+    }
+}
+
+// The size of the box or circle, and the space between it and the text
+pub(crate) const INDICATOR_SIZE: f64 = 13.0;
+pub(crate) const INDICATOR_GAP: f64 = 5.0;
+const MIN_HEIGHT: f64 = 17.0;
+
+// Returns the best size of a check box or radio button with the specified text.
+pub(crate) fn indicator_best_size(layout: Option<&TextLayout>) -> Size2<f64> {
+    match layout.map(theme::text_size) {
+        Some(text_size) => Size2::new(
+            (INDICATOR_SIZE + INDICATOR_GAP + text_size.width).ceil(),
+            text_size.height.max(MIN_HEIGHT).ceil(),
+        ),
+        None => Size2::new(INDICATOR_SIZE, MIN_HEIGHT),
+    }
+}
+
+// Returns the colors to fill and stroke the box or circle with.
+pub(crate) fn indicator_colors(enabled: bool, behavior: &ButtonBehavior) -> (Color, Color) {
+    if !enabled {
+        (Color::from_rgba(244, 244, 244, 255), Color::from_rgba(191, 191, 191, 255))
+    } else if behavior.pressed() {
+        (Color::from_rgba(204, 228, 247, 255), Color::from_rgba(0, 84, 153, 255))
+    } else if behavior.hot() {
+        (Color::from_rgba(255, 255, 255, 255), Color::from_rgba(0, 120, 215, 255))
+    } else {
+        (Color::from_rgba(255, 255, 255, 255), Color::from_rgba(51, 51, 51, 255))
+    }
+}
+
+// Paints the text of a check box or radio button to the right of the indicator, and a focus
+// rectangle around it if the control is focused.
+pub(crate) fn paint_indicator_text(
+    painter: &mut dyn Painter,
+    layout: Option<TextLayout>,
+    size: Size2<f64>,
+    focused: bool,
+) {
+    if let Some(layout) = layout {
+        let text_size = theme::text_size(&layout);
+        let x = INDICATOR_SIZE + INDICATOR_GAP;
+        let y = ((size.height - text_size.height) / 2.0).round();
+        painter.save();
+        painter.translate(x, y);
+        layout.draw(painter);
+        painter.restore();
+        if focused {
+            let (left, top) = (x as f32 - 1.5, y as f32 - 0.5);
+            let (right, bottom) =
+                (left + text_size.width as f32 + 3.0, top + text_size.height as f32 + 1.0);
+            painter.stroke_path(
+                &mut theme::rect_path(left, top, right, bottom).path_iter(),
+                &Brush::Solid(Color::from_rgba(0, 0, 0, 255)),
+                &StrokeStyle::with_width(1.0),
+            );
+        }
+    }
+}
+
+/// A box that can be checked and unchecked by clicking it, with text next to it.
+//#[dynamin::control]
+pub struct CheckBoxData {
+    sub_control: SubControlData,
+    behavior: ButtonBehavior,
+    state: Cell<CheckState>,
+    tri_state: Cell<bool>,
+}
+
+impl SubControlRef for CheckBoxData {
+    fn sub_control_ref(&self) -> &SubControlData { &self.sub_control }
+
+    fn best_size(&self) -> Size2<f64> {
+        indicator_best_size(self.behavior.text_layout(theme::text_color(true)).as_ref())
+    }
+}
+
+impl EventHandler for CheckBoxData {
+    fn on_event(&self, route: &mut EventRoute) {
+        match self.behavior.on_event(self, route) {
+            Some(ButtonAction::Released { clicked: true }) | Some(ButtonAction::Activated) => {
+                self.toggle();
+            }
+            _ => {}
+        }
+
+        if let Some(PaintingEvent { painter }) = route.event.downcast_mut() {
+            self.paint(&mut **painter);
+        }
+    }
+}
+
+impl CheckBoxData {
+    pub fn new() -> Self {
+        let sub_control = SubControlData::new();
+        sub_control.set_focusable(true);
+        CheckBoxData {
+            sub_control,
+            behavior: ButtonBehavior::new(false),
+            state: Cell::new(CheckState::Unchecked),
+            tri_state: Cell::new(false),
+        }
+    }
+
+    /// Returns the check box's text, without the `&` marking the mnemonic.
+    pub fn text(&self) -> String {
+        self.behavior.text.borrow().text.clone()
+    }
+
+    /// Sets the text shown next to the box. An `&` before a character makes it the mnemonic, like
+    /// with `Button::set_text()`.
+    pub fn set_text(&self, text: &str) {
+        *self.behavior.text.borrow_mut() = MnemonicText::parse(text);
+        self.sub_control.best_size_changed();
+        self.repaint_later();
+    }
+
+    pub fn checked(&self) -> bool {
+        self.state.get() == CheckState::Checked
+    }
+
+    pub fn set_checked(&self, checked: bool) {
+        self.set_check_state(if checked { CheckState::Checked } else { CheckState::Unchecked });
+    }
+
+    pub fn check_state(&self) -> CheckState {
+        self.state.get()
+    }
+
+    /// Sets the state of the check box. A `CheckedChangedEvent` is sent if it changes. Any state
+    /// can be set from code, even `Indeterminate` when the check box isn't tri-state.
+    pub fn set_check_state(&self, state: CheckState) {
+        if self.state.replace(state) != state {
+            self.event_handlers().send(&mut CheckedChangedEvent { state });
+            self.repaint_later();
+        }
+    }
+
+    /// Returns whether clicking the check box cycles through `Indeterminate` as well as
+    /// `Checked` and `Unchecked`.
+    pub fn tri_state(&self) -> bool {
+        self.tri_state.get()
+    }
+
+    pub fn set_tri_state(&self, tri_state: bool) {
+        self.tri_state.set(tri_state);
+    }
+
+    /// Changes the state as though the check box was clicked. A tri-state check box goes from
+    /// unchecked to checked to indeterminate.
+    pub fn toggle(&self) {
+        if !self.is_effectively_enabled() {
+            return;
+        }
+        let state = match self.state.get() {
+            CheckState::Unchecked => CheckState::Checked,
+            CheckState::Checked if self.tri_state.get() => CheckState::Indeterminate,
+            CheckState::Checked | CheckState::Indeterminate => CheckState::Unchecked,
+        };
+        self.set_check_state(state);
+    }
+
+    // Convenience method to add an event handler that is called for `CheckedChangedEvent`s.
+    pub fn on_checked_changed_event<F>(&self, mut handler: F)
+        where F: for<'a> FnMut(&'a mut EventRoute) + 'static
+    {
+        self.event_handlers().add(move |mut route| {
+            if let Some(_) = route.event.downcast_mut::<CheckedChangedEvent>() {
+                handler(&mut route);
+            }
+        });
+    }
+
+    fn paint(&self, painter: &mut dyn Painter) {
+        let enabled = self.is_effectively_enabled();
+        let size = self.size();
+        let (fill_color, border_color) = indicator_colors(enabled, &self.behavior);
+        let mark_color = if enabled {
+            Color::from_rgba(0, 0, 0, 255)
+        } else {
+            Color::from_rgba(191, 191, 191, 255)
+        };
+
+        let box_size = INDICATOR_SIZE as f32;
+        let top = ((size.height - INDICATOR_SIZE) / 2.0).round() as f32;
+        painter.fill_path(
+            &mut theme::rect_path(0.0, top, box_size, top + box_size).path_iter(),
+            &Brush::Solid(fill_color),
+        );
+        painter.stroke_path(
+            &mut theme::rect_path(0.5, top + 0.5, box_size - 0.5, top + box_size - 0.5).path_iter(),
+            &Brush::Solid(border_color),
+            &StrokeStyle::with_width(1.0),
+        );
+
+        match self.state.get() {
+            CheckState::Checked => {
+                let mut path = PathBuf::new();
+                path.move_to(Point2::new(3.0, top + 6.5));
+                path.line_to(Point2::new(5.5, top + 9.0));
+                path.line_to(Point2::new(10.0, top + 4.0));
+                painter.stroke_path(
+                    &mut path.path_iter(),
+                    &Brush::Solid(mark_color),
+                    &StrokeStyle::with_width(1.5),
+                );
+            }
+            CheckState::Indeterminate => {
+                painter.fill_path(
+                    &mut theme::rect_path(3.0, top + 3.0, box_size - 3.0, top + box_size - 3.0)
+                        .path_iter(),
+                    &Brush::Solid(mark_color),
+                );
+            }
+            CheckState::Unchecked => {}
+        }
+
+        let layout = self.behavior.text_layout(theme::text_color(enabled));
+        paint_indicator_text(painter, layout, size, self.focused());
+    }
+}
+
+#[test]
+fn test_check_box_toggle() {
+    let check_box = CheckBox::new();
+    let states = Rc::new(std::cell::RefCell::new(vec![]));
+    let states2 = states.clone();
+    check_box.on_checked_changed_event(move |route| {
+        let event = route.event.downcast_mut::<CheckedChangedEvent>().unwrap();
+        states2.borrow_mut().push(event.state);
+    });
+
+    check_box.toggle();
+    check_box.toggle();
+    check_box.set_tri_state(true);
+    check_box.toggle();
+    check_box.toggle();
+    check_box.toggle();
+    check_box.set_checked(true);
+    assert_eq!(*states.borrow(), vec![
+        CheckState::Checked, CheckState::Unchecked,
+        CheckState::Checked, CheckState::Indeterminate, CheckState::Unchecked,
+        CheckState::Checked,
+    ]);
+    assert!(check_box.checked());
+}
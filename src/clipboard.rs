@@ -0,0 +1,25 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ *
+ */
+
+use crate::backend::ClipboardBackend;
+use crate::generic_backend::GenericClipboardBackend;
+
+/// The system clipboard, shared with other applications.
+pub struct Clipboard;
+
+impl Clipboard {
+    /// Returns the text on the clipboard, or `None` if the clipboard is empty or holds something
+    /// other than text.
+    pub fn text() -> Option<String> {
+        ClipboardBackend::text()
+    }
+
+    /// Replaces the contents of the clipboard with the text.
+    pub fn set_text(text: &str) {
+        ClipboardBackend::set_text(text)
+    }
+}
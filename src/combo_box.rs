@@ -0,0 +1,839 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ *
+ */
+
+use std::cell::{Cell, RefCell};
+use std::ops::{Deref, Range};
+use std::rc::Rc;
+
+use zaffre::{Brush, Color, Painter, PathBuf, Point2, Rect, Size2, StrokeStyle};
+
+use crate::control::{
+    control_handle, set_focus, Control, FocusGainedEvent, FocusLostEvent, MouseButton,
+    MouseDownEvent, MouseDraggedEvent, MouseEnteredEvent, MouseLeftEvent, MouseMovedEvent,
+    MouseUpEvent, PaintingEvent, SizeChangedEvent, SubControl, SubControlData, SubControlRef,
+    Visibility,
+};
+use crate::event_vec::{EventHandler, EventRoute};
+use crate::keyboard::{Key, KeyDownEvent, Modifiers, TextInputEvent};
+use crate::list_view::SelectionChangedEvent;
+use crate::scroll_view::{ScrollBarVisibility, ScrollView};
+use crate::text_box::{TextBox, TextChangedEvent};
+use crate::theme;
+use crate::window::{Window, WindowEvent};
+
+// TODO: generate with a proc macro
+// start proc macro generated
+#[derive(Clone)]
+pub struct ComboBox(Rc<ComboBoxData>);
+
+impl ComboBox {
+    pub fn new() -> Self {
+        SubControl::register_handle(ComboBox(Rc::new(ComboBoxData::new())))
+    }
+}
+
+impl Default for ComboBox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deref for ComboBox {
+    type Target = Rc<ComboBoxData>;
+    fn deref(&self) -> &Rc<ComboBoxData> {
+        &self.0
+    }
+}
+
+impl From<ComboBox> for Rc<dyn Control> {
+    fn from(self_: ComboBox) -> Self {
+        self_.0 as Rc<dyn Control>
+    }
+}
+
+impl From<ComboBox> for Rc<dyn EventHandler> {
+    fn from(self_: ComboBox) -> Self {
+        self_.0 as Rc<dyn EventHandler>
+    }
+}
+// end proc macro generated
+
+// TODO: generate with a proc macro
+// start proc macro generated
+#[derive(Clone)]
+pub(crate) struct DropDownList(Rc<DropDownListData>);
+
+impl DropDownList {
+    pub(crate) fn new() -> Self {
+        SubControl::register_handle(DropDownList(Rc::new(DropDownListData::new())))
+    }
+}
+
+impl Deref for DropDownList {
+    type Target = Rc<DropDownListData>;
+    fn deref(&self) -> &Rc<DropDownListData> {
+        &self.0
+    }
+}
+
+impl From<DropDownList> for Rc<dyn Control> {
+    fn from(self_: DropDownList) -> Self {
+        self_.0 as Rc<dyn Control>
+    }
+}
+
+impl From<DropDownList> for Rc<dyn EventHandler> {
+    fn from(self_: DropDownList) -> Self {
+        self_.0 as Rc<dyn EventHandler>
+    }
+}
+// end proc macro generated
+
+// Sent to a dropdown list, and passed on to its combo box, when an item is clicked.
+struct ItemChosenEvent {
+    index: usize,
+}
+
+// Sent to a combo box when its text box loses the focus.
+struct TextBoxFocusLostEvent;
+
+// Sent to a dropdown list by its panel when the mouse moves or a button is released over it. The
+// location is in the panel's coordinates.
+enum PanelEvent {
+    Moved(Point2<f64>),
+    Released(Point2<f64>),
+}
+
+const PADDING_X: f64 = 4.0;
+const PADDING_Y: f64 = 3.0;
+const BUTTON_WIDTH: f64 = 17.0;
+const ITEM_PADDING_Y: f64 = 2.0;
+// The dropdown shows a scroll bar when there are more items than this.
+const MAX_VISIBLE_ITEMS: usize = 8;
+const DEFAULT_WIDTH: f64 = 120.0;
+
+/// A control for choosing one of a list of items from a dropdown.
+///
+/// A non-editable combo box shows the selected item and opens the dropdown when clicked. An
+/// editable one has a text box where any text can be typed, and a button on the right that opens
+/// the dropdown. While the dropdown is open, the arrow keys move through the items, Enter chooses
+/// one, and Escape closes the dropdown without changing the selection.
+//#[dynamin::control]
+pub struct ComboBoxData {
+    sub_control: SubControlData,
+    items: RefCell<Vec<String>>,
+    selected: Cell<Option<usize>>,
+    editable: Cell<bool>,
+    text_box: TextBox,
+    list: DropDownList,
+    // Created the first time the dropdown is shown
+    popup: RefCell<Option<Window>>,
+    hot: Cell<bool>,
+    // Whether the mouse was dragged into the dropdown after pressing the combo box, so releasing
+    // it over an item chooses the item
+    dragged_into_list: Cell<bool>,
+}
+
+impl SubControlRef for ComboBoxData {
+    fn sub_control_ref(&self) -> &SubControlData { &self.sub_control }
+
+    fn best_size(&self) -> Size2<f64> {
+        let line = theme::layout_line(theme::formatted_string("Xg", theme::text_color(true)));
+        let height = theme::text_size(&line).height + PADDING_Y * 2.0 + 2.0;
+        let text_width = self.items.borrow().iter()
+            .map(|item| theme::line_width(item))
+            .fold(0.0, f64::max);
+        let width = (text_width + PADDING_X * 2.0 + BUTTON_WIDTH + 2.0).max(DEFAULT_WIDTH);
+        Size2::new(width.ceil(), height.ceil())
+    }
+}
+
+impl EventHandler for ComboBoxData {
+    fn on_event(&self, route: &mut EventRoute) {
+        if let Some(MouseDownEvent { location, button: MouseButton::Left, .. }) =
+            route.event.downcast_mut()
+        {
+            if self.editable.get() && location.x < self.size().width - BUTTON_WIDTH {
+                return;
+            }
+            self.focus();
+            self.dragged_into_list.set(false);
+            self.set_dropped_down(!self.is_dropped_down());
+            route.handled = true;
+        } else if let Some(MouseDraggedEvent { location }) = route.event.downcast_mut() {
+            if let Some(index) = self.list_item_at(*location) {
+                self.dragged_into_list.set(true);
+                self.list.set_highlighted(Some(index));
+            }
+        } else if let Some(MouseUpEvent { location, button: MouseButton::Left }) =
+            route.event.downcast_mut()
+        {
+            let index = self.list_item_at(*location);
+            if let (true, Some(index)) = (self.dragged_into_list.get(), index) {
+                self.set_selected_index(Some(index));
+                self.set_dropped_down(false);
+            }
+            self.dragged_into_list.set(false);
+            route.handled = true;
+        } else if let Some(MouseEnteredEvent) = route.event.downcast_mut() {
+            self.hot.set(true);
+            self.repaint_later();
+        } else if let Some(MouseLeftEvent) = route.event.downcast_mut() {
+            self.hot.set(false);
+            self.repaint_later();
+        } else if let Some(event) = route.event.downcast_mut::<KeyDownEvent>() {
+            if self.on_key_down(event.key, event.modifiers) {
+                route.handled = true;
+            }
+        } else if let Some(TextInputEvent { text }) = route.event.downcast_mut() {
+            // Editable combo boxes get text input in their text box.
+            if !self.editable.get() {
+                self.select_by_prefix(text);
+                route.handled = true;
+            }
+        } else if let Some(ItemChosenEvent { index }) = route.event.downcast_mut() {
+            self.set_selected_index(Some(*index));
+            self.set_dropped_down(false);
+        } else if let Some(TextChangedEvent) = route.event.downcast_mut() {
+            // Typing in the text box selects the item with the same text, if any.
+            let text = self.text_box.text();
+            let index = self.items.borrow().iter().position(|item| *item == text);
+            self.set_selected_index(index);
+        } else if route.event.is::<FocusLostEvent>() || route.event.is::<TextBoxFocusLostEvent>() {
+            self.set_dropped_down(false);
+            self.repaint_later();
+        } else if let Some(FocusGainedEvent) = route.event.downcast_mut() {
+            self.repaint_later();
+        } else if let Some(SizeChangedEvent) = route.event.downcast_mut() {
+            self.layout();
+        } else if let Some(PaintingEvent { painter }) = route.event.downcast_mut() {
+            self.paint(&mut **painter);
+        }
+    }
+}
+
+impl ComboBoxData {
+    pub fn new() -> Self {
+        let sub_control = SubControlData::new();
+        sub_control.set_focusable(true);
+        ComboBoxData {
+            sub_control,
+            items: RefCell::new(vec![]),
+            selected: Cell::new(None),
+            editable: Cell::new(false),
+            text_box: TextBox::new(),
+            list: DropDownList::new(),
+            popup: RefCell::new(None),
+            hot: Cell::new(false),
+            dragged_into_list: Cell::new(false),
+        }
+    }
+
+    pub fn items(&self) -> Vec<String> {
+        self.items.borrow().clone()
+    }
+
+    /// Replaces the items and clears the selection.
+    pub fn set_items<S: AsRef<str>>(&self, items: &[S]) {
+        self.set_dropped_down(false);
+        *self.items.borrow_mut() = items.iter().map(|s| s.as_ref().to_owned()).collect();
+        self.set_selected_index(None);
+        self.repaint_later();
+    }
+
+    pub fn add_item(&self, item: &str) {
+        self.items.borrow_mut().push(item.to_owned());
+        if self.is_dropped_down() {
+            self.show_dropdown();
+        }
+    }
+
+    pub fn selected_index(&self) -> Option<usize> {
+        self.selected.get()
+    }
+
+    /// Selects the item at the index. In an editable combo box, the item's text replaces the text
+    /// in the text box.
+    pub fn set_selected_index(&self, index: Option<usize>) {
+        let index = index.filter(|&i| i < self.items.borrow().len());
+        if let (true, Some(i)) = (self.editable.get(), index) {
+            let text = self.items.borrow()[i].clone();
+            if self.text_box.text() != text {
+                self.text_box.set_text(&text);
+                self.text_box.select_all();
+            }
+        }
+        if self.selected.replace(index) != index {
+            self.event_handlers().send(&mut SelectionChangedEvent);
+            self.repaint_later();
+        }
+    }
+
+    /// Returns the text in the text box of an editable combo box, or the selected item's text.
+    pub fn text(&self) -> String {
+        if self.editable.get() {
+            self.text_box.text()
+        } else {
+            self.selected.get().map_or(String::new(), |i| self.items.borrow()[i].clone())
+        }
+    }
+
+    /// Sets the text in the text box of an editable combo box, and selects the item with the same
+    /// text if there is one. A non-editable combo box only selects the item.
+    pub fn set_text(&self, text: &str) {
+        if self.editable.get() {
+            self.text_box.set_text(text);
+        }
+        let index = self.items.borrow().iter().position(|item| item == text);
+        self.set_selected_index(index);
+    }
+
+    pub fn editable(&self) -> bool {
+        self.editable.get()
+    }
+
+    pub fn set_editable(&self, editable: bool) {
+        self.add_children();
+        self.layout();
+        if self.editable.replace(editable) == editable {
+            return;
+        }
+        let had_focus = self.focused() || self.text_box.focused();
+        self.sub_control.set_focusable(!editable);
+        self.text_box.set_visibility(if editable { Visibility::Visible } else { Visibility::Gone });
+        if editable {
+            self.text_box.set_text(&self.text_from_selection());
+        }
+        if had_focus {
+            self.focus();
+        }
+        self.repaint_later();
+    }
+
+    pub fn is_dropped_down(&self) -> bool {
+        self.popup.borrow().as_ref().map_or(false, |p| p.visibility() == Visibility::Visible)
+    }
+
+    /// Shows or hides the dropdown. It can only be shown while the combo box is in a window.
+    pub fn set_dropped_down(&self, dropped_down: bool) {
+        if dropped_down {
+            self.show_dropdown();
+        } else if let Some(popup) = self.popup.borrow().as_ref() {
+            popup.set_visibility(Visibility::Gone);
+        }
+        self.repaint_later();
+    }
+
+    // Convenience method to add an event handler that is called for `SelectionChangedEvent`s.
+    pub fn on_selection_changed_event<F>(&self, mut handler: F)
+        where F: for<'a> FnMut(&'a mut EventRoute) + 'static
+    {
+        self.event_handlers().add(move |mut route| {
+            if let Some(_) = route.event.downcast_mut::<SelectionChangedEvent>() {
+                handler(&mut route);
+            }
+        });
+    }
+
+    fn layout(&self) {
+        let size = self.size();
+        self.text_box.set_location(&Point2::new(0.0, 0.0));
+        // The text box and the button share a border.
+        let text_box_width = (size.width - BUTTON_WIDTH + 1.0).max(0.0);
+        self.text_box.set_size(&Size2::new(text_box_width, size.height));
+    }
+
+    fn text_from_selection(&self) -> String {
+        self.selected.get().map_or(String::new(), |i| self.items.borrow()[i].clone())
+    }
+
+    // Focuses the text box of an editable combo box, or the combo box itself.
+    fn focus(&self) {
+        if self.editable.get() {
+            set_focus(&(self.text_box.clone().into()));
+        } else if let Some(handle) = control_handle(self) {
+            set_focus(&handle);
+        }
+    }
+
+    // Children can't be added in `new()`, before the handle is registered.
+    fn add_children(&self) {
+        if self.text_box.parent().is_some() {
+            return;
+        }
+        self.text_box.set_visibility(Visibility::Gone);
+        self.children().borrow_mut().push(self.text_box.clone());
+        // Events aren't sent to ancestors, so the text box passes on the ones needed here.
+        let combo_box = control_handle(self).map(|c| Rc::downgrade(&c));
+        self.text_box.event_handlers().add(move |route| {
+            let combo_box = match combo_box.as_ref().and_then(|c| c.upgrade()) {
+                Some(c) => c,
+                None => return,
+            };
+            if route.event.is::<FocusLostEvent>() {
+                combo_box.event_handlers().send(&mut TextBoxFocusLostEvent);
+            } else if route.event.is::<TextChangedEvent>() {
+                combo_box.event_handlers().send(&mut TextChangedEvent);
+            }
+        });
+    }
+
+    fn popup(&self) -> Option<Window> {
+        if let Some(popup) = self.popup.borrow().as_ref() {
+            return Some(popup.clone());
+        }
+        let combo_box = Rc::downgrade(&control_handle(self)?);
+        let popup = Window::new_popup();
+        popup.set_child(self.list.clone().into());
+        let combo_box2 = combo_box.clone();
+        self.list.event_handlers().add(move |route| {
+            if let Some(ItemChosenEvent { index }) = route.event.downcast_mut() {
+                if let Some(combo_box) = combo_box2.upgrade() {
+                    combo_box.event_handlers().send(&mut ItemChosenEvent { index: *index });
+                }
+            }
+        });
+        popup.event_handlers().add(move |route| {
+            if let Some(WindowEvent::PopupDismissed) = route.event.downcast_mut() {
+                if let Some(combo_box) = combo_box.upgrade() {
+                    combo_box.repaint_later();
+                }
+            }
+        });
+        *self.popup.borrow_mut() = Some(popup.clone());
+        Some(popup)
+    }
+
+    // Sizes and positions the dropdown below the combo box and shows it.
+    fn show_dropdown(&self) {
+        let origin = match self.to_screen_coords(Point2::new(0.0, self.size().height)) {
+            Some(origin) => origin,
+            None => return,
+        };
+        let popup = match self.popup() {
+            Some(popup) => popup,
+            None => return,
+        };
+        let items = self.items();
+        let visible_items = items.len().min(MAX_VISIBLE_ITEMS).max(1);
+        *self.list.items.borrow_mut() = items;
+        let size = Size2::new(
+            self.size().width,
+            visible_items as f64 * self.list.item_height.get() + 2.0,
+        );
+        popup.set_owner(self.window().as_ref());
+        popup.set_location(&origin);
+        popup.set_size(&size);
+        self.list.set_size(&size);
+        // The items may have changed even if the size didn't.
+        self.list.layout();
+        self.list.set_highlighted(self.selected.get());
+        popup.set_visibility(Visibility::Visible);
+    }
+
+    // Returns the item of the dropdown at the point, in this control's coordinates.
+    fn list_item_at(&self, pt: Point2<f64>) -> Option<usize> {
+        if !self.is_dropped_down() {
+            return None;
+        }
+        let pt = self.map_point_to(&*self.list.0, pt)?;
+        self.list.item_at(pt)
+    }
+
+    // Selects the next item starting with the typed text after the selected one, like Windows.
+    fn select_by_prefix(&self, text: &str) {
+        let text = text.to_lowercase();
+        let len = self.items.borrow().len();
+        let current = if self.is_dropped_down() {
+            self.list.highlighted.get()
+        } else {
+            self.selected.get()
+        };
+        let start = current.map_or(0, |i| i + 1);
+        let found = (0..len).map(|i| (start + i) % len)
+            .find(|&i| self.items.borrow()[i].to_lowercase().starts_with(&text));
+        if let Some(index) = found {
+            if self.is_dropped_down() {
+                self.list.set_highlighted(Some(index));
+            } else {
+                self.set_selected_index(Some(index));
+            }
+        }
+    }
+
+    // Returns whether the key was handled.
+    fn on_key_down(&self, key: Key, modifiers: Modifiers) -> bool {
+        let Modifiers { shift, control, alt, logo } = modifiers;
+        if shift || control || logo {
+            return false;
+        }
+        let dropped_down = self.is_dropped_down();
+        if key == Key::F4 || (alt && (key == Key::Up || key == Key::Down)) {
+            if dropped_down {
+                let highlighted = self.list.highlighted.get();
+                self.set_selected_index(highlighted.or(self.selected.get()));
+            }
+            self.set_dropped_down(!dropped_down);
+            return true;
+        }
+        if alt {
+            return false;
+        }
+        let len = self.items.borrow().len();
+        if dropped_down {
+            match key {
+                Key::Enter => {
+                    let highlighted = self.list.highlighted.get();
+                    self.set_selected_index(highlighted.or(self.selected.get()));
+                    self.set_dropped_down(false);
+                }
+                Key::Escape => self.set_dropped_down(false),
+                _ => match move_index(key, self.list.highlighted.get(), len, MAX_VISIBLE_ITEMS) {
+                    Some(index) => self.list.set_highlighted(Some(index)),
+                    None => return false,
+                },
+            }
+            return true;
+        }
+        // The text box of an editable combo box handles Home and End.
+        if self.editable.get() && (key == Key::Home || key == Key::End) {
+            return false;
+        }
+        match move_index(key, self.selected.get(), len, MAX_VISIBLE_ITEMS) {
+            Some(index) => self.set_selected_index(Some(index)),
+            None => return false,
+        }
+        true
+    }
+
+    fn paint(&self, painter: &mut dyn Painter) {
+        let enabled = self.is_effectively_enabled();
+        let size = self.size();
+        let (width, height) = (size.width as f32, size.height as f32);
+        let active = self.hot.get() || self.is_dropped_down();
+        let border_color = if !enabled {
+            Color::from_rgba(191, 191, 191, 255)
+        } else if active {
+            Color::from_rgba(0, 120, 215, 255)
+        } else {
+            Color::from_rgba(122, 122, 122, 255)
+        };
+        let button_left = width - BUTTON_WIDTH as f32;
+
+        if self.editable.get() {
+            // The text box paints itself, so only the button is painted.
+            let background = if self.is_dropped_down() {
+                Color::from_rgba(204, 228, 247, 255)
+            } else if self.hot.get() && enabled {
+                Color::from_rgba(229, 241, 251, 255)
+            } else {
+                Color::from_rgba(255, 255, 255, 255)
+            };
+            painter.fill_path(
+                &mut theme::rect_path(button_left, 0.0, width, height).path_iter(),
+                &Brush::Solid(background),
+            );
+            painter.stroke_path(
+                &mut theme::rect_path(button_left + 0.5, 0.5, width - 0.5, height - 0.5)
+                    .path_iter(),
+                &Brush::Solid(border_color),
+                &StrokeStyle::with_width(1.0),
+            );
+        } else {
+            let background = if !enabled {
+                Color::from_rgba(244, 244, 244, 255)
+            } else if self.is_dropped_down() {
+                Color::from_rgba(204, 228, 247, 255)
+            } else if self.hot.get() {
+                Color::from_rgba(229, 241, 251, 255)
+            } else {
+                Color::from_rgba(225, 225, 225, 255)
+            };
+            painter.fill_path(
+                &mut theme::rect_path(0.0, 0.0, width, height).path_iter(),
+                &Brush::Solid(background),
+            );
+            painter.stroke_path(
+                &mut theme::rect_path(0.5, 0.5, width - 0.5, height - 0.5).path_iter(),
+                &Brush::Solid(border_color),
+                &StrokeStyle::with_width(1.0),
+            );
+            if self.focused() {
+                painter.fill_path(
+                    &mut theme::rect_path(3.0, 3.0, button_left, height - 3.0).path_iter(),
+                    &Brush::Solid(theme::item_selection_color(true)),
+                );
+            }
+
+            painter.save();
+            painter.clip_rect(Rect::new(0.0, 0.0, button_left, height));
+            let layout = theme::layout_line(
+                theme::formatted_string(&self.text(), theme::text_color(enabled)));
+            let top = ((size.height - theme::text_size(&layout).height) / 2.0).round();
+            painter.translate(PADDING_X + 2.0, top);
+            layout.draw(painter);
+            painter.restore();
+        }
+
+        let arrow_color = if enabled {
+            Color::from_rgba(96, 96, 96, 255)
+        } else {
+            Color::from_rgba(191, 191, 191, 255)
+        };
+        let center = Point2::new(button_left + BUTTON_WIDTH as f32 / 2.0, height / 2.0);
+        let mut path = PathBuf::new();
+        path.move_to(Point2::new(center.x - 4.0, center.y - 2.0));
+        path.line_to(Point2::new(center.x, center.y + 2.0));
+        path.line_to(Point2::new(center.x + 4.0, center.y - 2.0));
+        painter.stroke_path(
+            &mut path.path_iter(),
+            &Brush::Solid(arrow_color),
+            &StrokeStyle::with_width(1.0),
+        );
+    }
+}
+
+impl Default for ComboBoxData {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Returns the index that an arrow or page key moves to in a list of `len` items, or `None` if the
+// key doesn't move.
+fn move_index(key: Key, current: Option<usize>, len: usize, page_len: usize) -> Option<usize> {
+    if len == 0 {
+        return None;
+    }
+    Some(match key {
+        Key::Up => current.map_or(0, |i| i.saturating_sub(1)),
+        Key::Down => current.map_or(0, |i| (i + 1).min(len - 1)),
+        Key::PageUp => current.map_or(0, |i| i.saturating_sub(page_len - 1)),
+        Key::PageDown => current.map_or(0, |i| (i + page_len - 1).min(len - 1)),
+        Key::Home => 0,
+        Key::End => len - 1,
+        _ => return None,
+    })
+}
+
+// The list of items shown in the popup of a combo box. It follows the mouse and chooses the item
+// that is clicked.
+//#[dynamin::control]
+pub(crate) struct DropDownListData {
+    sub_control: SubControlData,
+    scroll_view: ScrollView,
+    panel: SubControl,
+    items: RefCell<Vec<String>>,
+    highlighted: Cell<Option<usize>>,
+    item_height: Cell<f64>,
+}
+
+impl SubControlRef for DropDownListData {
+    fn sub_control_ref(&self) -> &SubControlData { &self.sub_control }
+}
+
+impl EventHandler for DropDownListData {
+    fn on_event(&self, route: &mut EventRoute) {
+        if let Some(PanelEvent::Moved(location)) = route.event.downcast_mut() {
+            if let Some(index) = self.item_at_panel_point(*location) {
+                self.highlighted.set(Some(index));
+                self.repaint_later();
+            }
+        } else if let Some(PanelEvent::Released(location)) = route.event.downcast_mut() {
+            if let Some(index) = self.item_at_panel_point(*location) {
+                self.event_handlers().send(&mut ItemChosenEvent { index });
+            }
+        } else if let Some(SizeChangedEvent) = route.event.downcast_mut() {
+            self.layout();
+        } else if let Some(PaintingEvent { painter }) = route.event.downcast_mut() {
+            self.paint(&mut **painter);
+        }
+    }
+}
+
+impl DropDownListData {
+    pub(crate) fn new() -> Self {
+        let line = theme::layout_line(theme::formatted_string("Xg", theme::text_color(true)));
+        let text_height = theme::text_size(&line).height;
+        DropDownListData {
+            sub_control: SubControlData::new(),
+            scroll_view: ScrollView::new(),
+            panel: SubControl::new(),
+            items: RefCell::new(vec![]),
+            highlighted: Cell::new(None),
+            item_height: Cell::new((text_height + ITEM_PADDING_Y * 2.0).ceil()),
+        }
+    }
+
+    // Highlights the item and scrolls it into view.
+    pub(crate) fn set_highlighted(&self, index: Option<usize>) {
+        self.highlighted.set(index);
+        if let Some(index) = index {
+            let height = self.item_height.get();
+            let top = index as f64 * height;
+            let offset = self.scroll_view.scroll_offset();
+            let available = self.scroll_view.viewport_size().height;
+            let y = if top < offset.y {
+                top
+            } else if top + height > offset.y + available {
+                top + height - available
+            } else {
+                offset.y
+            };
+            self.scroll_view.set_scroll_offset(Point2::new(0.0, y));
+        }
+        self.repaint_later();
+    }
+
+    // Returns the item at the point, in this control's coordinates.
+    pub(crate) fn item_at(&self, pt: Point2<f64>) -> Option<usize> {
+        if !self.hit_test(pt.x, pt.y) {
+            return None;
+        }
+        let y = pt.y - 1.0 + self.scroll_view.scroll_offset().y;
+        let index = (y / self.item_height.get()).floor();
+        if index >= 0.0 && (index as usize) < self.items.borrow().len() {
+            Some(index as usize)
+        } else {
+            None
+        }
+    }
+
+    fn layout(&self) {
+        self.add_children();
+        let size = self.size();
+        // The border is outside the scroll view.
+        self.scroll_view.set_location(&Point2::new(1.0, 1.0));
+        self.scroll_view.set_size(&Size2::new(
+            (size.width - 2.0).max(0.0),
+            (size.height - 2.0).max(0.0),
+        ));
+        let height = self.items.borrow().len() as f64 * self.item_height.get();
+        self.panel.set_size(&Size2::new(0.0, height));
+        self.scroll_view.layout();
+    }
+
+    // Children can't be added in `new()`, before the handle is registered.
+    fn add_children(&self) {
+        if self.scroll_view.parent().is_some() {
+            return;
+        }
+        self.children().borrow_mut().push(self.scroll_view.clone());
+        self.scroll_view.set_horizontal_scroll_bar(ScrollBarVisibility::Never);
+        self.scroll_view.set_content(Some(self.panel.clone().into()));
+        // Mouse events aren't sent to ancestors, so the panel passes them on.
+        let list = control_handle(self).map(|l| Rc::downgrade(&l));
+        self.panel.event_handlers().add(move |route| {
+            let mut event = if let Some(MouseMovedEvent { location }) = route.event.downcast_mut() {
+                PanelEvent::Moved(*location)
+            } else if let Some(MouseDraggedEvent { location }) = route.event.downcast_mut() {
+                PanelEvent::Moved(*location)
+            } else if let Some(MouseUpEvent { location, button: MouseButton::Left }) =
+                route.event.downcast_mut()
+            {
+                PanelEvent::Released(*location)
+            } else {
+                route.handled = route.event.is::<MouseDownEvent>();
+                return;
+            };
+            if let Some(list) = list.as_ref().and_then(|l| l.upgrade()) {
+                list.event_handlers().send(&mut event);
+            }
+            route.handled = true;
+        });
+    }
+
+    fn item_at_panel_point(&self, pt: Point2<f64>) -> Option<usize> {
+        let offset = self.scroll_view.scroll_offset().y;
+        self.item_at(Point2::new(pt.x + 1.0, pt.y + 1.0 - offset))
+    }
+
+    // Returns the range of items that are at least partly in view.
+    fn visible_items(&self) -> Range<usize> {
+        let height = self.item_height.get();
+        let top = self.scroll_view.scroll_offset().y;
+        let bottom = top + self.scroll_view.viewport_size().height;
+        let end = ((bottom / height).ceil() as usize).min(self.items.borrow().len());
+        ((top / height).floor() as usize).min(end)..end
+    }
+
+    fn paint(&self, painter: &mut dyn Painter) {
+        let size = self.size();
+        let (width, height) = (size.width as f32, size.height as f32);
+        painter.fill_path(
+            &mut theme::rect_path(0.0, 0.0, width, height).path_iter(),
+            &Brush::Solid(Color::from_rgba(255, 255, 255, 255)),
+        );
+        painter.stroke_path(
+            &mut theme::rect_path(0.5, 0.5, width - 0.5, height - 0.5).path_iter(),
+            &Brush::Solid(Color::from_rgba(100, 100, 100, 255)),
+            &StrokeStyle::with_width(1.0),
+        );
+
+        let viewport_size = self.scroll_view.viewport_size();
+        painter.save();
+        painter.clip_rect(Rect::new(
+            1.0, 1.0, viewport_size.width as f32, viewport_size.height as f32));
+        let item_height = self.item_height.get();
+        let offset = self.scroll_view.scroll_offset().y;
+        let items = self.items.borrow();
+        for index in self.visible_items() {
+            let top = (1.0 + index as f64 * item_height - offset) as f32;
+            if self.highlighted.get() == Some(index) {
+                painter.fill_path(
+                    &mut theme::rect_path(1.0, top, 1.0 + viewport_size.width as f32,
+                        top + item_height as f32).path_iter(),
+                    &Brush::Solid(theme::item_selection_color(true)),
+                );
+            }
+            let layout = theme::layout_line(
+                theme::formatted_string(&items[index], theme::text_color(true)));
+            let text_top = ((item_height - theme::text_size(&layout).height) / 2.0).round();
+            painter.save();
+            painter.translate(1.0 + PADDING_X, top as f64 + text_top);
+            layout.draw(painter);
+            painter.restore();
+        }
+        painter.restore();
+    }
+}
+
+#[test]
+fn test_combo_box_keyboard() {
+    let combo_box = ComboBox::new();
+    combo_box.set_items(&["Apple", "Banana", "Blueberry", "Cherry"]);
+    let changes = Rc::new(Cell::new(0));
+    let changes2 = changes.clone();
+    combo_box.on_selection_changed_event(move |_| changes2.set(changes2.get() + 1));
+
+    let key = |key| {
+        let modifiers = Modifiers::none();
+        combo_box.event_handlers().send(&mut KeyDownEvent { key, modifiers, repeat: false })
+    };
+    assert!(key(Key::Down));
+    assert_eq!(combo_box.selected_index(), Some(0));
+    key(Key::End);
+    key(Key::Up);
+    assert_eq!(combo_box.text(), "Blueberry");
+    // Typing selects the next item starting with the text, wrapping around.
+    combo_box.event_handlers().send(&mut TextInputEvent { text: "b".to_owned() });
+    assert_eq!(combo_box.selected_index(), Some(1));
+    assert_eq!(changes.get(), 4);
+    // The dropdown can't be shown outside a window, so Escape isn't handled.
+    assert!(!key(Key::Escape));
+
+    combo_box.set_editable(true);
+    assert_eq!(combo_box.text(), "Banana");
+    combo_box.set_text("cherry");
+    assert_eq!(combo_box.selected_index(), None);
+    combo_box.set_text("Cherry");
+    assert_eq!(combo_box.selected_index(), Some(3));
+
+    // The text box is resized with the combo box, leaving room for the button.
+    combo_box.set_size(&Size2::new(150.0, 24.0));
+    assert_eq!(combo_box.text_box.size(), Size2::new(151.0 - BUTTON_WIDTH, 24.0));
+}
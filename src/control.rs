@@ -5,18 +5,24 @@
  *
  */
 
-use std::cell::{Cell, RefCell};
-use std::ops::Deref;
+use std::any::Any;
+use std::cell::{BorrowMutError, Cell, Ref, RefCell, RefMut};
+use std::mem;
+use std::ops::{Deref, DerefMut};
 use std::rc::{Rc, Weak};
 
 use zaffre::{Painter, Point2, Size2, Rect};
 
 use crate::Window;
 use crate::bitfield::BitField;
+use crate::backend::WindowBackend;
+use crate::generic_backend::GenericWindowBackend;
 use crate::event_vec::{EventHandler, EventHandlerVec, EventRoute};
+use crate::keyboard::Modifiers;
+use crate::window::{dismiss_popups, open_popups};
 
 /// Whether a control is visible or affects layout.
-#[derive(Copy, Clone, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum Visibility {
     /// The control is painted and can be interacted with, as long as its parent is visible as well.
     Visible,
@@ -48,25 +54,64 @@ fn u8_to_visibility(v: u8) -> Visibility {
 
 // Use a separate trait that isn't reexported to hide methods.
 pub trait PrivControl {
-    fn set_parent(&self, parent: Weak<dyn Control>);
+    fn set_parent(&self, parent: Option<Weak<dyn Control>>);
+    fn set_focused(&self, focused: bool);
 }
 
 pub trait Control : PrivControl {
     fn as_window(&self) -> Option<Window>;
 
+    /// Returns the control's own visibility, without taking its ancestors into account.
     fn visibility(&self) -> Visibility;
+    /// Sets the control's own visibility. A `VisibilityChangedEvent` is sent to the control, and
+    /// to each descendant whose effective visibility changes.
     fn set_visibility(&self, visibility: Visibility);
 
+    /// Returns whether this control is actually shown: it and all its ancestors are `Visible`, and
+    /// it is in a window that is visible.
+    fn is_effectively_visible(&self) -> bool {
+        self.visibility() == Visibility::Visible && match self.parent() {
+            Some(parent) => parent.is_effectively_visible(),
+            None => self.as_window().is_some(),
+        }
+    }
+
     fn location(&self) -> Point2<f64>;
     fn set_location(&self, location: &Point2<f64>);
 
     fn size(&self) -> Size2<f64>;
     fn set_size(&self, size: &Size2<f64>);
 
+    /// Returns the size that the control would like to be, based on its content. Layout
+    /// containers use it to size their children. The default implementation returns the current
+    /// size.
+    fn best_size(&self) -> Size2<f64> {
+        self.size()
+    }
+
     fn tab_index(&self) -> u16;
     fn set_tab_index(&self, tab_index: u16);
 
-    fn children(&self) -> &RefCell<ChildrenVec>;
+    /// Returns whether this control is enabled, without taking its ancestors into account.
+    fn enabled(&self) -> bool;
+    /// Enables or disables this control. Disabling a control also disables all its descendants.
+    /// An `EnabledChangedEvent` is sent to each control whose effective state changes.
+    fn set_enabled(&self, enabled: bool);
+
+    /// Returns whether this control and all its ancestors are enabled. A control that isn't
+    /// effectively enabled doesn't receive mouse or keyboard input and can't be focused.
+    fn is_effectively_enabled(&self) -> bool {
+        self.enabled() && self.parent().map_or(true, |p| p.is_effectively_enabled())
+    }
+
+    /// Returns whether this control can receive the keyboard focus.
+    fn focusable(&self) -> bool;
+    fn set_focusable(&self, focusable: bool);
+
+    /// Returns whether this control has the keyboard focus.
+    fn focused(&self) -> bool;
+
+    fn children(&self) -> &Children;
 
     fn parent(&self) -> Option<Rc<dyn Control>>;
 
@@ -78,32 +123,216 @@ pub trait Control : PrivControl {
 
     fn dispatch_painting(&self, event: &mut PaintingEvent);
 
-    fn child_at_point(&self, x: f64, y: f64) -> Option<Rc<dyn Control>> {
-        self.children().borrow().iter()
-            .find(|child| (child.location() + child.size()).contains_pt(Point2::new(x, y)))
-            .map(|c| c.clone())
+    /// Converts a point from this control's coordinates to the coordinates of the window's
+    /// client area. If the control isn't in a window, the point is converted to the coordinates
+    /// of the root of the control's tree instead.
+    fn to_window_coords(&self, pt: Point2<f64>) -> Point2<f64> {
+        if self.as_window().is_some() {
+            return pt;
+        }
+        let location = self.location();
+        let mut pt = Point2::new(pt.x + location.x, pt.y + location.y);
+        let mut ancestor = self.parent();
+        while let Some(a) = ancestor {
+            if a.as_window().is_some() {
+                break;
+            }
+            let location = a.location();
+            pt = Point2::new(pt.x + location.x, pt.y + location.y);
+            ancestor = a.parent();
+        }
+        pt
+    }
 
-        // for child in self.children().borrow().iter() {
-        //     if (child.location() + child.size()).contains_pt(Point2::new(x, y)) {
-        //         return Some(child.clone());
-        //     }
-        // }
-        // None
+    /// Converts a point from the coordinates of the window's client area to this control's
+    /// coordinates. This is the inverse of `to_window_coords()`.
+    fn from_window_coords(&self, pt: Point2<f64>) -> Point2<f64> {
+        let origin = self.to_window_coords(Point2::new(0.0, 0.0));
+        Point2::new(pt.x - origin.x, pt.y - origin.y)
     }
 
-    fn descendant_at_point(&self, x: f64, y: f64) -> Option<Rc<dyn Control>> {
-        self.children().borrow().iter()
-            .find(|child| (child.location() + child.size()).contains_pt(Point2::new(x, y)))
-            .map(|child| {
-                let (cx, cy) = (x - child.location().x, y - child.location().y);
-                child.descendant_at_point(cx, cy).unwrap_or_else(|| child.clone())
+    /// Converts a point from this control's coordinates to screen coordinates. Returns `None` if
+    /// the control isn't in a window or the window hasn't been created on screen yet.
+    fn to_screen_coords(&self, pt: Point2<f64>) -> Option<Point2<f64>> {
+        let origin = self.window()?.backend.client_origin()?;
+        let pt = self.to_window_coords(pt);
+        Some(Point2::new(pt.x + origin.x, pt.y + origin.y))
+    }
+
+    /// Converts a point from screen coordinates to this control's coordinates. Returns `None` if
+    /// the control isn't in a window or the window hasn't been created on screen yet.
+    fn from_screen_coords(&self, pt: Point2<f64>) -> Option<Point2<f64>> {
+        let origin = self.window()?.backend.client_origin()?;
+        Some(self.from_window_coords(Point2::new(pt.x - origin.x, pt.y - origin.y)))
+    }
+
+    /// Converts a point from this control's coordinates to the coordinates of `other`.
+    ///
+    /// If the controls are in different windows, the point is converted through screen
+    /// coordinates. Returns `None` if only one of the controls is in a window. If neither is in a
+    /// window, they should be in the same tree for the result to be meaningful.
+    fn map_point_to(&self, other: &dyn Control, pt: Point2<f64>) -> Option<Point2<f64>> {
+        match (self.window(), other.window()) {
+            (Some(window), Some(other_window)) if !Rc::ptr_eq(&window.0, &other_window.0) => {
+                self.to_screen_coords(pt).and_then(|pt| other.from_screen_coords(pt))
+            }
+            (Some(_), None) | (None, Some(_)) => None,
+            _ => Some(other.from_window_coords(self.to_window_coords(pt))),
+        }
+    }
+
+    /// Returns whether the point, in this control's coordinates, is inside the control. The
+    /// default implementation checks the control's rectangular bounds. Controls that aren't
+    /// rectangular, like round buttons, can override it so that clicks near their corners go to
+    /// the controls behind them.
+    fn hit_test(&self, x: f64, y: f64) -> bool {
+        let size = self.size();
+        x >= 0.0 && y >= 0.0 && x < size.width && y < size.height
+    }
+
+    /// Returns whether mouse input passes through this control to the controls behind it. The
+    /// children of a transparent control can still receive input.
+    fn input_transparent(&self) -> bool;
+    fn set_input_transparent(&self, transparent: bool);
+
+    /// Returns the topmost visible child that the point, in this control's coordinates, is over.
+    fn child_at_point(&self, x: f64, y: f64) -> Option<Rc<dyn Control>> {
+        // Later children are painted on top of earlier ones, so search from the end.
+        self.children().borrow().iter().rev()
+            .find(|child| {
+                let location = child.location();
+                child.visibility() == Visibility::Visible &&
+                    child.hit_test(x - location.x, y - location.y)
             })
+            .cloned()
+    }
+
+    /// Returns the deepest visible descendant that the point, in this control's coordinates, is
+    /// over and that isn't transparent to input.
+    fn descendant_at_point(&self, x: f64, y: f64) -> Option<Rc<dyn Control>> {
+        for child in self.children().borrow().iter().rev() {
+            let location = child.location();
+            let (cx, cy) = (x - location.x, y - location.y);
+            if child.visibility() != Visibility::Visible || !child.hit_test(cx, cy) {
+                continue;
+            }
+            if let Some(descendant) = child.descendant_at_point(cx, cy) {
+                return Some(descendant);
+            }
+            if !child.input_transparent() {
+                return Some(child.clone());
+            }
+        }
+        None
     }
 }
 
 pub struct ChildrenVec {
     pub(crate) control: Option<Weak<dyn Control>>,
     vec: Vec<Rc<dyn Control>>,
+    // The added and removed controls to notify once the list is no longer borrowed
+    pending: Vec<(Rc<dyn Control>, TreeEvent)>,
+    // Whether the list changed while it was borrowed
+    changed: bool,
+}
+
+enum TreeEvent {
+    Attached(Window),
+    Detached(Window),
+}
+
+/// The children of a control. It works like a `RefCell<ChildrenVec>`, except that the
+/// `AttachedToWindowEvent`s and `DetachedFromWindowEvent`s caused by changing the list are sent
+/// when the mutable borrow ends, so that their handlers can use the list.
+pub struct Children(RefCell<ChildrenVec>);
+
+impl Children {
+    pub fn new() -> Self {
+        Children(RefCell::new(ChildrenVec::new()))
+    }
+
+    /// Immutably borrows the list.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the list is mutably borrowed.
+    pub fn borrow(&self) -> Ref<'_, ChildrenVec> {
+        self.0.borrow()
+    }
+
+    /// Mutably borrows the list.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the list is borrowed.
+    pub fn borrow_mut(&self) -> ChildrenRefMut<'_> {
+        ChildrenRefMut(Some(self.0.borrow_mut()))
+    }
+
+    /// Mutably borrows the list, returning an error if it is borrowed.
+    pub fn try_borrow_mut(&self) -> Result<ChildrenRefMut<'_>, BorrowMutError> {
+        self.0.try_borrow_mut().map(|children| ChildrenRefMut(Some(children)))
+    }
+}
+
+impl Default for Children {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A mutable borrow of a control's children, returned by `Children::borrow_mut()`.
+pub struct ChildrenRefMut<'a>(Option<RefMut<'a, ChildrenVec>>);
+
+impl Deref for ChildrenRefMut<'_> {
+    type Target = ChildrenVec;
+    fn deref(&self) -> &ChildrenVec {
+        self.0.as_ref().unwrap()
+    }
+}
+
+impl DerefMut for ChildrenRefMut<'_> {
+    fn deref_mut(&mut self) -> &mut ChildrenVec {
+        self.0.as_mut().unwrap()
+    }
+}
+
+impl Drop for ChildrenRefMut<'_> {
+    fn drop(&mut self) {
+        let children = self.0.as_mut().unwrap();
+        let pending = mem::replace(&mut children.pending, vec![]);
+        let changed = mem::replace(&mut children.changed, false);
+        let control = children.control.as_ref().and_then(|c| c.upgrade());
+        // Release the borrow before any handler runs.
+        self.0 = None;
+        let mut detached = false;
+        for (control, event) in pending {
+            match event {
+                TreeEvent::Attached(window) => {
+                    send_to_subtree(&control, &mut AttachedToWindowEvent { window });
+                }
+                TreeEvent::Detached(window) => {
+                    detached = true;
+                    send_to_subtree(&control, &mut DetachedFromWindowEvent { window });
+                }
+            }
+        }
+        if detached {
+            check_mouse_capture();
+            if get_hot_control().and_then(|c| c.upgrade()).map_or(false, |c| c.window().is_none()) {
+                set_hot_control(None);
+            }
+        }
+        if let (true, Some(control)) = (changed, control) {
+            control.event_handlers().send(&mut ChildrenChangedEvent);
+        }
+    }
+}
+
+// Compares only the data pointers. Comparing fat pointers would also compare vtables, which can
+// differ for the same object.
+pub(crate) fn is_same_control(a: &Rc<dyn Control>, b: &Rc<dyn Control>) -> bool {
+    Rc::as_ptr(a) as *const () == Rc::as_ptr(b) as *const ()
 }
 
 impl ChildrenVec {
@@ -111,20 +340,112 @@ impl ChildrenVec {
         ChildrenVec {
             control: None,
             vec: vec![],
+            pending: vec![],
+            changed: false,
         }
     }
 
     pub fn clear(&mut self) {
-        self.vec.clear();
-        self.update_control();
+        while !self.vec.is_empty() {
+            self.remove_at(self.vec.len() - 1, true);
+        }
     }
 
+    /// Adds a control to the end of this list, making it a child of the control that owns the
+    /// list.
+    ///
+    /// If the control already has a parent, it is removed from that parent first. A control can
+    /// only be in one place in the control tree.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `new_control` is the control that owns this list or one of its ancestors, since
+    /// that would make the control tree into a cycle. Also panics if `new_control` is moved from
+    /// another parent whose children are borrowed, such as while iterating over them.
     pub fn push<T>(&mut self, new_control: T) where T: Into<Rc<dyn Control>> {
         let new_control = new_control.into();
-        new_control.set_parent(self.control.clone()
-            .expect("ChildrenVec control not set; need to call register_handle()"));
-        self.vec.push(new_control);
+        let control = self.control.clone()
+            .expect("ChildrenVec control not set; need to call register_handle()");
+
+        let mut ancestor = control.upgrade();
+        while let Some(a) = ancestor {
+            if is_same_control(&a, &new_control) {
+                panic!("can't add a control as a child of itself or of one of its descendants");
+            }
+            ancestor = a.parent();
+        }
+
+        let old_window = new_control.window();
+        if let Some(old_parent) = new_control.parent() {
+            // If the old parent is the control owning this list, its children are already
+            // borrowed (as `self`), so they can't be borrowed again.
+            if Rc::as_ptr(&old_parent) as *const () == control.as_ptr() as *const () {
+                self.remove_control_at(&new_control, false);
+            } else {
+                old_parent.children().try_borrow_mut()
+                    .expect("can't move a control from a parent whose children are borrowed")
+                    .remove_control_at(&new_control, false);
+            }
+        }
+
+        new_control.set_parent(Some(control));
+        self.vec.push(new_control.clone());
+        self.changed = true;
         self.update_control();
+
+        // Moving a control to a different parent in the same window doesn't detach it.
+        let new_window = new_control.window();
+        let same_window = match (&old_window, &new_window) {
+            (Some(old), Some(new)) => Rc::ptr_eq(&old.0, &new.0),
+            _ => false,
+        };
+        if !same_window {
+            if let Some(window) = old_window {
+                self.pending.push((new_control.clone(), TreeEvent::Detached(window)));
+            }
+            if let Some(window) = new_window {
+                self.pending.push((new_control, TreeEvent::Attached(window)));
+            }
+        }
+    }
+
+    /// Removes and returns the child at `index`. The removed control no longer has a parent.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn remove(&mut self, index: usize) -> Rc<dyn Control> {
+        self.remove_at(index, true)
+    }
+
+    /// Removes the specified control from this list if it is in it. Returns whether the control
+    /// was found.
+    pub fn remove_control(&mut self, control: &Rc<dyn Control>) -> bool {
+        self.remove_control_at(control, true)
+    }
+
+    fn remove_control_at(&mut self, control: &Rc<dyn Control>, notify: bool) -> bool {
+        match self.vec.iter().position(|c| is_same_control(c, control)) {
+            Some(index) => {
+                self.remove_at(index, notify);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // If `notify` is true and the control was in a window, a `DetachedFromWindowEvent` is sent to
+    // the removed subtree when the list is no longer borrowed.
+    fn remove_at(&mut self, index: usize, notify: bool) -> Rc<dyn Control> {
+        let child = self.vec.remove(index);
+        let window = if notify { child.window() } else { None };
+        child.set_parent(None);
+        self.changed = true;
+        self.update_control();
+        if let Some(window) = window {
+            self.pending.push((child.clone(), TreeEvent::Detached(window)));
+        }
+        child
     }
 
     fn update_control(&self) {
@@ -185,7 +506,8 @@ impl From<SubControl> for Rc<dyn EventHandler> {
 pub struct SubControlData {
     location: Cell<Point2<f64>>,
     size: Cell<Size2<f64>>,
-    children: RefCell<ChildrenVec>,
+    best_size: Cell<Option<Size2<f64>>>,
+    children: Children,
     parent: Cell<Option<Weak<dyn Control>>>,
     event_handlers: EventHandlerVec,
     //draw_commands: Cell<Vec<DrawCommand>>,
@@ -193,6 +515,7 @@ pub struct SubControlData {
     bit_fields: Cell<u8>,
 }
 
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum MouseButton {
     Left,
     Middle,
@@ -201,27 +524,151 @@ pub enum MouseButton {
     Forward,
 }
 
+// The `location` of mouse events is in the coordinates of the control receiving the event. When
+// the mouse is captured, it may be outside the control's bounds.
+
 #[non_exhaustive]
 pub struct MouseDownEvent {
+    pub location: Point2<f64>,
+    pub button: MouseButton,
+    /// The modifier keys held down when the button was pressed, whichever control was focused.
+    pub modifiers: Modifiers,
 }
 
 #[non_exhaustive]
 pub struct MouseUpEvent {
+    pub location: Point2<f64>,
+    pub button: MouseButton,
 }
 
 #[non_exhaustive]
 pub struct MouseMovedEvent {
+    pub location: Point2<f64>,
 }
 
+/// Sent instead of a `MouseMovedEvent` when the mouse is moved with a button held down. It is
+/// sent to the control the button was pressed on, even when the mouse is outside of it.
 #[non_exhaustive]
 pub struct MouseDraggedEvent {
+    pub location: Point2<f64>,
+}
+
+/// Sent to a control when it loses the mouse capture because `release_mouse()` was called,
+/// another control captured the mouse, or the control was disabled or removed from its window.
+/// It isn't sent when the implicit capture from pressing a button ends by releasing it.
+#[non_exhaustive]
+pub struct MouseCaptureLostEvent;
+
+/// The phase of a scroll gesture on a touchpad.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ScrollPhase {
+    /// The fingers were put on the touchpad and started scrolling.
+    Begin,
+    Update,
+    /// The fingers were lifted. The deltas are usually zero. `Momentum` events may follow.
+    End,
+    /// Scrolling that continues, slowing down, after the fingers were lifted. Not all platforms
+    /// send these; some leave it to the application.
+    Momentum,
+}
+
+/// Sent when the mouse wheel is turned or a touchpad is scrolled with the mouse over a control.
+/// If the control doesn't handle it, it is sent to each ancestor in turn, with `location`
+/// converted to the ancestor's coordinates, until one sets `EventRoute::handled`.
+#[non_exhaustive]
+pub struct MouseWheelEvent {
+    pub location: Point2<f64>,
+    /// The number of lines to scroll right, using the system's setting for how many lines a
+    /// notch of the wheel scrolls. Negative values scroll left. Touchpads and high-resolution
+    /// wheels can give fractions of a line.
+    pub delta_x: f64,
+    /// The number of lines to scroll down. Negative values scroll up.
+    pub delta_y: f64,
+    /// The distance to scroll right and down in pixels, if the device reports it. Controls
+    /// should prefer it to the line deltas when it is given, since it follows the fingers on a
+    /// touchpad exactly.
+    pub pixel_delta: Option<(f64, f64)>,
+    /// The phase of a touchpad gesture, or `None` for a mouse wheel, which doesn't have phases.
+    pub phase: Option<ScrollPhase>,
+    pub modifiers: Modifiers,
+}
+
+impl MouseWheelEvent {
+    /// Creates an event for scrolling by lines, without a pixel delta, phase, or modifiers. The
+    /// other fields can be set afterward.
+    pub fn new(location: Point2<f64>, delta_x: f64, delta_y: f64) -> Self {
+        MouseWheelEvent {
+            location,
+            delta_x,
+            delta_y,
+            pixel_delta: None,
+            phase: None,
+            modifiers: Modifiers::none(),
+        }
+    }
+}
+
+/// Sent to a control when it becomes effectively enabled or disabled, either because it was
+/// enabled or disabled itself or because one of its ancestors was.
+#[non_exhaustive]
+pub struct EnabledChangedEvent {
+    pub enabled: bool,
 }
 
+/// Sent when the user asks for a context menu, by releasing the right mouse button or by pressing
+/// the Menu key or Shift+F10. It is sent to the control under the mouse or the focused control, and
+/// then to each ancestor in turn until one handles it, usually by calling
+/// `Menu::show_context_menu()`.
+#[derive(Debug)]
 #[non_exhaustive]
-pub struct MouseEnteredEvent;
+pub struct ContextMenuRequestedEvent {
+    /// Where to show the menu, in the coordinates of the control receiving the event. When the
+    /// menu is requested with the keyboard, it is the top left corner of the focused control.
+    pub location: Point2<f64>,
+}
 
+/// Sent to a control when its own visibility changes, and to each of its descendants when their
+/// effective visibility changes as a result.
 #[non_exhaustive]
-pub struct MouseLeftEvent;
+pub struct VisibilityChangedEvent {
+    /// The visibility of the control receiving the event.
+    pub visibility: Visibility,
+    /// Whether the control receiving the event is now effectively visible.
+    pub effectively_visible: bool,
+}
+
+/// Sent to a control after it receives the keyboard focus.
+#[non_exhaustive]
+pub struct FocusGainedEvent;
+
+/// Sent to a control after it loses the keyboard focus.
+#[non_exhaustive]
+pub struct FocusLostEvent;
+
+/// Sent to a control and all its descendants after the control is added to a tree that is in a
+/// window. Controls can use it to start timers and animations or to create resources that need a
+/// window.
+#[non_exhaustive]
+pub struct AttachedToWindowEvent {
+    pub window: Window,
+}
+
+/// Sent to a control and all its descendants after the control is removed from a tree that is in
+/// a window. When this event is received, `Control::window()` already returns `None`.
+#[non_exhaustive]
+pub struct DetachedFromWindowEvent {
+    pub window: Window,
+}
+
+/// Sent to a control after its size changes.
+#[non_exhaustive]
+pub struct SizeChangedEvent;
+
+/// Sent to a control after children are added to it or removed from it, or after one of its
+/// children changes in a way that affects layout, such as becoming `Visibility::Gone`. Layout
+/// containers lay out their children again when they receive it.
+#[non_exhaustive]
+pub struct ChildrenChangedEvent;
 
 #[non_exhaustive]
 pub struct PaintingEvent {
@@ -234,10 +681,16 @@ const ENABLED_POS: u8 = 2;
 const VISIBILITY_POS: u8 = 3;
 const ELASTIC_X_POS: u8 = 5;
 const ELASTIC_Y_POS: u8 = 6;
+const INPUT_TRANSPARENT_POS: u8 = 7;
 
 impl PrivControl for SubControlData {
-    fn set_parent(&self, parent: Weak<dyn Control>) {
-        self.parent.set(Some(parent));
+    fn set_parent(&self, parent: Option<Weak<dyn Control>>) {
+        self.parent.set(parent);
+    }
+
+    fn set_focused(&self, focused: bool) {
+        self.bit_fields.set(self.bit_fields.get().set_bit(FOCUSED_POS, focused));
+        self.repaint_later();
     }
 }
 
@@ -250,27 +703,69 @@ impl Control for SubControlData {
         u8_to_visibility(self.bit_fields.get().get_bits(VISIBILITY_POS..ELASTIC_X_POS))
     }
     fn set_visibility(&self, visibility: Visibility) {
+        let old_visibility = self.visibility();
+        if old_visibility == visibility {
+            return;
+        }
+        let was_effectively_visible = self.is_effectively_visible();
         self.bit_fields.set(
             self.bit_fields
                 .get()
                 .set_bits(VISIBILITY_POS..ELASTIC_X_POS, visibility_to_u8(visibility)),
         );
+        let effectively_visible = self.is_effectively_visible();
+
+        self.event_handlers.send(&mut VisibilityChangedEvent { visibility, effectively_visible });
+        if effectively_visible != was_effectively_visible {
+            let children = self.children.borrow().to_vec();
+            for child in children.iter() {
+                send_visibility_changed(child, effectively_visible);
+            }
+        }
+        if visibility != Visibility::Visible {
+            if let Some(focused) = focused_control() {
+                if is_hidden_in_tree(&*focused) {
+                    clear_focus();
+                }
+            }
+        }
+        // Changing to or from `Gone` changes the layout of the parent.
+        if old_visibility == Visibility::Gone || visibility == Visibility::Gone {
+            if let Some(parent) = self.parent() {
+                parent.event_handlers().send(&mut ChildrenChangedEvent);
+                parent.repaint_later();
+            }
+        }
+        self.repaint_later();
     }
 
     fn location(&self) -> Point2<f64> {
         self.location.get()
     }
     fn set_location(&self, location: &Point2<f64>) {
-        self.location.set(*location);
-        self.repaint_later();
+        if self.location.get() != *location {
+            // The area the control is moving away from has to be repainted too.
+            self.repaint_later();
+            self.location.set(*location);
+            self.repaint_later();
+        }
     }
 
     fn size(&self) -> Size2<f64> {
         self.size.get()
     }
     fn set_size(&self, size: &Size2<f64>) {
-        self.size.set(*size);
-        self.repaint_later();
+        if self.size.get() != *size {
+            // When shrinking, the part of the old area outside the new one has to be repainted.
+            self.repaint_later();
+            self.size.set(*size);
+            self.event_handlers.send(&mut SizeChangedEvent);
+            self.repaint_later();
+        }
+    }
+
+    fn best_size(&self) -> Size2<f64> {
+        self.best_size.get().unwrap_or_else(|| self.size())
     }
 
     fn tab_index(&self) -> u16 {
@@ -280,7 +775,41 @@ impl Control for SubControlData {
         self.tab_index.set(tab_index)
     }
 
-    fn children(&self) -> &RefCell<ChildrenVec> {
+    fn enabled(&self) -> bool {
+        self.bit_fields.get().get_bit(ENABLED_POS)
+    }
+    fn set_enabled(&self, enabled: bool) {
+        if self.enabled() == enabled {
+            return;
+        }
+        self.bit_fields.set(self.bit_fields.get().set_bit(ENABLED_POS, enabled));
+
+        // If an ancestor is disabled, the effective state of this subtree doesn't change.
+        if !self.parent().map_or(true, |p| p.is_effectively_enabled()) {
+            return;
+        }
+        notify_enabled_changed(self, enabled);
+    }
+
+    fn focusable(&self) -> bool {
+        self.bit_fields.get().get_bit(FOCUSABLE_POS)
+    }
+    fn set_focusable(&self, focusable: bool) {
+        self.bit_fields.set(self.bit_fields.get().set_bit(FOCUSABLE_POS, focusable));
+    }
+
+    fn focused(&self) -> bool {
+        self.bit_fields.get().get_bit(FOCUSED_POS)
+    }
+
+    fn input_transparent(&self) -> bool {
+        self.bit_fields.get().get_bit(INPUT_TRANSPARENT_POS)
+    }
+    fn set_input_transparent(&self, transparent: bool) {
+        self.bit_fields.set(self.bit_fields.get().set_bit(INPUT_TRANSPARENT_POS, transparent));
+    }
+
+    fn children(&self) -> &Children {
         &self.children
     }
 
@@ -301,6 +830,10 @@ impl Control for SubControlData {
     }
 
     fn repaint_later(&self) {
+        if let Some(window) = self.window() {
+            let location = self.to_window_coords(Point2::new(0.0, 0.0));
+            window.backend.invalidate_area(location, self.size());
+        }
     }
 
     fn dispatch_painting(&self, event: &mut PaintingEvent) {
@@ -308,7 +841,7 @@ impl Control for SubControlData {
         for child in self.children().borrow().iter() {
             event.painter.save();
             event.painter.translate(child.location().x, child.location().y);
-            child.event_handlers().send(event);
+            child.dispatch_painting(event);
             event.painter.restore();
         }
     }
@@ -321,12 +854,27 @@ impl EventHandler for SubControlData {
 
 pub trait SubControlRef {
     fn sub_control_ref(&self) -> &SubControlData;
+
+    // These are here instead of only in `Control` because the blanket implementation of
+    // `Control` can't be specialized. Controls override these ones instead.
+
+    fn hit_test(&self, x: f64, y: f64) -> bool {
+        self.sub_control_ref().hit_test(x, y)
+    }
+
+    fn best_size(&self) -> Size2<f64> {
+        self.sub_control_ref().best_size()
+    }
 }
 
 impl<T> PrivControl for T where T: SubControlRef {
-    fn set_parent(&self, parent: Weak<dyn Control>) {
+    fn set_parent(&self, parent: Option<Weak<dyn Control>>) {
         self.sub_control_ref().set_parent(parent)
     }
+
+    fn set_focused(&self, focused: bool) {
+        self.sub_control_ref().set_focused(focused)
+    }
 }
 
 impl<T> Control for T where T: SubControlRef {
@@ -362,7 +910,40 @@ impl<T> Control for T where T: SubControlRef {
         self.sub_control_ref().set_tab_index(tab_index)
     }
 
-    fn children(&self) -> &RefCell<ChildrenVec> {
+    fn enabled(&self) -> bool {
+        self.sub_control_ref().enabled()
+    }
+    fn set_enabled(&self, enabled: bool) {
+        self.sub_control_ref().set_enabled(enabled)
+    }
+
+    fn focusable(&self) -> bool {
+        self.sub_control_ref().focusable()
+    }
+    fn set_focusable(&self, focusable: bool) {
+        self.sub_control_ref().set_focusable(focusable)
+    }
+
+    fn focused(&self) -> bool {
+        self.sub_control_ref().focused()
+    }
+
+    fn best_size(&self) -> Size2<f64> {
+        SubControlRef::best_size(self)
+    }
+
+    fn hit_test(&self, x: f64, y: f64) -> bool {
+        SubControlRef::hit_test(self, x, y)
+    }
+
+    fn input_transparent(&self) -> bool {
+        self.sub_control_ref().input_transparent()
+    }
+    fn set_input_transparent(&self, transparent: bool) {
+        self.sub_control_ref().set_input_transparent(transparent)
+    }
+
+    fn children(&self) -> &Children {
         self.sub_control_ref().children()
     }
 
@@ -393,7 +974,8 @@ impl SubControlData {
         SubControlData {
             location: Cell::new(Point2::new(0.0, 0.0)),
             size: Cell::new(Size2::new(50.0, 50.0)),
-            children: RefCell::new(ChildrenVec::new()),
+            best_size: Cell::new(None),
+            children: Children::new(),
             parent: Cell::new(None),
             event_handlers: EventHandlerVec::new(),
             tab_index: Cell::new(0),
@@ -411,12 +993,20 @@ impl SubControlData {
         &self.event_handlers
     }
 
-    pub fn focusable(&self) -> bool {
-        self.bit_fields.get().get_bit(FOCUSABLE_POS)
+    /// Sets the size that `best_size()` returns. It is for controls that have no content to
+    /// measure, such as a plain `SubControl` used as a container or placeholder. If it is `None`,
+    /// which is the default, `best_size()` returns the current size.
+    pub fn set_best_size(&self, size: Option<Size2<f64>>) {
+        self.best_size.set(size);
+        self.best_size_changed();
     }
 
-    pub fn set_focusable(&self, focusable: bool) {
-        self.bit_fields.set(self.bit_fields.get().set_bit(FOCUSABLE_POS, focusable));
+    /// Sends a `ChildrenChangedEvent` to the parent, so that a layout container lays out its
+    /// children again. Controls call it after a change to what they show changes `best_size()`.
+    pub fn best_size_changed(&self) {
+        if let Some(parent) = self.parent() {
+            parent.event_handlers().send(&mut ChildrenChangedEvent);
+        }
     }
 }
 
@@ -426,8 +1016,326 @@ impl Default for SubControlData {
     }
 }
 
+// Called after a control's effective enabled state changed because it was enabled or disabled
+// itself. Sends an `EnabledChangedEvent` to it and its descendants, and takes the focus and the
+// mouse capture away from the subtree if it was disabled.
+pub(crate) fn notify_enabled_changed(control: &dyn Control, enabled: bool) {
+    let mut event = EnabledChangedEvent { enabled };
+    control.event_handlers().send(&mut event);
+    let children = control.children().borrow().to_vec();
+    for child in children.iter() {
+        send_enabled_changed(child, &mut event);
+    }
+    if !enabled {
+        if let Some(focused) = focused_control() {
+            if !focused.is_effectively_enabled() {
+                clear_focus();
+            }
+        }
+        check_mouse_capture();
+    }
+    control.repaint_later();
+}
+
+// Sends an `EnabledChangedEvent` to a control and its descendants, skipping subtrees that are
+// disabled themselves since their effective state doesn't change.
+fn send_enabled_changed(control: &Rc<dyn Control>, event: &mut EnabledChangedEvent) {
+    if !control.enabled() {
+        return;
+    }
+    control.event_handlers().send(event);
+    let children = control.children().borrow().to_vec();
+    for child in children.iter() {
+        send_enabled_changed(child, event);
+    }
+}
+
+// Sends a `VisibilityChangedEvent` to a control and its descendants after the effective
+// visibility of an ancestor changed, skipping subtrees that are hidden themselves.
+pub(crate) fn send_visibility_changed(control: &Rc<dyn Control>, effectively_visible: bool) {
+    let visibility = control.visibility();
+    if visibility != Visibility::Visible {
+        return;
+    }
+    control.event_handlers().send(&mut VisibilityChangedEvent { visibility, effectively_visible });
+    let children = control.children().borrow().to_vec();
+    for child in children.iter() {
+        send_visibility_changed(child, effectively_visible);
+    }
+}
+
+// Returns whether the control or one of its ancestors below the window isn't `Visible`. Unlike
+// `is_effectively_visible()`, this doesn't require the control to be in a visible window.
+fn is_hidden_in_tree(control: &dyn Control) -> bool {
+    if control.as_window().is_some() {
+        return false;
+    }
+    control.visibility() != Visibility::Visible ||
+        control.parent().map_or(false, |p| is_hidden_in_tree(&*p))
+}
+
+// Sends an event to a control and then to each of its ancestors until one of them handles it.
+// Returns whether the event was handled.
+pub(crate) fn send_bubbling(control: &Rc<dyn Control>, event: &mut dyn Any) -> bool {
+    let mut target = Some(control.clone());
+    while let Some(c) = target {
+        if c.event_handlers().send(event) {
+            return true;
+        }
+        target = c.parent();
+    }
+    false
+}
+
+// Returns the handle that the control was registered with, which can be used to get a reference
+// to the control from a callback.
+pub(crate) fn control_handle(control: &dyn Control) -> Option<Rc<dyn Control>> {
+    control.children().borrow().control.as_ref().and_then(|c| c.upgrade())
+}
+
+// Sends a mouse or keyboard event to a control unless it is disabled.
+pub(crate) fn send_input_event(control: &Rc<dyn Control>, event: &mut dyn Any) {
+    if control.is_effectively_enabled() {
+        control.event_handlers().send(event);
+    }
+}
+
+struct MouseCapture {
+    control: Weak<dyn Control>,
+    // An implicit capture is started by pressing a mouse button and ends when all buttons are
+    // released. An explicit one lasts until `release_mouse()` is called.
+    explicit: bool,
+}
+
 thread_local! {
     static HOT_CONTROL: RefCell<Option<Weak<dyn Control>>> = Default::default();
+    static FOCUSED_CONTROL: RefCell<Option<Weak<dyn Control>>> = Default::default();
+    static MOUSE_CAPTURE: RefCell<Option<MouseCapture>> = Default::default();
+    // A bit for each `MouseButton` that is currently pressed
+    static PRESSED_MOUSE_BUTTONS: Cell<u8> = Cell::new(0);
+}
+
+/// Returns the control that has captured the mouse, if any.
+pub fn mouse_capture() -> Option<Rc<dyn Control>> {
+    MOUSE_CAPTURE.with(|capture| {
+        capture.borrow().as_ref().and_then(|c| c.control.upgrade())
+    })
+}
+
+/// Sends all mouse events to the specified control, even when the mouse is outside it, until
+/// `release_mouse()` is called. This is useful for dragging, such as the thumb of a scroll bar.
+///
+/// Pressing a mouse button on a control captures the mouse implicitly until the button is
+/// released, so it usually isn't necessary to call this function.
+pub fn capture_mouse(control: &Rc<dyn Control>) {
+    set_mouse_capture(Some(control), true);
+}
+
+/// Stops sending all mouse events to the control that captured the mouse.
+pub fn release_mouse() {
+    set_mouse_capture(None, false);
+    WindowBackend::release_mouse_capture();
+}
+
+fn set_mouse_capture(control: Option<&Rc<dyn Control>>, explicit: bool) {
+    let old = MOUSE_CAPTURE.with(|capture| {
+        capture.borrow_mut().take().and_then(|c| c.control.upgrade())
+    });
+    MOUSE_CAPTURE.with(|capture| {
+        *capture.borrow_mut() =
+            control.map(|c| MouseCapture { control: Rc::downgrade(c), explicit });
+    });
+    if let Some(old) = old {
+        if control.map_or(true, |c| !is_same_control(&old, c)) {
+            old.event_handlers().send(&mut MouseCaptureLostEvent);
+        }
+    }
+}
+
+// Called by the backends when the platform takes the mouse capture away, such as when another
+// application is activated in the middle of a drag. The buttons are treated as released, since the
+// button up events won't be received.
+pub(crate) fn dispatch_mouse_capture_lost() {
+    PRESSED_MOUSE_BUTTONS.with(|pressed| pressed.set(0));
+    set_mouse_capture(None, false);
+}
+
+// Releases the mouse capture if the capturing control was disabled or removed from its window.
+fn check_mouse_capture() {
+    if let Some(captured) = mouse_capture() {
+        if !captured.is_effectively_enabled() || captured.window().is_none() {
+            release_mouse();
+        }
+    }
+}
+
+fn mouse_button_bit(button: MouseButton) -> u8 {
+    1 << button as u8
+}
+
+// Returns the control that mouse events at the location, in the window's coordinates, are sent
+// to.
+fn mouse_target(window: &dyn Control, location: Point2<f64>) -> Option<Rc<dyn Control>> {
+    mouse_capture().or_else(|| window.descendant_at_point(location.x, location.y))
+}
+
+// Converts a location in the window's coordinates to the target's coordinates. The target may be
+// in a different window if it captured the mouse.
+fn to_target_coords(window: &dyn Control, target: &Rc<dyn Control>, location: Point2<f64>)
+    -> Point2<f64>
+{
+    window.map_point_to(&**target, location).unwrap_or_else(|| target.from_window_coords(location))
+}
+
+// When the mouse is captured, only the capturing control can be hot, and only while the mouse is
+// over it.
+fn update_hot_control(window: &dyn Control, location: Point2<f64>) {
+    let hot = match mouse_capture() {
+        Some(captured) => {
+            let pt = to_target_coords(window, &captured, location);
+            if captured.hit_test(pt.x, pt.y) { Some(captured) } else { None }
+        }
+        None => window.descendant_at_point(location.x, location.y),
+    };
+    set_hot_control(hot.as_ref());
+}
+
+// The following functions are called by the backends with the mouse location in the window's
+// coordinates.
+
+pub(crate) fn dispatch_mouse_down(
+    window: &dyn Control,
+    location: Point2<f64>,
+    button: MouseButton,
+    modifiers: Modifiers,
+) {
+    PRESSED_MOUSE_BUTTONS.with(|pressed| pressed.set(pressed.get() | mouse_button_bit(button)));
+    let popups = open_popups();
+    if let Some(target) = mouse_target(window, location) {
+        // A disabled control doesn't receive the press, so it doesn't capture the mouse either.
+        if mouse_capture().is_none() && target.is_effectively_enabled() {
+            set_mouse_capture(Some(&target), false);
+        }
+        let location = to_target_coords(window, &target, location);
+        send_input_event(&target, &mut MouseDownEvent { location, button, modifiers });
+    }
+    dismiss_popups(&popups, window);
+}
+
+pub(crate) fn dispatch_mouse_up(
+    window: &dyn Control,
+    location: Point2<f64>,
+    button: MouseButton,
+) {
+    let pressed = PRESSED_MOUSE_BUTTONS.with(|pressed| {
+        pressed.set(pressed.get() & !mouse_button_bit(button));
+        pressed.get()
+    });
+    if let Some(target) = mouse_target(window, location) {
+        let target_location = to_target_coords(window, &target, location);
+        send_input_event(&target, &mut MouseUpEvent { location: target_location, button });
+    }
+    // Like on Windows, the context menu is requested when the button is released, from the
+    // control under the mouse even if another one captured it.
+    if button == MouseButton::Right {
+        if let Some(control) = window.descendant_at_point(location.x, location.y) {
+            let location = to_target_coords(window, &control, location);
+            send_context_menu_requested(control, location);
+        }
+    }
+    if pressed == 0 {
+        let implicit = MOUSE_CAPTURE.with(|capture| {
+            capture.borrow().as_ref().map_or(false, |c| !c.explicit)
+        });
+        if implicit {
+            MOUSE_CAPTURE.with(|capture| *capture.borrow_mut() = None);
+        }
+    }
+    update_hot_control(window, location);
+}
+
+pub(crate) fn dispatch_mouse_moved(window: &dyn Control, location: Point2<f64>) {
+    update_hot_control(window, location);
+    let dragging = PRESSED_MOUSE_BUTTONS.with(|pressed| pressed.get() != 0);
+    if let Some(target) = mouse_target(window, location) {
+        let location = to_target_coords(window, &target, location);
+        if dragging {
+            send_input_event(&target, &mut MouseDraggedEvent { location });
+        } else {
+            send_input_event(&target, &mut MouseMovedEvent { location });
+        }
+    }
+}
+
+// The wheel scrolls what the mouse is over, so the event isn't sent to the control capturing the
+// mouse. The event's location is in the window's coordinates.
+pub(crate) fn dispatch_mouse_wheel(window: &dyn Control, mut event: MouseWheelEvent) {
+    let location = event.location;
+    let mut target = window.descendant_at_point(location.x, location.y);
+    while let Some(control) = target {
+        if control.is_effectively_enabled() {
+            event.location = to_target_coords(window, &control, location);
+            if control.event_handlers().send(&mut event) {
+                return;
+            }
+        }
+        target = control.parent();
+    }
+}
+
+// Sends a `ContextMenuRequestedEvent` to the control and then to its ancestors until one handles
+// it, converting the location to each one's coordinates. Returns whether it was handled.
+pub(crate) fn send_context_menu_requested(control: Rc<dyn Control>, location: Point2<f64>) -> bool {
+    let mut event = ContextMenuRequestedEvent { location };
+    let mut target = Some(control);
+    while let Some(control) = target {
+        if control.is_effectively_enabled() && control.event_handlers().send(&mut event) {
+            return true;
+        }
+        let offset = control.location();
+        event.location = Point2::new(event.location.x + offset.x, event.location.y + offset.y);
+        target = control.parent();
+    }
+    false
+}
+
+pub(crate) fn dispatch_mouse_left_window() {
+    if mouse_capture().is_none() {
+        set_hot_control(None);
+    }
+}
+
+/// Returns the control that has the keyboard focus, if any.
+pub fn focused_control() -> Option<Rc<dyn Control>> {
+    FOCUSED_CONTROL.with(|focused| focused.borrow().as_ref().and_then(|c| c.upgrade()))
+}
+
+/// Gives the keyboard focus to the specified control. Returns `false` and leaves the focus
+/// unchanged if the control isn't focusable, isn't effectively enabled, or is hidden.
+pub fn set_focus(control: &Rc<dyn Control>) -> bool {
+    if !control.focusable() || !control.is_effectively_enabled() || is_hidden_in_tree(&**control) {
+        return false;
+    }
+    if let Some(old) = focused_control() {
+        if is_same_control(&old, control) {
+            return true;
+        }
+    }
+    clear_focus();
+    FOCUSED_CONTROL.with(|focused| *focused.borrow_mut() = Some(Rc::downgrade(control)));
+    control.set_focused(true);
+    control.event_handlers().send(&mut FocusGainedEvent);
+    true
+}
+
+/// Removes the keyboard focus from the control that has it.
+pub fn clear_focus() {
+    // Don't hold the borrow while sending the event, since a handler may set the focus.
+    let old = FOCUSED_CONTROL.with(|focused| focused.borrow_mut().take());
+    if let Some(old) = old.and_then(|c| c.upgrade()) {
+        old.set_focused(false);
+        old.event_handlers().send(&mut FocusLostEvent);
+    }
 }
 
 pub(crate) fn get_hot_control() -> Option<Weak<dyn Control>> {
@@ -455,6 +1363,16 @@ pub(crate) fn set_hot_control(control: Option<&Rc<dyn Control>>) {
 }
 
 
+// Sends an event to a control, then to each of its descendants, parents before children.
+pub(crate) fn send_to_subtree(control: &Rc<dyn Control>, event: &mut dyn Any) {
+    control.event_handlers().send(event);
+    // Copy the list so that handlers can add and remove children.
+    let children = control.children().borrow().to_vec();
+    for child in children.iter() {
+        send_to_subtree(child, event);
+    }
+}
+
 pub fn set_tab_order<'a, I>(start_index: u16, controls: I)
 where
     I: Iterator<Item = &'a Rc<dyn Control>>, // TODO: I'm not sure what the `Item` type should be
@@ -477,3 +1395,302 @@ fn test_set_tab_order() {
     assert_eq!(child0.tab_index(), 4);
     assert_eq!(child1.tab_index(), 5);
 }
+
+#[test]
+fn test_reparent() {
+    let parent0 = SubControl::new();
+    let parent1 = SubControl::new();
+    let child = SubControl::new();
+    parent0.children().borrow_mut().push(child.clone());
+    parent1.children().borrow_mut().push(child.clone());
+    assert_eq!(parent0.children().borrow().len(), 0);
+    assert_eq!(parent1.children().borrow().len(), 1);
+    let child_rc: Rc<dyn Control> = child.clone().into();
+    let parent1_rc: Rc<dyn Control> = parent1.clone().into();
+    assert!(is_same_control(&child_rc.parent().unwrap(), &parent1_rc));
+
+    // Pushing to the same parent again moves the control to the end.
+    let child2 = SubControl::new();
+    parent1.children().borrow_mut().push(child2.clone());
+    parent1.children().borrow_mut().push(child.clone());
+    assert_eq!(parent1.children().borrow().len(), 2);
+    assert!(is_same_control(&parent1.children().borrow()[1], &child_rc));
+
+    parent1.children().borrow_mut().clear();
+    assert!(child_rc.parent().is_none());
+}
+
+#[test]
+#[should_panic(expected = "can't move a control from a parent whose children are borrowed")]
+fn test_push_from_borrowed_parent() {
+    let parent0 = SubControl::new();
+    let parent1 = SubControl::new();
+    parent0.children().borrow_mut().push(SubControl::new());
+    for child in parent0.children().borrow().iter() {
+        parent1.children().borrow_mut().push(child.clone());
+    }
+}
+
+#[test]
+#[should_panic(expected = "can't add a control as a child of itself")]
+fn test_push_cycle() {
+    let parent = SubControl::new();
+    let child = SubControl::new();
+    parent.children().borrow_mut().push(child.clone());
+    child.children().borrow_mut().push(parent.clone());
+}
+
+#[test]
+fn test_coords() {
+    let root = SubControl::new();
+    let parent = SubControl::new();
+    let child = SubControl::new();
+    let other = SubControl::new();
+    parent.set_location(&Point2::new(10.0, 20.0));
+    child.set_location(&Point2::new(3.0, 4.0));
+    other.set_location(&Point2::new(100.0, 100.0));
+    root.children().borrow_mut().push(parent.clone());
+    root.children().borrow_mut().push(other.clone());
+    parent.children().borrow_mut().push(child.clone());
+
+    let pt = child.to_window_coords(Point2::new(1.0, 1.0));
+    assert_eq!((pt.x, pt.y), (14.0, 25.0));
+    let pt = child.from_window_coords(Point2::new(14.0, 25.0));
+    assert_eq!((pt.x, pt.y), (1.0, 1.0));
+    let pt = child.map_point_to(&**other, Point2::new(0.0, 0.0)).unwrap();
+    assert_eq!((pt.x, pt.y), (-87.0, -76.0));
+    assert!(child.to_screen_coords(Point2::new(0.0, 0.0)).is_none());
+}
+
+#[test]
+fn test_descendant_at_point() {
+    let root = SubControl::new();
+    let bottom = SubControl::new();
+    let top = SubControl::new();
+    let top_child = SubControl::new();
+    bottom.set_size(&Size2::new(50.0, 50.0));
+    top.set_location(&Point2::new(25.0, 25.0));
+    top.set_size(&Size2::new(50.0, 50.0));
+    top_child.set_size(&Size2::new(10.0, 10.0));
+    root.set_size(&Size2::new(100.0, 100.0));
+    root.children().borrow_mut().push(bottom.clone());
+    root.children().borrow_mut().push(top.clone());
+    top.children().borrow_mut().push(top_child.clone());
+
+    let bottom: Rc<dyn Control> = bottom.into();
+    let top: Rc<dyn Control> = top.into();
+    let top_child: Rc<dyn Control> = top_child.into();
+    let at = |x, y| root.descendant_at_point(x, y);
+
+    assert!(is_same_control(&at(30.0, 30.0).unwrap(), &top_child));
+    assert!(is_same_control(&at(40.0, 40.0).unwrap(), &top));
+    assert!(is_same_control(&at(10.0, 10.0).unwrap(), &bottom));
+    assert!(at(90.0, 10.0).is_none());
+
+    top.set_input_transparent(true);
+    assert!(is_same_control(&at(40.0, 40.0).unwrap(), &bottom));
+    assert!(is_same_control(&at(30.0, 30.0).unwrap(), &top_child));
+
+    top.set_visibility(Visibility::Invisible);
+    assert!(is_same_control(&at(30.0, 30.0).unwrap(), &bottom));
+}
+
+#[test]
+fn test_enabled() {
+    let parent = SubControl::new();
+    let child = SubControl::new();
+    let grandchild = SubControl::new();
+    parent.children().borrow_mut().push(child.clone());
+    child.children().borrow_mut().push(grandchild.clone());
+    grandchild.set_focusable(true);
+
+    let changes = Rc::new(Cell::new(0));
+    let changes2 = changes.clone();
+    grandchild.event_handlers().add(move |route| {
+        if let Some(EnabledChangedEvent { .. }) = route.event.downcast_mut() {
+            changes2.set(changes2.get() + 1);
+        }
+    });
+
+    let grandchild: Rc<dyn Control> = grandchild.into();
+    assert!(set_focus(&grandchild));
+    assert!(grandchild.focused());
+
+    parent.set_enabled(false);
+    assert!(grandchild.enabled());
+    assert!(!grandchild.is_effectively_enabled());
+    assert_eq!(changes.get(), 1);
+    assert!(!grandchild.focused());
+    assert!(focused_control().is_none());
+    assert!(!set_focus(&grandchild));
+
+    // The child's subtree is already disabled by the parent, so nothing changes.
+    child.set_enabled(false);
+    parent.set_enabled(true);
+    assert_eq!(changes.get(), 1);
+    child.set_enabled(true);
+    assert_eq!(changes.get(), 2);
+    assert!(grandchild.is_effectively_enabled());
+}
+
+#[test]
+fn test_visibility() {
+    let parent = SubControl::new();
+    let child = SubControl::new();
+    parent.children().borrow_mut().push(child.clone());
+    child.set_focusable(true);
+
+    // Not in a window
+    assert!(!child.is_effectively_visible());
+
+    let events = Rc::new(RefCell::new(vec![]));
+    let events2 = events.clone();
+    child.event_handlers().add(move |route| {
+        if let Some(event) = route.event.downcast_mut::<VisibilityChangedEvent>() {
+            events2.borrow_mut().push(event.visibility);
+        }
+    });
+
+    let child_rc: Rc<dyn Control> = child.clone().into();
+    assert!(set_focus(&child_rc));
+    child.set_visibility(Visibility::Invisible);
+    assert!(focused_control().is_none());
+    assert!(!set_focus(&child_rc));
+    child.set_visibility(Visibility::Invisible);
+    child.set_visibility(Visibility::Visible);
+    parent.set_visibility(Visibility::Gone);
+    assert_eq!(*events.borrow(), vec![Visibility::Invisible, Visibility::Visible]);
+}
+
+#[test]
+fn test_attach_detach_events() {
+    let window = Window::new();
+    let root = SubControl::new();
+    let child = SubControl::new();
+    let extra = SubControl::new();
+    root.children().borrow_mut().push(child.clone());
+
+    // The events are sent after the lists are no longer borrowed, so the handler can change its
+    // parent's children and look at its window's children.
+    let events = Rc::new(RefCell::new(vec![]));
+    let (events2, extra2) = (events.clone(), extra.clone());
+    let (root2, window2) = (Rc::downgrade(&root.0), Rc::downgrade(&window.0));
+    child.event_handlers().add(move |route| {
+        let (root, window) = (root2.upgrade().unwrap(), window2.upgrade().unwrap());
+        let lens = (root.children().borrow().len(), window.children().borrow().len());
+        if let Some(_) = route.event.downcast_mut::<AttachedToWindowEvent>() {
+            events2.borrow_mut().push(("attached", lens));
+            root.children().borrow_mut().push(extra2.clone());
+        } else if let Some(_) = route.event.downcast_mut::<DetachedFromWindowEvent>() {
+            events2.borrow_mut().push(("detached", lens));
+            root.children().borrow_mut().remove_control(&extra2.clone().into());
+        }
+    });
+
+    window.set_child(root.clone().into());
+    assert!(extra.window().is_some());
+    window.set_child(SubControl::new().into());
+    assert_eq!(*events.borrow(), vec![("attached", (1, 1)), ("detached", (2, 1))]);
+    assert_eq!(root.children().borrow().len(), 1);
+}
+
+#[test]
+fn test_mouse_capture() {
+    let root = SubControl::new();
+    let child = SubControl::new();
+    root.set_size(&Size2::new(100.0, 100.0));
+    child.set_location(&Point2::new(10.0, 10.0));
+    child.set_size(&Size2::new(20.0, 20.0));
+    root.children().borrow_mut().push(child.clone());
+
+    let events = Rc::new(RefCell::new(vec![]));
+    let events2 = events.clone();
+    child.event_handlers().add(move |route| {
+        if let Some(event) = route.event.downcast_mut::<MouseDraggedEvent>() {
+            events2.borrow_mut().push(("dragged", event.location.x));
+        } else if let Some(event) = route.event.downcast_mut::<MouseUpEvent>() {
+            events2.borrow_mut().push(("up", event.location.x));
+        } else if let Some(MouseCaptureLostEvent) = route.event.downcast_mut() {
+            events2.borrow_mut().push(("lost", 0.0));
+        }
+    });
+
+    // The press implicitly captures the mouse until the button is released.
+    dispatch_mouse_down(&**root, Point2::new(15.0, 15.0), MouseButton::Left, Modifiers::none());
+    dispatch_mouse_moved(&**root, Point2::new(80.0, 15.0));
+    dispatch_mouse_up(&**root, Point2::new(90.0, 15.0), MouseButton::Left);
+    assert!(mouse_capture().is_none());
+    assert_eq!(*events.borrow(), vec![("dragged", 70.0), ("up", 80.0)]);
+
+    let child: Rc<dyn Control> = child.into();
+    events.borrow_mut().clear();
+    capture_mouse(&child);
+    dispatch_mouse_down(&**root, Point2::new(50.0, 50.0), MouseButton::Left, Modifiers::none());
+    dispatch_mouse_up(&**root, Point2::new(50.0, 50.0), MouseButton::Left);
+    assert!(is_same_control(&mouse_capture().unwrap(), &child));
+    child.set_enabled(false);
+    assert!(mouse_capture().is_none());
+    assert_eq!(*events.borrow(), vec![("up", 40.0), ("lost", 0.0)]);
+
+    // A disabled control doesn't capture the mouse when pressed.
+    dispatch_mouse_down(&**root, Point2::new(15.0, 15.0), MouseButton::Left, Modifiers::none());
+    assert!(mouse_capture().is_none());
+    dispatch_mouse_up(&**root, Point2::new(15.0, 15.0), MouseButton::Left);
+}
+
+#[test]
+fn test_mouse_capture_lost() {
+    let root = SubControl::new();
+    let child = SubControl::new();
+    root.set_size(&Size2::new(100.0, 100.0));
+    child.set_size(&Size2::new(20.0, 20.0));
+    root.children().borrow_mut().push(child.clone());
+    let events = Rc::new(RefCell::new(vec![]));
+    let events2 = events.clone();
+    child.event_handlers().add(move |route| {
+        if let Some(_) = route.event.downcast_mut::<MouseDraggedEvent>() {
+            events2.borrow_mut().push("dragged");
+        } else if let Some(_) = route.event.downcast_mut::<MouseMovedEvent>() {
+            events2.borrow_mut().push("moved");
+        } else if let Some(MouseCaptureLostEvent) = route.event.downcast_mut() {
+            events2.borrow_mut().push("lost");
+        }
+    });
+
+    // When the platform takes the capture in the middle of a drag, the drag ends.
+    dispatch_mouse_down(&**root, Point2::new(5.0, 5.0), MouseButton::Left, Modifiers::none());
+    dispatch_mouse_moved(&**root, Point2::new(10.0, 5.0));
+    dispatch_mouse_capture_lost();
+    assert!(mouse_capture().is_none());
+    dispatch_mouse_moved(&**root, Point2::new(15.0, 5.0));
+    assert_eq!(*events.borrow(), vec!["dragged", "lost", "moved"]);
+}
+
+// The invalidated areas are only recorded by the headless backend.
+#[cfg(feature = "headless")]
+#[test]
+fn test_moving_repaints_old_area() {
+    use crate::backend::take_invalidated_areas;
+
+    let window = Window::new();
+    let root = SubControl::new();
+    root.set_size(&Size2::new(100.0, 100.0));
+    let child = SubControl::new();
+    child.set_size(&Size2::new(20.0, 10.0));
+    root.children().borrow_mut().push(child.clone());
+    window.set_child(root.into());
+    take_invalidated_areas(&window);
+
+    child.set_location(&Point2::new(30.0, 40.0));
+    assert_eq!(take_invalidated_areas(&window), vec![
+        (Point2::new(0.0, 0.0), Size2::new(20.0, 10.0)),
+        (Point2::new(30.0, 40.0), Size2::new(20.0, 10.0)),
+    ]);
+    child.set_size(&Size2::new(5.0, 5.0));
+    assert_eq!(take_invalidated_areas(&window), vec![
+        (Point2::new(30.0, 40.0), Size2::new(20.0, 10.0)),
+        (Point2::new(30.0, 40.0), Size2::new(5.0, 5.0)),
+    ]);
+    child.set_location(&Point2::new(30.0, 40.0));
+    assert!(take_invalidated_areas(&window).is_empty());
+}
@@ -50,7 +50,9 @@ impl EventHandlerVec {
     // In this situation, the handler will be skipped and all the other handlers will be called. To
     // send an event to the current control without skipping the handler, add the event to the
     // `EventRoute::self_events` list.
-    pub fn send(&self, event: &mut dyn Any) {
+    //
+    // Returns whether one of the handlers set `EventRoute::handled`.
+    pub fn send(&self, event: &mut dyn Any) -> bool {
         let event_handlers = self.0.borrow().clone();
         let mut route = EventRoute { event, handled: false, self_events: SmallVec::new() };
         // Call more recently added handlers first so that they can override the behavior of those
@@ -67,9 +69,11 @@ impl EventHandlerVec {
                 }
             }
         }
+        let handled = route.handled;
         for mut event in route.self_events.into_iter() {
             self.send(&mut event);
         }
+        handled
     }
 }
 
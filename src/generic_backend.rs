@@ -5,7 +5,8 @@
  *
  */
 
-use std::rc::Weak;
+use std::rc::{Rc, Weak};
+use std::time::Duration;
 
 use zaffre::{Point2, Size2};
 
@@ -22,6 +23,25 @@ pub trait GenericCursorBackend {
     fn text() -> Self;
 }
 
+pub trait GenericClipboardBackend {
+    /// Returns the text on the system clipboard, or `None` if it doesn't contain text.
+    fn text() -> Option<String>;
+
+    fn set_text(text: &str);
+}
+
+pub trait GenericTimerBackend {
+    fn new() -> Self;
+
+    /// Starts calling `callback` every `interval` until `stop()` is called. If the timer is
+    /// already running, it is restarted.
+    fn start(&self, interval: Duration, callback: Rc<dyn Fn()>);
+
+    fn stop(&self);
+
+    fn is_running(&self) -> bool;
+}
+
 pub trait GenericWindowBackend {
     fn new() -> Self;
 
@@ -29,19 +49,50 @@ pub trait GenericWindowBackend {
 
     fn window(&self) -> Window;
 
-    //fn location(&self) -> Point2<f64>;
-    //fn set_location(&mut self, location: &Point2<f64>);
-    //fn size(&self) -> Size2<f64>;
-    //fn set_size(&mut self, size: &Size2<f64>);
     fn set_text(&self, text: &str);
 
     fn visibility(&self) -> Visibility;
 
     fn set_visibility(&self, visibility: Visibility);
 
+    fn location(&self) -> Point2<f64>;
+
     fn set_location(&self, location: &Point2<f64>);
 
+    fn size(&self) -> Size2<f64>;
+
+    fn set_size(&self, size: &Size2<f64>);
+
+    /// Returns the location of the top-left corner of the window's client area in screen
+    /// coordinates, or `None` if the native window hasn't been created yet.
+    fn client_origin(&self) -> Option<Point2<f64>>;
+
+    fn enabled(&self) -> bool;
+
+    /// Sets whether the window accepts mouse and keyboard input. The owner of a modal dialog is
+    /// disabled while the dialog is shown.
+    fn set_enabled(&self, enabled: bool);
+
     fn resizable(&self) -> bool;
 
     fn set_resizable(&self, resizable: bool);
+
+    /// Sets the window that owns this one, which it stays above.
+    fn set_owner(&self, owner: Option<&Window>);
+
+    /// Makes the window a popup that has no border, stays on top, and isn't activated when shown
+    /// or clicked. It is only called before the window is shown.
+    fn set_popup(&self, popup: bool);
+
+    /// Stops the native mouse capture of whichever window has it, so that mouse events outside
+    /// the application's windows are no longer received.
+    fn release_mouse_capture();
+
+    /// Makes the platform repaint the window's client area soon. Several calls before the
+    /// repaint only cause one.
+    fn invalidate(&self);
+
+    /// Like `invalidate()`, but only the area at `location` with `size` in client coordinates is
+    /// repainted.
+    fn invalidate_area(&self, location: Point2<f64>, size: Size2<f64>);
 }
@@ -0,0 +1,44 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ *
+ */
+
+use std::ffi::CStr;
+use std::os::raw::c_int;
+
+use gdk_sys::gdk_atom_intern_static_string;
+use glib_sys::{g_free, gpointer};
+use gtk_sys::{GtkClipboard, gtk_clipboard_get, gtk_clipboard_set_text, gtk_clipboard_wait_for_text};
+
+use crate::generic_backend::GenericClipboardBackend;
+
+fn clipboard() -> *mut GtkClipboard {
+    unsafe {
+        gtk_clipboard_get(gdk_atom_intern_static_string(b"CLIPBOARD\0".as_ptr() as *const _))
+    }
+}
+
+pub struct ClipboardBackend;
+
+impl GenericClipboardBackend for ClipboardBackend {
+    fn text() -> Option<String> {
+        unsafe {
+            // This runs a nested main loop until the owner of the clipboard responds.
+            let text = gtk_clipboard_wait_for_text(clipboard());
+            if text.is_null() {
+                return None;
+            }
+            let s = CStr::from_ptr(text).to_string_lossy().into_owned();
+            g_free(text as gpointer);
+            Some(s)
+        }
+    }
+
+    fn set_text(text: &str) {
+        unsafe {
+            gtk_clipboard_set_text(clipboard(), text.as_ptr() as *const _, text.len() as c_int);
+        }
+    }
+}
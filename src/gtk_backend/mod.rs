@@ -9,8 +9,12 @@ use std::os::raw::c_char;
 
 use smallvec::SmallVec;
 
+pub use self::clipboard_backend::ClipboardBackend;
+pub use self::timer_backend::TimerBackend;
 pub use self::window_backend::WindowBackend;
 
+pub mod clipboard_backend;
+pub mod timer_backend;
 pub mod window_backend;
 
 pub fn str_to_c_vec<'a: 'b, 'b, A: ::smallvec::Array<Item=u8>>(s: &'a str, buf: &'b mut SmallVec<A>) -> *const c_char {
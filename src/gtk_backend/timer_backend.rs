@@ -0,0 +1,61 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ *
+ */
+
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::Duration;
+
+use glib_sys::{g_source_remove, g_timeout_add_full, gboolean, gpointer, GTRUE, G_PRIORITY_DEFAULT};
+
+use crate::generic_backend::GenericTimerBackend;
+
+unsafe extern "C" fn timeout_callback(data: gpointer) -> gboolean {
+    let callback = &*(data as *const Rc<dyn Fn()>);
+    // Clone it so that it isn't freed if the callback stops the timer.
+    let callback = callback.clone();
+    callback();
+    GTRUE
+}
+
+unsafe extern "C" fn destroy_callback(data: gpointer) {
+    drop(Box::from_raw(data as *mut Rc<dyn Fn()>));
+}
+
+pub struct TimerBackend {
+    source_id: Cell<u32>,
+}
+
+impl GenericTimerBackend for TimerBackend {
+    fn new() -> Self {
+        TimerBackend {
+            source_id: Cell::new(0),
+        }
+    }
+
+    fn start(&self, interval: Duration, callback: Rc<dyn Fn()>) {
+        self.stop();
+        let ms = interval.as_millis().min(u32::MAX as u128) as u32;
+        // The box is freed by destroy_callback() when the source is removed.
+        let data = Box::into_raw(Box::new(callback)) as gpointer;
+        let id = unsafe {
+            g_timeout_add_full(
+                G_PRIORITY_DEFAULT, ms, Some(timeout_callback), data, Some(destroy_callback))
+        };
+        self.source_id.set(id);
+    }
+
+    fn stop(&self) {
+        let id = self.source_id.replace(0);
+        if id != 0 {
+            unsafe { g_source_remove(id); }
+        }
+    }
+
+    fn is_running(&self) -> bool {
+        self.source_id.get() != 0
+    }
+}
@@ -5,15 +5,24 @@
  *
  */
 
-use std::cell::Cell;
+use std::cell::{Cell, RefCell};
+use std::mem;
+use std::os::raw::{c_int, c_uint, c_ulong};
 use std::ptr;
-use std::rc::Weak;
+use std::rc::{Rc, Weak};
 
-use glib_sys::gboolean;
-use gtk_sys::{GTK_WINDOW_TOPLEVEL, GtkWidget, GtkWindow, gtk_widget_destroy, gtk_widget_hide, gtk_widget_show, gtk_window_new, gtk_window_set_resizable, gtk_window_set_title};
+use cairo_sys::cairo_t;
+use gdk_sys::{GDK_BUTTON_PRESS, GDK_BUTTON_PRESS_MASK, GDK_BUTTON_RELEASE_MASK, GDK_CONTROL_MASK, GDK_KEY_0, GDK_KEY_9, GDK_KEY_A, GDK_KEY_Alt_L, GDK_KEY_Alt_R, GDK_KEY_BackSpace, GDK_KEY_Control_L, GDK_KEY_Control_R, GDK_KEY_Delete, GDK_KEY_Down, GDK_KEY_End, GDK_KEY_Escape, GDK_KEY_F1, GDK_KEY_F12, GDK_KEY_Home, GDK_KEY_ISO_Left_Tab, GDK_KEY_Insert, GDK_KEY_KP_Enter, GDK_KEY_Left, GDK_KEY_Menu, GDK_KEY_PRESS, GDK_KEY_PRESS_MASK, GDK_KEY_Page_Down, GDK_KEY_Page_Up, GDK_KEY_RELEASE_MASK, GDK_KEY_Return, GDK_KEY_Right, GDK_KEY_Shift_L, GDK_KEY_Shift_R, GDK_KEY_Tab, GDK_KEY_Up, GDK_KEY_Z, GDK_KEY_space, GDK_LEAVE_NOTIFY_MASK, GDK_MOD1_MASK, GDK_POINTER_MOTION_MASK, GDK_SCROLL_DOWN, GDK_SCROLL_LEFT, GDK_SCROLL_MASK, GDK_SCROLL_RIGHT, GDK_SCROLL_SMOOTH, GDK_SCROLL_UP, GDK_SEAT_CAPABILITY_ALL_POINTING, GDK_SHIFT_MASK, GDK_SMOOTH_SCROLL_MASK, GDK_SOURCE_TOUCHPAD, GDK_SUPER_MASK, GdkEvent, GdkEventButton, GdkEventCrossing, GdkEventFocus, GdkEventKey, GdkEventMotion, GdkEventScroll, GdkModifierType, gdk_device_get_source, gdk_display_get_default, gdk_display_get_default_seat, gdk_event_get_scroll_deltas, gdk_event_get_source_device, gdk_event_is_scroll_stop_event, gdk_keyval_to_unicode, gdk_keyval_to_upper, gdk_seat_grab, gdk_seat_ungrab, gdk_window_get_origin};
+use glib_sys::{gboolean, gpointer, GFALSE, GTRUE};
+use gobject_sys::{GCallback, GClosure, GObject, g_signal_connect_data, g_signal_handler_disconnect};
+use gtk_sys::{GTK_WINDOW_POPUP, GTK_WINDOW_TOPLEVEL, GtkWidget, GtkWindow, gtk_widget_add_events, gtk_widget_destroy, gtk_widget_get_window, gtk_widget_hide, gtk_widget_queue_draw, gtk_widget_queue_draw_area, gtk_widget_set_sensitive, gtk_widget_show, gtk_window_get_position, gtk_window_get_size, gtk_window_move, gtk_window_new, gtk_window_resize, gtk_window_set_resizable, gtk_window_set_title, gtk_window_set_transient_for};
 use smallvec::SmallVec;
+use zaffre::{Color, Point2, RenderingBackend, Size2, SwapchainSurface};
 
-use crate::{Visibility, WindowData};
+use crate::control::{dispatch_mouse_capture_lost, dispatch_mouse_down, dispatch_mouse_left_window, dispatch_mouse_moved, dispatch_mouse_up, dispatch_mouse_wheel, PaintingEvent};
+use crate::keyboard::{dispatch_key_down, dispatch_key_up, dispatch_text_input, DIGIT_KEYS, LETTER_KEYS};
+use crate::window::{dismiss_all_popups, open_popups};
+use crate::{Control, Key, KeyDownEvent, KeyUpEvent, Modifiers, MouseButton, MouseWheelEvent, ScrollPhase, Visibility, Window, WindowData};
 use crate::generic_backend::GenericWindowBackend;
 
 use super::str_to_c_vec;
@@ -23,21 +32,418 @@ pub struct WindowBackend {
     handle: Cell<*mut GtkWidget>,
     visibility: Cell<Visibility>,
     text: Cell<String>,
+    location: Cell<Point2<f64>>,
+    size: Cell<Size2<f64>>,
+    enabled: Cell<bool>,
     resizable: Cell<bool>,
+    popup: Cell<bool>,
+    owner: RefCell<Option<Weak<WindowData>>>,
+    // Whether a touchpad scroll has begun and not yet ended
+    touchpad_scrolling: Cell<bool>,
+    // The signal handlers connected to the handle, which are disconnected before it is destroyed
+    handler_ids: RefCell<Vec<c_ulong>>,
+    // Created when the window is first drawn, as the handle has no `GdkWindow` until it's realized
+    surface: Cell<Option<SwapchainSurface>>,
+    // GTK doesn't mark repeated key presses, so they are found by comparing with the last one.
+    last_key_down: Cell<Option<c_uint>>,
+}
+
+// GTK doesn't have a setting for how many lines a notch of the mouse wheel scrolls.
+const WHEEL_SCROLL_LINES: f64 = 3.0;
+
+fn modifiers_from_state(state: GdkModifierType) -> Modifiers {
+    Modifiers {
+        shift: state & GDK_SHIFT_MASK != 0,
+        control: state & GDK_CONTROL_MASK != 0,
+        alt: state & GDK_MOD1_MASK != 0,
+        logo: state & GDK_SUPER_MASK != 0,
+    }
+}
+
+fn key_from_keyval(keyval: c_uint) -> Option<Key> {
+    // Letters are the same key whether or not Shift is held.
+    let keyval = unsafe { gdk_keyval_to_upper(keyval) } as c_int;
+    Some(match keyval {
+        GDK_KEY_Escape => Key::Escape,
+        // Shift+Tab gives a different keyval.
+        GDK_KEY_Tab | GDK_KEY_ISO_Left_Tab => Key::Tab,
+        GDK_KEY_BackSpace => Key::Backspace,
+        GDK_KEY_Return | GDK_KEY_KP_Enter => Key::Enter,
+        GDK_KEY_space => Key::Space,
+        GDK_KEY_Insert => Key::Insert,
+        GDK_KEY_Delete => Key::Delete,
+        GDK_KEY_Home => Key::Home,
+        GDK_KEY_End => Key::End,
+        GDK_KEY_Page_Up => Key::PageUp,
+        GDK_KEY_Page_Down => Key::PageDown,
+        GDK_KEY_Left => Key::Left,
+        GDK_KEY_Up => Key::Up,
+        GDK_KEY_Right => Key::Right,
+        GDK_KEY_Down => Key::Down,
+        GDK_KEY_Menu => Key::Menu,
+        GDK_KEY_Shift_L | GDK_KEY_Shift_R => Key::Shift,
+        GDK_KEY_Control_L | GDK_KEY_Control_R => Key::Control,
+        GDK_KEY_Alt_L | GDK_KEY_Alt_R => Key::Alt,
+        GDK_KEY_0..=GDK_KEY_9 => DIGIT_KEYS[(keyval - GDK_KEY_0) as usize],
+        GDK_KEY_A..=GDK_KEY_Z => LETTER_KEYS[(keyval - GDK_KEY_A) as usize],
+        GDK_KEY_F1..=GDK_KEY_F12 => [
+            Key::F1, Key::F2, Key::F3, Key::F4, Key::F5, Key::F6,
+            Key::F7, Key::F8, Key::F9, Key::F10, Key::F11, Key::F12,
+        ][(keyval - GDK_KEY_F1) as usize],
+        _ => return None,
+    })
+}
+
+fn mouse_button(button: c_uint) -> Option<MouseButton> {
+    Some(match button {
+        1 => MouseButton::Left,
+        2 => MouseButton::Middle,
+        3 => MouseButton::Right,
+        8 => MouseButton::Back,
+        9 => MouseButton::Forward,
+        _ => return None,
+    })
+}
+
+// Returns the window that a signal handler was connected for, or `None` if it was dropped.
+unsafe fn window_from_data(data: gpointer) -> Option<Window> {
+    (*(data as *const Weak<WindowData>)).upgrade().map(Window)
+}
+
+unsafe extern "C" fn free_window_data(data: gpointer, _closure: *mut GClosure) {
+    drop(Box::from_raw(data as *mut Weak<WindowData>));
+}
+
+unsafe extern "C" fn scroll_event_callback(
+    _widget: *mut GtkWidget,
+    event: *mut GdkEventScroll,
+    data: gpointer,
+) -> gboolean {
+    let window = match window_from_data(data) {
+        Some(window) => window,
+        None => return GFALSE,
+    };
+    let backend = &window.backend;
+    let scroll = &*event;
+    let event = event as *mut GdkEvent;
+    // Smooth deltas are in notches of the wheel, but touchpads and high-resolution wheels give
+    // fractions of a notch.
+    let (notches_x, notches_y) = match scroll.direction {
+        GDK_SCROLL_UP => (0.0, -1.0),
+        GDK_SCROLL_DOWN => (0.0, 1.0),
+        GDK_SCROLL_LEFT => (-1.0, 0.0),
+        GDK_SCROLL_RIGHT => (1.0, 0.0),
+        _ => {
+            let (mut dx, mut dy) = (0.0, 0.0);
+            gdk_event_get_scroll_deltas(event, &mut dx, &mut dy);
+            (dx, dy)
+        }
+    };
+    let mut wheel_event = MouseWheelEvent::new(
+        Point2::new(scroll.x, scroll.y),
+        notches_x * WHEEL_SCROLL_LINES,
+        notches_y * WHEEL_SCROLL_LINES,
+    );
+    wheel_event.modifiers = modifiers_from_state(scroll.state);
+
+    // GTK 3 marks the end of a touchpad scroll but not the beginning, and it leaves momentum to
+    // the application.
+    let device = gdk_event_get_source_device(event);
+    if scroll.direction == GDK_SCROLL_SMOOTH && !device.is_null() &&
+        gdk_device_get_source(device) == GDK_SOURCE_TOUCHPAD
+    {
+        wheel_event.phase = Some(if gdk_event_is_scroll_stop_event(event) != GFALSE {
+            backend.touchpad_scrolling.set(false);
+            ScrollPhase::End
+        } else if backend.touchpad_scrolling.replace(true) {
+            ScrollPhase::Update
+        } else {
+            ScrollPhase::Begin
+        });
+    }
+
+    dispatch_mouse_wheel(&**window, wheel_event);
+    GTRUE
+}
+
+unsafe extern "C" fn draw_callback(
+    widget: *mut GtkWidget,
+    cr: *mut cairo_t,
+    data: gpointer,
+) -> gboolean {
+    let window = match window_from_data(data) {
+        Some(window) => window,
+        None => return GFALSE,
+    };
+    let backend = &window.backend;
+    let mut surface = backend.surface.take().unwrap_or_else(|| {
+        SwapchainSurface::from_gdk_window(gtk_widget_get_window(widget), RenderingBackend::Gpu)
+    });
+
+    let mut painter = surface.start_painting(cr);
+    painter.clear(Color::from_rgba(200, 200, 200, 255));
+    let mut event = PaintingEvent {
+        painter,
+    };
+    if let Some(child) = window.children().borrow().first() {
+        child.dispatch_painting(&mut event);
+    }
+
+    surface.end_painting(cr);
+    backend.surface.set(Some(surface));
+    GTRUE
+}
+
+// GTK grabs the pointer while a button is held, so events outside the window are received during
+// a drag, like with `SetCapture()` on Windows.
+unsafe extern "C" fn button_press_event_callback(
+    _widget: *mut GtkWidget,
+    event: *mut GdkEventButton,
+    data: gpointer,
+) -> gboolean {
+    let event = &*event;
+    // A double click also sends GDK_2BUTTON_PRESS after the second press, which isn't needed.
+    if event.type_ != GDK_BUTTON_PRESS {
+        return GFALSE;
+    }
+    let (window, button) = match (window_from_data(data), mouse_button(event.button)) {
+        (Some(window), Some(button)) => (window, button),
+        _ => return GFALSE,
+    };
+    // Presses outside the application's windows, including on their title bars, are sent to the
+    // popup that grabbed the pointer.
+    let size = window.backend.size();
+    let outside = event.x < 0.0 || event.y < 0.0 || event.x >= size.width || event.y >= size.height;
+    if window.backend.popup.get() && outside {
+        dismiss_all_popups();
+        return GTRUE;
+    }
+    let modifiers = modifiers_from_state(event.state);
+    dispatch_mouse_down(&*window, Point2::new(event.x, event.y), button, modifiers);
+    GTRUE
+}
+
+unsafe extern "C" fn button_release_event_callback(
+    _widget: *mut GtkWidget,
+    event: *mut GdkEventButton,
+    data: gpointer,
+) -> gboolean {
+    let event = &*event;
+    let (window, button) = match (window_from_data(data), mouse_button(event.button)) {
+        (Some(window), Some(button)) => (window, button),
+        _ => return GFALSE,
+    };
+    dispatch_mouse_up(&*window, Point2::new(event.x, event.y), button);
+    GTRUE
+}
+
+unsafe extern "C" fn motion_notify_event_callback(
+    _widget: *mut GtkWidget,
+    event: *mut GdkEventMotion,
+    data: gpointer,
+) -> gboolean {
+    let event = &*event;
+    let window = match window_from_data(data) {
+        Some(window) => window,
+        None => return GFALSE,
+    };
+    dispatch_mouse_moved(&*window, Point2::new(event.x, event.y));
+    GTRUE
+}
+
+unsafe extern "C" fn leave_notify_event_callback(
+    _widget: *mut GtkWidget,
+    _event: *mut GdkEventCrossing,
+    _data: gpointer,
+) -> gboolean {
+    dispatch_mouse_left_window();
+    GFALSE
+}
+
+unsafe extern "C" fn key_event_callback(
+    _widget: *mut GtkWidget,
+    event: *mut GdkEventKey,
+    data: gpointer,
+) -> gboolean {
+    let event = &*event;
+    let window = match window_from_data(data) {
+        Some(window) => window,
+        None => return GFALSE,
+    };
+    let backend = &window.backend;
+    let modifiers = modifiers_from_state(event.state);
+    let pressed = event.type_ == GDK_KEY_PRESS;
+    if pressed {
+        let repeat = backend.last_key_down.replace(Some(event.keyval)) == Some(event.keyval);
+        if let Some(key) = key_from_keyval(event.keyval) {
+            dispatch_key_down(&window, KeyDownEvent { key, modifiers, repeat });
+        }
+        // TODO: use a GtkIMContext so that input methods and dead keys work
+        let c = gdk_keyval_to_unicode(event.keyval);
+        if c != 0 && !modifiers.control && !modifiers.alt {
+            if let Some(c) = std::char::from_u32(c) {
+                dispatch_text_input(&window, c.encode_utf8(&mut [0; 4]));
+            }
+        }
+    } else {
+        if backend.last_key_down.get() == Some(event.keyval) {
+            backend.last_key_down.set(None);
+        }
+        if let Some(key) = key_from_keyval(event.keyval) {
+            dispatch_key_up(&window, KeyUpEvent { key, modifiers });
+        }
+    }
+    GTRUE
+}
+
+unsafe extern "C" fn map_event_callback(
+    widget: *mut GtkWidget,
+    _event: *mut GdkEvent,
+    _data: gpointer,
+) -> gboolean {
+    grab_pointer(widget);
+    GFALSE
+}
+
+// Grabs the pointer for a popup, so that it receives presses outside the application's windows
+// and can dismiss itself. Presses in the application's windows are still sent to them.
+unsafe fn grab_pointer(popup: *mut GtkWidget) {
+    gdk_seat_grab(
+        gdk_display_get_default_seat(gdk_display_get_default()),
+        gtk_widget_get_window(popup),
+        GDK_SEAT_CAPABILITY_ALL_POINTING,
+        GTRUE,
+        ptr::null_mut(),
+        ptr::null(),
+        None,
+        ptr::null_mut(),
+    );
+}
+
+// Popups are dismissed when another application is activated. Popups don't take the focus, so
+// this isn't called when one is shown.
+unsafe extern "C" fn focus_out_event_callback(
+    _widget: *mut GtkWidget,
+    _event: *mut GdkEventFocus,
+    _data: gpointer,
+) -> gboolean {
+    dismiss_all_popups();
+    // The implicit grab of a drag ends when the window loses the focus.
+    dispatch_mouse_capture_lost();
+    GFALSE
 }
 
 impl WindowBackend {
     fn delete_handle(&self) {
         if !self.handle.get().is_null() {
-            unsafe { gtk_widget_destroy(self.handle.get()); }
+            unsafe {
+                for id in self.handler_ids.borrow_mut().drain(..) {
+                    g_signal_handler_disconnect(self.handle.get() as *mut GObject, id);
+                }
+                gtk_widget_destroy(self.handle.get());
+            }
             self.handle.set(ptr::null_mut());
+            self.surface.set(None);
         }
     }
 
+    // Connects a signal of the handle to a handler that is passed a boxed `Weak` of the window.
+    // Unlike a pointer to the backend, it stays valid if the backend moves, and the handler can
+    // tell if the window was dropped. The box is freed when the handler is disconnected.
+    unsafe fn connect_signal(&self, signal: &[u8], handler: GCallback) {
+        let window = self.window.take();
+        let data = Box::new(window.clone().expect("backend.window should be set"));
+        self.window.set(window);
+        let id = g_signal_connect_data(
+            self.handle.get() as *mut GObject,
+            signal.as_ptr() as *const _,
+            handler,
+            Box::into_raw(data) as gpointer,
+            Some(free_window_data),
+            0,
+        );
+        self.handler_ids.borrow_mut().push(id);
+    }
+
     fn recreate_handle(&self) {
         unsafe {
-            self.handle.set(gtk_window_new(GTK_WINDOW_TOPLEVEL));
+            // GTK doesn't focus popup windows, and the window manager doesn't decorate them.
+            let kind = if self.popup.get() { GTK_WINDOW_POPUP } else { GTK_WINDOW_TOPLEVEL };
+            let handle = gtk_window_new(kind);
+            self.handle.set(handle);
             // TODO: have to set text, resizable, etc.
+            let (location, size) = (self.location.get(), self.size.get());
+            gtk_window_move(handle as *mut GtkWindow, location.x as i32, location.y as i32);
+            gtk_window_resize(handle as *mut GtkWindow, size.width as i32, size.height as i32);
+            gtk_widget_set_sensitive(handle, self.enabled.get() as gboolean);
+
+            gtk_widget_add_events(handle, (
+                GDK_BUTTON_PRESS_MASK | GDK_BUTTON_RELEASE_MASK | GDK_POINTER_MOTION_MASK |
+                GDK_LEAVE_NOTIFY_MASK | GDK_KEY_PRESS_MASK | GDK_KEY_RELEASE_MASK |
+                GDK_SCROLL_MASK | GDK_SMOOTH_SCROLL_MASK
+            ) as i32);
+            let draw_callback: unsafe extern "C" fn(*mut GtkWidget, *mut cairo_t, gpointer)
+                -> gboolean = draw_callback;
+            self.connect_signal(b"draw\0", Some(mem::transmute(draw_callback)));
+            let button_press_callback: unsafe extern "C" fn(*mut GtkWidget, *mut GdkEventButton,
+                gpointer) -> gboolean = button_press_event_callback;
+            self.connect_signal(
+                b"button-press-event\0",
+                Some(mem::transmute(button_press_callback)),
+            );
+            let button_release_callback: unsafe extern "C" fn(*mut GtkWidget, *mut GdkEventButton,
+                gpointer) -> gboolean = button_release_event_callback;
+            self.connect_signal(
+                b"button-release-event\0",
+                Some(mem::transmute(button_release_callback)),
+            );
+            let motion_callback: unsafe extern "C" fn(*mut GtkWidget, *mut GdkEventMotion,
+                gpointer) -> gboolean = motion_notify_event_callback;
+            self.connect_signal(b"motion-notify-event\0", Some(mem::transmute(motion_callback)));
+            let leave_callback: unsafe extern "C" fn(*mut GtkWidget, *mut GdkEventCrossing,
+                gpointer) -> gboolean = leave_notify_event_callback;
+            self.connect_signal(b"leave-notify-event\0", Some(mem::transmute(leave_callback)));
+            let key_callback: unsafe extern "C" fn(*mut GtkWidget, *mut GdkEventKey, gpointer)
+                -> gboolean = key_event_callback;
+            self.connect_signal(b"key-press-event\0", Some(mem::transmute(key_callback)));
+            self.connect_signal(b"key-release-event\0", Some(mem::transmute(key_callback)));
+            if self.popup.get() {
+                // The window has to be viewable to grab the pointer.
+                let map_callback: unsafe extern "C" fn(*mut GtkWidget, *mut GdkEvent, gpointer)
+                    -> gboolean = map_event_callback;
+                self.connect_signal(b"map-event\0", Some(mem::transmute(map_callback)));
+            }
+            let scroll_callback: unsafe extern "C" fn(*mut GtkWidget, *mut GdkEventScroll, gpointer)
+                -> gboolean = scroll_event_callback;
+            self.connect_signal(b"scroll-event\0", Some(mem::transmute(scroll_callback)));
+            let focus_out_callback: unsafe extern "C" fn(*mut GtkWidget, *mut GdkEventFocus,
+                gpointer) -> gboolean = focus_out_event_callback;
+            self.connect_signal(b"focus-out-event\0", Some(mem::transmute(focus_out_callback)));
+        }
+        self.update_transient_for();
+    }
+
+    // Makes the window manager keep the window above its owner.
+    fn update_transient_for(&self) {
+        if !self.is_handle_created() {
+            return;
+        }
+        let owner = self.owner.borrow().as_ref().and_then(|o| o.upgrade());
+        let owner_handle = owner.as_ref().map_or(ptr::null_mut(), |o| o.backend.handle());
+        unsafe {
+            gtk_window_set_transient_for(
+                self.handle.get() as *mut GtkWindow,
+                owner_handle as *mut GtkWindow);
+        }
+    }
+
+    // Ends the grab of a popup being hidden. The grab moves back to the popup shown before it, like
+    // the menu that a submenu was opened from.
+    unsafe fn release_pointer(&self) {
+        gdk_seat_ungrab(gdk_display_get_default_seat(gdk_display_get_default()));
+        let previous = open_popups().into_iter().rev().find(|p| !ptr::eq(&p.backend, self));
+        if let Some(previous) = previous {
+            grab_pointer(previous.backend.handle());
         }
     }
 
@@ -53,6 +459,12 @@ impl WindowBackend {
     }
 }
 
+impl Drop for WindowBackend {
+    fn drop(&mut self) {
+        self.delete_handle();
+    }
+}
+
 impl GenericWindowBackend for WindowBackend {
     fn new() -> WindowBackend {
         WindowBackend {
@@ -60,7 +472,16 @@ impl GenericWindowBackend for WindowBackend {
             handle: Cell::new(ptr::null_mut()),
             visibility: Cell::new(Visibility::Gone),
             text: Cell::new("".to_string()),
+            location: Cell::new(Point2::new(0.0, 0.0)),
+            size: Cell::new(Size2::new(400.0, 300.0)),
+            enabled: Cell::new(true),
             resizable: Cell::new(true),
+            popup: Cell::new(false),
+            owner: RefCell::new(None),
+            touchpad_scrolling: Cell::new(false),
+            handler_ids: RefCell::new(vec![]),
+            surface: Cell::new(None),
+            last_key_down: Cell::new(None),
         }
     }
 
@@ -68,6 +489,15 @@ impl GenericWindowBackend for WindowBackend {
         self.window.set(Some(window));
     }
 
+    fn window(&self) -> Window {
+        let window = self.window.take();
+        // The unwrap can't fail because `self` is a reference to the window so obviously it hasn't
+        // been dropped.
+        let window_copy = window.as_ref().expect("backend.window should be set").upgrade().unwrap();
+        self.window.set(window);
+        Window(window_copy)
+    }
+
     fn set_text(&self, text: &str) {
         self.text.set(text.to_owned());
         if self.is_handle_created() {
@@ -90,13 +520,85 @@ impl GenericWindowBackend for WindowBackend {
             unsafe { gtk_widget_show(self.handle()); }
         } else {
             if self.is_handle_created() {
-                unsafe { gtk_widget_hide(self.handle.get()); }
+                unsafe {
+                    if self.popup.get() {
+                        self.release_pointer();
+                    }
+                    gtk_widget_hide(self.handle.get());
+                }
             }
         }
     }
 
+    fn location(&self) -> Point2<f64> {
+        if !self.is_handle_created() {
+            return self.location.get();
+        }
+        let (mut x, mut y) = (0, 0);
+        unsafe { gtk_window_get_position(self.handle.get() as *mut GtkWindow, &mut x, &mut y); }
+        Point2::new(x as f64, y as f64)
+    }
+
     fn set_location(&self, location: &Point2<f64>) {
-        todo!()
+        self.location.set(*location);
+        if self.is_handle_created() {
+            unsafe {
+                gtk_window_move(
+                    self.handle.get() as *mut GtkWindow,
+                    location.x as i32,
+                    location.y as i32);
+            }
+        }
+    }
+
+    fn size(&self) -> Size2<f64> {
+        if !self.is_handle_created() {
+            return self.size.get();
+        }
+        let (mut width, mut height) = (0, 0);
+        unsafe {
+            gtk_window_get_size(self.handle.get() as *mut GtkWindow, &mut width, &mut height);
+        }
+        Size2::new(width as f64, height as f64)
+    }
+
+    fn set_size(&self, size: &Size2<f64>) {
+        self.size.set(*size);
+        if self.is_handle_created() {
+            unsafe {
+                gtk_window_resize(
+                    self.handle.get() as *mut GtkWindow,
+                    size.width as i32,
+                    size.height as i32);
+            }
+        }
+    }
+
+    fn client_origin(&self) -> Option<Point2<f64>> {
+        if !self.is_handle_created() {
+            return None;
+        }
+        unsafe {
+            // The GdkWindow is null until the widget is realized.
+            let gdk_window = gtk_widget_get_window(self.handle.get());
+            if gdk_window.is_null() {
+                return None;
+            }
+            let (mut x, mut y) = (0, 0);
+            gdk_window_get_origin(gdk_window, &mut x, &mut y);
+            Some(Point2::new(x as f64, y as f64))
+        }
+    }
+
+    fn enabled(&self) -> bool {
+        self.enabled.get()
+    }
+
+    fn set_enabled(&self, enabled: bool) {
+        self.enabled.set(enabled);
+        if self.is_handle_created() {
+            unsafe { gtk_widget_set_sensitive(self.handle.get(), enabled as gboolean); }
+        }
     }
 
     fn resizable(&self) -> bool {
@@ -113,5 +615,43 @@ impl GenericWindowBackend for WindowBackend {
             }
         }
     }
+
+    fn set_owner(&self, owner: Option<&Window>) {
+        *self.owner.borrow_mut() = owner.map(|o| Rc::downgrade(&o.0));
+        self.update_transient_for();
+    }
+
+    fn set_popup(&self, popup: bool) {
+        self.popup.set(popup);
+    }
+
+    // GTK only grabs the pointer implicitly while a button is pressed, and ends the grab itself
+    // when the button is released.
+    fn release_mouse_capture() {
+    }
+
+    fn invalidate(&self) {
+        if self.is_handle_created() {
+            unsafe { gtk_widget_queue_draw(self.handle.get()); }
+        }
+    }
+
+    fn invalidate_area(&self, location: Point2<f64>, size: Size2<f64>) {
+        if self.is_handle_created() {
+            // Rounding outward covers any pixel the control paints part of.
+            let (left, top) = (location.x.floor(), location.y.floor());
+            let right = (location.x + size.width).ceil();
+            let bottom = (location.y + size.height).ceil();
+            unsafe {
+                gtk_widget_queue_draw_area(
+                    self.handle.get(),
+                    left as c_int,
+                    top as c_int,
+                    (right - left) as c_int,
+                    (bottom - top) as c_int,
+                );
+            }
+        }
+    }
 }
 
@@ -0,0 +1,27 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ *
+ */
+
+use std::cell::RefCell;
+
+use crate::generic_backend::GenericClipboardBackend;
+
+thread_local! {
+    // The clipboard is only shared within the thread, since there is no system to share it with.
+    static CLIPBOARD_TEXT: RefCell<Option<String>> = RefCell::new(None);
+}
+
+pub struct ClipboardBackend;
+
+impl GenericClipboardBackend for ClipboardBackend {
+    fn text() -> Option<String> {
+        CLIPBOARD_TEXT.with(|text| text.borrow().clone())
+    }
+
+    fn set_text(text: &str) {
+        CLIPBOARD_TEXT.with(|clipboard| *clipboard.borrow_mut() = Some(text.to_owned()));
+    }
+}
@@ -0,0 +1,17 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ *
+ */
+
+//! A backend that doesn't show anything on screen, enabled with the `headless` feature. Input is
+//! injected with the functions in this module, so that controls can be tested without a display.
+
+pub use self::clipboard_backend::ClipboardBackend;
+pub use self::timer_backend::{fire_timers, TimerBackend};
+pub use self::window_backend::{inject_app_deactivated, inject_key_down, inject_key_up, inject_mouse_down, inject_mouse_moved, inject_mouse_up, inject_mouse_wheel, inject_text_input, take_invalidated_areas, WindowBackend};
+
+pub mod clipboard_backend;
+pub mod timer_backend;
+pub mod window_backend;
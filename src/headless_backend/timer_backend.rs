@@ -0,0 +1,56 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ *
+ */
+
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+use std::time::Duration;
+
+use crate::generic_backend::GenericTimerBackend;
+
+// The callback of a running timer
+type TimerState = RefCell<Option<Rc<dyn Fn()>>>;
+
+thread_local! {
+    static TIMERS: RefCell<Vec<Weak<TimerState>>> = RefCell::new(vec![]);
+}
+
+/// Calls the handler of each running timer once, as though their intervals had all passed.
+/// Timers never fire on their own with this backend.
+pub fn fire_timers() {
+    let callbacks: Vec<Rc<dyn Fn()>> = TIMERS.with(|timers| {
+        let mut timers = timers.borrow_mut();
+        timers.retain(|t| t.strong_count() > 0);
+        timers.iter().filter_map(|t| t.upgrade()).filter_map(|t| t.borrow().clone()).collect()
+    });
+    for callback in callbacks {
+        callback();
+    }
+}
+
+pub struct TimerBackend {
+    state: Rc<TimerState>,
+}
+
+impl GenericTimerBackend for TimerBackend {
+    fn new() -> Self {
+        let state = Rc::new(RefCell::new(None));
+        TIMERS.with(|timers| timers.borrow_mut().push(Rc::downgrade(&state)));
+        TimerBackend { state }
+    }
+
+    fn start(&self, _interval: Duration, callback: Rc<dyn Fn()>) {
+        *self.state.borrow_mut() = Some(callback);
+    }
+
+    fn stop(&self) {
+        *self.state.borrow_mut() = None;
+    }
+
+    fn is_running(&self) -> bool {
+        self.state.borrow().is_some()
+    }
+}
@@ -0,0 +1,207 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ *
+ */
+
+use std::cell::{Cell, RefCell};
+use std::rc::Weak;
+
+use zaffre::{Point2, Size2};
+
+use crate::control::{dispatch_mouse_down, dispatch_mouse_moved, dispatch_mouse_up, dispatch_mouse_wheel};
+use crate::window::dismiss_all_popups;
+use crate::keyboard::{dispatch_key_down, dispatch_key_up, dispatch_text_input};
+use crate::{Key, KeyDownEvent, KeyUpEvent, Modifiers, MouseButton, MouseWheelEvent, Visibility, Window, WindowData};
+use crate::generic_backend::GenericWindowBackend;
+
+pub struct WindowBackend {
+    window: Cell<Option<Weak<WindowData>>>,
+    visibility: Cell<Visibility>,
+    location: Cell<Point2<f64>>,
+    size: Cell<Size2<f64>>,
+    enabled: Cell<bool>,
+    resizable: Cell<bool>,
+    // The areas invalidated since `take_invalidated_areas()` was last called
+    invalidated_areas: RefCell<Vec<(Point2<f64>, Size2<f64>)>>,
+}
+
+impl GenericWindowBackend for WindowBackend {
+    fn new() -> WindowBackend {
+        WindowBackend {
+            window: Cell::new(None),
+            visibility: Cell::new(Visibility::Gone),
+            location: Cell::new(Point2::new(0.0, 0.0)),
+            size: Cell::new(Size2::new(400.0, 300.0)),
+            enabled: Cell::new(true),
+            resizable: Cell::new(true),
+            invalidated_areas: RefCell::new(vec![]),
+        }
+    }
+
+    fn set_window(&self, window: Weak<WindowData>) {
+        self.window.set(Some(window));
+    }
+
+    fn window(&self) -> Window {
+        let window = self.window.take();
+        // The unwrap can't fail because `self` is a reference to the window so obviously it hasn't
+        // been dropped.
+        let window_copy = window.as_ref().expect("backend.window should be set").upgrade().unwrap();
+        self.window.set(window);
+        Window(window_copy)
+    }
+
+    fn set_text(&self, _text: &str) {
+    }
+
+    fn visibility(&self) -> Visibility {
+        self.visibility.get()
+    }
+
+    fn set_visibility(&self, visibility: Visibility) {
+        self.visibility.set(visibility);
+    }
+
+    fn location(&self) -> Point2<f64> {
+        self.location.get()
+    }
+
+    fn set_location(&self, location: &Point2<f64>) {
+        self.location.set(*location);
+    }
+
+    fn size(&self) -> Size2<f64> {
+        self.size.get()
+    }
+
+    fn set_size(&self, size: &Size2<f64>) {
+        self.size.set(*size);
+    }
+
+    // There is no border, so the client area is at the window's location.
+    fn client_origin(&self) -> Option<Point2<f64>> {
+        Some(self.location.get())
+    }
+
+    fn enabled(&self) -> bool {
+        self.enabled.get()
+    }
+
+    fn set_enabled(&self, enabled: bool) {
+        self.enabled.set(enabled);
+    }
+
+    fn resizable(&self) -> bool {
+        self.resizable.get()
+    }
+
+    fn set_resizable(&self, resizable: bool) {
+        self.resizable.set(resizable);
+    }
+
+    // `WindowData` keeps track of the owner, and there's nothing on screen to keep above it.
+    fn set_owner(&self, _owner: Option<&Window>) {
+    }
+
+    // Nothing is shown or activated, so a popup is like any other window.
+    fn set_popup(&self, _popup: bool) {
+    }
+
+    // Nothing outside the windows sends input.
+    fn release_mouse_capture() {
+    }
+
+    fn invalidate(&self) {
+        self.invalidate_area(Point2::new(0.0, 0.0), self.size.get());
+    }
+
+    fn invalidate_area(&self, location: Point2<f64>, size: Size2<f64>) {
+        self.invalidated_areas.borrow_mut().push((location, size));
+    }
+}
+
+// Each of these sends input to a window the same way the other backends do when the platform
+// reports it. Locations are in the coordinates of the window's client area.
+
+pub fn inject_mouse_down(
+    window: &Window,
+    location: Point2<f64>,
+    button: MouseButton,
+    modifiers: Modifiers,
+) {
+    dispatch_mouse_down(&**window, location, button, modifiers);
+}
+
+pub fn inject_mouse_up(window: &Window, location: Point2<f64>, button: MouseButton) {
+    dispatch_mouse_up(&**window, location, button);
+}
+
+pub fn inject_mouse_moved(window: &Window, location: Point2<f64>) {
+    dispatch_mouse_moved(&**window, location);
+}
+
+/// Sends a mouse wheel or touchpad scroll to the control under `event.location`.
+pub fn inject_mouse_wheel(window: &Window, event: MouseWheelEvent) {
+    dispatch_mouse_wheel(&**window, event);
+}
+
+pub fn inject_key_down(window: &Window, key: Key, modifiers: Modifiers) {
+    dispatch_key_down(window, KeyDownEvent { key, modifiers, repeat: false });
+}
+
+pub fn inject_key_up(window: &Window, key: Key, modifiers: Modifiers) {
+    dispatch_key_up(window, KeyUpEvent { key, modifiers });
+}
+
+pub fn inject_text_input(window: &Window, text: &str) {
+    dispatch_text_input(window, text);
+}
+
+/// Returns the areas of the window that were asked to be repainted since the last call, in the
+/// order they were invalidated. Nothing is painted with this backend.
+pub fn take_invalidated_areas(window: &Window) -> Vec<(Point2<f64>, Size2<f64>)> {
+    window.backend.invalidated_areas.take()
+}
+
+/// Acts as if another application was activated, which dismisses the popups.
+pub fn inject_app_deactivated() {
+    dismiss_all_popups();
+}
+
+#[test]
+fn test_inject_mouse_wheel() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use zaffre::Size2;
+
+    use crate::{Control, ScrollPhase, SubControl};
+
+    let window = Window::new();
+    let parent = SubControl::new();
+    parent.set_size(&Size2::new(100.0, 100.0));
+    let child = SubControl::new();
+    child.set_location(&Point2::new(10.0, 20.0));
+    parent.children().borrow_mut().push(child.clone());
+    window.set_child(parent.clone().into());
+
+    // The child doesn't handle the event, so it bubbles to the parent in its coordinates.
+    let events = Rc::new(RefCell::new(vec![]));
+    let events2 = events.clone();
+    parent.event_handlers().add(move |route| {
+        if let Some(event) = route.event.downcast_mut::<MouseWheelEvent>() {
+            events2.borrow_mut().push((event.location, event.pixel_delta, event.phase));
+            route.handled = true;
+        }
+    });
+    let mut event = MouseWheelEvent::new(Point2::new(15.0, 25.0), 0.0, 0.25);
+    event.pixel_delta = Some((0.0, 4.0));
+    event.phase = Some(ScrollPhase::Begin);
+    inject_mouse_wheel(&window, event);
+    assert_eq!(
+        *events.borrow(),
+        vec![(Point2::new(15.0, 25.0), Some((0.0, 4.0)), Some(ScrollPhase::Begin))],
+    );
+}
@@ -5,94 +5,689 @@
  *
  */
 
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::Range;
 use std::sync::Arc;
 
+// Leaves are built to about this many bytes. Two leaves are merged when joined if the result
+// isn't over twice this.
+const CHUNK_TARGET_LEN: usize = 1024;
+const CHUNK_MAX_LEN: usize = CHUNK_TARGET_LEN * 2;
 
+// A node of a rope. Nodes are never modified after being created, so edits create new nodes
+// along the path to the change and share the rest of the tree with the old rope.
 enum Node {
     Internal {
         left: Arc<Node>,
         right: Arc<Node>,
         // cache
-        height: i32,
-        len: uint,
+        height: u32,
+        len: usize,
+        chars: usize,
+        utf16: usize,
+        newlines: usize,
     },
     Leaf(String),
 }
 
 impl Node {
-    fn new_internal(left: Arc<Node>, right: Arc<Node>) -> Node {
-        Node::Internal {
-            left: left,
-            right: right,
-            height: left.height() + right.height(),
-            len: left.len() + right.len()
+    fn new_internal(left: Arc<Node>, right: Arc<Node>) -> Arc<Node> {
+        Arc::new(Node::Internal {
+            height: left.height().max(right.height()) + 1,
+            len: left.len() + right.len(),
+            chars: left.chars() + right.chars(),
+            utf16: left.utf16() + right.utf16(),
+            newlines: left.newlines() + right.newlines(),
+            left,
+            right,
+        })
+    }
+
+    // Builds a balanced tree from a string, breaking it into chunks on character boundaries.
+    fn from_str(s: &str) -> Arc<Node> {
+        let mut chunks = vec![];
+        let mut rest = s;
+        while rest.len() > CHUNK_MAX_LEN {
+            let mut split = CHUNK_TARGET_LEN;
+            while !rest.is_char_boundary(split) {
+                split -= 1;
+            }
+            let (chunk, after) = rest.split_at(split);
+            chunks.push(chunk);
+            rest = after;
+        }
+        chunks.push(rest);
+        Self::from_chunks(&chunks)
+    }
+
+    fn from_chunks(chunks: &[&str]) -> Arc<Node> {
+        if chunks.len() == 1 {
+            return Arc::new(Node::Leaf(chunks[0].to_owned()));
+        }
+        let (left, right) = chunks.split_at(chunks.len() / 2);
+        Self::new_internal(Self::from_chunks(left), Self::from_chunks(right))
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            &Node::Internal { len, .. } => len,
+            &Node::Leaf(ref s) => s.len(),
+        }
+    }
+
+    fn chars(&self) -> usize {
+        match self {
+            &Node::Internal { chars, .. } => chars,
+            &Node::Leaf(ref s) => s.chars().count(),
+        }
+    }
+
+    fn utf16(&self) -> usize {
+        match self {
+            &Node::Internal { utf16, .. } => utf16,
+            &Node::Leaf(ref s) => s.chars().map(char::len_utf16).sum(),
+        }
+    }
+
+    fn newlines(&self) -> usize {
+        match self {
+            &Node::Internal { newlines, .. } => newlines,
+            &Node::Leaf(ref s) => s.bytes().filter(|&b| b == b'\n').count(),
+        }
+    }
+
+    fn height(&self) -> u32 {
+        match self {
+            &Node::Internal { height, .. } => height,
+            &Node::Leaf(_) => 0,
+        }
+    }
+
+    fn children(&self) -> (&Arc<Node>, &Arc<Node>) {
+        match self {
+            &Node::Internal { ref left, ref right, .. } => (left, right),
+            &Node::Leaf(_) => panic!("leaf has no children"),
+        }
+    }
+
+    // Concatenates two trees, rebalancing so that the heights of siblings differ by at most one.
+    fn join(left: &Arc<Node>, right: &Arc<Node>) -> Arc<Node> {
+        if left.len() == 0 {
+            return right.clone();
+        }
+        if right.len() == 0 {
+            return left.clone();
+        }
+        if let (&Node::Leaf(ref l), &Node::Leaf(ref r)) = (&**left, &**right) {
+            if l.len() + r.len() <= CHUNK_MAX_LEN {
+                return Arc::new(Node::Leaf(format!("{}{}", l, r)));
+            }
+        }
+        let (left_height, right_height) = (left.height(), right.height());
+        if left_height > right_height + 1 {
+            let (ll, lr) = left.children();
+            Self::balance(ll.clone(), Self::join(lr, right))
+        } else if right_height > left_height + 1 {
+            let (rl, rr) = right.children();
+            Self::balance(Self::join(left, rl), rr.clone())
+        } else {
+            Self::new_internal(left.clone(), right.clone())
+        }
+    }
+
+    // Makes a node from two subtrees whose heights differ by at most two, rotating if needed.
+    fn balance(left: Arc<Node>, right: Arc<Node>) -> Arc<Node> {
+        let (left_height, right_height) = (left.height(), right.height());
+        if right_height > left_height + 1 {
+            let (rl, rr) = right.children();
+            if rl.height() > rr.height() {
+                let (rll, rlr) = rl.children();
+                Self::new_internal(
+                    Self::new_internal(left, rll.clone()),
+                    Self::new_internal(rlr.clone(), rr.clone()),
+                )
+            } else {
+                Self::new_internal(Self::new_internal(left, rl.clone()), rr.clone())
+            }
+        } else if left_height > right_height + 1 {
+            let (ll, lr) = left.children();
+            if lr.height() > ll.height() {
+                let (lrl, lrr) = lr.children();
+                Self::new_internal(
+                    Self::new_internal(ll.clone(), lrl.clone()),
+                    Self::new_internal(lrr.clone(), right),
+                )
+            } else {
+                Self::new_internal(ll.clone(), Self::new_internal(lr.clone(), right))
+            }
+        } else {
+            Self::new_internal(left, right)
+        }
+    }
+
+    // Splits a tree into the bytes before and after an index on a character boundary.
+    fn split(node: &Arc<Node>, index: usize) -> (Arc<Node>, Arc<Node>) {
+        match &**node {
+            &Node::Internal { ref left, ref right, .. } => {
+                match index.cmp(&left.len()) {
+                    Ordering::Equal => (left.clone(), right.clone()),
+                    Ordering::Less => {
+                        let (before, after) = Self::split(left, index);
+                        (before, Self::join(&after, right))
+                    }
+                    Ordering::Greater => {
+                        let (before, after) = Self::split(right, index - left.len());
+                        (Self::join(left, &before), after)
+                    }
+                }
+            }
+            &Node::Leaf(ref s) => {
+                if index == 0 {
+                    (Arc::new(Node::Leaf(String::new())), node.clone())
+                } else if index == s.len() {
+                    (node.clone(), Arc::new(Node::Leaf(String::new())))
+                } else {
+                    assert!(s.is_char_boundary(index), "index is not on a character boundary");
+                    let (before, after) = s.split_at(index);
+                    (
+                        Arc::new(Node::Leaf(before.to_owned())),
+                        Arc::new(Node::Leaf(after.to_owned())),
+                    )
+                }
+            }
         }
     }
 
-    unsafe fn new_unchecked<T: Iterator<u8>>(string: T, chunk_target_len: uint) {
+    // Returns the number of characters that end at or before an index.
+    fn byte_to_char(&self, index: usize) -> usize {
+        match self {
+            &Node::Internal { ref left, ref right, .. } => {
+                if index <= left.len() {
+                    left.byte_to_char(index)
+                } else {
+                    left.chars() + right.byte_to_char(index - left.len())
+                }
+            }
+            &Node::Leaf(ref s) => {
+                s.char_indices().take_while(|&(i, c)| i + c.len_utf8() <= index).count()
+            }
+        }
+    }
 
-        // TODO: search backwards using is_char_boundary() looking for a place to break
+    fn char_to_byte(&self, char_index: usize) -> usize {
+        match self {
+            &Node::Internal { ref left, ref right, .. } => {
+                if char_index <= left.chars() {
+                    left.char_to_byte(char_index)
+                } else {
+                    left.len() + right.char_to_byte(char_index - left.chars())
+                }
+            }
+            &Node::Leaf(ref s) => s.char_indices().nth(char_index).map_or(s.len(), |(i, _)| i),
+        }
     }
 
-    fn len(&self) -> uint {
+    // Returns the number of UTF-16 code units in the characters that end at or before an index.
+    fn byte_to_utf16(&self, index: usize) -> usize {
         match self {
-            &Node::Internal { ref len, .. } => *len,
-            &Node::Leaf(ref s) => s.len()
+            &Node::Internal { ref left, ref right, .. } => {
+                if index <= left.len() {
+                    left.byte_to_utf16(index)
+                } else {
+                    left.utf16() + right.byte_to_utf16(index - left.len())
+                }
+            }
+            &Node::Leaf(ref s) => {
+                s.char_indices().take_while(|&(i, c)| i + c.len_utf8() <= index)
+                    .map(|(_, c)| c.len_utf16()).sum()
+            }
         }
     }
 
-    fn height(&self) -> i32 {
+    fn utf16_to_byte(&self, utf16_index: usize) -> usize {
         match self {
-            &Node::Internal { ref height, .. } => *height,
-            &Node::Leaf(ref s) => 1
+            &Node::Internal { ref left, ref right, .. } => {
+                if utf16_index <= left.utf16() {
+                    left.utf16_to_byte(utf16_index)
+                } else {
+                    left.len() + right.utf16_to_byte(utf16_index - left.utf16())
+                }
+            }
+            &Node::Leaf(ref s) => {
+                let mut units = 0;
+                for (i, c) in s.char_indices() {
+                    units += c.len_utf16();
+                    if units > utf16_index {
+                        return i;
+                    }
+                }
+                s.len()
+            }
         }
     }
 
-    fn insert_str(&self, idx: uint, string: &str, balance_factor: i32,
-                  chunk_target_len: uint) -> Node {
+    // Returns the number of newlines in the bytes before an index.
+    fn byte_to_line(&self, index: usize) -> usize {
         match self {
-            &Node::Internal { ref height, .. } => {
+            &Node::Internal { ref left, ref right, .. } => {
+                if index <= left.len() {
+                    left.byte_to_line(index)
+                } else {
+                    left.newlines() + right.byte_to_line(index - left.len())
+                }
+            }
+            &Node::Leaf(ref s) => s.as_bytes()[..index].iter().filter(|&&b| b == b'\n').count(),
+        }
+    }
 
+    // Returns the index of the byte after the specified newline, counting from zero.
+    fn newline_end(&self, newline: usize) -> usize {
+        match self {
+            &Node::Internal { ref left, ref right, .. } => {
+                if newline < left.newlines() {
+                    left.newline_end(newline)
+                } else {
+                    left.len() + right.newline_end(newline - left.newlines())
+                }
             }
             &Node::Leaf(ref s) => {
-                let (before, after) = s.split_at(idx);
-                Node::new_unchecked(before.as_bytes()
-                                    .chain(string.as_bytes())
-                                    .chain(after.as_bytes()), chunk_target_len);
+                s.match_indices('\n').nth(newline).map(|(i, _)| i + 1).expect("not enough newlines")
             }
         }
     }
+
+    fn char_at(&self, index: usize) -> char {
+        match self {
+            &Node::Internal { ref left, ref right, .. } => {
+                if index < left.len() {
+                    left.char_at(index)
+                } else {
+                    right.char_at(index - left.len())
+                }
+            }
+            &Node::Leaf(ref s) => s[index..].chars().next().expect("index out of bounds"),
+        }
+    }
 }
 
+/// An immutable string stored as a balanced tree of chunks.
+///
+/// Editing a rope returns a new rope that shares most of its tree with the old one, so edits and
+/// clones are cheap even for large text, and old versions can be kept around for undo. Indexes
+/// are byte indexes that must be on character boundaries.
+#[derive(Clone)]
 pub struct ImmRope {
     root: Arc<Node>,
 }
 
 impl ImmRope {
-    fn new() -> ImmRope {
+    pub fn new() -> ImmRope {
         ImmRope {
             root: Arc::new(Node::Leaf(String::new()))
         }
     }
 
-    fn insert(&self, idx: uint, ch: char) -> ImmRope {
+    /// Returns the length in bytes.
+    pub fn len(&self) -> usize {
+        self.root.len()
+    }
 
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
     }
 
-    fn bytes(&self) {
+    pub fn len_chars(&self) -> usize {
+        self.root.chars()
+    }
 
+    /// Returns the length in UTF-16 code units, as used by Windows and JavaScript.
+    pub fn len_utf16(&self) -> usize {
+        self.root.utf16()
     }
 
-    fn len(&self) -> uint {
-        self.root.len()
+    /// Returns the number of lines, which is one more than the number of newlines.
+    pub fn len_lines(&self) -> usize {
+        self.root.newlines() + 1
+    }
+
+    /// Returns a rope with another rope appended. The trees of both ropes are shared with the
+    /// result.
+    pub fn concat(&self, other: &ImmRope) -> ImmRope {
+        ImmRope { root: Node::join(&self.root, &other.root) }
+    }
+
+    /// Returns a rope with a string inserted at a byte index.
+    pub fn insert(&self, index: usize, s: &str) -> ImmRope {
+        self.replace(index..index, s)
+    }
+
+    /// Returns a rope with a range of bytes removed.
+    pub fn remove(&self, range: Range<usize>) -> ImmRope {
+        self.replace(range, "")
+    }
+
+    /// Returns a rope with a range of bytes replaced by a string.
+    pub fn replace(&self, range: Range<usize>, s: &str) -> ImmRope {
+        assert!(range.start <= range.end && range.end <= self.len(), "range out of bounds");
+        let (before, rest) = Node::split(&self.root, range.start);
+        let (_, after) = Node::split(&rest, range.end - range.start);
+        let middle = Node::join(&before, &Node::from_str(s));
+        ImmRope { root: Node::join(&middle, &after) }
+    }
+
+    /// Returns the bytes in a range as a new rope.
+    pub fn slice(&self, range: Range<usize>) -> ImmRope {
+        assert!(range.start <= range.end && range.end <= self.len(), "range out of bounds");
+        let (_, rest) = Node::split(&self.root, range.start);
+        let (middle, _) = Node::split(&rest, range.end - range.start);
+        ImmRope { root: middle }
+    }
+
+    /// Returns the character starting at a byte index.
+    pub fn char_at(&self, index: usize) -> char {
+        self.root.char_at(index)
+    }
+
+    /// Returns the number of characters before a byte index. If the index is in the middle of a
+    /// character, that character isn't counted.
+    pub fn byte_to_char(&self, index: usize) -> usize {
+        assert!(index <= self.len(), "index out of bounds");
+        self.root.byte_to_char(index)
     }
 
-    fn chunk_target_len() -> int {
-        3000
+    pub fn char_to_byte(&self, char_index: usize) -> usize {
+        assert!(char_index <= self.len_chars(), "index out of bounds");
+        self.root.char_to_byte(char_index)
+    }
+
+    /// Returns the number of UTF-16 code units before a byte index. If the index is in the middle
+    /// of a character, that character isn't counted.
+    pub fn byte_to_utf16(&self, index: usize) -> usize {
+        assert!(index <= self.len(), "index out of bounds");
+        self.root.byte_to_utf16(index)
+    }
+
+    /// Returns the byte index of a UTF-16 code unit index. If the index is between the two
+    /// halves of a surrogate pair, the index of the character they encode is returned.
+    pub fn utf16_to_byte(&self, utf16_index: usize) -> usize {
+        assert!(utf16_index <= self.len_utf16(), "index out of bounds");
+        self.root.utf16_to_byte(utf16_index)
+    }
+
+    /// Returns the line that a byte index is on. A newline is part of the line it ends.
+    pub fn byte_to_line(&self, index: usize) -> usize {
+        assert!(index <= self.len(), "index out of bounds");
+        self.root.byte_to_line(index)
+    }
+
+    /// Returns the byte index where a line starts.
+    pub fn line_to_byte(&self, line: usize) -> usize {
+        assert!(line < self.len_lines(), "line out of bounds");
+        if line == 0 { 0 } else { self.root.newline_end(line - 1) }
+    }
+
+    /// Returns the byte range of a line, not including the newline that ends it.
+    pub fn line_range(&self, line: usize) -> Range<usize> {
+        let start = self.line_to_byte(line);
+        let end = if line + 1 < self.len_lines() {
+            self.line_to_byte(line + 1) - 1
+        } else {
+            self.len()
+        };
+        start..end
+    }
+
+    /// Returns the text of a line, not including the newline that ends it.
+    pub fn line(&self, line: usize) -> String {
+        self.slice(self.line_range(line)).to_string()
+    }
+
+    /// Returns an iterator over the chunks of text the rope is stored in, in order.
+    pub fn chunks(&self) -> Chunks {
+        Chunks { stack: vec![&*self.root] }
+    }
+
+    pub fn chars<'a>(&'a self) -> impl Iterator<Item = char> + 'a {
+        self.chunks().flat_map(str::chars)
+    }
+
+    pub fn bytes<'a>(&'a self) -> impl Iterator<Item = u8> + 'a {
+        self.chunks().flat_map(str::bytes)
+    }
+
+    /// Returns an iterator over the lines of the rope, without the newlines that end them. Each
+    /// line is a rope that shares its tree with this one. Like `len_lines()`, text ending in a
+    /// newline has an empty last line.
+    pub fn lines(&self) -> Lines {
+        Lines { rope: self, line: 0 }
+    }
+}
+
+/// An iterator over the chunks of an `ImmRope`, returned by `ImmRope::chunks()`.
+pub struct Chunks<'a> {
+    stack: Vec<&'a Node>,
+}
+
+impl<'a> Iterator for Chunks<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        while let Some(node) = self.stack.pop() {
+            match node {
+                &Node::Internal { ref left, ref right, .. } => {
+                    self.stack.push(right);
+                    self.stack.push(left);
+                }
+                &Node::Leaf(ref s) => {
+                    if !s.is_empty() {
+                        return Some(s);
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+/// An iterator over the lines of an `ImmRope`, returned by `ImmRope::lines()`.
+pub struct Lines<'a> {
+    rope: &'a ImmRope,
+    line: usize,
+}
+
+impl<'a> Iterator for Lines<'a> {
+    type Item = ImmRope;
+
+    fn next(&mut self) -> Option<ImmRope> {
+        if self.line >= self.rope.len_lines() {
+            return None;
+        }
+        let line = self.rope.slice(self.rope.line_range(self.line));
+        self.line += 1;
+        Some(line)
+    }
+}
+
+impl<'a> From<&'a str> for ImmRope {
+    fn from(s: &'a str) -> Self {
+        ImmRope { root: Node::from_str(s) }
+    }
+}
+
+impl From<String> for ImmRope {
+    fn from(s: String) -> Self {
+        ImmRope::from(&*s)
+    }
+}
+
+impl PartialEq for ImmRope {
+    fn eq(&self, other: &ImmRope) -> bool {
+        Arc::ptr_eq(&self.root, &other.root) ||
+            (self.len() == other.len() && self.bytes().eq(other.bytes()))
+    }
+}
+
+impl Eq for ImmRope {}
+
+impl PartialEq<str> for ImmRope {
+    fn eq(&self, other: &str) -> bool {
+        self.len() == other.len() && self.bytes().eq(other.bytes())
+    }
+}
+
+impl<'a> PartialEq<&'a str> for ImmRope {
+    fn eq(&self, other: &&'a str) -> bool {
+        *self == **other
     }
 }
 
-fn main() {
-    let x = ImmRope::new();
-    println!("{}", x.len());
+impl Default for ImmRope {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for ImmRope {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for chunk in self.chunks() {
+            f.write_str(chunk)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for ImmRope {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.to_string(), f)
+    }
+}
+
+#[test]
+fn test_rope_editing() {
+    let text: String = (0..2000).map(|i| format!("line {} é\n", i)).collect();
+    let mut rope = ImmRope::from(&*text);
+    let mut expected = text.clone();
+    assert_eq!(rope.to_string(), expected);
+    assert_eq!(rope.len_lines(), 2001);
+
+    for i in 0..200 {
+        let index = (i * 7919) % expected.len();
+        let index = (0..=index).rev().find(|&i| expected.is_char_boundary(i)).unwrap();
+        if i % 3 == 0 {
+            let end = (index + 50).min(expected.len());
+            let end = (index..=end).rev().find(|&i| expected.is_char_boundary(i)).unwrap();
+            rope = rope.remove(index..end);
+            expected.replace_range(index..end, "");
+        } else {
+            rope = rope.insert(index, "ab\nçd");
+            expected.insert_str(index, "ab\nçd");
+        }
+    }
+    assert_eq!(rope.to_string(), expected);
+    assert_eq!(rope.len_chars(), expected.chars().count());
+    assert_eq!(rope.len_lines(), expected.matches('\n').count() + 1);
+    let line = 1234;
+    let start = rope.line_to_byte(line);
+    assert_eq!(rope.byte_to_line(start), line);
+    assert_eq!(rope.line(line), expected.lines().nth(line).unwrap());
+    assert_eq!(rope.slice(100..400).to_string(), &expected[100..400]);
+    assert_eq!(rope.char_to_byte(rope.byte_to_char(500)), 500);
+}
+
+// A small xorshift generator, so that the randomized test is repeatable without a dependency
+#[cfg(test)]
+struct XorShift(u64);
+
+#[cfg(test)]
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    // Returns a random character boundary in the string.
+    fn boundary(&mut self, s: &str) -> usize {
+        let mut index = self.below(s.len() + 1);
+        while !s.is_char_boundary(index) {
+            index -= 1;
+        }
+        index
+    }
+
+    fn string(&mut self) -> String {
+        const PIECES: [&str; 8] = ["a", "bc", "\n", " ", "é", "日本", "😀", "line\n"];
+        let len = self.below(if self.below(10) == 0 { 2000 } else { 20 });
+        (0..len).map(|_| PIECES[self.below(PIECES.len())]).collect()
+    }
+}
+
+#[test]
+fn test_rope_matches_string() {
+    let mut rng = XorShift(0x2545_F491_4F6C_DD1D);
+    let mut rope = ImmRope::new();
+    let mut expected = String::new();
+    let mut snapshots = vec![];
+
+    for _ in 0..500 {
+        match rng.below(5) {
+            0 | 1 => {
+                let (index, s) = (rng.boundary(&expected), rng.string());
+                rope = rope.insert(index, &s);
+                expected.insert_str(index, &s);
+            }
+            2 => {
+                let (a, b) = (rng.boundary(&expected), rng.boundary(&expected));
+                let range = a.min(b)..a.max(b);
+                rope = rope.remove(range.clone());
+                expected.replace_range(range, "");
+            }
+            3 => {
+                let (a, b) = (rng.boundary(&expected), rng.boundary(&expected));
+                let range = a.min(b)..a.max(b);
+                assert_eq!(rope.slice(range.clone()), &expected[range]);
+            }
+            _ => {
+                let s = rng.string();
+                rope = rope.concat(&ImmRope::from(&*s));
+                expected.push_str(&s);
+            }
+        }
+        snapshots.push((rope.clone(), expected.clone()));
+
+        assert_eq!(rope, &*expected);
+        assert_eq!(rope.len_chars(), expected.chars().count());
+        assert_eq!(rope.len_utf16(), expected.encode_utf16().count());
+        assert_eq!(rope.len_lines(), expected.split('\n').count());
+
+        let index = rng.boundary(&expected);
+        let chars = expected[..index].chars().count();
+        let utf16 = expected[..index].encode_utf16().count();
+        let line = expected[..index].matches('\n').count();
+        assert_eq!(rope.byte_to_char(index), chars);
+        assert_eq!(rope.char_to_byte(chars), index);
+        assert_eq!(rope.byte_to_utf16(index), utf16);
+        assert_eq!(rope.utf16_to_byte(utf16), index);
+        assert_eq!(rope.byte_to_line(index), line);
+        let line_start = expected[..index].rfind('\n').map_or(0, |i| i + 1);
+        assert_eq!(rope.line_to_byte(line), line_start);
+        if index < expected.len() {
+            assert_eq!(rope.char_at(index), expected[index..].chars().next().unwrap());
+        }
+    }
+
+    let lines: Vec<String> = rope.lines().map(|line| line.to_string()).collect();
+    assert_eq!(lines, expected.split('\n').collect::<Vec<_>>());
+    assert_eq!(rope.chunks().collect::<String>(), expected);
+
+    // Old versions are unaffected by later edits.
+    for (rope, expected) in snapshots {
+        assert_eq!(rope, &*expected);
+    }
 }
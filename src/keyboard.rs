@@ -0,0 +1,258 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ *
+ */
+
+use std::any::Any;
+use std::fmt;
+use std::rc::Rc;
+
+use zaffre::Point2;
+
+use crate::control::{
+    Control, focused_control, send_bubbling, send_context_menu_requested, Visibility,
+};
+use crate::window::{open_popups, WindowData};
+
+/// A key on the keyboard, independent of the keyboard layout's modifiers.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum Key {
+    Escape,
+    Tab,
+    Backspace,
+    Enter,
+    Space,
+    Insert,
+    Delete,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Left,
+    Up,
+    Right,
+    Down,
+    /// The key that opens a context menu, usually next to the right Ctrl key.
+    Menu,
+    Shift,
+    Control,
+    Alt,
+    F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+    A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    D0, D1, D2, D3, D4, D5, D6, D7, D8, D9,
+}
+
+pub(crate) const LETTER_KEYS: [Key; 26] = [
+    Key::A, Key::B, Key::C, Key::D, Key::E, Key::F, Key::G, Key::H, Key::I, Key::J, Key::K, Key::L,
+    Key::M, Key::N, Key::O, Key::P, Key::Q, Key::R, Key::S, Key::T, Key::U, Key::V, Key::W, Key::X,
+    Key::Y, Key::Z,
+];
+
+pub(crate) const DIGIT_KEYS: [Key; 10] = [
+    Key::D0, Key::D1, Key::D2, Key::D3, Key::D4, Key::D5, Key::D6, Key::D7, Key::D8, Key::D9,
+];
+
+/// The modifier keys held down when an event happened.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub control: bool,
+    pub alt: bool,
+    /// The Windows key or the Command key on macOS
+    pub logo: bool,
+}
+
+impl Modifiers {
+    pub fn none() -> Self {
+        Self::default()
+    }
+}
+
+/// A key pressed with modifiers, like Ctrl+S, that runs a command.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Shortcut {
+    pub modifiers: Modifiers,
+    pub key: Key,
+}
+
+impl Shortcut {
+    pub fn new(modifiers: Modifiers, key: Key) -> Self {
+        Shortcut { modifiers, key }
+    }
+
+    /// Returns whether the key and modifiers of the event are exactly the shortcut's.
+    pub fn matches(&self, event: &KeyDownEvent) -> bool {
+        event.key == self.key && event.modifiers == self.modifiers
+    }
+}
+
+/// Formats the shortcut the way menus show it, like `Ctrl+Shift+S`.
+impl fmt::Display for Shortcut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let logo = if cfg!(windows) { "Win+" } else { "Super+" };
+        let Modifiers { shift, control, alt, logo: has_logo } = self.modifiers;
+        let modifier_names =
+            [(control, "Ctrl+"), (shift, "Shift+"), (alt, "Alt+"), (has_logo, logo)];
+        for &(down, name) in modifier_names.iter() {
+            if down {
+                f.write_str(name)?;
+            }
+        }
+        let name = match self.key {
+            Key::Escape => "Esc",
+            Key::Backspace => "Backspace",
+            Key::Delete => "Del",
+            Key::Insert => "Ins",
+            Key::PageUp => "PgUp",
+            Key::PageDown => "PgDn",
+            key => {
+                if let Some(c) = key_to_char(key) {
+                    return write!(f, "{}", c.to_ascii_uppercase());
+                }
+                return write!(f, "{:?}", key);
+            }
+        };
+        f.write_str(name)
+    }
+}
+
+/// Sent to the focused control when a key is pressed. If the focused control doesn't handle it,
+/// it is sent to each ancestor in turn until one sets `EventRoute::handled`.
+#[non_exhaustive]
+pub struct KeyDownEvent {
+    pub key: Key,
+    pub modifiers: Modifiers,
+    /// Whether the event is from the key being held down and repeating.
+    pub repeat: bool,
+}
+
+/// Sent to the focused control when a key is released. It bubbles the same way as
+/// `KeyDownEvent`.
+#[non_exhaustive]
+pub struct KeyUpEvent {
+    pub key: Key,
+    pub modifiers: Modifiers,
+}
+
+/// Sent to the focused control when text is typed, after the `KeyDownEvent` for the key that
+/// produced it. The text has already been through the keyboard layout and any input method, so
+/// it doesn't correspond to a single key. It bubbles the same way as `KeyDownEvent`.
+#[non_exhaustive]
+pub struct TextInputEvent {
+    pub text: String,
+}
+
+// Sent to the controls in a window, until one handles it, when a letter or digit is pressed with
+// Alt and the focused control didn't handle it.
+pub(crate) struct MnemonicEvent {
+    pub(crate) c: char,
+}
+
+// Returns the lowercase character on a letter or digit key.
+pub(crate) fn key_to_char(key: Key) -> Option<char> {
+    if let Some(i) = LETTER_KEYS.iter().position(|k| *k == key) {
+        Some((b'a' + i as u8) as char)
+    } else if let Some(i) = DIGIT_KEYS.iter().position(|k| *k == key) {
+        Some((b'0' + i as u8) as char)
+    } else {
+        None
+    }
+}
+
+// Sends an event to each visible and enabled control in a tree, parents first, until one of them
+// handles it.
+fn send_to_shown_until_handled(control: &Rc<dyn Control>, event: &mut dyn Any) -> bool {
+    if control.visibility() != Visibility::Visible || !control.enabled() {
+        return false;
+    }
+    if control.event_handlers().send(event) {
+        return true;
+    }
+    let children = control.children().borrow().to_vec();
+    children.iter().any(|child| send_to_shown_until_handled(child, event))
+}
+
+// Returns the focused control if it is in the specified window.
+fn focused_control_in(window: &WindowData) -> Option<Rc<dyn Control>> {
+    focused_control().filter(|focused| {
+        focused.window().map_or(false, |w| std::ptr::eq(&**w, window))
+    })
+}
+
+// Called by the backends when a key is pressed in a window.
+pub(crate) fn dispatch_key_down(window: &WindowData, mut event: KeyDownEvent) {
+    // Popups aren't activated, so they don't get keys from the platform. The content of the last
+    // one shown, like an open menu, gets the first chance to handle them instead.
+    if let Some(popup) = open_popups().last() {
+        let children = popup.children().borrow().to_vec();
+        if children.iter().any(|child| child.event_handlers().send(&mut event)) {
+            return;
+        }
+    }
+    // Accelerators take precedence over the focused control, so a text box doesn't swallow them.
+    let accelerator = window.accelerators().into_iter().find(|action| {
+        action.enabled() && action.shortcut().map_or(false, |s| s.matches(&event))
+    });
+    if let Some(action) = accelerator {
+        action.trigger();
+        return;
+    }
+    let focused = focused_control_in(window);
+    if let Some(focused) = focused.as_ref() {
+        if send_bubbling(focused, &mut event) {
+            return;
+        }
+    }
+    if event.repeat {
+        return;
+    }
+    let shift = Modifiers { shift: true, ..Modifiers::none() };
+    let menu_key = (event.key == Key::Menu && event.modifiers == Modifiers::none()) ||
+        (event.key == Key::F10 && event.modifiers == shift);
+    if let (true, Some(focused)) = (menu_key, focused) {
+        send_context_menu_requested(focused, Point2::new(0.0, 0.0));
+        return;
+    }
+    let alt = Modifiers { alt: true, ..Modifiers::none() };
+    if event.modifiers == alt {
+        if let Some(c) = key_to_char(event.key) {
+            let children = window.children().borrow().to_vec();
+            let mut mnemonic_event = MnemonicEvent { c };
+            children.iter().any(|child| send_to_shown_until_handled(child, &mut mnemonic_event));
+        }
+        return;
+    }
+    if event.modifiers != Modifiers::none() {
+        return;
+    }
+    let button = match event.key {
+        Key::Enter => window.default_button(),
+        Key::Escape => window.cancel_button(),
+        _ => None,
+    };
+    if let Some(button) = button {
+        button.click();
+    }
+}
+
+// Called by the backends when a key is released in a window.
+pub(crate) fn dispatch_key_up(window: &WindowData, mut event: KeyUpEvent) {
+    if let Some(focused) = focused_control_in(window) {
+        send_bubbling(&focused, &mut event);
+    }
+}
+
+// Called by the backends when text is typed in a window. Control characters are dropped, since
+// keys like Backspace and Enter are handled through `KeyDownEvent`.
+pub(crate) fn dispatch_text_input(window: &WindowData, text: &str) {
+    let text: String = text.chars().filter(|c| !c.is_control()).collect();
+    if text.is_empty() {
+        return;
+    }
+    if let Some(focused) = focused_control_in(window) {
+        send_bubbling(&focused, &mut TextInputEvent { text });
+    }
+}
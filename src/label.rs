@@ -0,0 +1,645 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ *
+ */
+
+use std::cell::{Cell, RefCell};
+use std::ops::{Deref, Range};
+use std::rc::{Rc, Weak};
+
+use zaffre::text::{FormattedString, TextLayout};
+use zaffre::{Brush, Color, Painter, Point2, Size2};
+
+use crate::clipboard::Clipboard;
+use crate::control::{
+    control_handle, Control, FocusLostEvent, MouseButton, MouseCaptureLostEvent, MouseDownEvent,
+    MouseDraggedEvent, MouseUpEvent, PaintingEvent, set_focus, SubControl, SubControlData,
+    SubControlRef,
+};
+use crate::event_vec::{EventHandler, EventRoute};
+use crate::keyboard::{Key, KeyDownEvent, MnemonicEvent, Modifiers};
+use crate::layout::{HorizontalAlignment, VerticalAlignment};
+use crate::mnemonic::MnemonicText;
+use crate::theme;
+
+// TODO: generate with a proc macro
+// start proc macro generated
+#[derive(Clone)]
+pub struct Label(Rc<LabelData>);
+
+impl Label {
+    pub fn new() -> Self {
+        SubControl::register_handle(Label(Rc::new(LabelData::new())))
+    }
+}
+
+impl Default for Label {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deref for Label {
+    type Target = Rc<LabelData>;
+    fn deref(&self) -> &Rc<LabelData> {
+        &self.0
+    }
+}
+
+impl From<Label> for Rc<dyn Control> {
+    fn from(self_: Label) -> Self {
+        self_.0 as Rc<dyn Control>
+    }
+}
+
+impl From<Label> for Rc<dyn EventHandler> {
+    fn from(self_: Label) -> Self {
+        self_.0 as Rc<dyn EventHandler>
+    }
+}
+// end proc macro generated
+
+/// Where text that doesn't fit is cut off and replaced with an ellipsis.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Ellipsis {
+    /// The text is cut off at the edge without an ellipsis.
+    None,
+    /// The end of the text is replaced with an ellipsis, like "A long senten…".
+    End,
+    /// The middle of the text is replaced with an ellipsis, like "C:\Users\…\file.txt". This is
+    /// useful for paths. Wrapped text is always cut off at the end.
+    Middle,
+}
+
+const ELLIPSIS: &str = "\u{2026}";
+
+// A line of a label as it is shown, which may have parts of the text replaced with an ellipsis
+struct ShownLine {
+    // The line's part of the label's text, keeping its formatting
+    string: FormattedString,
+    // Each part of the label's text in the line, as the byte index in `string` where it starts
+    // and its range in the label's text
+    pieces: Vec<(usize, Range<usize>)>,
+    // The location of the line in the control
+    location: Point2<f64>,
+}
+
+impl ShownLine {
+    fn new(string: &FormattedString, range: Range<usize>) -> Self {
+        ShownLine {
+            string: string.substring(range.clone()),
+            pieces: vec![(0, range)],
+            location: Point2::new(0.0, 0.0),
+        }
+    }
+
+    // Returns the line with the end replaced by an ellipsis so that it fits in the width.
+    fn with_end_ellipsis(string: &FormattedString, range: Range<usize>, width: f64) -> Self {
+        let line = &string.text()[range.clone()];
+        let ellipsis = ellipsis(string);
+        // Keeps the text up to `end`, without the whitespace before the ellipsis.
+        let shown = |end: usize| {
+            let kept = line[..end].trim_end().len();
+            let mut shown = string.substring(range.start..range.start + kept);
+            shown.append(&ellipsis);
+            (kept, shown)
+        };
+        let boundaries = char_boundaries(line);
+        let count = boundaries.partition_point(|&i| line_width(&shown(i).1) <= width);
+        let (kept, shown) = shown(boundaries[count.max(1) - 1]);
+        ShownLine {
+            string: shown,
+            pieces: vec![(0, range.start..range.start + kept)],
+            location: Point2::new(0.0, 0.0),
+        }
+    }
+
+    // Returns the line with the middle replaced by an ellipsis so that it fits in the width.
+    fn with_middle_ellipsis(string: &FormattedString, range: Range<usize>, width: f64) -> Self {
+        let line = &string.text()[range.clone()];
+        let ellipsis = ellipsis(string);
+        let boundaries = char_boundaries(line);
+        let char_count = boundaries.len() - 1;
+        // Keeps `n` characters, split between the start and the end.
+        let shown = |n: usize| {
+            let head = boundaries[(n + 1) / 2];
+            let tail = boundaries[char_count - n / 2];
+            let mut shown = string.substring(range.start..range.start + head);
+            shown.append(&ellipsis);
+            shown.append(&string.substring(range.start + tail..range.end));
+            (head, tail, shown)
+        };
+        let count = (0..char_count).collect::<Vec<_>>()
+            .partition_point(|&n| line_width(&shown(n).2) <= width);
+        let (head, tail, shown) = shown(count.max(1) - 1);
+        let tail_start = head + ELLIPSIS.len();
+        ShownLine {
+            string: shown,
+            pieces: vec![
+                (0, range.start..range.start + head),
+                (tail_start, range.start + tail..range.end),
+            ],
+            location: Point2::new(0.0, 0.0),
+        }
+    }
+
+    fn text(&self) -> &str {
+        self.string.text()
+    }
+
+    // Returns the index in the label's text of a byte index in the shown line. An index in an
+    // ellipsis is moved to the start of the text it replaced.
+    fn to_text_index(&self, index: usize) -> usize {
+        let mut result = self.pieces[0].1.start;
+        for (start, range) in &self.pieces {
+            if index < *start {
+                break;
+            }
+            result = range.start + (index - start).min(range.len());
+        }
+        result
+    }
+
+    // Returns the byte index in the shown line of an index in the label's text. If the index
+    // was replaced with an ellipsis, the start of the ellipsis is returned.
+    fn to_shown_index(&self, index: usize) -> usize {
+        let mut result = 0;
+        for (start, range) in &self.pieces {
+            if index < range.start {
+                break;
+            }
+            result = start + (index - range.start).min(range.len());
+        }
+        result
+    }
+
+    fn text_range(&self) -> Range<usize> {
+        self.pieces[0].1.start..self.pieces.last().unwrap().1.end
+    }
+}
+
+fn char_boundaries(text: &str) -> Vec<usize> {
+    text.char_indices().map(|(i, _)| i).chain(Some(text.len())).collect()
+}
+
+// Returns an ellipsis in the initial font and color of a string.
+fn ellipsis(string: &FormattedString) -> FormattedString {
+    let mut ellipsis = string.clone();
+    ellipsis.clear_and_set_text_from(&ELLIPSIS.to_owned());
+    ellipsis
+}
+
+// Returns the width of a string laid out on one line, including trailing whitespace.
+fn line_width(string: &FormattedString) -> f64 {
+    theme::layout_x_of_index(&theme::layout_line(string.clone()), string.text().len())
+}
+
+/// A control that shows text.
+///
+/// A label can have a mnemonic, marked with `&` like in `Button::set_text()`. Since a label can't
+/// be focused itself, pressing its mnemonic focuses its target control, such as the text box it
+/// describes. The text can also be formatted, with `set_formatted_text()`.
+//#[dynamin::control]
+pub struct LabelData {
+    sub_control: SubControlData,
+    text: RefCell<MnemonicText>,
+    // The text with its formatting, which is the default font and color unless it was set with
+    // `set_formatted_text()`
+    string: RefCell<FormattedString>,
+    word_wrap: Cell<bool>,
+    ellipsis: Cell<Ellipsis>,
+    alignment: Cell<(HorizontalAlignment, VerticalAlignment)>,
+    target: RefCell<Option<Weak<dyn Control>>>,
+    selectable: Cell<bool>,
+    // The selected range of the text, as (anchor, caret)
+    selection: Cell<(usize, usize)>,
+    selecting: Cell<bool>,
+}
+
+impl SubControlRef for LabelData {
+    fn sub_control_ref(&self) -> &SubControlData { &self.sub_control }
+
+    // The best size is the size of the text without wrapping.
+    fn best_size(&self) -> Size2<f64> {
+        let paragraphs = self.paragraphs();
+        let width = paragraphs.iter().map(|p| line_width(&p.string)).fold(0.0, f64::max);
+        Size2::new(width.ceil(), paragraphs.len() as f64 * line_height(&paragraphs))
+    }
+}
+
+impl EventHandler for LabelData {
+    fn on_event(&self, route: &mut EventRoute) {
+        if let Some(MnemonicEvent { c }) = route.event.downcast_mut() {
+            if self.text.borrow().matches(*c) {
+                if let Some(target) = self.target() {
+                    if set_focus(&target) {
+                        route.handled = true;
+                    }
+                }
+            }
+        }
+
+        if self.selectable.get() {
+            self.on_selection_event(route);
+        }
+
+        if let Some(PaintingEvent { painter }) = route.event.downcast_mut() {
+            self.paint(&mut **painter);
+        }
+    }
+}
+
+impl LabelData {
+    pub fn new() -> Self {
+        LabelData {
+            sub_control: SubControlData::new(),
+            text: RefCell::new(MnemonicText::default()),
+            string: RefCell::new(theme::formatted_string("", theme::text_color(true))),
+            word_wrap: Cell::new(false),
+            ellipsis: Cell::new(Ellipsis::None),
+            alignment: Cell::new((HorizontalAlignment::Left, VerticalAlignment::Top)),
+            target: RefCell::new(None),
+            selectable: Cell::new(false),
+            selection: Cell::new((0, 0)),
+            selecting: Cell::new(false),
+        }
+    }
+
+    /// Returns the label's text, without the `&` marking the mnemonic.
+    pub fn text(&self) -> String {
+        self.text.borrow().text.clone()
+    }
+
+    /// Sets the text shown. An `&` before a character makes it the mnemonic, which is underlined
+    /// and focuses the target when pressed with Alt. Use `&&` for an ampersand. Newlines start a
+    /// new line.
+    pub fn set_text(&self, text: &str) {
+        let text = MnemonicText::parse(text);
+        let mut string = theme::formatted_string(&text.text, theme::text_color(true));
+        if let Some(range) = text.mnemonic_range() {
+            string.set_underline(range, true);
+        }
+        *self.text.borrow_mut() = text;
+        self.set_string(string);
+    }
+
+    /// Returns the label's text with its formatting.
+    pub fn formatted_text(&self) -> FormattedString {
+        self.string.borrow().clone()
+    }
+
+    /// Sets the text shown, keeping its fonts, colors and other formatting through wrapping and
+    /// ellipsis. The text has no mnemonic. When the label is disabled, it is drawn in the
+    /// disabled text color.
+    pub fn set_formatted_text(&self, text: FormattedString) {
+        *self.text.borrow_mut() =
+            MnemonicText { text: text.text().to_owned(), mnemonic_index: None };
+        self.set_string(text);
+    }
+
+    fn set_string(&self, string: FormattedString) {
+        *self.string.borrow_mut() = string;
+        self.selection.set((0, 0));
+        self.sub_control.best_size_changed();
+        self.repaint_later();
+    }
+
+    /// Returns whether lines too wide for the label are wrapped. It is off by default.
+    pub fn word_wrap(&self) -> bool {
+        self.word_wrap.get()
+    }
+
+    pub fn set_word_wrap(&self, word_wrap: bool) {
+        self.word_wrap.set(word_wrap);
+        self.sub_control.best_size_changed();
+        self.repaint_later();
+    }
+
+    pub fn ellipsis(&self) -> Ellipsis {
+        self.ellipsis.get()
+    }
+
+    /// Sets how text that doesn't fit is cut off. With word wrap on, the lines that don't fit
+    /// vertically are dropped and the last line shown ends with an ellipsis.
+    pub fn set_ellipsis(&self, ellipsis: Ellipsis) {
+        self.ellipsis.set(ellipsis);
+        self.sub_control.best_size_changed();
+        self.repaint_later();
+    }
+
+    /// Returns how the text is positioned in the label. It is at the top left by default.
+    pub fn alignment(&self) -> (HorizontalAlignment, VerticalAlignment) {
+        self.alignment.get()
+    }
+
+    pub fn set_alignment(&self, horizontal: HorizontalAlignment, vertical: VerticalAlignment) {
+        self.alignment.set((horizontal, vertical));
+        self.repaint_later();
+    }
+
+    /// Returns the control that is focused when the mnemonic is pressed.
+    pub fn target(&self) -> Option<Rc<dyn Control>> {
+        self.target.borrow().as_ref().and_then(Weak::upgrade)
+    }
+
+    /// Sets the control that is focused when the mnemonic is pressed. The label only holds a weak
+    /// reference to it.
+    pub fn set_target(&self, target: Option<&Rc<dyn Control>>) {
+        *self.target.borrow_mut() = target.map(Rc::downgrade);
+    }
+
+    /// Returns whether the text can be selected with the mouse and copied.
+    pub fn selectable(&self) -> bool {
+        self.selectable.get()
+    }
+
+    /// Sets whether the text can be selected with the mouse and copied with Ctrl+C. A selectable
+    /// label can be focused.
+    pub fn set_selectable(&self, selectable: bool) {
+        self.selectable.set(selectable);
+        self.set_focusable(selectable);
+        if !selectable {
+            self.selection.set((0, 0));
+            self.selecting.set(false);
+        }
+        self.repaint_later();
+    }
+
+    /// Returns the selected range of the text. It is empty if nothing is selected.
+    pub fn selection(&self) -> Range<usize> {
+        let (anchor, caret) = self.selection.get();
+        anchor.min(caret)..anchor.max(caret)
+    }
+
+    pub fn selected_text(&self) -> String {
+        self.text.borrow().text[self.selection()].to_owned()
+    }
+
+    fn on_selection_event(&self, route: &mut EventRoute) {
+        if let Some(MouseDownEvent { button: MouseButton::Left, location, .. }) =
+            route.event.downcast_mut()
+        {
+            if let Some(handle) = control_handle(self) {
+                set_focus(&handle);
+            }
+            let index = self.index_at_point(*location);
+            self.selection.set((index, index));
+            self.selecting.set(true);
+            self.repaint_later();
+            route.handled = true;
+        }
+
+        if let Some(MouseDraggedEvent { location }) = route.event.downcast_mut() {
+            if self.selecting.get() {
+                let (anchor, _) = self.selection.get();
+                self.selection.set((anchor, self.index_at_point(*location)));
+                self.repaint_later();
+            }
+        }
+
+        if let Some(MouseUpEvent { button: MouseButton::Left, .. }) = route.event.downcast_mut() {
+            self.selecting.set(false);
+            route.handled = true;
+        }
+
+        if let Some(MouseCaptureLostEvent) = route.event.downcast_mut() {
+            self.selecting.set(false);
+        }
+
+        if let Some(FocusLostEvent) = route.event.downcast_mut() {
+            self.repaint_later();
+        }
+
+        if let Some(KeyDownEvent { key, modifiers, .. }) = route.event.downcast_mut() {
+            let control = Modifiers { control: true, ..Modifiers::none() };
+            if *modifiers == control {
+                match key {
+                    Key::C | Key::Insert => {
+                        if !self.selection().is_empty() {
+                            Clipboard::set_text(&self.selected_text());
+                        }
+                        route.handled = true;
+                    }
+                    Key::A => {
+                        let len = self.text.borrow().text.len();
+                        self.selection.set((0, len));
+                        self.repaint_later();
+                        route.handled = true;
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    // Returns each paragraph of the text, which is separated from the next by a newline, laid out
+    // on one line.
+    fn paragraphs(&self) -> Vec<Paragraph> {
+        let string = self.string.borrow();
+        let mut paragraphs = vec![];
+        let mut start = 0;
+        for text in string.text().split('\n') {
+            let range = start..start + text.len();
+            let paragraph = string.substring(range.clone());
+            let layout = theme::layout_line(paragraph.clone());
+            paragraphs.push(Paragraph { string: paragraph, range, layout });
+            start += text.len() + 1;
+        }
+        paragraphs
+    }
+
+    // Breaks the text into the lines shown, applying wrapping, ellipsis and alignment. Returns
+    // them with the height of each line.
+    fn shown_lines(&self) -> (Vec<ShownLine>, f64) {
+        let string = self.string.borrow();
+        let text = string.text();
+        let size = self.size();
+        let paragraphs = self.paragraphs();
+        let line_height = line_height(&paragraphs);
+        let (horizontal, vertical) = self.alignment.get();
+        let ellipsis = self.ellipsis.get();
+        let max_lines = ((size.height / line_height).floor() as usize).max(1);
+
+        let wrap = self.word_wrap.get() && size.width > 0.0;
+        let wrap_width = if wrap { Some(size.width) } else { None };
+        // The range of each line in the text, and its width
+        let mut ranges = vec![];
+        for paragraph in &paragraphs {
+            let x_of = |index: usize| theme::layout_x_of_index(&paragraph.layout, index);
+            let paragraph_text = &text[paragraph.range.clone()];
+            for line in theme::wrap_layout(paragraph_text, &paragraph.layout, wrap_width) {
+                let start = paragraph.range.start;
+                let width = x_of(line.end) - x_of(line.start);
+                ranges.push((start + line.start..start + line.end, width));
+            }
+        }
+
+        let mut lines = vec![];
+        let truncated = wrap && ranges.len() > max_lines;
+        if truncated {
+            ranges.truncate(max_lines);
+        }
+        let last = ranges.len() - 1;
+        for (i, (range, width)) in ranges.into_iter().enumerate() {
+            let fits = width <= size.width;
+            let line = if truncated && i == last && ellipsis != Ellipsis::None {
+                // Show that the text continues past the last line shown.
+                let paragraph_end =
+                    text[range.start..].find('\n').map_or(text.len(), |i| range.start + i);
+                ShownLine::with_end_ellipsis(&string, range.start..paragraph_end, size.width)
+            } else if fits || ellipsis == Ellipsis::None {
+                ShownLine::new(&string, range)
+            } else if ellipsis == Ellipsis::Middle && !wrap {
+                ShownLine::with_middle_ellipsis(&string, range, size.width)
+            } else {
+                ShownLine::with_end_ellipsis(&string, range, size.width)
+            };
+            lines.push(line);
+        }
+
+        let top = vertical.offset(size.height, lines.len() as f64 * line_height);
+        for (i, line) in lines.iter_mut().enumerate() {
+            let x = horizontal.offset(size.width, line_width(&line.string));
+            line.location = Point2::new(x.round(), (top + i as f64 * line_height).round());
+        }
+        (lines, line_height)
+    }
+
+    // Returns the index in the text closest to a point.
+    fn index_at_point(&self, location: Point2<f64>) -> usize {
+        let (lines, line_height) = self.shown_lines();
+        let line = lines.iter()
+            .position(|line| location.y < line.location.y + line_height)
+            .unwrap_or(lines.len() - 1);
+        let line = &lines[line];
+        let layout = theme::layout_line(line.string.clone());
+        line.to_text_index(theme::layout_index_at_x(&layout, location.x - line.location.x))
+    }
+
+    fn paint(&self, painter: &mut dyn Painter) {
+        let enabled = self.is_effectively_enabled();
+        let selection = if self.selectable.get() && self.focused() {
+            self.selection()
+        } else {
+            0..0
+        };
+        let (lines, line_height) = self.shown_lines();
+
+        for line in lines {
+            let range = line.text_range();
+            let mut string = line.string.clone();
+            if !enabled {
+                string.set_brush(0..line.text().len(), Brush::Solid(theme::text_color(false)));
+            }
+
+            let start = line.to_shown_index(selection.start.max(range.start).min(range.end));
+            let end = line.to_shown_index(selection.end.max(range.start).min(range.end));
+            if end > start {
+                let layout = theme::layout_line(line.string.clone());
+                let left = line.location.x + theme::layout_x_of_index(&layout, start);
+                let right = line.location.x + theme::layout_x_of_index(&layout, end);
+                painter.fill_path(
+                    &mut theme::rect_path(
+                        left as f32, line.location.y as f32,
+                        right as f32, (line.location.y + line_height) as f32,
+                    ).path_iter(),
+                    &Brush::Solid(Color::from_rgba(0, 120, 215, 255)),
+                );
+                string.set_brush(start..end, Brush::Solid(Color::from_rgba(255, 255, 255, 255)));
+            }
+
+            painter.save();
+            painter.translate(line.location.x, line.location.y);
+            theme::layout_line(string).draw(painter);
+            painter.restore();
+        }
+    }
+}
+
+// A paragraph of a label's text laid out on one line, for measuring it
+struct Paragraph {
+    string: FormattedString,
+    // The range of the paragraph in the text
+    range: Range<usize>,
+    layout: TextLayout,
+}
+
+// Returns the height of each line of the text, which fits the tallest paragraph. Lines are at
+// least as tall as a line in the default font, so an empty label isn't zero high.
+fn line_height(paragraphs: &[Paragraph]) -> f64 {
+    let line = theme::layout_line(theme::formatted_string("Xg", theme::text_color(true)));
+    paragraphs.iter()
+        .map(|p| theme::text_size(&p.layout).height)
+        .fold(theme::text_size(&line).height, f64::max)
+        .ceil()
+}
+
+#[test]
+fn test_shown_line_ellipsis() {
+    let text = "C:\\Users\\someone\\Documents\\report.txt";
+    let string = theme::formatted_string(text, theme::text_color(true));
+    let width = theme::line_width("C:\\Users\\some") + 0.5;
+
+    let line = ShownLine::with_end_ellipsis(&string, 0..text.len(), width);
+    assert!(line.text().ends_with(ELLIPSIS));
+    assert!(line_width(&line.string) <= width);
+    assert_eq!(line.to_text_index(line.text().len()), line.pieces[0].1.end);
+
+    let line = ShownLine::with_middle_ellipsis(&string, 0..text.len(), width);
+    assert!(line.text().starts_with("C:"));
+    assert!(line.text().ends_with(".txt"));
+    assert!(line_width(&line.string) <= width);
+    // Indexes in the elided middle map to the start of the ellipsis.
+    let (tail_start, tail) = line.pieces[1].clone();
+    assert_eq!(line.to_shown_index(tail.start - 1), line.pieces[0].1.end);
+    assert_eq!(line.to_text_index(tail_start + 1), tail.start + 1);
+    assert_eq!(line.to_shown_index(text.len()), line.text().len());
+}
+
+#[test]
+fn test_label_wrapping() {
+    let label = Label::new();
+    label.set_text("the quick brown fox jumps over the lazy dog");
+    label.set_word_wrap(true);
+    let width = theme::line_width("the quick brown ") + 0.5;
+    label.set_size(&Size2::new(width, 1000.0));
+
+    let (lines, line_height) = label.shown_lines();
+    assert!(lines.len() > 1);
+    assert_eq!(lines[0].text().trim_end(), "the quick brown");
+    for (i, line) in lines.iter().enumerate() {
+        // Trailing spaces can hang past the edge.
+        assert!(theme::line_width(line.text().trim_end()) <= width);
+        assert_eq!(line.location.y, (i as f64 * line_height).round());
+    }
+    let shown: String = lines.iter().map(|line| line.text()).collect();
+    assert_eq!(shown, label.text());
+
+    // Lines that don't fit vertically are dropped, and the last one shown ends with an ellipsis.
+    label.set_ellipsis(Ellipsis::End);
+    label.set_size(&Size2::new(width, line_height * 2.0));
+    let (lines, _) = label.shown_lines();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[1].text().ends_with(ELLIPSIS));
+}
+
+#[test]
+fn test_label_mnemonic_focuses_target() {
+    use crate::control::focused_control;
+
+    let label = Label::new();
+    label.set_text("&Name:");
+    let target = SubControl::new();
+    target.set_focusable(true);
+    let target: Rc<dyn Control> = target.into();
+    label.set_target(Some(&target));
+
+    let mut event = MnemonicEvent { c: 'x' };
+    assert!(!label.event_handlers().send(&mut event));
+    assert!(focused_control().is_none());
+    let mut event = MnemonicEvent { c: 'N' };
+    assert!(label.event_handlers().send(&mut event));
+    assert!(target.focused());
+}
@@ -0,0 +1,232 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ *
+ */
+
+use std::cell::Cell;
+use std::ops::Deref;
+use std::rc::Rc;
+
+use zaffre::{Point2, Size2};
+
+use crate::control::{
+    ChildrenChangedEvent, Control, SizeChangedEvent, SubControl, SubControlData, SubControlRef,
+    Visibility,
+};
+use crate::event_vec::{EventHandler, EventRoute};
+
+/// How content is positioned horizontally within the space available for it.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum HorizontalAlignment {
+    Left,
+    Center,
+    Right,
+}
+
+/// How content is positioned vertically within the space available for it.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum VerticalAlignment {
+    Top,
+    Center,
+    Bottom,
+}
+
+// Returns the offset of content of the specified length, aligned in the available length.
+fn align_offset(available: f64, len: f64, start: bool, end: bool) -> f64 {
+    if start {
+        0.0
+    } else if end {
+        available - len
+    } else {
+        ((available - len) / 2.0).round()
+    }
+}
+
+impl HorizontalAlignment {
+    pub(crate) fn offset(self, available: f64, width: f64) -> f64 {
+        align_offset(available, width,
+            self == HorizontalAlignment::Left, self == HorizontalAlignment::Right)
+    }
+}
+
+impl VerticalAlignment {
+    pub(crate) fn offset(self, available: f64, height: f64) -> f64 {
+        align_offset(available, height,
+            self == VerticalAlignment::Top, self == VerticalAlignment::Bottom)
+    }
+}
+
+/// The direction that a control is laid out or oriented in.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Orientation {
+    Horizontal,
+    Vertical,
+}
+
+// TODO: generate with a proc macro
+// start proc macro generated
+#[derive(Clone)]
+pub struct StackPanel(Rc<StackPanelData>);
+
+impl StackPanel {
+    pub fn new() -> Self {
+        SubControl::register_handle(StackPanel(Rc::new(StackPanelData::new())))
+    }
+}
+
+impl Default for StackPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deref for StackPanel {
+    type Target = Rc<StackPanelData>;
+    fn deref(&self) -> &Rc<StackPanelData> {
+        &self.0
+    }
+}
+
+impl From<StackPanel> for Rc<dyn Control> {
+    fn from(self_: StackPanel) -> Self {
+        self_.0 as Rc<dyn Control>
+    }
+}
+
+impl From<StackPanel> for Rc<dyn EventHandler> {
+    fn from(self_: StackPanel) -> Self {
+        self_.0 as Rc<dyn EventHandler>
+    }
+}
+// end proc macro generated
+
+/// A container that places its children one after another in a row or column.
+///
+/// Each child is given its best size along the panel's orientation and is stretched to fill the
+/// panel in the other direction. Children that are `Visibility::Invisible` still take up space,
+/// but children that are `Visibility::Gone` are skipped as though they weren't in the panel.
+//#[dynamin::control]
+pub struct StackPanelData {
+    sub_control: SubControlData,
+    orientation: Cell<Orientation>,
+    spacing: Cell<f64>,
+}
+
+impl SubControlRef for StackPanelData {
+    fn sub_control_ref(&self) -> &SubControlData { &self.sub_control }
+}
+
+impl EventHandler for StackPanelData {
+    fn on_event(&self, route: &mut EventRoute) {
+        if let Some(SizeChangedEvent) = route.event.downcast_mut() {
+            self.layout();
+        } else if let Some(ChildrenChangedEvent) = route.event.downcast_mut() {
+            self.layout();
+        }
+    }
+}
+
+impl StackPanelData {
+    pub fn new() -> Self {
+        StackPanelData {
+            sub_control: SubControlData::new(),
+            orientation: Cell::new(Orientation::Vertical),
+            spacing: Cell::new(0.0),
+        }
+    }
+
+    pub fn orientation(&self) -> Orientation {
+        self.orientation.get()
+    }
+
+    pub fn set_orientation(&self, orientation: Orientation) {
+        self.orientation.set(orientation);
+        self.layout();
+        self.repaint_later();
+    }
+
+    /// Returns the space between each child.
+    pub fn spacing(&self) -> f64 {
+        self.spacing.get()
+    }
+
+    pub fn set_spacing(&self, spacing: f64) {
+        self.spacing.set(spacing);
+        self.layout();
+        self.repaint_later();
+    }
+
+    /// Sets the location and size of each child. This is done automatically when the panel's
+    /// size, orientation or spacing changes and when a `ChildrenChangedEvent` is received. It
+    /// needs to be called after changing a child in a way that changes its best size.
+    pub fn layout(&self) {
+        let size = self.size();
+        let mut pos = 0.0;
+        let mut first = true;
+        // Copy the list, since setting a child's size can make it change its own children.
+        let children = self.children().borrow().to_vec();
+        for child in children.iter() {
+            if child.visibility() == Visibility::Gone {
+                continue;
+            }
+            if !first {
+                pos += self.spacing.get();
+            }
+            first = false;
+
+            let child_size = child.best_size();
+            match self.orientation.get() {
+                Orientation::Horizontal => {
+                    child.set_location(&Point2::new(pos, 0.0));
+                    child.set_size(&Size2::new(child_size.width, size.height));
+                    pos += child_size.width;
+                }
+                Orientation::Vertical => {
+                    child.set_location(&Point2::new(0.0, pos));
+                    child.set_size(&Size2::new(size.width, child_size.height));
+                    pos += child_size.height;
+                }
+            }
+        }
+    }
+}
+
+impl Default for StackPanelData {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[test]
+fn test_stack_panel_layout() {
+    let panel = StackPanel::new();
+    panel.set_size(&Size2::new(100.0, 200.0));
+    panel.set_spacing(5.0);
+    let children: Vec<SubControl> = (0..3).map(|_| SubControl::new()).collect();
+    for child in children.iter() {
+        child.set_best_size(Some(Size2::new(20.0, 10.0)));
+        panel.children().borrow_mut().push(child.clone());
+    }
+
+    // The panel lays out again whenever a child is added or becomes or stops being `Gone`.
+    children[0].set_visibility(Visibility::Invisible);
+    children[1].set_visibility(Visibility::Gone);
+    assert_eq!(children[0].location().y, 0.0);
+    assert_eq!(children[2].location().y, 15.0);
+    let size = children[2].size();
+    assert_eq!((size.width, size.height), (100.0, 10.0));
+
+    // The stretched width from the vertical layout isn't used as the child's best width.
+    panel.set_orientation(Orientation::Horizontal);
+    children[1].set_visibility(Visibility::Visible);
+    assert_eq!(children[1].location().x, 25.0);
+    assert_eq!(children[2].location().x, 50.0);
+    let size = children[2].size();
+    assert_eq!((size.width, size.height), (20.0, 200.0));
+
+    panel.set_size(&Size2::new(100.0, 50.0));
+    let size = children[2].size();
+    assert_eq!((size.width, size.height), (20.0, 50.0));
+}
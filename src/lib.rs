@@ -12,28 +12,77 @@
 
 #[allow(dead_code)]
 
-pub use button::{Button, ClickEvent};
-pub use control::{ChildrenVec, Control, MouseButton, MouseEnteredEvent, MouseLeftEvent, MouseDownEvent, MouseUpEvent, MouseDraggedEvent, MouseMovedEvent, PaintingEvent, set_tab_order, SubControl, Visibility};
+pub use action::{Action, ActionChangedEvent, TriggeredEvent};
+pub use button::{Button, ClickEvent, IconPlacement};
+pub use clipboard::Clipboard;
+pub use combo_box::ComboBox;
+pub use check_box::{CheckBox, CheckedChangedEvent, CheckState};
+pub use control::{AttachedToWindowEvent, capture_mouse, Children, ChildrenChangedEvent, ChildrenRefMut, ChildrenVec, clear_focus, ContextMenuRequestedEvent, Control, DetachedFromWindowEvent, EnabledChangedEvent, FocusGainedEvent, FocusLostEvent, focused_control, mouse_capture, MouseButton, MouseCaptureLostEvent, MouseEnteredEvent, MouseLeftEvent, MouseDownEvent, MouseUpEvent, MouseDraggedEvent, MouseMovedEvent, MouseWheelEvent, PaintingEvent, release_mouse, ScrollPhase, set_focus, set_tab_order, SizeChangedEvent, SubControl, Visibility, VisibilityChangedEvent};
 pub use cursor::{Cursor, CursorData};
 pub use event_vec::EventHandlerVec;
+pub use immrope::{Chunks, ImmRope, Lines};
+pub use keyboard::{Key, KeyDownEvent, KeyUpEvent, Modifiers, Shortcut, TextInputEvent};
+pub use label::{Ellipsis, Label};
+pub use list_view::{ItemActivatedEvent, ListDataSource, ListView, SelectionChangedEvent, SelectionMode};
+pub use layout::{HorizontalAlignment, Orientation, StackPanel, VerticalAlignment};
+pub use menu::{Menu, MenuBar, MenuItem};
+pub use progress_bar::ProgressBar;
+pub use radio_button::{RadioButton, RadioGroup};
+pub use scroll_bar::{ScrollBar, ValueChangedEvent};
+pub use scroll_view::{ScrollBarVisibility, ScrollOffsetChangedEvent, ScrollView};
+pub use slider::Slider;
+pub use spinner::Spinner;
+pub use table::{CellInfo, CellPainter, SortDirection, SortRequestedEvent, Table, TableModel};
+pub use text_area::{TextArea};
+pub use text_box::{TextBox, TextChangedEvent};
+pub use timer::Timer;
+pub use tool_bar::ToolBar;
+pub use tree_view::{ExpandedChangedEvent, TreeModel, TreeView};
 pub use window::{Window, WindowData, WindowBorderStyle, WindowEvent};
 use zaffre::RenderingBackend;
 
+mod action;
 mod bitfield;
 mod button;
+mod check_box;
+mod clipboard;
+mod combo_box;
 mod control;
 mod cursor;
 mod event_vec;
+mod immrope;
+mod keyboard;
+mod label;
+mod layout;
+mod list_view;
+mod menu;
+mod mnemonic;
+mod progress_bar;
+mod radio_button;
+mod scroll_bar;
+mod scroll_view;
+mod slider;
+mod spinner;
+mod table;
+mod text_area;
+mod text_box;
+mod theme;
+mod timer;
+mod tool_bar;
+mod tree_view;
 mod window;
 
 mod generic_backend;
 
-#[cfg(windows)]
+#[cfg(all(windows, not(feature = "headless")))]
 #[path = "windows_backend/mod.rs"]
 pub mod backend;
-#[cfg(unix)]
+#[cfg(all(unix, not(feature = "headless")))]
 #[path = "gtk_backend/mod.rs"]
 pub mod backend;
+#[cfg(feature = "headless")]
+#[path = "headless_backend/mod.rs"]
+pub mod backend;
 
 pub fn set_rendering_preference(pref: RenderingBackend) {
 
@@ -0,0 +1,852 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ *
+ */
+
+use std::cell::{Cell, RefCell};
+use std::ops::{Deref, Range};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use zaffre::{Brush, Color, Painter, Point2, Rect, Size2, StrokeStyle};
+
+use crate::control::{
+    control_handle, set_focus, Control, FocusGainedEvent, FocusLostEvent, MouseButton,
+    MouseDownEvent, MouseDraggedEvent, PaintingEvent, SizeChangedEvent, SubControl,
+    SubControlData, SubControlRef, Visibility,
+};
+use crate::event_vec::{EventHandler, EventRoute};
+use crate::keyboard::{Key, KeyDownEvent, Modifiers, TextInputEvent};
+use crate::scroll_view::{ScrollBarVisibility, ScrollOffsetChangedEvent, ScrollView};
+use crate::theme;
+
+// TODO: generate with a proc macro
+// start proc macro generated
+#[derive(Clone)]
+pub struct ListView(Rc<ListViewData>);
+
+impl ListView {
+    pub fn new() -> Self {
+        SubControl::register_handle(ListView(Rc::new(ListViewData::new())))
+    }
+}
+
+impl Default for ListView {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deref for ListView {
+    type Target = Rc<ListViewData>;
+    fn deref(&self) -> &Rc<ListViewData> {
+        &self.0
+    }
+}
+
+impl From<ListView> for Rc<dyn Control> {
+    fn from(self_: ListView) -> Self {
+        self_.0 as Rc<dyn Control>
+    }
+}
+
+impl From<ListView> for Rc<dyn EventHandler> {
+    fn from(self_: ListView) -> Self {
+        self_.0 as Rc<dyn EventHandler>
+    }
+}
+// end proc macro generated
+
+/// Provides the items shown in a `ListView`.
+///
+/// The list view only creates views for the items that are scrolled into view and reuses them
+/// for other items as it is scrolled, so a list can have any number of items without using
+/// memory for each one.
+pub trait ListDataSource {
+    /// Returns the number of items.
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the height of every item. All items have the same height so that the items in
+    /// view can be found without measuring the ones before them.
+    fn item_height(&self) -> f64;
+
+    /// Creates a control to show items in. It is always passed to `recycle_item_view()` before
+    /// it is shown.
+    fn create_item_view(&self) -> Rc<dyn Control>;
+
+    /// Updates a view to show the item at `index`. A view is reused for different items as the
+    /// list is scrolled, so this has to replace everything shown for the previous item.
+    fn recycle_item_view(&self, view: &Rc<dyn Control>, index: usize);
+
+    /// Returns the text that typing matches to find an item, or `None` if the item can't be
+    /// found by typing. The default implementation returns `None` for every item.
+    fn item_text(&self, _index: usize) -> Option<String> {
+        None
+    }
+}
+
+/// How many items can be selected at once.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SelectionMode {
+    Single,
+    /// Ranges of items can be selected with Shift, and items can be added to or removed from the
+    /// selection with Ctrl.
+    Multiple,
+}
+
+/// Sent to a list view after the selected items change, whether by the user or by code.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct SelectionChangedEvent;
+
+/// Sent to a list view when Enter is pressed on an item.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct ItemActivatedEvent {
+    pub index: usize,
+}
+
+// Sent to a list or tree view by the panel in its scroll view when a mouse button is pressed or
+// dragged over the panel. The location is in the panel's coordinates, and `button` is `None` for
+// a drag, which has no modifiers.
+pub(crate) struct PanelMouseEvent {
+    pub(crate) location: Point2<f64>,
+    pub(crate) button: Option<MouseButton>,
+    pub(crate) modifiers: Modifiers,
+}
+
+// Mouse events aren't sent to ancestors, so this makes the panel pass them on to the control
+// containing it as `PanelMouseEvent`s.
+pub(crate) fn forward_panel_mouse_events(panel: &SubControl, target: &dyn Control) {
+    let target = control_handle(target).map(|t| Rc::downgrade(&t));
+    panel.event_handlers().add(move |route| {
+        let mut event = if let Some(MouseDownEvent { location, button, modifiers }) =
+            route.event.downcast_mut()
+        {
+            PanelMouseEvent { location: *location, button: Some(*button), modifiers: *modifiers }
+        } else if let Some(MouseDraggedEvent { location }) = route.event.downcast_mut() {
+            PanelMouseEvent { location: *location, button: None, modifiers: Modifiers::none() }
+        } else {
+            return;
+        };
+        if let Some(target) = target.as_ref().and_then(|t| t.upgrade()) {
+            route.handled = target.event_handlers().send(&mut event);
+        }
+    });
+}
+
+// The scroll view only moves the panel, so this makes it tell the control containing it when it
+// scrolls, which then shows the items scrolled into view.
+pub(crate) fn forward_scroll_events(scroll_view: &ScrollView, target: &dyn Control) {
+    let target = control_handle(target).map(|t| Rc::downgrade(&t));
+    scroll_view.event_handlers().add(move |route| {
+        if let Some(ScrollOffsetChangedEvent) = route.event.downcast_mut() {
+            if let Some(target) = target.as_ref().and_then(|t| t.upgrade()) {
+                target.event_handlers().send(&mut ScrollOffsetChangedEvent);
+            }
+        }
+    });
+}
+
+// A set of indexes stored as sorted ranges that don't touch, so that selecting every item of a
+// long list takes no more memory than selecting one.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+struct IndexRanges(Vec<Range<usize>>);
+
+impl IndexRanges {
+    fn from_range(range: Range<usize>) -> Self {
+        IndexRanges(if range.is_empty() { vec![] } else { vec![range] })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn len(&self) -> usize {
+        self.0.iter().map(|r| r.len()).sum()
+    }
+
+    fn first(&self) -> Option<usize> {
+        self.0.first().map(|r| r.start)
+    }
+
+    fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.0.iter().flat_map(|r| r.clone())
+    }
+
+    fn contains(&self, index: usize) -> bool {
+        let i = self.0.partition_point(|r| r.end <= index);
+        self.0.get(i).map_or(false, |r| r.start <= index)
+    }
+
+    fn insert(&mut self, index: usize) {
+        if self.contains(index) {
+            return;
+        }
+        // The first range that ends at or after the index either ends right before it or starts
+        // after it.
+        let i = self.0.partition_point(|r| r.end < index);
+        let joins_prev = self.0.get(i).map_or(false, |r| r.end == index);
+        let next = if joins_prev { i + 1 } else { i };
+        let joins_next = self.0.get(next).map_or(false, |r| r.start == index + 1);
+        match (joins_prev, joins_next) {
+            (true, true) => {
+                self.0[i].end = self.0[next].end;
+                self.0.remove(next);
+            }
+            (true, false) => self.0[i].end = index + 1,
+            (false, true) => self.0[next].start = index,
+            (false, false) => self.0.insert(i, index..index + 1),
+        }
+    }
+
+    // Returns whether the index was in the set.
+    fn remove(&mut self, index: usize) -> bool {
+        if !self.contains(index) {
+            return false;
+        }
+        let i = self.0.partition_point(|r| r.end <= index);
+        let Range { start, end } = self.0[i].clone();
+        let parts = [start..index, index + 1..end];
+        self.0.splice(i..i + 1, parts.iter().filter(|r| !r.is_empty()).cloned());
+        true
+    }
+
+    // Removes the indexes from `len` on and returns whether there were any.
+    fn truncate(&mut self, len: usize) -> bool {
+        let i = self.0.partition_point(|r| r.end <= len);
+        if i == self.0.len() {
+            return false;
+        }
+        let start = self.0[i].start;
+        self.0.truncate(i);
+        if start < len {
+            self.0.push(start..len);
+        }
+        true
+    }
+}
+
+// Typed characters are added to the search until this long passes without typing.
+const TYPE_AHEAD_TIMEOUT: Duration = Duration::from_millis(1000);
+const DEFAULT_WIDTH: f64 = 200.0;
+const DEFAULT_HEIGHT: f64 = 150.0;
+
+/// A scrolling list of items provided by a `ListDataSource`.
+///
+/// Only the items in view have controls, which are reused as the list is scrolled. Item views are
+/// made transparent to input so that clicking them selects the item, but their children can
+/// still be interacted with.
+//#[dynamin::control]
+pub struct ListViewData {
+    sub_control: SubControlData,
+    scroll_view: ScrollView,
+    // The content of the scroll view, which is as tall as all the items and holds the views
+    panel: SubControl,
+    source: RefCell<Option<Rc<dyn ListDataSource>>>,
+    // The views that are showing items, with the index of the item each shows
+    views: RefCell<Vec<(usize, Rc<dyn Control>)>>,
+    // Hidden views that can be reused for items scrolled into view
+    spare_views: RefCell<Vec<Rc<dyn Control>>>,
+    selection_mode: Cell<SelectionMode>,
+    selected: RefCell<IndexRanges>,
+    // The item with the focus rectangle, which arrow keys move from
+    current: Cell<Option<usize>>,
+    // The item that a range selected with Shift starts from
+    anchor: Cell<Option<usize>>,
+    search: RefCell<String>,
+    last_typed: Cell<Option<Instant>>,
+}
+
+impl SubControlRef for ListViewData {
+    fn sub_control_ref(&self) -> &SubControlData { &self.sub_control }
+
+    fn best_size(&self) -> Size2<f64> {
+        Size2::new(DEFAULT_WIDTH, DEFAULT_HEIGHT)
+    }
+}
+
+impl EventHandler for ListViewData {
+    fn on_event(&self, route: &mut EventRoute) {
+        if let Some(PanelMouseEvent { location, button, modifiers }) = route.event.downcast_mut() {
+            let index = self.item_at(location.y);
+            match (*button, index) {
+                (Some(button), _) => self.on_item_pressed(index, button, *modifiers),
+                // Dragging selects a range from where the button was pressed.
+                (None, Some(index)) => {
+                    let extend = self.selection_mode.get() == SelectionMode::Multiple;
+                    self.select_item(index, extend, false);
+                }
+                (None, None) => {}
+            }
+            route.handled = true;
+        } else if let Some(event) = route.event.downcast_mut::<KeyDownEvent>() {
+            if self.on_key_down(event.key, event.modifiers) {
+                route.handled = true;
+            }
+        } else if let Some(TextInputEvent { text }) = route.event.downcast_mut() {
+            // A space is Ctrl+Space or selection, not the start of a search.
+            if text != " " || !self.search.borrow().is_empty() {
+                self.type_ahead(text);
+            }
+            route.handled = true;
+        } else if let Some(FocusGainedEvent) = route.event.downcast_mut() {
+            self.repaint_later();
+        } else if let Some(FocusLostEvent) = route.event.downcast_mut() {
+            self.repaint_later();
+        } else if let Some(SizeChangedEvent) = route.event.downcast_mut() {
+            self.layout();
+        } else if let Some(ScrollOffsetChangedEvent) = route.event.downcast_mut() {
+            self.layout();
+        } else if let Some(PaintingEvent { painter }) = route.event.downcast_mut() {
+            self.paint(&mut **painter);
+        }
+    }
+}
+
+impl ListViewData {
+    pub fn new() -> Self {
+        let sub_control = SubControlData::new();
+        sub_control.set_focusable(true);
+        ListViewData {
+            sub_control,
+            scroll_view: ScrollView::new(),
+            panel: SubControl::new(),
+            source: RefCell::new(None),
+            views: RefCell::new(vec![]),
+            spare_views: RefCell::new(vec![]),
+            selection_mode: Cell::new(SelectionMode::Single),
+            selected: RefCell::new(IndexRanges::default()),
+            current: Cell::new(None),
+            anchor: Cell::new(None),
+            search: RefCell::new(String::new()),
+            last_typed: Cell::new(None),
+        }
+    }
+
+    pub fn data_source(&self) -> Option<Rc<dyn ListDataSource>> {
+        self.source.borrow().clone()
+    }
+
+    /// Sets where the items come from. The selection is cleared and the views for the old
+    /// source's items are dropped.
+    pub fn set_data_source(&self, source: Option<Rc<dyn ListDataSource>>) {
+        self.add_children();
+        self.views.borrow_mut().clear();
+        self.spare_views.borrow_mut().clear();
+        self.panel.children().borrow_mut().clear();
+        *self.source.borrow_mut() = source;
+        self.current.set(None);
+        self.anchor.set(None);
+        self.scroll_view.set_scroll_offset(Point2::new(0.0, 0.0));
+        if !std::mem::take(&mut *self.selected.borrow_mut()).is_empty() {
+            self.event_handlers().send(&mut SelectionChangedEvent);
+        }
+        self.layout();
+    }
+
+    /// Shows the data source's items again after they change. Every view is updated, and
+    /// selected items past the end of the list are unselected.
+    pub fn reload(&self) {
+        let len = self.len();
+        let views = std::mem::take(&mut *self.views.borrow_mut());
+        self.spare_views.borrow_mut().extend(views.into_iter().map(|(_, view)| view));
+        if self.current.get().map_or(false, |i| i >= len) {
+            self.current.set(len.checked_sub(1));
+        }
+        if self.anchor.get().map_or(false, |i| i >= len) {
+            self.anchor.set(None);
+        }
+        let removed = self.selected.borrow_mut().truncate(len);
+        if removed {
+            self.event_handlers().send(&mut SelectionChangedEvent);
+        }
+        self.layout();
+    }
+
+    pub fn selection_mode(&self) -> SelectionMode {
+        self.selection_mode.get()
+    }
+
+    /// Sets how many items can be selected. Changing to `Single` unselects all but the current
+    /// item.
+    pub fn set_selection_mode(&self, mode: SelectionMode) {
+        self.selection_mode.set(mode);
+        if mode == SelectionMode::Single && self.selected.borrow().len() > 1 {
+            self.set_selected_index(self.current.get());
+        }
+    }
+
+    /// Returns the indexes of the selected items in increasing order.
+    pub fn selected_indices(&self) -> Vec<usize> {
+        self.selected.borrow().iter().collect()
+    }
+
+    /// Returns the selected items as ranges of indexes in increasing order. Unlike
+    /// `selected_indices()`, this doesn't take memory for each item when many are selected.
+    pub fn selected_ranges(&self) -> Vec<Range<usize>> {
+        self.selected.borrow().0.clone()
+    }
+
+    /// Returns the index of the first selected item.
+    pub fn selected_index(&self) -> Option<usize> {
+        self.selected.borrow().first()
+    }
+
+    /// Selects only the item at `index`, or no item if it is `None`, and scrolls it into view.
+    pub fn set_selected_index(&self, index: Option<usize>) {
+        match index.filter(|&i| i < self.len()) {
+            Some(index) => self.select_item(index, false, false),
+            None => self.clear_selection(),
+        }
+    }
+
+    pub fn is_selected(&self, index: usize) -> bool {
+        self.selected.borrow().contains(index)
+    }
+
+    /// Returns the item that has the focus rectangle and that the arrow keys move from.
+    pub fn current_index(&self) -> Option<usize> {
+        self.current.get()
+    }
+
+    /// Selects every item if multiple items can be selected.
+    pub fn select_all(&self) {
+        if self.selection_mode.get() == SelectionMode::Multiple {
+            self.set_selection(IndexRanges::from_range(0..self.len()));
+        }
+    }
+
+    pub fn clear_selection(&self) {
+        self.set_selection(IndexRanges::default());
+    }
+
+    /// Scrolls as little as possible to make the item at `index` entirely visible.
+    pub fn scroll_to_item(&self, index: usize) {
+        let height = match self.data_source() {
+            Some(source) => source.item_height(),
+            None => return,
+        };
+        let top = index as f64 * height;
+        let offset = self.scroll_view.scroll_offset();
+        let viewport_height = self.scroll_view.viewport_size().height;
+        if top < offset.y {
+            self.scroll_view.set_scroll_offset(Point2::new(offset.x, top));
+        } else if top + height > offset.y + viewport_height {
+            let bottom_offset = top + height - viewport_height;
+            self.scroll_view.set_scroll_offset(Point2::new(offset.x, bottom_offset));
+        }
+    }
+
+    // Convenience method to add an event handler that is called for `SelectionChangedEvent`s.
+    pub fn on_selection_changed_event<F>(&self, mut handler: F)
+        where F: for<'a> FnMut(&'a mut EventRoute) + 'static
+    {
+        self.event_handlers().add(move |mut route| {
+            if let Some(_) = route.event.downcast_mut::<SelectionChangedEvent>() {
+                handler(&mut route);
+            }
+        });
+    }
+
+    // Convenience method to add an event handler that is called for `ItemActivatedEvent`s.
+    pub fn on_item_activated_event<F>(&self, mut handler: F)
+        where F: for<'a> FnMut(&'a mut EventRoute) + 'static
+    {
+        self.event_handlers().add(move |mut route| {
+            if let Some(_) = route.event.downcast_mut::<ItemActivatedEvent>() {
+                handler(&mut route);
+            }
+        });
+    }
+
+    /// Creates, reuses, and positions the views for the items in view. This is done
+    /// automatically when the list view's size or data source changes and when it is scrolled.
+    pub fn layout(&self) {
+        let size = self.size();
+        self.scroll_view.set_location(&Point2::new(0.0, 0.0));
+        self.scroll_view.set_size(&size);
+        let source = match self.data_source() {
+            Some(source) => source,
+            None => return,
+        };
+        let height = source.item_height();
+        // The scroll view stretches the panel to the width of the view.
+        self.panel.set_size(&Size2::new(0.0, height * source.len() as f64));
+        self.scroll_view.layout();
+        let visible = self.visible_range();
+
+        // Views for items scrolled out of view are hidden to be reused.
+        let mut views = self.views.borrow_mut();
+        let mut spare_views = self.spare_views.borrow_mut();
+        views.retain(|(index, view)| {
+            visible.contains(index) || {
+                spare_views.push(view.clone());
+                false
+            }
+        });
+        for index in visible {
+            if views.iter().any(|&(i, _)| i == index) {
+                continue;
+            }
+            let view = spare_views.pop().unwrap_or_else(|| {
+                let view = source.create_item_view();
+                view.set_input_transparent(true);
+                self.panel.children().borrow_mut().push(view.clone());
+                view
+            });
+            source.recycle_item_view(&view, index);
+            views.push((index, view));
+        }
+        for view in spare_views.iter() {
+            view.set_visibility(Visibility::Gone);
+        }
+        let width = self.panel.size().width;
+        for (index, view) in views.iter() {
+            view.set_visibility(Visibility::Visible);
+            view.set_location(&Point2::new(0.0, *index as f64 * height));
+            view.set_size(&Size2::new(width, height));
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.data_source().map_or(0, |s| s.len())
+    }
+
+    // Children can't be added in `new()`, before the handle is registered.
+    fn add_children(&self) {
+        if self.scroll_view.parent().is_some() {
+            return;
+        }
+        self.children().borrow_mut().push(self.scroll_view.clone());
+        self.scroll_view.set_horizontal_scroll_bar(ScrollBarVisibility::Never);
+        self.scroll_view.set_content(Some(self.panel.clone().into()));
+        forward_panel_mouse_events(&self.panel, self);
+        forward_scroll_events(&self.scroll_view, self);
+    }
+
+    // Returns the indexes of the items that are at least partly in view.
+    fn visible_range(&self) -> Range<usize> {
+        let source = match self.data_source() {
+            Some(source) => source,
+            None => return 0..0,
+        };
+        let height = source.item_height();
+        if height <= 0.0 {
+            return 0..0;
+        }
+        let top = self.scroll_view.scroll_offset().y;
+        let bottom = top + self.scroll_view.viewport_size().height;
+        let start = (top / height).floor() as usize;
+        let end = ((bottom / height).ceil() as usize).min(source.len());
+        start.min(end)..end
+    }
+
+    // Returns the index of the item at the y coordinate in the panel.
+    fn item_at(&self, y: f64) -> Option<usize> {
+        let height = self.data_source()?.item_height();
+        if y < 0.0 || height <= 0.0 {
+            return None;
+        }
+        Some((y / height) as usize).filter(|&i| i < self.len())
+    }
+
+    // Returns the number of items that fit in the view, used by Page Up and Page Down.
+    fn page_len(&self) -> usize {
+        let height = self.data_source().map_or(0.0, |s| s.item_height());
+        if height <= 0.0 {
+            return 1;
+        }
+        ((self.scroll_view.viewport_size().height / height) as usize).max(1)
+    }
+
+    fn set_selection(&self, selected: IndexRanges) {
+        if *self.selected.borrow() != selected {
+            *self.selected.borrow_mut() = selected;
+            self.event_handlers().send(&mut SelectionChangedEvent);
+            self.repaint_later();
+        }
+    }
+
+    // Makes the item current and selects it. With `extend`, the range from the anchor to the item
+    // is selected instead. With `toggle`, the item is added to or removed from the selection.
+    fn select_item(&self, index: usize, extend: bool, toggle: bool) {
+        let multiple = self.selection_mode.get() == SelectionMode::Multiple;
+        let mut selected = self.selected.borrow().clone();
+        match self.anchor.get() {
+            Some(anchor) if multiple && extend => {
+                selected = IndexRanges::from_range(anchor.min(index)..anchor.max(index) + 1);
+            }
+            _ if multiple && toggle => {
+                if !selected.remove(index) {
+                    selected.insert(index);
+                }
+                self.anchor.set(Some(index));
+            }
+            _ => {
+                selected = IndexRanges::from_range(index..index + 1);
+                self.anchor.set(Some(index));
+            }
+        }
+        self.current.set(Some(index));
+        self.set_selection(selected);
+        self.scroll_to_item(index);
+        self.repaint_later();
+    }
+
+    fn on_item_pressed(&self, index: Option<usize>, button: MouseButton, modifiers: Modifiers) {
+        if let Some(handle) = control_handle(self) {
+            set_focus(&handle);
+        }
+        let Modifiers { shift, control, .. } = modifiers;
+        match index {
+            // Right-clicking a selected item keeps the selection for a context menu.
+            Some(index) if button == MouseButton::Right && self.is_selected(index) => {
+                self.current.set(Some(index));
+                self.repaint_later();
+            }
+            Some(index) => self.select_item(index, shift, control && !shift),
+            None if !shift && !control => self.clear_selection(),
+            None => {}
+        }
+    }
+
+    // Returns whether the key was handled.
+    fn on_key_down(&self, key: Key, modifiers: Modifiers) -> bool {
+        let Modifiers { shift, control, alt, logo } = modifiers;
+        let len = self.len();
+        if alt || logo || len == 0 {
+            return false;
+        }
+        let current = self.current.get();
+        if let Some(index) = current {
+            match key {
+                Key::Space => {
+                    self.select_item(index, shift, control);
+                    return true;
+                }
+                Key::Enter if !shift && !control => {
+                    self.event_handlers().send(&mut ItemActivatedEvent { index });
+                    return true;
+                }
+                _ => {}
+            }
+        }
+        let target = match key {
+            Key::Up => current.map_or(0, |i| i.saturating_sub(1)),
+            Key::Down => current.map_or(0, |i| (i + 1).min(len - 1)),
+            Key::Home => 0,
+            Key::End => len - 1,
+            Key::PageUp => current.map_or(0, |i| i.saturating_sub(self.page_len())),
+            Key::PageDown => current.map_or(0, |i| (i + self.page_len()).min(len - 1)),
+            Key::A if control && !shift => {
+                self.select_all();
+                return true;
+            }
+            _ => return false,
+        };
+        // Ctrl moves the focus rectangle without changing the selection.
+        if control && !shift && self.selection_mode.get() == SelectionMode::Multiple {
+            self.current.set(Some(target));
+            self.scroll_to_item(target);
+            self.repaint_later();
+        } else {
+            self.select_item(target, shift, false);
+        }
+        true
+    }
+
+    // Selects the next item whose text starts with what has been typed recently.
+    fn type_ahead(&self, text: &str) {
+        let now = Instant::now();
+        if self.last_typed.replace(Some(now)).map_or(true, |t| now - t > TYPE_AHEAD_TIMEOUT) {
+            self.search.borrow_mut().clear();
+        }
+        self.search.borrow_mut().push_str(&text.to_lowercase());
+        let source = match self.data_source() {
+            Some(source) => source,
+            None => return,
+        };
+        let len = source.len();
+        let search = self.search.borrow().clone();
+        let first = search.chars().next().unwrap_or(' ');
+        // Typing the same letter repeatedly cycles through the items starting with it. Otherwise,
+        // the current item stays selected as long as it matches.
+        let (prefix, start) = if search.chars().all(|c| c == first) {
+            (first.to_string(), self.current.get().map_or(0, |i| i + 1))
+        } else {
+            (search, self.current.get().unwrap_or(0))
+        };
+        let found = (0..len).map(|i| (start + i) % len).find(|&index| {
+            source.item_text(index).map_or(false, |t| t.to_lowercase().starts_with(&prefix))
+        });
+        if let Some(index) = found {
+            self.select_item(index, false, false);
+        }
+    }
+
+    fn paint(&self, painter: &mut dyn Painter) {
+        let size = self.size();
+        // Items partly scrolled out of view would be painted outside the list view.
+        painter.clip_rect(Rect::new(0.0, 0.0, size.width as f32, size.height as f32));
+        painter.fill_path(
+            &mut theme::rect_path(0.0, 0.0, size.width as f32, size.height as f32).path_iter(),
+            &Brush::Solid(Color::from_rgba(255, 255, 255, 255)),
+        );
+        let height = match self.data_source() {
+            Some(source) => source.item_height(),
+            None => return,
+        };
+
+        let focused = self.focused();
+        let offset = self.scroll_view.scroll_offset().y;
+        let width = self.scroll_view.viewport_size().width as f32;
+        let item_top = |index: usize| (index as f64 * height - offset) as f32;
+        let selection_color = theme::item_selection_color(focused);
+        let selected = self.selected.borrow();
+        for index in self.visible_range().filter(|&i| selected.contains(i)) {
+            let top = item_top(index);
+            painter.fill_path(
+                &mut theme::rect_path(0.0, top, width, top + height as f32).path_iter(),
+                &Brush::Solid(selection_color),
+            );
+        }
+        if let Some(index) = self.current.get().filter(|_| focused) {
+            let top = item_top(index);
+            painter.stroke_path(
+                &mut theme::rect_path(0.5, top + 0.5, width - 0.5, top + height as f32 - 0.5)
+                    .path_iter(),
+                &Brush::Solid(theme::item_focus_color()),
+                &StrokeStyle::with_width(1.0),
+            );
+        }
+    }
+}
+
+impl Default for ListViewData {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+struct TestSource {
+    names: Vec<String>,
+    created: Cell<usize>,
+}
+
+#[cfg(test)]
+impl ListDataSource for TestSource {
+    fn len(&self) -> usize { self.names.len() }
+    fn item_height(&self) -> f64 { 20.0 }
+    fn create_item_view(&self) -> Rc<dyn Control> {
+        self.created.set(self.created.get() + 1);
+        SubControl::new().into()
+    }
+    fn recycle_item_view(&self, _view: &Rc<dyn Control>, _index: usize) {}
+    fn item_text(&self, index: usize) -> Option<String> { Some(self.names[index].clone()) }
+}
+
+#[test]
+fn test_list_view_virtualization() {
+    let list = ListView::new();
+    list.set_size(&Size2::new(200.0, 100.0));
+    let names = (0..20000).map(|i| format!("item {}", i)).collect();
+    let source = Rc::new(TestSource { names, created: Cell::new(0) });
+    list.set_data_source(Some(source.clone()));
+
+    // Five items fit, and a sixth is partly visible after scrolling.
+    assert_eq!(list.visible_range(), 0..5);
+    assert_eq!(source.created.get(), 5);
+    list.scroll_to_item(10);
+    assert_eq!(list.visible_range(), 6..11);
+    list.scroll_view.scroll_by(0.0, 5.0);
+    assert_eq!(list.visible_range(), 6..12);
+    assert_eq!(source.created.get(), 6);
+    assert_eq!(list.panel.children().borrow().len(), 6);
+
+    // Selecting every item is stored as one range.
+    list.set_selection_mode(SelectionMode::Multiple);
+    list.select_all();
+    assert_eq!(list.selected_ranges(), vec![0..20000]);
+    assert!(list.is_selected(19999));
+}
+
+#[test]
+fn test_index_ranges() {
+    let mut ranges = IndexRanges::from_range(2..5);
+    ranges.insert(6);
+    ranges.insert(0);
+    assert_eq!(ranges.0, vec![0..1, 2..5, 6..7]);
+    // Filling a gap joins the ranges on both sides.
+    ranges.insert(5);
+    ranges.insert(1);
+    assert_eq!(ranges.0, vec![0..7]);
+    assert!(ranges.remove(3));
+    assert!(!ranges.remove(3));
+    assert_eq!(ranges.0, vec![0..3, 4..7]);
+    assert_eq!((ranges.len(), ranges.contains(3), ranges.contains(4)), (6, false, true));
+    assert!(ranges.truncate(5));
+    assert!(!ranges.truncate(5));
+    assert_eq!(ranges.iter().collect::<Vec<_>>(), vec![0, 1, 2, 4]);
+}
+
+#[test]
+fn test_list_view_selection() {
+    let list = ListView::new();
+    list.set_size(&Size2::new(200.0, 100.0));
+    let names = ["apple", "banana", "blueberry", "cherry", "apricot"];
+    let names = names.iter().map(|s| s.to_string()).collect();
+    list.set_data_source(Some(Rc::new(TestSource { names, created: Cell::new(0) })));
+    let changes = Rc::new(Cell::new(0));
+    let changes2 = changes.clone();
+    list.on_selection_changed_event(move |_| changes2.set(changes2.get() + 1));
+
+    let key = |key, shift, control| {
+        let modifiers = Modifiers { shift, control, ..Modifiers::none() };
+        list.event_handlers().send(&mut KeyDownEvent { key, modifiers, repeat: false });
+    };
+    key(Key::Down, false, false);
+    key(Key::Down, false, false);
+    assert_eq!(list.selected_indices(), vec![1]);
+    key(Key::End, true, false);
+    assert_eq!(list.selected_indices(), vec![4]);
+
+    list.set_selection_mode(SelectionMode::Multiple);
+    key(Key::Up, true, false);
+    key(Key::Up, true, false);
+    assert_eq!(list.selected_indices(), vec![2, 3, 4]);
+    key(Key::Home, false, true);
+    key(Key::Space, false, true);
+    assert_eq!(list.selected_indices(), vec![0, 2, 3, 4]);
+    assert_eq!(changes.get(), 6);
+
+    let type_text = |text: &str| {
+        list.event_handlers().send(&mut TextInputEvent { text: text.to_owned() });
+    };
+    type_text("b");
+    assert_eq!(list.selected_indices(), vec![1]);
+    type_text("l");
+    assert_eq!(list.selected_indices(), vec![2]);
+    list.last_typed.set(None);
+    type_text("a");
+    type_text("a");
+    assert_eq!(list.selected_indices(), vec![0]);
+
+    // Clicks use the modifiers held with them, even while the list doesn't have the focus.
+    let click = |index: usize, modifiers| {
+        let location = Point2::new(5.0, index as f64 * 20.0 + 5.0);
+        let button = MouseButton::Left;
+        list.panel.event_handlers().send(&mut MouseDownEvent { location, button, modifiers });
+    };
+    click(3, Modifiers { control: true, ..Modifiers::none() });
+    assert_eq!(list.selected_indices(), vec![0, 3]);
+    click(1, Modifiers { shift: true, ..Modifiers::none() });
+    assert_eq!(list.selected_indices(), vec![1, 2, 3]);
+}
@@ -0,0 +1,1057 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ *
+ */
+
+use std::cell::{Cell, RefCell};
+use std::ops::Deref;
+use std::rc::{Rc, Weak};
+
+use zaffre::{Brush, Color, Painter, PathBuf, Point2, Size2, StrokeStyle};
+
+use crate::action::{subscribe, Action, Subscription};
+use crate::button::ClickEvent;
+use crate::control::{
+    control_handle, Control, MouseButton, MouseDownEvent, MouseDraggedEvent, MouseLeftEvent,
+    MouseMovedEvent, MouseUpEvent, PaintingEvent, SubControl, SubControlData, SubControlRef,
+    Visibility, VisibilityChangedEvent,
+};
+use crate::event_vec::{EventHandler, EventHandlerVec, EventRoute};
+use crate::keyboard::{key_to_char, Key, KeyDownEvent, MnemonicEvent, Modifiers, Shortcut};
+use crate::mnemonic::MnemonicText;
+use crate::theme;
+use crate::window::{dismiss_all_popups, Window};
+
+/// An item in a `Menu`: a command, a checkable option, a submenu, or a separator line.
+#[derive(Clone)]
+pub struct MenuItem(Rc<MenuItemData>);
+
+impl MenuItem {
+    /// Creates an item with the text. An `&` before a character makes it the mnemonic, which
+    /// activates the item when pressed while the menu is open.
+    pub fn new(text: &str) -> Self {
+        MenuItem(Rc::new(MenuItemData::new(text, false)))
+    }
+
+    /// Creates a line separating groups of items.
+    pub fn separator() -> Self {
+        MenuItem(Rc::new(MenuItemData::new("", true)))
+    }
+
+    /// Creates an item that triggers the action and shows its text, shortcut and state.
+    pub fn with_action(action: &Action) -> Self {
+        let item = Self::new("");
+        item.set_action(Some(action));
+        item
+    }
+
+    /// Creates an item that opens the submenu.
+    pub fn with_submenu(text: &str, submenu: Menu) -> Self {
+        let item = Self::new(text);
+        item.set_submenu(Some(submenu));
+        item
+    }
+}
+
+impl Deref for MenuItem {
+    type Target = Rc<MenuItemData>;
+    fn deref(&self) -> &Rc<MenuItemData> {
+        &self.0
+    }
+}
+
+pub struct MenuItemData {
+    text: RefCell<MnemonicText>,
+    shortcut: Cell<Option<Shortcut>>,
+    checkable: Cell<bool>,
+    checked: Cell<bool>,
+    enabled: Cell<bool>,
+    separator: bool,
+    submenu: RefCell<Option<Menu>>,
+    action: RefCell<Option<Action>>,
+    action_subscription: RefCell<Option<Subscription>>,
+    // The view of the menu the item was last shown in, which is repainted when the item changes
+    view: Rc<RefCell<Option<Weak<dyn Control>>>>,
+    event_handlers: EventHandlerVec,
+}
+
+impl MenuItemData {
+    fn new(text: &str, separator: bool) -> Self {
+        MenuItemData {
+            text: RefCell::new(MnemonicText::parse(text)),
+            shortcut: Cell::new(None),
+            checkable: Cell::new(false),
+            checked: Cell::new(false),
+            enabled: Cell::new(true),
+            separator,
+            submenu: RefCell::new(None),
+            action: RefCell::new(None),
+            action_subscription: RefCell::new(None),
+            view: Rc::new(RefCell::new(None)),
+            event_handlers: EventHandlerVec::new(),
+        }
+    }
+
+    /// Returns the text without the `&` that marks the mnemonic.
+    pub fn text(&self) -> String {
+        self.mnemonic_text().text
+    }
+
+    pub fn set_text(&self, text: &str) {
+        *self.text.borrow_mut() = MnemonicText::parse(text);
+        self.repaint_view();
+    }
+
+    pub fn shortcut(&self) -> Option<Shortcut> {
+        self.action().map_or(self.shortcut.get(), |a| a.shortcut())
+    }
+
+    /// Sets the shortcut shown on the right of the item. Showing it doesn't make pressing it
+    /// click the item.
+    pub fn set_shortcut(&self, shortcut: Option<Shortcut>) {
+        self.shortcut.set(shortcut);
+        self.repaint_view();
+    }
+
+    pub fn checkable(&self) -> bool {
+        self.action().map_or(self.checkable.get(), |a| a.checkable())
+    }
+
+    /// Sets whether clicking the item toggles a check mark next to it.
+    pub fn set_checkable(&self, checkable: bool) {
+        self.checkable.set(checkable);
+        self.repaint_view();
+    }
+
+    pub fn checked(&self) -> bool {
+        self.action().map_or(self.checked.get(), |a| a.checked())
+    }
+
+    pub fn set_checked(&self, checked: bool) {
+        self.checked.set(checked);
+        self.repaint_view();
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.action().map_or(self.enabled.get(), |a| a.enabled())
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.set(enabled);
+        self.repaint_view();
+    }
+
+    pub fn is_separator(&self) -> bool {
+        self.separator
+    }
+
+    pub fn submenu(&self) -> Option<Menu> {
+        self.submenu.borrow().clone()
+    }
+
+    pub fn set_submenu(&self, submenu: Option<Menu>) {
+        *self.submenu.borrow_mut() = submenu;
+        self.repaint_view();
+    }
+
+    pub fn action(&self) -> Option<Action> {
+        self.action.borrow().clone()
+    }
+
+    /// Sets the action that clicking the item triggers. While it is set, the item shows the
+    /// action's text, shortcut, enabled and checked state instead of its own.
+    pub fn set_action(&self, action: Option<&Action>) {
+        self.action.replace(action.cloned());
+        let subscription = action.map(|action| {
+            let view = Rc::downgrade(&self.view);
+            subscribe(action, move || {
+                if let Some(view) = view.upgrade() {
+                    if let Some(view) = view.borrow().as_ref().and_then(Weak::upgrade) {
+                        view.repaint_later();
+                    }
+                }
+            })
+        });
+        self.action_subscription.replace(subscription);
+        self.repaint_view();
+    }
+
+    pub fn event_handlers(&self) -> &EventHandlerVec {
+        &self.event_handlers
+    }
+
+    // Repaints the open menu showing the item, if there is one.
+    fn repaint_view(&self) {
+        if let Some(view) = self.view.borrow().as_ref().and_then(Weak::upgrade) {
+            view.repaint_later();
+        }
+    }
+
+    /// Toggles the check mark if the item is checkable and sends a `ClickEvent`, as if the user
+    /// clicked the item. If it has an action, the action is triggered instead of toggling the
+    /// item's own check mark.
+    pub fn click(&self) {
+        if let Some(action) = self.action() {
+            self.event_handlers.send(&mut ClickEvent);
+            action.trigger();
+            return;
+        }
+        if self.checkable.get() {
+            self.checked.set(!self.checked.get());
+            self.repaint_view();
+        }
+        self.event_handlers.send(&mut ClickEvent);
+    }
+
+    // Convenience method to add an event handler that is called for `ClickEvent`s.
+    pub fn on_click_event<F>(&self, mut handler: F)
+        where F: for<'a> FnMut(&'a mut EventRoute) + 'static
+    {
+        self.event_handlers.add(move |mut route| {
+            if let Some(_) = route.event.downcast_mut::<ClickEvent>() {
+                handler(&mut route);
+            }
+        });
+    }
+
+    fn mnemonic_text(&self) -> MnemonicText {
+        match self.action() {
+            Some(action) => MnemonicText::parse(&action.text()),
+            None => self.text.borrow().clone(),
+        }
+    }
+
+    // Whether the item can be highlighted and activated
+    fn is_selectable(&self) -> bool {
+        !self.separator && self.enabled()
+    }
+}
+
+/// A list of items shown in a popup, either as a context menu, from a `MenuBar`, or as the
+/// submenu of another item.
+#[derive(Clone)]
+pub struct Menu(Rc<MenuData>);
+
+impl Menu {
+    pub fn new() -> Self {
+        Menu(Rc::new(MenuData {
+            items: RefCell::new(vec![]),
+            view: MenuView::new(),
+            popup: RefCell::new(None),
+        }))
+    }
+}
+
+impl Default for Menu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deref for Menu {
+    type Target = Rc<MenuData>;
+    fn deref(&self) -> &Rc<MenuData> {
+        &self.0
+    }
+}
+
+pub struct MenuData {
+    items: RefCell<Vec<MenuItem>>,
+    view: MenuView,
+    // Created the first time the menu is shown
+    popup: RefCell<Option<Window>>,
+}
+
+impl MenuData {
+    pub fn items(&self) -> Vec<MenuItem> {
+        self.items.borrow().clone()
+    }
+
+    pub fn add_item(&self, item: MenuItem) {
+        self.items.borrow_mut().push(item);
+    }
+
+    pub fn insert_item(&self, index: usize, item: MenuItem) {
+        self.items.borrow_mut().insert(index, item);
+    }
+
+    pub fn remove_item(&self, index: usize) -> MenuItem {
+        self.items.borrow_mut().remove(index)
+    }
+
+    pub fn clear(&self) {
+        self.items.borrow_mut().clear();
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.popup.borrow().as_ref().map_or(false, |p| p.visibility() == Visibility::Visible)
+    }
+
+    /// Hides the menu and any submenu open from it.
+    pub fn close(&self) {
+        if let Some(popup) = self.popup.borrow().as_ref() {
+            popup.set_visibility(Visibility::Gone);
+        }
+    }
+
+    /// Shows the menu with its top left corner at the location, in the control's coordinates. It
+    /// does nothing if the control isn't in a window.
+    ///
+    /// This is usually called from a handler for `ContextMenuRequestedEvent`, with the location
+    /// from the event.
+    pub fn show_context_menu(&self, control: &dyn Control, location: Point2<f64>) {
+        let (window, location) = match (control.window(), control.to_screen_coords(location)) {
+            (Some(window), Some(location)) => (window, location),
+            _ => return,
+        };
+        dismiss_all_popups();
+        self.show(&window, location, None);
+    }
+
+    // Shows the menu at the location in screen coordinates. The opener is the menu bar or menu
+    // view that opened the menu, and is sent a `MenuClosedEvent` when it closes.
+    pub(crate) fn show(
+        &self,
+        owner: &Window,
+        location: Point2<f64>,
+        opener: Option<Weak<dyn Control>>,
+    ) {
+        let popup = self.popup.borrow().clone().unwrap_or_else(|| {
+            let popup = Window::new_popup();
+            popup.set_child(self.view.clone().into());
+            *self.popup.borrow_mut() = Some(popup.clone());
+            popup
+        });
+        popup.set_visibility(Visibility::Gone);
+        let view: Rc<dyn Control> = self.view.clone().into();
+        for item in self.items() {
+            *item.view.borrow_mut() = Some(Rc::downgrade(&view));
+        }
+        *self.view.items.borrow_mut() = self.items();
+        *self.view.opener.borrow_mut() = opener;
+        self.view.hot.set(None);
+        let size = self.view.best_size();
+        self.view.set_size(&size);
+        popup.set_owner(Some(owner));
+        popup.set_location(&location);
+        popup.set_size(&size);
+        popup.set_visibility(Visibility::Visible);
+    }
+
+    // Highlights the first item that can be activated, for when the menu is opened with the
+    // keyboard.
+    pub(crate) fn select_first_item(&self) {
+        self.view.move_hot(true);
+    }
+}
+
+// Sent to the control that opened a menu when the menu is closed.
+struct MenuClosedEvent;
+
+// Sent to the control that opened a menu when Left or Right is pressed in the menu and not
+// handled by it, so that a menu bar can open the menu next to it.
+struct MenuSideKeyEvent {
+    key: Key,
+}
+
+const ITEM_PADDING_Y: f64 = 4.0;
+const SEPARATOR_HEIGHT: f64 = 9.0;
+// The space on the left of the text for the check mark
+const CHECK_WIDTH: f64 = 28.0;
+// The space on the right of the text for the submenu arrow
+const ARROW_WIDTH: f64 = 22.0;
+const SHORTCUT_GAP: f64 = 24.0;
+const BORDER_PADDING: f64 = 2.0;
+
+// TODO: generate with a proc macro
+// start proc macro generated
+#[derive(Clone)]
+pub(crate) struct MenuView(Rc<MenuViewData>);
+
+impl MenuView {
+    pub(crate) fn new() -> Self {
+        SubControl::register_handle(MenuView(Rc::new(MenuViewData::new())))
+    }
+}
+
+impl Deref for MenuView {
+    type Target = Rc<MenuViewData>;
+    fn deref(&self) -> &Rc<MenuViewData> {
+        &self.0
+    }
+}
+
+impl From<MenuView> for Rc<dyn Control> {
+    fn from(self_: MenuView) -> Self {
+        self_.0 as Rc<dyn Control>
+    }
+}
+
+impl From<MenuView> for Rc<dyn EventHandler> {
+    fn from(self_: MenuView) -> Self {
+        self_.0 as Rc<dyn EventHandler>
+    }
+}
+// end proc macro generated
+
+// The content of a menu's popup, which paints the items and handles the mouse and keyboard.
+//#[dynamin::control]
+pub(crate) struct MenuViewData {
+    sub_control: SubControlData,
+    items: RefCell<Vec<MenuItem>>,
+    hot: Cell<Option<usize>>,
+    // The index of the item whose submenu is open
+    open_submenu: Cell<Option<usize>>,
+    opener: RefCell<Option<Weak<dyn Control>>>,
+    item_height: f64,
+}
+
+impl SubControlRef for MenuViewData {
+    fn sub_control_ref(&self) -> &SubControlData { &self.sub_control }
+
+    fn best_size(&self) -> Size2<f64> {
+        let items = self.items.borrow();
+        let text_width = items.iter()
+            .map(|item| theme::line_width(&item.text()))
+            .fold(0.0, f64::max);
+        let shortcut_width = items.iter()
+            .filter_map(|item| item.shortcut())
+            .map(|shortcut| theme::line_width(&shortcut.to_string()) + SHORTCUT_GAP)
+            .fold(0.0, f64::max);
+        let height = items.iter().map(|item| self.height_of(item)).sum::<f64>();
+        Size2::new(
+            (CHECK_WIDTH + text_width + shortcut_width + ARROW_WIDTH + BORDER_PADDING * 2.0).ceil(),
+            height + BORDER_PADDING * 2.0,
+        )
+    }
+}
+
+impl EventHandler for MenuViewData {
+    fn on_event(&self, route: &mut EventRoute) {
+        if let Some(MouseMovedEvent { location }) = route.event.downcast_mut() {
+            self.on_mouse_moved(*location);
+        } else if let Some(MouseDraggedEvent { location }) = route.event.downcast_mut() {
+            self.on_mouse_moved(*location);
+        } else if let Some(MouseLeftEvent) = route.event.downcast_mut() {
+            // The item with an open submenu stays highlighted.
+            if self.open_submenu.get().is_none() {
+                self.hot.set(None);
+                self.repaint_later();
+            }
+        } else if let Some(MouseDownEvent { .. }) = route.event.downcast_mut() {
+            route.handled = true;
+        } else if let Some(MouseUpEvent { location, button }) = route.event.downcast_mut() {
+            if *button != MouseButton::Middle {
+                if let Some(index) = self.item_at(location.y) {
+                    self.activate(index, false);
+                }
+            }
+            route.handled = true;
+        } else if let Some(event) = route.event.downcast_mut::<KeyDownEvent>() {
+            self.on_key_down(event.key, event.modifiers);
+            // An open menu takes all keys, like native menus.
+            route.handled = true;
+        } else if let Some(MenuSideKeyEvent { key }) = route.event.downcast_mut() {
+            // Left closes the submenu that sent it; Right is passed on toward the menu bar.
+            if *key == Key::Left {
+                self.close_submenu();
+            } else {
+                self.send_to_opener(&mut MenuSideKeyEvent { key: *key });
+            }
+        } else if let Some(MenuClosedEvent) = route.event.downcast_mut() {
+            self.open_submenu.set(None);
+            self.repaint_later();
+        } else if let Some(VisibilityChangedEvent { effectively_visible: false, .. }) =
+            route.event.downcast_mut()
+        {
+            self.close_submenu();
+            self.send_to_opener(&mut MenuClosedEvent);
+        } else if let Some(PaintingEvent { painter }) = route.event.downcast_mut() {
+            self.paint(&mut **painter);
+        }
+    }
+}
+
+impl MenuViewData {
+    pub(crate) fn new() -> Self {
+        let line = theme::layout_line(theme::formatted_string("Xg", theme::text_color(true)));
+        MenuViewData {
+            sub_control: SubControlData::new(),
+            items: RefCell::new(vec![]),
+            hot: Cell::new(None),
+            open_submenu: Cell::new(None),
+            opener: RefCell::new(None),
+            item_height: (theme::text_size(&line).height + ITEM_PADDING_Y * 2.0).ceil(),
+        }
+    }
+
+    fn height_of(&self, item: &MenuItem) -> f64 {
+        if item.is_separator() { SEPARATOR_HEIGHT } else { self.item_height }
+    }
+
+    // Returns the top of the item in this control's coordinates.
+    fn item_top(&self, index: usize) -> f64 {
+        let items = self.items.borrow();
+        BORDER_PADDING + items[..index].iter().map(|item| self.height_of(item)).sum::<f64>()
+    }
+
+    fn item_at(&self, y: f64) -> Option<usize> {
+        let mut top = BORDER_PADDING;
+        for (i, item) in self.items.borrow().iter().enumerate() {
+            let height = self.height_of(item);
+            if y >= top && y < top + height {
+                return Some(i);
+            }
+            top += height;
+        }
+        None
+    }
+
+    fn send_to_opener(&self, event: &mut dyn std::any::Any) {
+        let opener = self.opener.borrow().as_ref().and_then(|o| o.upgrade());
+        if let Some(opener) = opener {
+            opener.event_handlers().send(event);
+        }
+    }
+
+    fn on_mouse_moved(&self, location: Point2<f64>) {
+        let index = self.item_at(location.y)
+            .filter(|&i| self.items.borrow()[i].is_selectable());
+        if index.is_none() || index == self.hot.get() {
+            return;
+        }
+        self.hot.set(index);
+        self.repaint_later();
+        // Pointing at an item opens its submenu and closes any other one.
+        let index = index.unwrap();
+        if self.items.borrow()[index].submenu().is_some() {
+            self.open_submenu_at(index);
+        } else {
+            self.close_submenu();
+        }
+    }
+
+    // Highlights the next item that can be activated, wrapping around at the ends.
+    fn move_hot(&self, forward: bool) {
+        let items = self.items.borrow();
+        let len = items.len();
+        let start = match (self.hot.get(), forward) {
+            (Some(i), true) => i + 1,
+            (Some(i), false) => i + len - 1,
+            (None, true) => 0,
+            (None, false) => len.saturating_sub(1),
+        };
+        let steps = (0..len).map(|n| if forward { start + n } else { start + len - n });
+        if let Some(index) = steps.map(|i| i % len.max(1)).find(|&i| items[i].is_selectable()) {
+            self.hot.set(Some(index));
+            self.repaint_later();
+        }
+    }
+
+    fn on_key_down(&self, key: Key, modifiers: Modifiers) {
+        if modifiers != Modifiers::none() {
+            return;
+        }
+        let hot_item = self.hot.get().map(|i| self.items.borrow()[i].clone());
+        match key {
+            Key::Up => self.move_hot(false),
+            Key::Down => self.move_hot(true),
+            Key::Home => {
+                self.hot.set(None);
+                self.move_hot(true);
+            }
+            Key::End => {
+                self.hot.set(None);
+                self.move_hot(false);
+            }
+            Key::Enter | Key::Space => {
+                if let Some(index) = self.hot.get() {
+                    self.activate(index, true);
+                }
+            }
+            Key::Right if hot_item.as_ref().map_or(false, |i| i.submenu().is_some()) => {
+                self.activate(self.hot.get().unwrap(), true);
+            }
+            Key::Left | Key::Right => self.send_to_opener(&mut MenuSideKeyEvent { key }),
+            Key::Escape => self.close(),
+            _ => {
+                let c = match key_to_char(key) {
+                    Some(c) => c,
+                    None => return,
+                };
+                let index = self.items.borrow().iter()
+                    .position(|item| item.is_selectable() && item.mnemonic_text().matches(c));
+                if let Some(index) = index {
+                    self.hot.set(Some(index));
+                    self.activate(index, true);
+                }
+            }
+        }
+    }
+
+    // Opens the item's submenu, or closes all menus and clicks the item. When activated with the
+    // keyboard, the first item of the submenu is highlighted.
+    fn activate(&self, index: usize, keyboard: bool) {
+        let item = self.items.borrow()[index].clone();
+        if !item.is_selectable() {
+            return;
+        }
+        if let Some(submenu) = item.submenu() {
+            self.hot.set(Some(index));
+            self.open_submenu_at(index);
+            if keyboard {
+                submenu.select_first_item();
+            }
+            return;
+        }
+        dismiss_all_popups();
+        item.click();
+    }
+
+    fn open_submenu_at(&self, index: usize) {
+        if self.open_submenu.get() == Some(index) {
+            return;
+        }
+        self.close_submenu();
+        let submenu = match self.items.borrow()[index].submenu() {
+            Some(submenu) => submenu,
+            None => return,
+        };
+        // The submenu's first item is lined up with the item, overlapping the menu slightly.
+        let pt = Point2::new(
+            self.size().width - BORDER_PADDING,
+            self.item_top(index) - BORDER_PADDING,
+        );
+        let (owner, location) = match (self.window(), self.to_screen_coords(pt)) {
+            (Some(owner), Some(location)) => (owner, location),
+            _ => return,
+        };
+        let opener = control_handle(self).map(|c| Rc::downgrade(&c));
+        submenu.show(&owner, location, opener);
+        self.open_submenu.set(Some(index));
+        self.repaint_later();
+    }
+
+    fn close_submenu(&self) {
+        if let Some(index) = self.open_submenu.take() {
+            if let Some(submenu) = self.items.borrow()[index].submenu() {
+                submenu.close();
+            }
+            self.repaint_later();
+        }
+    }
+
+    // Hides the popup that this view is in.
+    fn close(&self) {
+        if let Some(window) = self.window() {
+            window.set_visibility(Visibility::Gone);
+        }
+    }
+
+    fn paint(&self, painter: &mut dyn Painter) {
+        let size = self.size();
+        let (width, height) = (size.width as f32, size.height as f32);
+        painter.fill_path(
+            &mut theme::rect_path(0.0, 0.0, width, height).path_iter(),
+            &Brush::Solid(Color::from_rgba(242, 242, 242, 255)),
+        );
+        painter.stroke_path(
+            &mut theme::rect_path(0.5, 0.5, width - 0.5, height - 0.5).path_iter(),
+            &Brush::Solid(Color::from_rgba(204, 204, 204, 255)),
+            &StrokeStyle::with_width(1.0),
+        );
+
+        let left = BORDER_PADDING as f32;
+        let right = width - BORDER_PADDING as f32;
+        let mut top = BORDER_PADDING;
+        for (i, item) in self.items.borrow().iter().enumerate() {
+            let item_height = self.height_of(item);
+            if item.is_separator() {
+                let y = (top + SEPARATOR_HEIGHT / 2.0).floor() as f32;
+                painter.fill_path(
+                    &mut theme::rect_path(left + CHECK_WIDTH as f32, y, right, y + 1.0).path_iter(),
+                    &Brush::Solid(Color::from_rgba(215, 215, 215, 255)),
+                );
+                top += item_height;
+                continue;
+            }
+            let enabled = item.enabled();
+            let (item_top, item_bottom) = (top as f32, (top + item_height) as f32);
+            if self.hot.get() == Some(i) {
+                painter.fill_path(
+                    &mut theme::rect_path(left, item_top, right, item_bottom).path_iter(),
+                    &Brush::Solid(Color::from_rgba(145, 201, 247, 255)),
+                );
+            }
+            let text_color = if enabled {
+                Color::from_rgba(0, 0, 0, 255)
+            } else {
+                Color::from_rgba(109, 109, 109, 255)
+            };
+            let center_y = item_top + item_height as f32 / 2.0;
+            if item.checked() {
+                let x = left + CHECK_WIDTH as f32 / 2.0;
+                let mut path = PathBuf::new();
+                path.move_to(Point2::new(x - 4.0, center_y));
+                path.line_to(Point2::new(x - 1.5, center_y + 3.0));
+                path.line_to(Point2::new(x + 4.0, center_y - 4.0));
+                painter.stroke_path(
+                    &mut path.path_iter(),
+                    &Brush::Solid(text_color),
+                    &StrokeStyle::with_width(1.0),
+                );
+            }
+
+            let text = item.mnemonic_text();
+            let mut string = theme::formatted_string(&text.text, text_color);
+            if let Some(range) = text.mnemonic_range() {
+                string.set_underline(range, true);
+            }
+            let layout = theme::layout_line(string);
+            let text_top = ((item_height - theme::text_size(&layout).height) / 2.0).round();
+            painter.save();
+            painter.translate(left as f64 + CHECK_WIDTH, top + text_top);
+            layout.draw(painter);
+            painter.restore();
+
+            if let Some(shortcut) = item.shortcut() {
+                let layout = theme::layout_line(
+                    theme::formatted_string(&shortcut.to_string(), text_color));
+                let shortcut_left = right as f64 - ARROW_WIDTH - theme::text_size(&layout).width;
+                painter.save();
+                painter.translate(shortcut_left, top + text_top);
+                layout.draw(painter);
+                painter.restore();
+            }
+
+            if item.submenu().is_some() {
+                let x = right - ARROW_WIDTH as f32 / 2.0;
+                let mut path = PathBuf::new();
+                path.move_to(Point2::new(x - 2.0, center_y - 4.0));
+                path.line_to(Point2::new(x + 2.0, center_y));
+                path.line_to(Point2::new(x - 2.0, center_y + 4.0));
+                painter.stroke_path(
+                    &mut path.path_iter(),
+                    &Brush::Solid(text_color),
+                    &StrokeStyle::with_width(1.0),
+                );
+            }
+            top += item_height;
+        }
+    }
+}
+
+// TODO: generate with a proc macro
+// start proc macro generated
+#[derive(Clone)]
+pub struct MenuBar(Rc<MenuBarData>);
+
+impl MenuBar {
+    pub fn new() -> Self {
+        SubControl::register_handle(MenuBar(Rc::new(MenuBarData::new())))
+    }
+}
+
+impl Default for MenuBar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deref for MenuBar {
+    type Target = Rc<MenuBarData>;
+    fn deref(&self) -> &Rc<MenuBarData> {
+        &self.0
+    }
+}
+
+impl From<MenuBar> for Rc<dyn Control> {
+    fn from(self_: MenuBar) -> Self {
+        self_.0 as Rc<dyn Control>
+    }
+}
+
+impl From<MenuBar> for Rc<dyn EventHandler> {
+    fn from(self_: MenuBar) -> Self {
+        self_.0 as Rc<dyn EventHandler>
+    }
+}
+// end proc macro generated
+
+const BAR_ITEM_PADDING_X: f64 = 7.0;
+const BAR_PADDING_Y: f64 = 3.0;
+
+/// A bar at the top of a window that shows the items of a menu, each of which opens its submenu.
+///
+/// Pressing Alt with an item's mnemonic opens it, and Left and Right move between the menus
+/// while one is open.
+//#[dynamin::control]
+pub struct MenuBarData {
+    sub_control: SubControlData,
+    menu: RefCell<Option<Menu>>,
+    hot: Cell<Option<usize>>,
+    // The index of the item whose submenu is open
+    open: Cell<Option<usize>>,
+}
+
+impl SubControlRef for MenuBarData {
+    fn sub_control_ref(&self) -> &SubControlData { &self.sub_control }
+
+    fn best_size(&self) -> Size2<f64> {
+        let line = theme::layout_line(theme::formatted_string("Xg", theme::text_color(true)));
+        let height = theme::text_size(&line).height + BAR_PADDING_Y * 2.0;
+        let width = self.item_bounds().last().map_or(0.0, |&(left, width)| left + width);
+        Size2::new(width.ceil(), height.ceil())
+    }
+}
+
+impl EventHandler for MenuBarData {
+    fn on_event(&self, route: &mut EventRoute) {
+        if let Some(MouseDownEvent { location, button: MouseButton::Left, .. }) =
+            route.event.downcast_mut()
+        {
+            match self.item_at(location.x) {
+                Some(index) if self.open.get() == Some(index) => self.close(),
+                Some(index) => self.open_item(index, false),
+                None => {}
+            }
+            route.handled = true;
+        } else if let Some(MouseMovedEvent { location }) = route.event.downcast_mut() {
+            let index = self.item_at(location.x);
+            if self.hot.replace(index) != index {
+                self.repaint_later();
+            }
+            // While a menu is open, pointing at another item opens its menu instead.
+            if let (Some(_), Some(index)) = (self.open.get(), index) {
+                if self.open.get() != Some(index) {
+                    self.open_item(index, false);
+                }
+            }
+        } else if let Some(MouseLeftEvent) = route.event.downcast_mut() {
+            self.hot.set(None);
+            self.repaint_later();
+        } else if let Some(MnemonicEvent { c }) = route.event.downcast_mut() {
+            let index = self.items().iter().position(|item| {
+                item.enabled() && item.mnemonic_text().matches(*c)
+            });
+            if let Some(index) = index {
+                self.open_item(index, true);
+                route.handled = true;
+            }
+        } else if let Some(MenuSideKeyEvent { key }) = route.event.downcast_mut() {
+            let len = self.items().len();
+            if let (Some(open), true) = (self.open.get(), len > 0) {
+                let offset = if *key == Key::Left { len - 1 } else { 1 };
+                let index = (open + offset) % len;
+                self.open_item(index, true);
+            }
+        } else if let Some(MenuClosedEvent) = route.event.downcast_mut() {
+            // Opening another menu closes the previous one first, so this is only for the one
+            // that is open.
+            let open_menu = self.open.get()
+                .and_then(|i| self.items().get(i).and_then(|item| item.submenu()));
+            if open_menu.map_or(true, |m| !m.is_open()) {
+                self.open.set(None);
+                self.repaint_later();
+            }
+        } else if let Some(PaintingEvent { painter }) = route.event.downcast_mut() {
+            self.paint(&mut **painter);
+        }
+    }
+}
+
+impl MenuBarData {
+    pub fn new() -> Self {
+        MenuBarData {
+            sub_control: SubControlData::new(),
+            menu: RefCell::new(None),
+            hot: Cell::new(None),
+            open: Cell::new(None),
+        }
+    }
+
+    pub fn menu(&self) -> Option<Menu> {
+        self.menu.borrow().clone()
+    }
+
+    /// Sets the menu whose items are shown in the bar. Each item should have a submenu.
+    pub fn set_menu(&self, menu: Option<Menu>) {
+        self.close();
+        *self.menu.borrow_mut() = menu;
+        self.repaint_later();
+    }
+
+    fn items(&self) -> Vec<MenuItem> {
+        self.menu().map_or(vec![], |m| m.items())
+    }
+
+    // Returns the left edge and width of each item.
+    fn item_bounds(&self) -> Vec<(f64, f64)> {
+        let mut left = 0.0;
+        self.items().iter().map(|item| {
+            let width = (theme::line_width(&item.text()) + BAR_ITEM_PADDING_X * 2.0).ceil();
+            left += width;
+            (left - width, width)
+        }).collect()
+    }
+
+    fn item_at(&self, x: f64) -> Option<usize> {
+        self.item_bounds().iter().position(|&(left, width)| x >= left && x < left + width)
+    }
+
+    // Opens the item's submenu below it, or clicks the item if it has none.
+    fn open_item(&self, index: usize, keyboard: bool) {
+        let item = self.items()[index].clone();
+        if !item.enabled() {
+            return;
+        }
+        self.close();
+        let submenu = match item.submenu() {
+            Some(submenu) => submenu,
+            None => {
+                item.click();
+                return;
+            }
+        };
+        let left = self.item_bounds()[index].0;
+        let pt = Point2::new(left, self.size().height);
+        let (owner, location) = match (self.window(), self.to_screen_coords(pt)) {
+            (Some(owner), Some(location)) => (owner, location),
+            _ => return,
+        };
+        let opener = control_handle(self).map(|c| Rc::downgrade(&c));
+        submenu.show(&owner, location, opener);
+        if keyboard {
+            submenu.select_first_item();
+        }
+        self.open.set(Some(index));
+        self.repaint_later();
+    }
+
+    fn close(&self) {
+        if let Some(index) = self.open.take() {
+            if let Some(submenu) = self.items().get(index).and_then(|item| item.submenu()) {
+                submenu.close();
+            }
+            self.repaint_later();
+        }
+    }
+
+    fn paint(&self, painter: &mut dyn Painter) {
+        let size = self.size();
+        painter.fill_path(
+            &mut theme::rect_path(0.0, 0.0, size.width as f32, size.height as f32).path_iter(),
+            &Brush::Solid(Color::from_rgba(255, 255, 255, 255)),
+        );
+        let bar_enabled = self.is_effectively_enabled();
+        let height = size.height as f32;
+        for (i, (item, &(left, width))) in self.items().iter().zip(self.item_bounds().iter())
+            .enumerate()
+        {
+            let colors = if self.open.get() == Some(i) {
+                Some((Color::from_rgba(204, 232, 255, 255), Color::from_rgba(153, 209, 255, 255)))
+            } else if self.hot.get() == Some(i) && item.enabled() && bar_enabled {
+                Some((Color::from_rgba(229, 243, 255, 255), Color::from_rgba(204, 232, 255, 255)))
+            } else {
+                None
+            };
+            let (l, r) = (left as f32, (left + width) as f32);
+            if let Some((fill, border)) = colors {
+                painter.fill_path(
+                    &mut theme::rect_path(l, 0.0, r, height).path_iter(),
+                    &Brush::Solid(fill),
+                );
+                painter.stroke_path(
+                    &mut theme::rect_path(l + 0.5, 0.5, r - 0.5, height - 0.5).path_iter(),
+                    &Brush::Solid(border),
+                    &StrokeStyle::with_width(1.0),
+                );
+            }
+            let text = item.mnemonic_text();
+            let mut string = theme::formatted_string(
+                &text.text, theme::text_color(item.enabled() && bar_enabled));
+            if let Some(range) = text.mnemonic_range() {
+                string.set_underline(range, true);
+            }
+            let layout = theme::layout_line(string);
+            let text_top = ((size.height - theme::text_size(&layout).height) / 2.0).round();
+            painter.save();
+            painter.translate(left + BAR_ITEM_PADDING_X, text_top);
+            layout.draw(painter);
+            painter.restore();
+        }
+    }
+}
+
+impl Default for MenuBarData {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[test]
+fn test_menu_keyboard() {
+    let clicks = Rc::new(RefCell::new(vec![]));
+    let item = |text: &str| {
+        let item = MenuItem::new(text);
+        let clicks = clicks.clone();
+        let text = item.text();
+        item.on_click_event(move |_| clicks.borrow_mut().push(text.clone()));
+        item
+    };
+    let open = item("&Open");
+    let control = Modifiers { control: true, ..Modifiers::none() };
+    open.set_shortcut(Some(Shortcut::new(control, Key::O)));
+    assert_eq!(open.shortcut().unwrap().to_string(), "Ctrl+O");
+    let save = item("&Save");
+    save.set_enabled(false);
+    let word_wrap = item("&Word Wrap");
+    word_wrap.set_checkable(true);
+
+    let view = MenuView::new();
+    *view.items.borrow_mut() = vec![open, MenuItem::separator(), save, word_wrap.clone()];
+    let key = |key| {
+        let modifiers = Modifiers::none();
+        view.event_handlers().send(&mut KeyDownEvent { key, modifiers, repeat: false });
+    };
+    // The separator and the disabled item are skipped.
+    key(Key::Down);
+    assert_eq!(view.hot.get(), Some(0));
+    key(Key::Down);
+    assert_eq!(view.hot.get(), Some(3));
+    key(Key::Down);
+    assert_eq!(view.hot.get(), Some(0));
+    key(Key::Up);
+    key(Key::Enter);
+    // The mnemonic of a disabled item does nothing.
+    key(Key::S);
+    key(Key::W);
+    assert_eq!(*clicks.borrow(), vec!["Word Wrap", "Word Wrap"]);
+    assert!(!word_wrap.checked());
+    assert_eq!(view.item_at(view.item_top(2) + 1.0), Some(2));
+}
+
+// The invalidated areas are only recorded by the headless backend.
+#[cfg(feature = "headless")]
+#[test]
+fn test_menu_item_repaints_view() {
+    use crate::backend::take_invalidated_areas;
+
+    let window = Window::new();
+    let view = SubControl::new();
+    window.set_child(view.clone().into());
+    let view: Rc<dyn Control> = view.into();
+    let item = MenuItem::new("&Open");
+    *item.view.borrow_mut() = Some(Rc::downgrade(&view));
+    take_invalidated_areas(&window);
+
+    // Changing the item repaints the menu showing it, as changing its action does.
+    item.set_enabled(false);
+    assert!(!take_invalidated_areas(&window).is_empty());
+    item.set_text("&Open...");
+    assert!(!take_invalidated_areas(&window).is_empty());
+    item.set_checked(true);
+    assert!(!take_invalidated_areas(&window).is_empty());
+}
@@ -0,0 +1,77 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ *
+ */
+
+use std::ops::Range;
+
+// A mnemonic is a character in a control's text that can be pressed with Alt to activate the
+// control. In the text given to the control, it is marked with an `&` before it, like `&Save`.
+// A literal ampersand is written `&&`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) struct MnemonicText {
+    // The text without the `&` markers
+    pub(crate) text: String,
+    // The byte index in `text` of the mnemonic character
+    pub(crate) mnemonic_index: Option<usize>,
+}
+
+impl MnemonicText {
+    pub(crate) fn parse(s: &str) -> Self {
+        let mut text = String::with_capacity(s.len());
+        let mut mnemonic_index = None;
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            if c == '&' {
+                match chars.next() {
+                    Some('&') => text.push('&'),
+                    Some(c) => {
+                        // Only the first marked character is the mnemonic.
+                        if mnemonic_index.is_none() {
+                            mnemonic_index = Some(text.len());
+                        }
+                        text.push(c);
+                    }
+                    None => text.push('&'),
+                }
+            } else {
+                text.push(c);
+            }
+        }
+        MnemonicText { text, mnemonic_index }
+    }
+
+    // Returns the byte range of the mnemonic character, for underlining it.
+    pub(crate) fn mnemonic_range(&self) -> Option<Range<usize>> {
+        self.mnemonic_index.map(|i| {
+            let len = self.text[i..].chars().next().map_or(0, |c| c.len_utf8());
+            i..i + len
+        })
+    }
+
+    // Returns whether the mnemonic matches the character, ignoring case.
+    pub(crate) fn matches(&self, c: char) -> bool {
+        self.mnemonic_index
+            .and_then(|i| self.text[i..].chars().next())
+            .map_or(false, |m| m.to_lowercase().eq(c.to_lowercase()))
+    }
+}
+
+#[test]
+fn test_parse_mnemonic() {
+    let m = MnemonicText::parse("&Save");
+    assert_eq!(m.text, "Save");
+    assert_eq!(m.mnemonic_range(), Some(0..1));
+    assert!(m.matches('s'));
+    assert!(!m.matches('a'));
+
+    let m = MnemonicText::parse("Fish && Ch&ips&");
+    assert_eq!(m.text, "Fish & Chips&");
+    assert_eq!(m.mnemonic_range(), Some(9..10));
+
+    let m = MnemonicText::parse("Plain");
+    assert_eq!(m.mnemonic_range(), None);
+    assert!(!m.matches('p'));
+}
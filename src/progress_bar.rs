@@ -0,0 +1,291 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ *
+ */
+
+use std::cell::Cell;
+use std::ops::Deref;
+use std::rc::Rc;
+use std::time::Duration;
+
+use zaffre::{Brush, Color, Painter, Size2, StrokeStyle};
+
+use crate::control::{
+    AttachedToWindowEvent, Control, DetachedFromWindowEvent, PaintingEvent, SubControl,
+    SubControlData, SubControlRef, VisibilityChangedEvent,
+};
+use crate::event_vec::{EventHandler, EventRoute};
+use crate::theme;
+use crate::timer::Animation;
+
+// TODO: generate with a proc macro
+// start proc macro generated
+#[derive(Clone)]
+pub struct ProgressBar(Rc<ProgressBarData>);
+
+impl ProgressBar {
+    pub fn new() -> Self {
+        SubControl::register_handle(ProgressBar(Rc::new(ProgressBarData::new())))
+    }
+}
+
+impl Default for ProgressBar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deref for ProgressBar {
+    type Target = Rc<ProgressBarData>;
+    fn deref(&self) -> &Rc<ProgressBarData> {
+        &self.0
+    }
+}
+
+impl From<ProgressBar> for Rc<dyn Control> {
+    fn from(self_: ProgressBar) -> Self {
+        self_.0 as Rc<dyn Control>
+    }
+}
+
+impl From<ProgressBar> for Rc<dyn EventHandler> {
+    fn from(self_: ProgressBar) -> Self {
+        self_.0 as Rc<dyn EventHandler>
+    }
+}
+// end proc macro generated
+
+// How long the chunk takes to cross the bar in indeterminate mode
+const CYCLE: Duration = Duration::from_millis(2000);
+// The width of the chunk as a fraction of the bar's width
+const CHUNK_FRACTION: f64 = 0.25;
+
+/// A bar that fills up as an operation progresses.
+///
+/// If the progress can't be measured, the bar can be made indeterminate, and then a chunk moves
+/// across it repeatedly. The chunk is only animated while the bar is visible in a window.
+//#[dynamin::control]
+pub struct ProgressBarData {
+    sub_control: SubControlData,
+    minimum: Cell<f64>,
+    maximum: Cell<f64>,
+    value: Cell<f64>,
+    indeterminate: Cell<bool>,
+    animation: Animation,
+}
+
+impl SubControlRef for ProgressBarData {
+    fn sub_control_ref(&self) -> &SubControlData { &self.sub_control }
+
+    fn best_size(&self) -> Size2<f64> {
+        Size2::new(160.0, 15.0)
+    }
+}
+
+impl EventHandler for ProgressBarData {
+    fn on_event(&self, route: &mut EventRoute) {
+        if let Some(VisibilityChangedEvent { .. }) = route.event.downcast_mut() {
+            self.update_animation();
+        } else if let Some(AttachedToWindowEvent { .. }) = route.event.downcast_mut() {
+            self.update_animation();
+        } else if let Some(DetachedFromWindowEvent { .. }) = route.event.downcast_mut() {
+            self.update_animation();
+        } else if let Some(PaintingEvent { painter }) = route.event.downcast_mut() {
+            self.paint(&mut **painter);
+        }
+    }
+}
+
+impl ProgressBarData {
+    pub fn new() -> Self {
+        ProgressBarData {
+            sub_control: SubControlData::new(),
+            minimum: Cell::new(0.0),
+            maximum: Cell::new(100.0),
+            value: Cell::new(0.0),
+            indeterminate: Cell::new(false),
+            animation: Animation::new(),
+        }
+    }
+
+    pub fn minimum(&self) -> f64 {
+        self.minimum.get()
+    }
+
+    pub fn maximum(&self) -> f64 {
+        self.maximum.get()
+    }
+
+    /// Sets the range of the value, which is limited to it. If `maximum` is less than `minimum`,
+    /// it is set to `minimum`.
+    pub fn set_range(&self, minimum: f64, maximum: f64) {
+        self.minimum.set(minimum);
+        self.maximum.set(maximum.max(minimum));
+        self.set_value(self.value.get());
+        self.repaint_later();
+    }
+
+    pub fn value(&self) -> f64 {
+        self.value.get()
+    }
+
+    /// Sets the progress, limited to the range. The bar is empty at the minimum and full at the
+    /// maximum.
+    pub fn set_value(&self, value: f64) {
+        let value = value.max(self.minimum.get()).min(self.maximum.get());
+        if self.value.replace(value) != value {
+            self.repaint_later();
+        }
+    }
+
+    pub fn indeterminate(&self) -> bool {
+        self.indeterminate.get()
+    }
+
+    /// Sets whether the bar shows a moving chunk instead of the value, for when the progress
+    /// isn't known. The value is kept and shown again when this is set back to `false`.
+    pub fn set_indeterminate(&self, indeterminate: bool) {
+        if self.indeterminate.replace(indeterminate) != indeterminate {
+            self.update_animation();
+            self.repaint_later();
+        }
+    }
+
+    // Returns how much of the bar is filled, from 0 to 1.
+    fn fraction(&self) -> f64 {
+        let range = self.maximum.get() - self.minimum.get();
+        if range > 0.0 {
+            (self.value.get() - self.minimum.get()) / range
+        } else {
+            0.0
+        }
+    }
+
+    // There's no point in repainting when nothing is shown, so the animation only runs while the
+    // chunk could be seen.
+    fn update_animation(&self) {
+        if self.indeterminate.get() && self.is_effectively_visible() {
+            self.animation.start(self);
+        } else {
+            self.animation.stop();
+        }
+    }
+
+    fn paint(&self, painter: &mut dyn Painter) {
+        let size = self.size();
+        let (width, height) = (size.width as f32, size.height as f32);
+        painter.fill_path(
+            &mut theme::rect_path(0.0, 0.0, width, height).path_iter(),
+            &Brush::Solid(Color::from_rgba(230, 230, 230, 255)),
+        );
+
+        // The fill is inside the border.
+        let inner_width = (size.width - 2.0).max(0.0);
+        let (start, end) = if self.indeterminate.get() {
+            chunk_bounds(inner_width, self.animation.elapsed())
+        } else {
+            (0.0, (inner_width * self.fraction()).round())
+        };
+        if end > start {
+            let fill = if self.is_effectively_enabled() {
+                Color::from_rgba(6, 176, 37, 255)
+            } else {
+                Color::from_rgba(188, 188, 188, 255)
+            };
+            painter.fill_path(
+                &mut theme::rect_path(1.0 + start as f32, 1.0, 1.0 + end as f32, height - 1.0)
+                    .path_iter(),
+                &Brush::Solid(fill),
+            );
+        }
+
+        painter.stroke_path(
+            &mut theme::rect_path(0.5, 0.5, width - 0.5, height - 0.5).path_iter(),
+            &Brush::Solid(Color::from_rgba(188, 188, 188, 255)),
+            &StrokeStyle::with_width(1.0),
+        );
+    }
+}
+
+impl Default for ProgressBarData {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Returns the start and end of the indeterminate chunk within `width`. The chunk enters at the
+// left, crosses the bar and leaves at the right once each cycle.
+fn chunk_bounds(width: f64, elapsed: Duration) -> (f64, f64) {
+    let cycle = CYCLE.as_secs_f64();
+    let phase = elapsed.as_secs_f64() % cycle / cycle;
+    let chunk = (width * CHUNK_FRACTION).round();
+    let start = (-chunk + (width + chunk) * phase).round();
+    (start.max(0.0), (start + chunk).min(width))
+}
+
+#[test]
+fn test_progress_bar() {
+    let bar = ProgressBar::new();
+    bar.set_range(10.0, 60.0);
+    assert_eq!(bar.value(), 10.0);
+    bar.set_value(35.0);
+    assert_eq!(bar.fraction(), 0.5);
+    bar.set_value(100.0);
+    assert_eq!(bar.value(), 60.0);
+    bar.set_range(0.0, 30.0);
+    assert_eq!((bar.value(), bar.fraction()), (30.0, 1.0));
+
+    // The bar isn't in a window, so there's nothing to animate.
+    bar.set_indeterminate(true);
+    assert!(!bar.animation.is_running());
+
+    // The chunk is a quarter of the bar and slides in from the left.
+    assert_eq!(chunk_bounds(100.0, Duration::from_millis(0)), (0.0, 0.0));
+    assert_eq!(chunk_bounds(100.0, Duration::from_millis(400)), (0.0, 25.0));
+    assert_eq!(chunk_bounds(100.0, Duration::from_millis(800)), (25.0, 50.0));
+    assert_eq!(chunk_bounds(100.0, Duration::from_millis(1800)), (88.0, 100.0));
+    assert_eq!(chunk_bounds(100.0, Duration::from_millis(2800)), (25.0, 50.0));
+}
+
+// Showing windows isn't wanted outside the headless backend.
+#[cfg(feature = "headless")]
+#[test]
+fn test_indeterminate_progress_bar_repaints() {
+    use zaffre::Point2;
+
+    use crate::backend::{fire_timers, take_invalidated_areas};
+    use crate::{Visibility, Window};
+
+    let window = Window::new();
+    let panel = SubControl::new();
+    panel.set_size(&Size2::new(200.0, 100.0));
+    let bar = ProgressBar::new();
+    bar.set_location(&Point2::new(10.0, 20.0));
+    bar.set_size(&Size2::new(100.0, 15.0));
+    bar.set_indeterminate(true);
+    panel.children().borrow_mut().push(bar.clone());
+    window.set_child(panel.into());
+    window.set_visibility(Visibility::Visible);
+    take_invalidated_areas(&window);
+
+    // Each frame of the animation repaints only the bar.
+    let bar_area = (Point2::new(10.0, 20.0), Size2::new(100.0, 15.0));
+    fire_timers();
+    assert_eq!(take_invalidated_areas(&window), vec![bar_area]);
+    assert!(take_invalidated_areas(&window).is_empty());
+    fire_timers();
+    assert_eq!(take_invalidated_areas(&window), vec![bar_area]);
+
+    // The animation stops while the bar can't be seen and when it shows its value.
+    bar.set_visibility(Visibility::Invisible);
+    take_invalidated_areas(&window);
+    fire_timers();
+    assert!(take_invalidated_areas(&window).is_empty());
+    bar.set_visibility(Visibility::Visible);
+    bar.set_indeterminate(false);
+    take_invalidated_areas(&window);
+    fire_timers();
+    assert!(take_invalidated_areas(&window).is_empty());
+}
@@ -0,0 +1,355 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ *
+ */
+
+use std::cell::{Cell, RefCell};
+use std::ops::Deref;
+use std::ptr;
+use std::rc::{Rc, Weak};
+
+use zaffre::{Brush, Color, Painter, Point2, Size2, StrokeStyle};
+
+use crate::button::{ButtonAction, ButtonBehavior};
+use crate::check_box::{
+    CheckedChangedEvent, CheckState, indicator_best_size, indicator_colors, INDICATOR_SIZE,
+    paint_indicator_text,
+};
+use crate::control::{
+    Control, PaintingEvent, set_focus, SubControl, SubControlData, SubControlRef,
+};
+use crate::event_vec::{EventHandler, EventRoute};
+use crate::keyboard::{Key, KeyDownEvent, Modifiers};
+use crate::mnemonic::MnemonicText;
+use crate::theme;
+
+// TODO: generate with a proc macro
+// start proc macro generated
+#[derive(Clone)]
+pub struct RadioButton(Rc<RadioButtonData>);
+
+impl RadioButton {
+    pub fn new() -> Self {
+        SubControl::register_handle(RadioButton(Rc::new(RadioButtonData::new())))
+    }
+}
+
+impl Default for RadioButton {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deref for RadioButton {
+    type Target = Rc<RadioButtonData>;
+    fn deref(&self) -> &Rc<RadioButtonData> {
+        &self.0
+    }
+}
+
+impl From<RadioButton> for Rc<dyn Control> {
+    fn from(self_: RadioButton) -> Self {
+        self_.0 as Rc<dyn Control>
+    }
+}
+
+impl From<RadioButton> for Rc<dyn EventHandler> {
+    fn from(self_: RadioButton) -> Self {
+        self_.0 as Rc<dyn EventHandler>
+    }
+}
+// end proc macro generated
+
+/// A set of radio buttons where at most one is checked at a time.
+///
+/// A group only holds weak references to its buttons, and each button holds a strong reference
+/// to its group, so the group lives as long as any of its buttons.
+#[derive(Clone, Default)]
+pub struct RadioGroup(Rc<RadioGroupData>);
+
+#[derive(Default)]
+struct RadioGroupData {
+    buttons: RefCell<Vec<Weak<RadioButtonData>>>,
+}
+
+impl RadioGroup {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the radio button to this group, removing it from any group it was in. If the button
+    /// is checked, the other buttons in the group are unchecked.
+    pub fn add(&self, button: &RadioButton) {
+        if let Some(old_group) = button.group.replace(Some(self.clone())) {
+            old_group.0.buttons.borrow_mut().retain(|b| {
+                b.upgrade().map_or(false, |b| !Rc::ptr_eq(&b, &button.0))
+            });
+        }
+        self.0.buttons.borrow_mut().push(Rc::downgrade(&button.0));
+        if button.checked() {
+            self.uncheck_others(&button.0);
+        }
+    }
+
+    /// Returns the checked radio button in this group, if any.
+    pub fn checked(&self) -> Option<RadioButton> {
+        self.buttons().into_iter().find(|b| b.checked()).map(RadioButton)
+    }
+
+    fn buttons(&self) -> Vec<Rc<RadioButtonData>> {
+        let mut buttons = self.0.buttons.borrow_mut();
+        buttons.retain(|b| b.upgrade().is_some());
+        buttons.iter().filter_map(|b| b.upgrade()).collect()
+    }
+
+    fn uncheck_others(&self, checked: &RadioButtonData) {
+        for button in self.buttons() {
+            if !ptr::eq(&*button, checked) {
+                button.set_state(false);
+            }
+        }
+    }
+}
+
+/// A circle that is checked by clicking it, with text next to it. Checking a radio button
+/// unchecks the other buttons in its `RadioGroup`.
+//#[dynamin::control]
+pub struct RadioButtonData {
+    sub_control: SubControlData,
+    behavior: ButtonBehavior,
+    checked: Cell<bool>,
+    group: RefCell<Option<RadioGroup>>,
+}
+
+impl SubControlRef for RadioButtonData {
+    fn sub_control_ref(&self) -> &SubControlData { &self.sub_control }
+
+    fn best_size(&self) -> Size2<f64> {
+        indicator_best_size(self.behavior.text_layout(theme::text_color(true)).as_ref())
+    }
+}
+
+impl EventHandler for RadioButtonData {
+    fn on_event(&self, route: &mut EventRoute) {
+        match self.behavior.on_event(self, route) {
+            Some(ButtonAction::Released { clicked: true }) | Some(ButtonAction::Activated) => {
+                if self.is_effectively_enabled() {
+                    self.set_checked(true);
+                }
+            }
+            _ => {}
+        }
+
+        if let Some(KeyDownEvent { key, modifiers, .. }) = route.event.downcast_mut() {
+            if *modifiers == Modifiers::none() {
+                let forward = match key {
+                    Key::Down | Key::Right => Some(true),
+                    Key::Up | Key::Left => Some(false),
+                    _ => None,
+                };
+                if let Some(forward) = forward {
+                    if self.select_adjacent(forward) {
+                        route.handled = true;
+                    }
+                }
+            }
+        }
+
+        if let Some(PaintingEvent { painter }) = route.event.downcast_mut() {
+            self.paint(&mut **painter);
+        }
+    }
+}
+
+impl RadioButtonData {
+    pub fn new() -> Self {
+        let sub_control = SubControlData::new();
+        sub_control.set_focusable(true);
+        RadioButtonData {
+            sub_control,
+            behavior: ButtonBehavior::new(false),
+            checked: Cell::new(false),
+            group: RefCell::new(None),
+        }
+    }
+
+    /// Returns the radio button's text, without the `&` marking the mnemonic.
+    pub fn text(&self) -> String {
+        self.behavior.text.borrow().text.clone()
+    }
+
+    /// Sets the text shown next to the circle. An `&` before a character makes it the mnemonic,
+    /// like with `Button::set_text()`.
+    pub fn set_text(&self, text: &str) {
+        *self.behavior.text.borrow_mut() = MnemonicText::parse(text);
+        self.sub_control.best_size_changed();
+        self.repaint_later();
+    }
+
+    pub fn checked(&self) -> bool {
+        self.checked.get()
+    }
+
+    /// Checks or unchecks the radio button. Checking it unchecks the other buttons in its group.
+    /// A `CheckedChangedEvent` is sent to each button whose state changes.
+    pub fn set_checked(&self, checked: bool) {
+        if checked {
+            if let Some(group) = self.group() {
+                group.uncheck_others(self);
+            }
+        }
+        self.set_state(checked);
+    }
+
+    pub fn group(&self) -> Option<RadioGroup> {
+        self.group.borrow().clone()
+    }
+
+    // Convenience method to add an event handler that is called for `CheckedChangedEvent`s.
+    pub fn on_checked_changed_event<F>(&self, mut handler: F)
+        where F: for<'a> FnMut(&'a mut EventRoute) + 'static
+    {
+        self.event_handlers().add(move |mut route| {
+            if let Some(_) = route.event.downcast_mut::<CheckedChangedEvent>() {
+                handler(&mut route);
+            }
+        });
+    }
+
+    fn set_state(&self, checked: bool) {
+        if self.checked.replace(checked) != checked {
+            let state = if checked { CheckState::Checked } else { CheckState::Unchecked };
+            self.event_handlers().send(&mut CheckedChangedEvent { state });
+            self.repaint_later();
+        }
+    }
+
+    // Moves focus to the next or previous enabled and visible button in the group and checks it,
+    // wrapping around at the ends. Returns whether there was such a button.
+    fn select_adjacent(&self, forward: bool) -> bool {
+        let buttons = match self.group() {
+            Some(group) => group.buttons(),
+            None => return false,
+        };
+        let index = match buttons.iter().position(|b| ptr::eq(&**b, self)) {
+            Some(index) => index,
+            None => return false,
+        };
+        let len = buttons.len();
+        for i in 1..len {
+            let next = if forward { (index + i) % len } else { (index + len - i) % len };
+            let button = &buttons[next];
+            if button.is_effectively_enabled() && button.is_effectively_visible() {
+                set_focus(&(button.clone() as Rc<dyn Control>));
+                button.set_checked(true);
+                return true;
+            }
+        }
+        false
+    }
+
+    fn paint(&self, painter: &mut dyn Painter) {
+        let enabled = self.is_effectively_enabled();
+        let size = self.size();
+        let (fill_color, border_color) = indicator_colors(enabled, &self.behavior);
+
+        let radius = (INDICATOR_SIZE / 2.0) as f32;
+        let center = Point2::new(radius, (size.height / 2.0).round() as f32);
+        painter.fill_path(
+            &mut theme::circle_path(center, radius).path_iter(),
+            &Brush::Solid(fill_color),
+        );
+        painter.stroke_path(
+            &mut theme::circle_path(center, radius - 0.5).path_iter(),
+            &Brush::Solid(border_color),
+            &StrokeStyle::with_width(1.0),
+        );
+        if self.checked.get() {
+            let dot_color = if enabled {
+                Color::from_rgba(0, 0, 0, 255)
+            } else {
+                Color::from_rgba(191, 191, 191, 255)
+            };
+            painter.fill_path(
+                &mut theme::circle_path(center, radius - 3.5).path_iter(),
+                &Brush::Solid(dot_color),
+            );
+        }
+
+        let layout = self.behavior.text_layout(theme::text_color(enabled));
+        paint_indicator_text(painter, layout, size, self.focused());
+    }
+}
+
+#[test]
+fn test_radio_group() {
+    let group = RadioGroup::new();
+    let a = RadioButton::new();
+    let b = RadioButton::new();
+    let c = RadioButton::new();
+    group.add(&a);
+    group.add(&b);
+    group.add(&c);
+    assert!(group.checked().is_none());
+
+    a.set_checked(true);
+    assert!(a.checked());
+    b.set_checked(true);
+    assert!(!a.checked() && b.checked() && !c.checked());
+    assert!(Rc::ptr_eq(&group.checked().unwrap().0, &b.0));
+
+    // a checked button added to the group unchecks the others
+    let d = RadioButton::new();
+    d.set_checked(true);
+    group.add(&d);
+    assert!(!b.checked() && d.checked());
+
+    // moving to another group leaves the old one
+    let other = RadioGroup::new();
+    other.add(&d);
+    assert!(group.checked().is_none());
+    c.set_checked(true);
+    assert!(c.checked() && d.checked());
+}
+
+#[test]
+fn test_radio_arrow_keys() {
+    use crate::layout::StackPanel;
+    use crate::Window;
+
+    let window = Window::new();
+    let panel = StackPanel::new();
+    window.set_child(panel.clone().into());
+    let group = RadioGroup::new();
+    let buttons: Vec<_> = (0..3).map(|_| RadioButton::new()).collect();
+    for button in &buttons {
+        group.add(button);
+        panel.children().borrow_mut().push(button.clone());
+    }
+    // The arrows skip disabled buttons.
+    buttons[1].set_enabled(false);
+
+    assert!(set_focus(&(buttons[0].0.clone() as Rc<dyn Control>)));
+    let press = |from: usize, key| {
+        let modifiers = Modifiers::none();
+        buttons[from].event_handlers().send(&mut KeyDownEvent { key, modifiers, repeat: false });
+    };
+    let assert_selected = |index: usize| {
+        for (i, button) in buttons.iter().enumerate() {
+            assert_eq!(button.checked(), i == index);
+            assert_eq!(button.focused(), i == index);
+        }
+    };
+
+    press(0, Key::Down);
+    assert_selected(2);
+    // The selection wraps around at the ends.
+    press(2, Key::Right);
+    assert_selected(0);
+    press(0, Key::Up);
+    assert_selected(2);
+    press(2, Key::Left);
+    assert_selected(0);
+}
@@ -0,0 +1,504 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ *
+ */
+
+use std::cell::Cell;
+use std::ops::Deref;
+use std::rc::Rc;
+
+use zaffre::{Brush, Color, Painter, PathBuf, Point2, Size2};
+
+use crate::button::AutoRepeat;
+use crate::control::{
+    Control, MouseButton, MouseCaptureLostEvent, MouseDownEvent, MouseDraggedEvent,
+    MouseLeftEvent, MouseMovedEvent, MouseUpEvent, MouseWheelEvent, PaintingEvent, SubControl,
+    SubControlData, SubControlRef,
+};
+use crate::event_vec::{EventHandler, EventRoute};
+use crate::layout::Orientation;
+use crate::scroll_view::SCROLL_BAR_SIZE;
+use crate::theme;
+
+// TODO: generate with a proc macro
+// start proc macro generated
+#[derive(Clone)]
+pub struct ScrollBar(Rc<ScrollBarData>);
+
+impl ScrollBar {
+    pub fn new() -> Self {
+        SubControl::register_handle(ScrollBar(Rc::new(ScrollBarData::new())))
+    }
+}
+
+impl Default for ScrollBar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deref for ScrollBar {
+    type Target = Rc<ScrollBarData>;
+    fn deref(&self) -> &Rc<ScrollBarData> {
+        &self.0
+    }
+}
+
+impl From<ScrollBar> for Rc<dyn Control> {
+    fn from(self_: ScrollBar) -> Self {
+        self_.0 as Rc<dyn Control>
+    }
+}
+
+impl From<ScrollBar> for Rc<dyn EventHandler> {
+    fn from(self_: ScrollBar) -> Self {
+        self_.0 as Rc<dyn EventHandler>
+    }
+}
+// end proc macro generated
+
+const MIN_THUMB_SIZE: f64 = 17.0;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub(crate) enum ScrollBarPart {
+    LineUp,
+    PageUp,
+    Thumb,
+    PageDown,
+    LineDown,
+}
+
+// The positions along a scroll bar of the start of the thumb, its length, and the length of the
+// track the thumb moves in. The track starts after the up arrow button.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct ThumbGeometry {
+    pub(crate) pos: f64,
+    pub(crate) len: f64,
+    pub(crate) track_len: f64,
+}
+
+// Returns where the thumb is on a scroll bar of the length, when `value` is between zero and
+// `range` and `page` of the range is visible. Returns `None` if there is nothing to scroll or the
+// scroll bar is too short to have a thumb.
+pub(crate) fn thumb_geometry(bar_len: f64, value: f64, range: f64, page: f64)
+    -> Option<ThumbGeometry>
+{
+    let track_len = bar_len - SCROLL_BAR_SIZE * 2.0;
+    if range <= 0.0 || track_len < MIN_THUMB_SIZE {
+        return None;
+    }
+    let len = (track_len * page / (range + page)).max(MIN_THUMB_SIZE).min(track_len).round();
+    let pos = SCROLL_BAR_SIZE + ((track_len - len) * value / range).round();
+    Some(ThumbGeometry { pos, len, track_len })
+}
+
+// Returns the part of a scroll bar of the length at the position along it.
+pub(crate) fn part_at(pos: f64, bar_len: f64, thumb: Option<ThumbGeometry>) -> ScrollBarPart {
+    if pos < SCROLL_BAR_SIZE {
+        ScrollBarPart::LineUp
+    } else if pos >= bar_len - SCROLL_BAR_SIZE {
+        ScrollBarPart::LineDown
+    } else {
+        match thumb {
+            Some(thumb) if pos < thumb.pos => ScrollBarPart::PageUp,
+            Some(thumb) if pos < thumb.pos + thumb.len => ScrollBarPart::Thumb,
+            _ => ScrollBarPart::PageDown,
+        }
+    }
+}
+
+// Returns the color of the thumb or of an arrow button's triangle.
+pub(crate) fn part_color(part: ScrollBarPart, enabled: bool, hot: bool, pressed: bool) -> Color {
+    if !enabled {
+        Color::from_rgba(191, 191, 191, 255)
+    } else if pressed {
+        Color::from_rgba(96, 96, 96, 255)
+    } else if hot {
+        Color::from_rgba(166, 166, 166, 255)
+    } else if part == ScrollBarPart::Thumb {
+        Color::from_rgba(205, 205, 205, 255)
+    } else {
+        Color::from_rgba(96, 96, 96, 255)
+    }
+}
+
+// Paints a scroll bar with the bounds (left, top, right, bottom).
+pub(crate) fn paint_scroll_bar(
+    painter: &mut dyn Painter,
+    orientation: Orientation,
+    (left, top, right, bottom): (f64, f64, f64, f64),
+    thumb: Option<ThumbGeometry>,
+    part_color: &dyn Fn(ScrollBarPart) -> Color,
+) {
+    painter.fill_path(
+        &mut theme::rect_path(left as f32, top as f32, right as f32, bottom as f32).path_iter(),
+        &Brush::Solid(Color::from_rgba(240, 240, 240, 255)),
+    );
+
+    // Returns a rectangle along the scroll bar, from `start` to `end`, inset by `inset` across it.
+    let rect_along = |start: f64, end: f64, inset: f64| match orientation {
+        Orientation::Horizontal => theme::rect_path(
+            (left + start) as f32, (top + inset) as f32,
+            (left + end) as f32, (bottom - inset) as f32,
+        ),
+        Orientation::Vertical => theme::rect_path(
+            (left + inset) as f32, (top + start) as f32,
+            (right - inset) as f32, (top + end) as f32,
+        ),
+    };
+
+    if let Some(thumb) = thumb {
+        painter.fill_path(
+            &mut rect_along(thumb.pos, thumb.pos + thumb.len, 1.0).path_iter(),
+            &Brush::Solid(part_color(ScrollBarPart::Thumb)),
+        );
+    }
+
+    // Each arrow button has a small triangle pointing away from the middle of the scroll bar.
+    let len = along(orientation, Point2::new(right - left, bottom - top));
+    let half = SCROLL_BAR_SIZE / 2.0;
+    for &(part, center, dir) in [
+        (ScrollBarPart::LineUp, half, -1.0),
+        (ScrollBarPart::LineDown, len - half, 1.0),
+    ].iter() {
+        // The points are given as (distance along the scroll bar, distance across it).
+        let point = |a: f64, c: f64| match orientation {
+            Orientation::Horizontal => Point2::new((left + a) as f32, (top + c) as f32),
+            Orientation::Vertical => Point2::new((left + c) as f32, (top + a) as f32),
+        };
+        let mut path = PathBuf::new();
+        path.move_to(point(center + 2.0 * dir, half));
+        path.line_to(point(center - 2.0 * dir, half - 4.0));
+        path.line_to(point(center - 2.0 * dir, half + 4.0));
+        path.close();
+        painter.fill_path(&mut path.path_iter(), &Brush::Solid(part_color(part)));
+    }
+}
+
+// Returns the coordinate of the point along the orientation.
+pub(crate) fn along(orientation: Orientation, pt: Point2<f64>) -> f64 {
+    match orientation {
+        Orientation::Horizontal => pt.x,
+        Orientation::Vertical => pt.y,
+    }
+}
+
+/// Sent to a `ScrollBar` or `Slider` when its value changes, whether by the user or by
+/// `set_value()`.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct ValueChangedEvent {
+    pub value: f64,
+}
+
+/// A scroll bar that isn't attached to a `ScrollView`, for scrolling something that the
+/// application paints itself.
+///
+/// The value is between the minimum and the maximum. The page size is how much of the range is
+/// visible at once, which sets the length of the thumb and how far clicking the track moves.
+/// Holding an arrow button or the track down repeats it.
+//#[dynamin::control]
+pub struct ScrollBarData {
+    sub_control: SubControlData,
+    orientation: Cell<Orientation>,
+    minimum: Cell<f64>,
+    maximum: Cell<f64>,
+    value: Cell<f64>,
+    page_size: Cell<f64>,
+    step: Cell<f64>,
+    hot_part: Cell<Option<ScrollBarPart>>,
+    pressed_part: Cell<Option<ScrollBarPart>>,
+    // Where on the thumb it was grabbed, while it is being dragged
+    grab_pos: Cell<f64>,
+    // The last mouse position along the scroll bar while a part is pressed
+    mouse_pos: Cell<f64>,
+    auto_repeat: AutoRepeat,
+}
+
+impl SubControlRef for ScrollBarData {
+    fn sub_control_ref(&self) -> &SubControlData { &self.sub_control }
+
+    fn best_size(&self) -> Size2<f64> {
+        let len = SCROLL_BAR_SIZE * 2.0 + MIN_THUMB_SIZE * 3.0;
+        match self.orientation.get() {
+            Orientation::Horizontal => Size2::new(len, SCROLL_BAR_SIZE),
+            Orientation::Vertical => Size2::new(SCROLL_BAR_SIZE, len),
+        }
+    }
+}
+
+impl EventHandler for ScrollBarData {
+    fn on_event(&self, route: &mut EventRoute) {
+        if let Some(MouseDownEvent { location, button: MouseButton::Left, .. }) =
+            route.event.downcast_mut()
+        {
+            self.press(self.along(*location));
+            route.handled = true;
+        } else if let Some(MouseDraggedEvent { location }) = route.event.downcast_mut() {
+            let pos = self.along(*location);
+            self.mouse_pos.set(pos);
+            if self.pressed_part.get() == Some(ScrollBarPart::Thumb) {
+                self.drag_thumb(pos - self.grab_pos.get());
+            }
+            self.set_hot_part(self.part_under(*location));
+        } else if let Some(MouseUpEvent { button: MouseButton::Left, .. }) =
+            route.event.downcast_mut()
+        {
+            self.release();
+        } else if let Some(MouseCaptureLostEvent) = route.event.downcast_mut() {
+            self.release();
+        } else if let Some(MouseMovedEvent { location }) = route.event.downcast_mut() {
+            self.set_hot_part(self.part_under(*location));
+        } else if let Some(MouseLeftEvent) = route.event.downcast_mut() {
+            self.set_hot_part(None);
+        } else if let Some(event) = route.event.downcast_mut::<MouseWheelEvent>() {
+            let delta = match self.orientation.get() {
+                Orientation::Horizontal if event.delta_x != 0.0 => event.delta_x,
+                _ => event.delta_y,
+            };
+            let old_value = self.value.get();
+            self.set_value(old_value + delta * self.step.get());
+            route.handled = self.value.get() != old_value;
+        } else if self.auto_repeat.is_repeat_event(route) {
+            self.repeat();
+        } else if let Some(PaintingEvent { painter }) = route.event.downcast_mut() {
+            self.paint(&mut **painter);
+        }
+    }
+}
+
+impl ScrollBarData {
+    pub fn new() -> Self {
+        ScrollBarData {
+            sub_control: SubControlData::new(),
+            orientation: Cell::new(Orientation::Vertical),
+            minimum: Cell::new(0.0),
+            maximum: Cell::new(100.0),
+            value: Cell::new(0.0),
+            page_size: Cell::new(10.0),
+            step: Cell::new(1.0),
+            hot_part: Cell::new(None),
+            pressed_part: Cell::new(None),
+            grab_pos: Cell::new(0.0),
+            mouse_pos: Cell::new(0.0),
+            auto_repeat: AutoRepeat::new(),
+        }
+    }
+
+    pub fn orientation(&self) -> Orientation {
+        self.orientation.get()
+    }
+
+    /// Sets whether the scroll bar is horizontal or vertical. It is vertical by default.
+    pub fn set_orientation(&self, orientation: Orientation) {
+        self.orientation.set(orientation);
+        self.repaint_later();
+    }
+
+    pub fn minimum(&self) -> f64 {
+        self.minimum.get()
+    }
+
+    pub fn maximum(&self) -> f64 {
+        self.maximum.get()
+    }
+
+    /// Sets the range of the value, which is limited to it. If `maximum` is less than `minimum`,
+    /// it is set to `minimum`.
+    pub fn set_range(&self, minimum: f64, maximum: f64) {
+        self.minimum.set(minimum);
+        self.maximum.set(maximum.max(minimum));
+        self.set_value(self.value.get());
+        self.repaint_later();
+    }
+
+    pub fn value(&self) -> f64 {
+        self.value.get()
+    }
+
+    /// Sets the value, limited to the range, and sends a `ValueChangedEvent` if it changed.
+    pub fn set_value(&self, value: f64) {
+        let value = value.max(self.minimum.get()).min(self.maximum.get());
+        if self.value.replace(value) != value {
+            self.repaint_later();
+            self.event_handlers().send(&mut ValueChangedEvent { value });
+        }
+    }
+
+    pub fn page_size(&self) -> f64 {
+        self.page_size.get()
+    }
+
+    /// Sets how much of the range is visible at once. Clicking the track moves the value by
+    /// this much.
+    pub fn set_page_size(&self, page_size: f64) {
+        self.page_size.set(page_size.max(0.0));
+        self.repaint_later();
+    }
+
+    pub fn step(&self) -> f64 {
+        self.step.get()
+    }
+
+    /// Sets how far clicking an arrow button moves the value. The mouse wheel moves it by this
+    /// much for each line.
+    pub fn set_step(&self, step: f64) {
+        self.step.set(step);
+    }
+
+    // Returns the position along the scroll bar of the point.
+    fn along(&self, pt: Point2<f64>) -> f64 {
+        along(self.orientation.get(), pt)
+    }
+
+    fn len(&self) -> f64 {
+        let size = self.size();
+        along(self.orientation.get(), Point2::new(size.width, size.height))
+    }
+
+    fn thumb_geometry(&self) -> Option<ThumbGeometry> {
+        let range = self.maximum.get() - self.minimum.get();
+        let value = self.value.get() - self.minimum.get();
+        thumb_geometry(self.len(), value, range, self.page_size.get())
+    }
+
+    fn part_under(&self, pt: Point2<f64>) -> Option<ScrollBarPart> {
+        if !self.hit_test(pt.x, pt.y) {
+            return None;
+        }
+        Some(part_at(self.along(pt), self.len(), self.thumb_geometry()))
+    }
+
+    fn set_hot_part(&self, part: Option<ScrollBarPart>) {
+        if self.hot_part.replace(part) != part {
+            self.repaint_later();
+        }
+    }
+
+    fn press(&self, pos: f64) {
+        if !self.is_effectively_enabled() {
+            return;
+        }
+        let part = part_at(pos, self.len(), self.thumb_geometry());
+        self.pressed_part.set(Some(part));
+        self.mouse_pos.set(pos);
+        self.repaint_later();
+        if part == ScrollBarPart::Thumb {
+            self.grab_pos.set(pos - self.thumb_geometry().map_or(0.0, |t| t.pos));
+            return;
+        }
+        self.repeat();
+        self.auto_repeat.start(self);
+    }
+
+    // Moves the value for the pressed arrow button or track. Like native scroll bars, it pauses
+    // while the mouse is off the pressed part, so paging stops when the thumb reaches the mouse.
+    fn repeat(&self) {
+        let pressed = match self.pressed_part.get() {
+            Some(part) => part,
+            None => return,
+        };
+        if part_at(self.mouse_pos.get(), self.len(), self.thumb_geometry()) != pressed {
+            return;
+        }
+        let value = self.value.get();
+        match pressed {
+            ScrollBarPart::LineUp => self.set_value(value - self.step.get()),
+            ScrollBarPart::LineDown => self.set_value(value + self.step.get()),
+            ScrollBarPart::PageUp => self.set_value(value - self.page_size.get()),
+            ScrollBarPart::PageDown => self.set_value(value + self.page_size.get()),
+            ScrollBarPart::Thumb => {}
+        }
+    }
+
+    // Sets the value so that the thumb starts at the position along the scroll bar.
+    fn drag_thumb(&self, thumb_pos: f64) {
+        let thumb = match self.thumb_geometry() {
+            Some(thumb) => thumb,
+            None => return,
+        };
+        let free_len = thumb.track_len - thumb.len;
+        if free_len <= 0.0 {
+            return;
+        }
+        let range = self.maximum.get() - self.minimum.get();
+        self.set_value(self.minimum.get() + (thumb_pos - SCROLL_BAR_SIZE) / free_len * range);
+    }
+
+    fn release(&self) {
+        self.auto_repeat.stop();
+        if self.pressed_part.take().is_some() {
+            self.repaint_later();
+        }
+    }
+
+    fn paint(&self, painter: &mut dyn Painter) {
+        let size = self.size();
+        let enabled = self.is_effectively_enabled();
+        let (hot, pressed) = (self.hot_part.get(), self.pressed_part.get());
+        paint_scroll_bar(
+            painter,
+            self.orientation.get(),
+            (0.0, 0.0, size.width, size.height),
+            self.thumb_geometry(),
+            &|part| part_color(part, enabled, hot == Some(part), pressed == Some(part)),
+        );
+    }
+
+    // Convenience method to add an event handler that is called for `ValueChangedEvent`s.
+    pub fn on_value_changed_event<F>(&self, mut handler: F)
+        where F: for<'a> FnMut(&'a mut EventRoute) + 'static
+    {
+        self.event_handlers().add(move |mut route| {
+            if let Some(_) = route.event.downcast_mut::<ValueChangedEvent>() {
+                handler(&mut route);
+            }
+        });
+    }
+}
+
+impl Default for ScrollBarData {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[test]
+fn test_scroll_bar() {
+    use crate::control::{dispatch_mouse_down, dispatch_mouse_moved, dispatch_mouse_up};
+    use crate::keyboard::Modifiers;
+
+    let root = SubControl::new();
+    root.set_size(&Size2::new(300.0, 300.0));
+    let bar = ScrollBar::new();
+    bar.set_location(&Point2::new(10.0, 0.0));
+    bar.set_size(&Size2::new(SCROLL_BAR_SIZE, 134.0));
+    bar.set_range(0.0, 100.0);
+    bar.set_page_size(100.0);
+    root.children().borrow_mut().push(bar.clone());
+    // The track is 100 long, so the thumb is half of it and each pixel is two units.
+    assert_eq!(bar.thumb_geometry().unwrap().len, 50.0);
+
+    let press = |y: f64| {
+        dispatch_mouse_down(&**root, Point2::new(15.0, y), MouseButton::Left, Modifiers::none());
+        dispatch_mouse_up(&**root, Point2::new(15.0, y), MouseButton::Left);
+    };
+    press(130.0);
+    assert_eq!(bar.value(), 1.0);
+    press(100.0);
+    assert_eq!(bar.value(), 100.0);
+    press(5.0);
+    press(5.0);
+    assert_eq!(bar.value(), 98.0);
+
+    // The thumb keeps following the mouse outside the scroll bar.
+    dispatch_mouse_down(&**root, Point2::new(15.0, 100.0), MouseButton::Left, Modifiers::none());
+    dispatch_mouse_moved(&**root, Point2::new(200.0, 80.0));
+    assert_eq!(bar.value(), 58.0);
+    dispatch_mouse_moved(&**root, Point2::new(200.0, -50.0));
+    assert_eq!(bar.value(), 0.0);
+    dispatch_mouse_up(&**root, Point2::new(200.0, -50.0), MouseButton::Left);
+    assert_eq!(bar.pressed_part.get(), None);
+}
@@ -0,0 +1,574 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ *
+ */
+
+use std::cell::{Cell, RefCell};
+use std::ops::Deref;
+use std::rc::Rc;
+
+use zaffre::{Brush, Color, Painter, Point2, Rect, Size2};
+
+use crate::control::{
+    ChildrenChangedEvent, Control, MouseButton, MouseDownEvent, MouseDraggedEvent, MouseLeftEvent,
+    MouseMovedEvent, MouseUpEvent, MouseWheelEvent, PaintingEvent, SizeChangedEvent, SubControl,
+    SubControlData, SubControlRef,
+};
+use crate::event_vec::{EventHandler, EventRoute};
+use crate::keyboard::{Key, KeyDownEvent, Modifiers};
+use crate::layout::Orientation;
+use crate::scroll_bar::{
+    along, paint_scroll_bar, part_at, part_color, thumb_geometry, ScrollBarPart, ThumbGeometry,
+};
+use crate::theme;
+
+// TODO: generate with a proc macro
+// start proc macro generated
+#[derive(Clone)]
+pub struct ScrollView(Rc<ScrollViewData>);
+
+impl ScrollView {
+    pub fn new() -> Self {
+        SubControl::register_handle(ScrollView(Rc::new(ScrollViewData::new())))
+    }
+}
+
+impl Default for ScrollView {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deref for ScrollView {
+    type Target = Rc<ScrollViewData>;
+    fn deref(&self) -> &Rc<ScrollViewData> {
+        &self.0
+    }
+}
+
+impl From<ScrollView> for Rc<dyn Control> {
+    fn from(self_: ScrollView) -> Self {
+        self_.0 as Rc<dyn Control>
+    }
+}
+
+impl From<ScrollView> for Rc<dyn EventHandler> {
+    fn from(self_: ScrollView) -> Self {
+        self_.0 as Rc<dyn EventHandler>
+    }
+}
+// end proc macro generated
+
+/// When a scroll bar is shown.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ScrollBarVisibility {
+    /// The scroll bar is shown only when the content doesn't fit.
+    Auto,
+    Always,
+    /// The scroll bar is never shown, and the content is made to fit in that direction.
+    Never,
+}
+
+/// Sent to a scroll view after its content is scrolled, whether by the user or by code.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct ScrollOffsetChangedEvent;
+
+// The width of a vertical scroll bar and the height of a horizontal one. The arrow buttons are
+// square.
+pub(crate) const SCROLL_BAR_SIZE: f64 = 17.0;
+// The number of pixels scrolled for each line of a mouse wheel event or arrow button click
+pub(crate) const LINE_SIZE: f64 = 16.0;
+
+/// A control that shows part of a larger content control and scroll bars to move around it.
+///
+/// The content is given its best size, stretched to fill the view in each direction where it is
+/// smaller. Scrolling moves the content, so its children receive input and can convert
+/// coordinates as usual. Painting is clipped to the area inside the scroll bars.
+//#[dynamin::control]
+pub struct ScrollViewData {
+    sub_control: SubControlData,
+    // The only child of the scroll view. It has the size of the visible area, so that input
+    // over the scroll bars doesn't go to the content under them, and it holds the content.
+    viewport: SubControl,
+    content: RefCell<Option<Rc<dyn Control>>>,
+    offset: Cell<Point2<f64>>,
+    h_visibility: Cell<ScrollBarVisibility>,
+    v_visibility: Cell<ScrollBarVisibility>,
+    // Whether each scroll bar was shown by the last layout
+    h_shown: Cell<bool>,
+    v_shown: Cell<bool>,
+    hot_part: Cell<Option<(Orientation, ScrollBarPart)>>,
+    // The scroll bar whose thumb is being dragged and where on the thumb it was grabbed
+    dragging: Cell<Option<(Orientation, f64)>>,
+}
+
+impl SubControlRef for ScrollViewData {
+    fn sub_control_ref(&self) -> &SubControlData { &self.sub_control }
+
+    fn best_size(&self) -> Size2<f64> {
+        let content_size = self.content().map_or(Size2::new(0.0, 0.0), |c| c.best_size());
+        let mut size = content_size;
+        if self.v_visibility.get() == ScrollBarVisibility::Always {
+            size.width += SCROLL_BAR_SIZE;
+        }
+        if self.h_visibility.get() == ScrollBarVisibility::Always {
+            size.height += SCROLL_BAR_SIZE;
+        }
+        size
+    }
+}
+
+impl EventHandler for ScrollViewData {
+    fn on_event(&self, route: &mut EventRoute) {
+        if let Some(event) = route.event.downcast_mut::<MouseWheelEvent>() {
+            let (mut dx, mut dy) = event.pixel_delta
+                .unwrap_or((event.delta_x * LINE_SIZE, event.delta_y * LINE_SIZE));
+            // Shift turns a vertical wheel into a horizontal one.
+            if event.modifiers.shift && dx == 0.0 {
+                dx = dy;
+                dy = 0.0;
+            }
+            let old_offset = self.scroll_offset();
+            self.scroll_by(dx, dy);
+            // Let an outer scroll view have the event if this one is already scrolled to the end.
+            route.handled = self.scroll_offset() != old_offset;
+        } else if let Some(event) = route.event.downcast_mut::<KeyDownEvent>() {
+            if event.modifiers == Modifiers::none() {
+                let page = self.page_size(Orientation::Vertical);
+                match event.key {
+                    Key::PageUp => self.scroll_by(0.0, -page),
+                    Key::PageDown => self.scroll_by(0.0, page),
+                    _ => return,
+                }
+                route.handled = true;
+            }
+        } else if let Some(MouseDownEvent { location, button: MouseButton::Left, .. }) =
+            route.event.downcast_mut()
+        {
+            if let Some((orientation, part)) = self.part_at(*location) {
+                self.press_part(orientation, part, *location);
+            }
+        } else if let Some(MouseDraggedEvent { location }) = route.event.downcast_mut() {
+            if let Some((orientation, grab_pos)) = self.dragging.get() {
+                self.drag_thumb(orientation, along(orientation, *location) - grab_pos);
+            }
+        } else if let Some(MouseUpEvent { button: MouseButton::Left, .. }) =
+            route.event.downcast_mut()
+        {
+            if self.dragging.take().is_some() {
+                self.repaint_later();
+            }
+        } else if let Some(MouseMovedEvent { location }) = route.event.downcast_mut() {
+            self.set_hot_part(self.part_at(*location));
+        } else if let Some(MouseLeftEvent) = route.event.downcast_mut() {
+            self.set_hot_part(None);
+        } else if let Some(SizeChangedEvent) = route.event.downcast_mut() {
+            self.layout();
+        } else if let Some(ChildrenChangedEvent) = route.event.downcast_mut() {
+            self.layout();
+        } else if let Some(PaintingEvent { painter }) = route.event.downcast_mut() {
+            self.paint(&mut **painter);
+        }
+    }
+}
+
+impl ScrollViewData {
+    pub fn new() -> Self {
+        ScrollViewData {
+            sub_control: SubControlData::new(),
+            viewport: SubControl::new(),
+            content: RefCell::new(None),
+            offset: Cell::new(Point2::new(0.0, 0.0)),
+            h_visibility: Cell::new(ScrollBarVisibility::Auto),
+            v_visibility: Cell::new(ScrollBarVisibility::Auto),
+            h_shown: Cell::new(false),
+            v_shown: Cell::new(false),
+            hot_part: Cell::new(None),
+            dragging: Cell::new(None),
+        }
+    }
+
+    pub fn content(&self) -> Option<Rc<dyn Control>> {
+        self.content.borrow().clone()
+    }
+
+    /// Sets the control shown in the scroll view and scrolls back to the top left.
+    pub fn set_content(&self, content: Option<Rc<dyn Control>>) {
+        // Children can't be added in `new()`, before the handle is registered.
+        if self.viewport.parent().is_none() {
+            // The content tells the viewport when its best size changes, and the scroll view has
+            // to lay out again then.
+            let viewport: Rc<dyn Control> = self.viewport.clone().into();
+            let viewport = Rc::downgrade(&viewport);
+            self.viewport.event_handlers().add(move |route| {
+                if let Some(ChildrenChangedEvent) = route.event.downcast_mut() {
+                    if let Some(scroll_view) = viewport.upgrade().and_then(|v| v.parent()) {
+                        scroll_view.event_handlers().send(&mut ChildrenChangedEvent);
+                    }
+                }
+            });
+            self.children().borrow_mut().push(self.viewport.clone());
+        }
+        *self.content.borrow_mut() = content.clone();
+        self.offset.set(Point2::new(0.0, 0.0));
+        let mut children = self.viewport.children().borrow_mut();
+        children.clear();
+        if let Some(content) = content {
+            children.push(content);
+        }
+        drop(children);
+        self.layout();
+    }
+
+    /// Returns when the horizontal scroll bar is shown.
+    pub fn horizontal_scroll_bar(&self) -> ScrollBarVisibility {
+        self.h_visibility.get()
+    }
+
+    pub fn set_horizontal_scroll_bar(&self, visibility: ScrollBarVisibility) {
+        self.h_visibility.set(visibility);
+        self.layout();
+    }
+
+    /// Returns when the vertical scroll bar is shown.
+    pub fn vertical_scroll_bar(&self) -> ScrollBarVisibility {
+        self.v_visibility.get()
+    }
+
+    pub fn set_vertical_scroll_bar(&self, visibility: ScrollBarVisibility) {
+        self.v_visibility.set(visibility);
+        self.layout();
+    }
+
+    /// Returns the size of the area that the content is shown in, which is the size of the
+    /// scroll view minus any scroll bars.
+    pub fn viewport_size(&self) -> Size2<f64> {
+        self.viewport.size()
+    }
+
+    /// Returns how far the content is scrolled. The offset is the point of the content shown at
+    /// the top left of the view.
+    pub fn scroll_offset(&self) -> Point2<f64> {
+        self.offset.get()
+    }
+
+    /// Returns the largest offset that the content can be scrolled to.
+    pub fn max_scroll_offset(&self) -> Point2<f64> {
+        let viewport_size = self.viewport.size();
+        let content_size = self.content().map_or(viewport_size, |c| c.size());
+        Point2::new(
+            (content_size.width - viewport_size.width).max(0.0),
+            (content_size.height - viewport_size.height).max(0.0),
+        )
+    }
+
+    /// Scrolls the content to the offset, which is limited to between zero and
+    /// `max_scroll_offset()`.
+    pub fn set_scroll_offset(&self, offset: Point2<f64>) {
+        let max = self.max_scroll_offset();
+        let offset = Point2::new(offset.x.min(max.x).max(0.0), offset.y.min(max.y).max(0.0));
+        let changed = self.offset.replace(offset) != offset;
+        if let Some(content) = self.content() {
+            content.set_location(&Point2::new(-offset.x, -offset.y));
+        }
+        if changed {
+            self.repaint_later();
+            self.event_handlers().send(&mut ScrollOffsetChangedEvent);
+        }
+    }
+
+    /// Scrolls the content by the specified number of pixels. Positive values scroll right and
+    /// down.
+    pub fn scroll_by(&self, dx: f64, dy: f64) {
+        let offset = self.offset.get();
+        self.set_scroll_offset(Point2::new(offset.x + dx, offset.y + dy));
+    }
+
+    /// Scrolls as little as possible to make a descendant of the content visible. If the
+    /// control is larger than the view, its top left corner is made visible.
+    pub fn scroll_into_view(&self, control: &dyn Control) {
+        let location = match control.map_point_to(&**self.viewport, Point2::new(0.0, 0.0)) {
+            Some(location) => location,
+            None => return,
+        };
+        let size = control.size();
+        let viewport_size = self.viewport.size();
+        // Returns how far to scroll to show the range within the length.
+        fn scroll_distance(start: f64, len: f64, available: f64) -> f64 {
+            if start < 0.0 || len > available {
+                start
+            } else if start + len > available {
+                start + len - available
+            } else {
+                0.0
+            }
+        }
+        self.scroll_by(
+            scroll_distance(location.x, size.width, viewport_size.width),
+            scroll_distance(location.y, size.height, viewport_size.height),
+        );
+    }
+
+    /// Decides which scroll bars to show and sets the size and location of the content. This is
+    /// done automatically when the scroll view's size changes and when the content sends a
+    /// `ChildrenChangedEvent` to its parent. It needs to be called after changing the content in
+    /// another way that changes its best size.
+    pub fn layout(&self) {
+        let size = self.size();
+        let content = self.content();
+        let best_size = content.as_ref().map_or(Size2::new(0.0, 0.0), |c| c.best_size());
+
+        // Showing one scroll bar takes space from the content and can make the other needed.
+        let needed = |visibility: ScrollBarVisibility, content_len: f64, available: f64| {
+            match visibility {
+                ScrollBarVisibility::Auto => content_len > available,
+                ScrollBarVisibility::Always => true,
+                ScrollBarVisibility::Never => false,
+            }
+        };
+        let (mut h_shown, mut v_shown) = (false, false);
+        for _ in 0..2 {
+            let width = size.width - if v_shown { SCROLL_BAR_SIZE } else { 0.0 };
+            let height = size.height - if h_shown { SCROLL_BAR_SIZE } else { 0.0 };
+            h_shown = needed(self.h_visibility.get(), best_size.width, width);
+            v_shown = needed(self.v_visibility.get(), best_size.height, height);
+        }
+        self.h_shown.set(h_shown);
+        self.v_shown.set(v_shown);
+
+        let viewport_size = Size2::new(
+            (size.width - if v_shown { SCROLL_BAR_SIZE } else { 0.0 }).max(0.0),
+            (size.height - if h_shown { SCROLL_BAR_SIZE } else { 0.0 }).max(0.0),
+        );
+        self.viewport.set_location(&Point2::new(0.0, 0.0));
+        self.viewport.set_size(&viewport_size);
+        if let Some(content) = content {
+            let width = if self.h_visibility.get() == ScrollBarVisibility::Never {
+                viewport_size.width
+            } else {
+                best_size.width.max(viewport_size.width)
+            };
+            let height = if self.v_visibility.get() == ScrollBarVisibility::Never {
+                viewport_size.height
+            } else {
+                best_size.height.max(viewport_size.height)
+            };
+            content.set_size(&Size2::new(width, height));
+        }
+        // The content may have shrunk, so the offset may need to be limited again.
+        self.set_scroll_offset(self.offset.get());
+    }
+
+    // Returns the distance scrolled by clicking the track of a scroll bar or pressing Page Up or
+    // Page Down, which leaves a line of the previous page visible.
+    fn page_size(&self, orientation: Orientation) -> f64 {
+        let viewport_size = self.viewport.size();
+        let len = match orientation {
+            Orientation::Horizontal => viewport_size.width,
+            Orientation::Vertical => viewport_size.height,
+        };
+        (len - LINE_SIZE).max(LINE_SIZE)
+    }
+
+    fn shown(&self, orientation: Orientation) -> bool {
+        match orientation {
+            Orientation::Horizontal => self.h_shown.get(),
+            Orientation::Vertical => self.v_shown.get(),
+        }
+    }
+
+    // Returns the bounds of a scroll bar as (left, top, right, bottom).
+    fn bar_bounds(&self, orientation: Orientation) -> (f64, f64, f64, f64) {
+        let viewport_size = self.viewport.size();
+        match orientation {
+            Orientation::Horizontal => (
+                0.0, viewport_size.height,
+                viewport_size.width, viewport_size.height + SCROLL_BAR_SIZE,
+            ),
+            Orientation::Vertical => (
+                viewport_size.width, 0.0,
+                viewport_size.width + SCROLL_BAR_SIZE, viewport_size.height,
+            ),
+        }
+    }
+
+    // Returns `None` if the scroll bar is too short to have a thumb.
+    fn thumb_geometry(&self, orientation: Orientation) -> Option<ThumbGeometry> {
+        let (viewport_len, offset, max_offset) = match orientation {
+            Orientation::Horizontal => (
+                self.viewport.size().width, self.offset.get().x, self.max_scroll_offset().x,
+            ),
+            Orientation::Vertical => (
+                self.viewport.size().height, self.offset.get().y, self.max_scroll_offset().y,
+            ),
+        };
+        thumb_geometry(viewport_len, offset, max_offset, viewport_len)
+    }
+
+    // Returns the scroll bar and the part of it that the point is over.
+    fn part_at(&self, pt: Point2<f64>) -> Option<(Orientation, ScrollBarPart)> {
+        [Orientation::Horizontal, Orientation::Vertical].iter().cloned()
+            .filter(|&orientation| self.shown(orientation))
+            .find(|&orientation| {
+                let (left, top, right, bottom) = self.bar_bounds(orientation);
+                pt.x >= left && pt.x < right && pt.y >= top && pt.y < bottom
+            })
+            .map(|orientation| {
+                let (left, top, right, bottom) = self.bar_bounds(orientation);
+                let (pos, len) = match orientation {
+                    Orientation::Horizontal => (pt.x - left, right - left),
+                    Orientation::Vertical => (pt.y - top, bottom - top),
+                };
+                (orientation, part_at(pos, len, self.thumb_geometry(orientation)))
+            })
+    }
+
+    fn set_hot_part(&self, part: Option<(Orientation, ScrollBarPart)>) {
+        if self.hot_part.replace(part) != part {
+            self.repaint_later();
+        }
+    }
+
+    fn press_part(&self, orientation: Orientation, part: ScrollBarPart, pt: Point2<f64>) {
+        let amount = match part {
+            ScrollBarPart::LineUp => -LINE_SIZE,
+            ScrollBarPart::LineDown => LINE_SIZE,
+            ScrollBarPart::PageUp => -self.page_size(orientation),
+            ScrollBarPart::PageDown => self.page_size(orientation),
+            ScrollBarPart::Thumb => {
+                if let Some(thumb) = self.thumb_geometry(orientation) {
+                    let (left, top, _, _) = self.bar_bounds(orientation);
+                    let bar_start = along(orientation, Point2::new(left, top));
+                    let grab = bar_start + thumb.pos - along(orientation, pt);
+                    self.dragging.set(Some((orientation, grab)));
+                    self.repaint_later();
+                }
+                return;
+            }
+        };
+        match orientation {
+            Orientation::Horizontal => self.scroll_by(amount, 0.0),
+            Orientation::Vertical => self.scroll_by(0.0, amount),
+        }
+    }
+
+    // Scrolls so that the thumb starts at the position, in the scroll view's coordinates.
+    fn drag_thumb(&self, orientation: Orientation, thumb_pos: f64) {
+        let thumb = match self.thumb_geometry(orientation) {
+            Some(thumb) => thumb,
+            None => return,
+        };
+        let (left, top, _, _) = self.bar_bounds(orientation);
+        let pos = thumb_pos - along(orientation, Point2::new(left, top)) - SCROLL_BAR_SIZE;
+        let max = self.max_scroll_offset();
+        let offset = self.offset.get();
+        let free_len = thumb.track_len - thumb.len;
+        if free_len <= 0.0 {
+            return;
+        }
+        match orientation {
+            Orientation::Horizontal => {
+                self.set_scroll_offset(Point2::new(pos / free_len * max.x, offset.y));
+            }
+            Orientation::Vertical => {
+                self.set_scroll_offset(Point2::new(offset.x, pos / free_len * max.y));
+            }
+        }
+    }
+
+    fn paint(&self, painter: &mut dyn Painter) {
+        let enabled = self.is_effectively_enabled();
+        let track_color = Color::from_rgba(240, 240, 240, 255);
+        for &orientation in [Orientation::Horizontal, Orientation::Vertical].iter() {
+            if self.shown(orientation) {
+                self.paint_scroll_bar(painter, orientation, enabled);
+            }
+        }
+        if self.h_shown.get() && self.v_shown.get() {
+            let viewport_size = self.viewport.size();
+            let (left, top) = (viewport_size.width as f32, viewport_size.height as f32);
+            let size = SCROLL_BAR_SIZE as f32;
+            painter.fill_path(
+                &mut theme::rect_path(left, top, left + size, top + size).path_iter(),
+                &Brush::Solid(track_color),
+            );
+        }
+
+        // The clip stays set while the children are painted.
+        let viewport_size = self.viewport.size();
+        painter.clip_rect(
+            Rect::new(0.0, 0.0, viewport_size.width as f32, viewport_size.height as f32));
+    }
+
+    fn paint_scroll_bar(&self, painter: &mut dyn Painter, orientation: Orientation, enabled: bool) {
+        let hot = self.hot_part.get();
+        let dragging = self.dragging.get().map(|(o, _)| o) == Some(orientation);
+        paint_scroll_bar(
+            painter,
+            orientation,
+            self.bar_bounds(orientation),
+            self.thumb_geometry(orientation),
+            &|part| part_color(
+                part,
+                enabled,
+                hot == Some((orientation, part)),
+                part == ScrollBarPart::Thumb && dragging,
+            ),
+        );
+    }
+}
+
+impl Default for ScrollViewData {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[test]
+fn test_scroll_view() {
+    use crate::control::dispatch_mouse_wheel;
+
+    let view = ScrollView::new();
+    view.set_size(&Size2::new(100.0, 100.0));
+    let content = SubControl::new();
+    content.set_size(&Size2::new(300.0, 90.0));
+    let item = SubControl::new();
+    item.set_location(&Point2::new(20.0, 50.0));
+    item.set_size(&Size2::new(10.0, 10.0));
+    content.children().borrow_mut().push(item.clone());
+    view.set_content(Some(content.clone().into()));
+
+    // The horizontal scroll bar leaves too little height, so the vertical one is needed too.
+    assert!(view.h_shown.get() && view.v_shown.get());
+    assert_eq!(view.viewport_size(), Size2::new(83.0, 83.0));
+    assert_eq!(view.max_scroll_offset(), Point2::new(217.0, 7.0));
+
+    view.set_scroll_offset(Point2::new(500.0, -5.0));
+    assert_eq!(view.scroll_offset(), Point2::new(217.0, 0.0));
+    assert_eq!(content.location(), Point2::new(-217.0, 0.0));
+
+    view.scroll_into_view(&**item);
+    assert_eq!(view.scroll_offset(), Point2::new(20.0, 0.0));
+
+    // The wheel over the content bubbles up to the scroll view.
+    let root: Rc<dyn Control> = view.clone().into();
+    dispatch_mouse_wheel(&*root, MouseWheelEvent::new(Point2::new(10.0, 10.0), 1.0, 0.0));
+    assert_eq!(view.scroll_offset(), Point2::new(20.0 + LINE_SIZE, 0.0));
+    let mut event = MouseWheelEvent::new(Point2::new(10.0, 10.0), 0.0, -1.0);
+    event.pixel_delta = Some((0.0, -5.5));
+    event.modifiers.shift = true;
+    dispatch_mouse_wheel(&*root, event);
+    assert_eq!(view.scroll_offset(), Point2::new(20.0 + LINE_SIZE - 5.5, 0.0));
+    // The content isn't under the scroll bars for input.
+    assert!(view.descendant_at_point(90.0, 10.0).is_none());
+
+    // The view lays out again when its size or the content's best size changes.
+    view.set_size(&Size2::new(400.0, 100.0));
+    assert!(!view.h_shown.get() && !view.v_shown.get());
+    content.set_best_size(Some(Size2::new(300.0, 200.0)));
+    assert!(!view.h_shown.get() && view.v_shown.get());
+    assert_eq!(view.viewport_size(), Size2::new(383.0, 100.0));
+}
@@ -0,0 +1,479 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ *
+ */
+
+use std::cell::Cell;
+use std::ops::Deref;
+use std::rc::Rc;
+
+use zaffre::{Brush, Color, Painter, Point2, Size2, StrokeStyle};
+
+use crate::button::AutoRepeat;
+use crate::control::{
+    control_handle, set_focus, Control, FocusGainedEvent, FocusLostEvent, MouseButton,
+    MouseCaptureLostEvent, MouseDownEvent, MouseDraggedEvent, MouseLeftEvent, MouseMovedEvent,
+    MouseUpEvent, MouseWheelEvent, PaintingEvent, SubControl, SubControlData, SubControlRef,
+};
+use crate::event_vec::{EventHandler, EventRoute};
+use crate::keyboard::{Key, KeyDownEvent, Modifiers};
+use crate::layout::Orientation;
+use crate::scroll_bar::{along, ValueChangedEvent};
+use crate::theme;
+
+// TODO: generate with a proc macro
+// start proc macro generated
+#[derive(Clone)]
+pub struct Slider(Rc<SliderData>);
+
+impl Slider {
+    pub fn new() -> Self {
+        SubControl::register_handle(Slider(Rc::new(SliderData::new())))
+    }
+}
+
+impl Default for Slider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deref for Slider {
+    type Target = Rc<SliderData>;
+    fn deref(&self) -> &Rc<SliderData> {
+        &self.0
+    }
+}
+
+impl From<Slider> for Rc<dyn Control> {
+    fn from(self_: Slider) -> Self {
+        self_.0 as Rc<dyn Control>
+    }
+}
+
+impl From<Slider> for Rc<dyn EventHandler> {
+    fn from(self_: Slider) -> Self {
+        self_.0 as Rc<dyn EventHandler>
+    }
+}
+// end proc macro generated
+
+// The thumb's size across the track and along it
+const THUMB_LENGTH: f64 = 21.0;
+const THUMB_THICKNESS: f64 = 11.0;
+const TRACK_THICKNESS: f64 = 4.0;
+const MARGIN: f64 = 2.0;
+// The space for tick marks below or right of the thumb
+const TICK_SPACE: f64 = 6.0;
+const TICK_LENGTH: f64 = 4.0;
+
+// What the mouse was pressed on
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum SliderPart {
+    Thumb,
+    // The track before or after the thumb
+    Decrease,
+    Increase,
+}
+
+/// A control for choosing a value in a range by dragging a thumb along a track.
+///
+/// The arrow keys move the value by the step, Page Up and Page Down move it by the page size,
+/// and Home and End move it to the minimum and maximum. Clicking the track moves the value by
+/// the page size toward the mouse, repeating while the button is held down. A horizontal slider
+/// increases to the right and a vertical one increases upward.
+//#[dynamin::control]
+pub struct SliderData {
+    sub_control: SubControlData,
+    orientation: Cell<Orientation>,
+    minimum: Cell<f64>,
+    maximum: Cell<f64>,
+    value: Cell<f64>,
+    step: Cell<f64>,
+    page_size: Cell<f64>,
+    tick_frequency: Cell<f64>,
+    hot: Cell<bool>,
+    pressed: Cell<Option<SliderPart>>,
+    // Where on the thumb it was grabbed, while it is being dragged
+    grab_pos: Cell<f64>,
+    // The last mouse position along the slider while the track is pressed
+    mouse_pos: Cell<f64>,
+    auto_repeat: AutoRepeat,
+}
+
+impl SubControlRef for SliderData {
+    fn sub_control_ref(&self) -> &SubControlData { &self.sub_control }
+
+    fn best_size(&self) -> Size2<f64> {
+        let ticks = if self.tick_frequency.get() > 0.0 { TICK_SPACE } else { 0.0 };
+        let thickness = THUMB_LENGTH + MARGIN * 2.0 + ticks;
+        let len = THUMB_THICKNESS * 10.0;
+        match self.orientation.get() {
+            Orientation::Horizontal => Size2::new(len, thickness),
+            Orientation::Vertical => Size2::new(thickness, len),
+        }
+    }
+}
+
+impl EventHandler for SliderData {
+    fn on_event(&self, route: &mut EventRoute) {
+        if let Some(MouseDownEvent { location, button: MouseButton::Left, .. }) =
+            route.event.downcast_mut()
+        {
+            if let Some(handle) = control_handle(self) {
+                set_focus(&handle);
+            }
+            self.press(self.along(*location));
+            route.handled = true;
+        } else if let Some(MouseDraggedEvent { location }) = route.event.downcast_mut() {
+            let pos = self.along(*location);
+            self.mouse_pos.set(pos);
+            if self.pressed.get() == Some(SliderPart::Thumb) {
+                self.set_value(self.value_at(pos - self.grab_pos.get()));
+            }
+        } else if let Some(MouseUpEvent { button: MouseButton::Left, .. }) =
+            route.event.downcast_mut()
+        {
+            self.release();
+        } else if let Some(MouseCaptureLostEvent) = route.event.downcast_mut() {
+            self.release();
+        } else if let Some(MouseMovedEvent { location }) = route.event.downcast_mut() {
+            let hot = self.part_at(self.along(*location)) == SliderPart::Thumb;
+            if self.hot.replace(hot) != hot {
+                self.repaint_later();
+            }
+        } else if let Some(MouseLeftEvent) = route.event.downcast_mut() {
+            if self.hot.replace(false) {
+                self.repaint_later();
+            }
+        } else if let Some(event) = route.event.downcast_mut::<MouseWheelEvent>() {
+            let old_value = self.value.get();
+            // Scrolling up or right increases the value.
+            let delta = if event.delta_x != 0.0 { event.delta_x } else { -event.delta_y };
+            self.set_value(old_value + delta * self.step.get());
+            route.handled = self.value.get() != old_value;
+        } else if let Some(event) = route.event.downcast_mut::<KeyDownEvent>() {
+            if event.modifiers == Modifiers::none() {
+                route.handled = self.on_key_down(event.key);
+            }
+        } else if self.auto_repeat.is_repeat_event(route) {
+            self.repeat();
+        } else if let Some(FocusGainedEvent) = route.event.downcast_mut() {
+            self.repaint_later();
+        } else if let Some(FocusLostEvent) = route.event.downcast_mut() {
+            self.repaint_later();
+        } else if let Some(PaintingEvent { painter }) = route.event.downcast_mut() {
+            self.paint(&mut **painter);
+        }
+    }
+}
+
+impl SliderData {
+    pub fn new() -> Self {
+        let sub_control = SubControlData::new();
+        sub_control.set_focusable(true);
+        SliderData {
+            sub_control,
+            orientation: Cell::new(Orientation::Horizontal),
+            minimum: Cell::new(0.0),
+            maximum: Cell::new(100.0),
+            value: Cell::new(0.0),
+            step: Cell::new(1.0),
+            page_size: Cell::new(10.0),
+            tick_frequency: Cell::new(0.0),
+            hot: Cell::new(false),
+            pressed: Cell::new(None),
+            grab_pos: Cell::new(0.0),
+            mouse_pos: Cell::new(0.0),
+            auto_repeat: AutoRepeat::new(),
+        }
+    }
+
+    pub fn orientation(&self) -> Orientation {
+        self.orientation.get()
+    }
+
+    /// Sets whether the slider is horizontal or vertical. It is horizontal by default.
+    pub fn set_orientation(&self, orientation: Orientation) {
+        self.orientation.set(orientation);
+        self.repaint_later();
+    }
+
+    pub fn minimum(&self) -> f64 {
+        self.minimum.get()
+    }
+
+    pub fn maximum(&self) -> f64 {
+        self.maximum.get()
+    }
+
+    /// Sets the range of the value, which is limited to it. If `maximum` is less than `minimum`,
+    /// it is set to `minimum`.
+    pub fn set_range(&self, minimum: f64, maximum: f64) {
+        self.minimum.set(minimum);
+        self.maximum.set(maximum.max(minimum));
+        self.set_value(self.value.get());
+        self.repaint_later();
+    }
+
+    pub fn value(&self) -> f64 {
+        self.value.get()
+    }
+
+    /// Sets the value, limited to the range, and sends a `ValueChangedEvent` if it changed.
+    pub fn set_value(&self, value: f64) {
+        let value = value.max(self.minimum.get()).min(self.maximum.get());
+        if self.value.replace(value) != value {
+            self.repaint_later();
+            self.event_handlers().send(&mut ValueChangedEvent { value });
+        }
+    }
+
+    pub fn step(&self) -> f64 {
+        self.step.get()
+    }
+
+    /// Sets how far the arrow keys and each line of the mouse wheel move the value.
+    pub fn set_step(&self, step: f64) {
+        self.step.set(step);
+    }
+
+    pub fn page_size(&self) -> f64 {
+        self.page_size.get()
+    }
+
+    /// Sets how far Page Up, Page Down and clicking the track move the value.
+    pub fn set_page_size(&self, page_size: f64) {
+        self.page_size.set(page_size);
+    }
+
+    pub fn tick_frequency(&self) -> f64 {
+        self.tick_frequency.get()
+    }
+
+    /// Sets the distance between tick marks, which are drawn below a horizontal slider and right
+    /// of a vertical one, starting at the minimum. Zero, the default, means no tick marks.
+    pub fn set_tick_frequency(&self, frequency: f64) {
+        self.tick_frequency.set(frequency.max(0.0));
+        self.repaint_later();
+    }
+
+    fn on_key_down(&self, key: Key) -> bool {
+        let value = self.value.get();
+        match key {
+            Key::Left | Key::Down => self.set_value(value - self.step.get()),
+            Key::Right | Key::Up => self.set_value(value + self.step.get()),
+            Key::PageDown => self.set_value(value - self.page_size.get()),
+            Key::PageUp => self.set_value(value + self.page_size.get()),
+            Key::Home => self.set_value(self.minimum.get()),
+            Key::End => self.set_value(self.maximum.get()),
+            _ => return false,
+        }
+        true
+    }
+
+    // Returns the position along the slider of the point. For a vertical slider, it is measured
+    // from the bottom, so that it increases with the value.
+    fn along(&self, pt: Point2<f64>) -> f64 {
+        match self.orientation.get() {
+            Orientation::Horizontal => pt.x,
+            Orientation::Vertical => self.size().height - pt.y,
+        }
+    }
+
+    // Returns the length that the thumb's start moves along.
+    fn free_len(&self) -> f64 {
+        let size = self.size();
+        (along(self.orientation.get(), Point2::new(size.width, size.height)) - THUMB_THICKNESS)
+            .max(0.0)
+    }
+
+    // Returns the position of the start of the thumb for the value.
+    fn pos_of(&self, value: f64) -> f64 {
+        let range = self.maximum.get() - self.minimum.get();
+        if range <= 0.0 {
+            return 0.0;
+        }
+        ((value - self.minimum.get()) / range * self.free_len()).round()
+    }
+
+    // Returns the value for the thumb starting at the position.
+    fn value_at(&self, pos: f64) -> f64 {
+        let free_len = self.free_len();
+        if free_len <= 0.0 {
+            return self.minimum.get();
+        }
+        self.minimum.get() + pos / free_len * (self.maximum.get() - self.minimum.get())
+    }
+
+    fn part_at(&self, pos: f64) -> SliderPart {
+        let thumb_pos = self.pos_of(self.value.get());
+        if pos < thumb_pos {
+            SliderPart::Decrease
+        } else if pos < thumb_pos + THUMB_THICKNESS {
+            SliderPart::Thumb
+        } else {
+            SliderPart::Increase
+        }
+    }
+
+    fn press(&self, pos: f64) {
+        if !self.is_effectively_enabled() {
+            return;
+        }
+        let part = self.part_at(pos);
+        self.pressed.set(Some(part));
+        self.mouse_pos.set(pos);
+        self.repaint_later();
+        if part == SliderPart::Thumb {
+            self.grab_pos.set(pos - self.pos_of(self.value.get()));
+            return;
+        }
+        self.repeat();
+        self.auto_repeat.start(self);
+    }
+
+    // Pages toward the mouse while the track is held down, stopping when the thumb reaches it.
+    fn repeat(&self) {
+        let pressed = self.pressed.get();
+        if pressed.is_none() || self.part_at(self.mouse_pos.get()) != pressed.unwrap() {
+            return;
+        }
+        let value = self.value.get();
+        match pressed.unwrap() {
+            SliderPart::Decrease => self.set_value(value - self.page_size.get()),
+            SliderPart::Increase => self.set_value(value + self.page_size.get()),
+            SliderPart::Thumb => {}
+        }
+    }
+
+    fn release(&self) {
+        self.auto_repeat.stop();
+        if self.pressed.take().is_some() {
+            self.repaint_later();
+        }
+    }
+
+    fn paint(&self, painter: &mut dyn Painter) {
+        let size = self.size();
+        let enabled = self.is_effectively_enabled();
+        let orientation = self.orientation.get();
+        let len = along(orientation, Point2::new(size.width, size.height));
+        // The center of the track, across the slider
+        let center = MARGIN + THUMB_LENGTH / 2.0;
+
+        // Returns a rectangle with the positions along the slider (measured from the bottom if it
+        // is vertical) and across it.
+        let rect = |start: f64, end: f64, top: f64, bottom: f64| match orientation {
+            Orientation::Horizontal => {
+                theme::rect_path(start as f32, top as f32, end as f32, bottom as f32)
+            }
+            Orientation::Vertical => theme::rect_path(
+                top as f32, (size.height - end) as f32,
+                bottom as f32, (size.height - start) as f32,
+            ),
+        };
+
+        let half_thumb = THUMB_THICKNESS / 2.0;
+        let track_top = center - TRACK_THICKNESS / 2.0;
+        painter.fill_path(
+            &mut rect(half_thumb, len - half_thumb, track_top, track_top + TRACK_THICKNESS)
+                .path_iter(),
+            &Brush::Solid(Color::from_rgba(214, 214, 214, 255)),
+        );
+
+        let frequency = self.tick_frequency.get();
+        let range = self.maximum.get() - self.minimum.get();
+        if frequency > 0.0 && range > 0.0 {
+            let tick_top = MARGIN + THUMB_LENGTH + TICK_SPACE - TICK_LENGTH;
+            let count = (range / frequency).floor() as usize;
+            let values = (0..=count).map(|i| self.minimum.get() + i as f64 * frequency)
+                .chain(std::iter::once(self.maximum.get()));
+            for value in values {
+                let pos = self.pos_of(value) + half_thumb.floor();
+                painter.fill_path(
+                    &mut rect(pos, pos + 1.0, tick_top, tick_top + TICK_LENGTH).path_iter(),
+                    &Brush::Solid(Color::from_rgba(196, 196, 196, 255)),
+                );
+            }
+        }
+
+        let thumb_color = if !enabled || self.pressed.get() == Some(SliderPart::Thumb) {
+            Color::from_rgba(204, 204, 204, 255)
+        } else if self.hot.get() {
+            Color::from_rgba(23, 23, 23, 255)
+        } else {
+            Color::from_rgba(0, 120, 215, 255)
+        };
+        let thumb_pos = self.pos_of(self.value.get());
+        painter.fill_path(
+            &mut rect(thumb_pos, thumb_pos + THUMB_THICKNESS, MARGIN, MARGIN + THUMB_LENGTH)
+                .path_iter(),
+            &Brush::Solid(thumb_color),
+        );
+
+        if self.focused() {
+            painter.stroke_path(
+                &mut theme::rect_path(0.5, 0.5, size.width as f32 - 0.5, size.height as f32 - 0.5)
+                    .path_iter(),
+                &Brush::Solid(theme::item_focus_color()),
+                &StrokeStyle::with_width(1.0),
+            );
+        }
+    }
+
+    // Convenience method to add an event handler that is called for `ValueChangedEvent`s.
+    pub fn on_value_changed_event<F>(&self, mut handler: F)
+        where F: for<'a> FnMut(&'a mut EventRoute) + 'static
+    {
+        self.event_handlers().add(move |mut route| {
+            if let Some(_) = route.event.downcast_mut::<ValueChangedEvent>() {
+                handler(&mut route);
+            }
+        });
+    }
+}
+
+impl Default for SliderData {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[test]
+fn test_slider() {
+    use std::cell::RefCell;
+
+    let slider = Slider::new();
+    slider.set_size(&Size2::new(111.0, 25.0));
+    slider.set_range(0.0, 50.0);
+    slider.set_page_size(20.0);
+    let values = Rc::new(RefCell::new(vec![]));
+    let values2 = values.clone();
+    slider.on_value_changed_event(move |route| {
+        let event = route.event.downcast_mut::<ValueChangedEvent>().unwrap();
+        values2.borrow_mut().push(event.value);
+    });
+
+    let key = |key| {
+        let mut event = KeyDownEvent { key, modifiers: Modifiers::none(), repeat: false };
+        slider.event_handlers().send(&mut event)
+    };
+    assert!(key(Key::Right));
+    assert!(key(Key::PageUp));
+    assert!(key(Key::End));
+    // Nothing changes at the end of the range, so no event is sent.
+    assert!(key(Key::Up));
+    assert!(key(Key::PageDown));
+    assert!(!key(Key::Tab));
+    assert_eq!(*values.borrow(), vec![1.0, 21.0, 50.0, 30.0]);
+
+    // The thumb's start moves along 100 pixels, two for each unit.
+    assert_eq!(slider.pos_of(30.0), 60.0);
+    assert_eq!(slider.value_at(10.0), 5.0);
+    slider.set_orientation(Orientation::Vertical);
+    slider.set_size(&Size2::new(25.0, 111.0));
+    assert_eq!(slider.along(Point2::new(0.0, 100.0)), 11.0);
+}
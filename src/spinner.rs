@@ -0,0 +1,185 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ *
+ */
+
+use std::cell::Cell;
+use std::f64::consts::PI;
+use std::ops::Deref;
+use std::rc::Rc;
+use std::time::Duration;
+
+use zaffre::{Brush, Color, Painter, Point2, Size2};
+
+use crate::control::{
+    AttachedToWindowEvent, Control, DetachedFromWindowEvent, PaintingEvent, SubControl,
+    SubControlData, SubControlRef, VisibilityChangedEvent,
+};
+use crate::event_vec::{EventHandler, EventRoute};
+use crate::theme;
+use crate::timer::Animation;
+
+// TODO: generate with a proc macro
+// start proc macro generated
+#[derive(Clone)]
+pub struct Spinner(Rc<SpinnerData>);
+
+impl Spinner {
+    pub fn new() -> Self {
+        SubControl::register_handle(Spinner(Rc::new(SpinnerData::new())))
+    }
+}
+
+impl Default for Spinner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deref for Spinner {
+    type Target = Rc<SpinnerData>;
+    fn deref(&self) -> &Rc<SpinnerData> {
+        &self.0
+    }
+}
+
+impl From<Spinner> for Rc<dyn Control> {
+    fn from(self_: Spinner) -> Self {
+        self_.0 as Rc<dyn Control>
+    }
+}
+
+impl From<Spinner> for Rc<dyn EventHandler> {
+    fn from(self_: Spinner) -> Self {
+        self_.0 as Rc<dyn EventHandler>
+    }
+}
+// end proc macro generated
+
+const DOT_COUNT: usize = 8;
+// How long the brightest dot takes to go around the circle
+const REVOLUTION: Duration = Duration::from_millis(1000);
+
+/// A ring of dots with a bright one circling around, to show that something is in progress when
+/// there's no progress to measure.
+///
+/// A spinner is blank until `set_running(true)` is called. It only animates while it is visible
+/// in a window.
+//#[dynamin::control]
+pub struct SpinnerData {
+    sub_control: SubControlData,
+    running: Cell<bool>,
+    animation: Animation,
+}
+
+impl SubControlRef for SpinnerData {
+    fn sub_control_ref(&self) -> &SubControlData { &self.sub_control }
+
+    fn best_size(&self) -> Size2<f64> {
+        Size2::new(24.0, 24.0)
+    }
+}
+
+impl EventHandler for SpinnerData {
+    fn on_event(&self, route: &mut EventRoute) {
+        if let Some(VisibilityChangedEvent { .. }) = route.event.downcast_mut() {
+            self.update_animation();
+        } else if let Some(AttachedToWindowEvent { .. }) = route.event.downcast_mut() {
+            self.update_animation();
+        } else if let Some(DetachedFromWindowEvent { .. }) = route.event.downcast_mut() {
+            self.update_animation();
+        } else if let Some(PaintingEvent { painter }) = route.event.downcast_mut() {
+            self.paint(&mut **painter);
+        }
+    }
+}
+
+impl SpinnerData {
+    pub fn new() -> Self {
+        SpinnerData {
+            sub_control: SubControlData::new(),
+            running: Cell::new(false),
+            animation: Animation::new(),
+        }
+    }
+
+    pub fn running(&self) -> bool {
+        self.running.get()
+    }
+
+    /// Sets whether the spinner is shown spinning. A spinner that isn't running paints nothing.
+    pub fn set_running(&self, running: bool) {
+        if self.running.replace(running) != running {
+            self.update_animation();
+            self.repaint_later();
+        }
+    }
+
+    fn update_animation(&self) {
+        if self.running.get() && self.is_effectively_visible() {
+            self.animation.start(self);
+        } else {
+            self.animation.stop();
+        }
+    }
+
+    fn paint(&self, painter: &mut dyn Painter) {
+        if !self.running.get() {
+            return;
+        }
+        let size = self.size();
+        let diameter = size.width.min(size.height);
+        let dot_radius = diameter / 10.0;
+        let ring_radius = diameter / 2.0 - dot_radius;
+        let center = Point2::new(size.width / 2.0, size.height / 2.0);
+        let head = head_position(self.animation.elapsed());
+        for i in 0..DOT_COUNT {
+            // Starting at the top, the dots go clockwise.
+            let angle = i as f64 * 2.0 * PI / DOT_COUNT as f64 - PI / 2.0;
+            let dot_center = Point2::new(
+                (center.x + ring_radius * angle.cos()) as f32,
+                (center.y + ring_radius * angle.sin()) as f32,
+            );
+            painter.fill_path(
+                &mut theme::circle_path(dot_center, dot_radius as f32).path_iter(),
+                &Brush::Solid(Color::from_rgba(0, 120, 215, dot_alpha(i, head))),
+            );
+        }
+    }
+}
+
+impl Default for SpinnerData {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Returns where the brightest dot is, as a fractional index into the dots.
+fn head_position(elapsed: Duration) -> f64 {
+    let revolution = REVOLUTION.as_secs_f64();
+    elapsed.as_secs_f64() % revolution / revolution * DOT_COUNT as f64
+}
+
+// The dots behind the head fade out, so it looks like it leaves a trail.
+fn dot_alpha(index: usize, head: f64) -> u8 {
+    let behind = (head - index as f64).rem_euclid(DOT_COUNT as f64);
+    let brightness = 1.0 - behind / DOT_COUNT as f64;
+    (40.0 + 215.0 * brightness).round() as u8
+}
+
+#[test]
+fn test_spinner() {
+    let spinner = Spinner::new();
+    spinner.set_running(true);
+    // The spinner isn't in a window, so there's nothing to animate.
+    assert!(!spinner.animation.is_running());
+
+    assert_eq!(head_position(Duration::from_millis(250)), 2.0);
+    assert_eq!(head_position(Duration::from_millis(1500)), 4.0);
+    assert_eq!(dot_alpha(2, 2.0), 255);
+    assert_eq!(dot_alpha(1, 2.0), 228);
+    // The dot just ahead of the head is the faintest.
+    assert_eq!(dot_alpha(3, 2.0), 67);
+}
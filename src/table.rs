@@ -0,0 +1,861 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ *
+ */
+
+use std::cell::{Cell, RefCell};
+use std::ops::{Deref, Range};
+use std::rc::Rc;
+
+use zaffre::{Brush, Color, Painter, PathBuf, Point2, Rect, Size2, StrokeStyle};
+
+use crate::control::{
+    control_handle, set_focus, Control, FocusGainedEvent, FocusLostEvent, MouseButton,
+    MouseCaptureLostEvent, MouseDownEvent, MouseDraggedEvent, MouseLeftEvent, MouseMovedEvent,
+    MouseUpEvent, PaintingEvent, SizeChangedEvent, SubControl, SubControlData, SubControlRef,
+};
+use crate::event_vec::{EventHandler, EventRoute};
+use crate::keyboard::{Key, KeyDownEvent, Modifiers};
+use crate::list_view::{
+    forward_panel_mouse_events, forward_scroll_events, PanelMouseEvent, SelectionChangedEvent,
+};
+use crate::scroll_view::{ScrollOffsetChangedEvent, ScrollView};
+use crate::theme;
+
+// TODO: generate with a proc macro
+// start proc macro generated
+#[derive(Clone)]
+pub struct Table(Rc<TableData>);
+
+impl Table {
+    pub fn new() -> Self {
+        SubControl::register_handle(Table(Rc::new(TableData::new())))
+    }
+}
+
+impl Default for Table {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deref for Table {
+    type Target = Rc<TableData>;
+    fn deref(&self) -> &Rc<TableData> {
+        &self.0
+    }
+}
+
+impl From<Table> for Rc<dyn Control> {
+    fn from(self_: Table) -> Self {
+        self_.0 as Rc<dyn Control>
+    }
+}
+
+impl From<Table> for Rc<dyn EventHandler> {
+    fn from(self_: Table) -> Self {
+        self_.0 as Rc<dyn EventHandler>
+    }
+}
+// end proc macro generated
+
+/// Provides the cells shown in a `Table`. Columns are identified by the index they were added to
+/// the table with, which doesn't change when the user reorders them.
+pub trait TableModel {
+    fn row_count(&self) -> usize;
+
+    /// Returns the text of a cell. It is painted unless the column has a cell painter.
+    fn cell_text(&self, row: usize, column: usize) -> String;
+}
+
+/// Describes the cell being painted by a `CellPainter`.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct CellInfo {
+    pub row: usize,
+    pub column: usize,
+    pub size: Size2<f64>,
+    pub selected: bool,
+    pub enabled: bool,
+}
+
+/// Paints the cells of a table column instead of the default text.
+///
+/// The painter of the event is translated so that the cell's top left corner is at the origin,
+/// and painting is clipped to the cell. The selection background is already painted. Any
+/// closure taking the event and a `CellInfo` can be used as a cell painter.
+pub trait CellPainter {
+    fn paint_cell(&self, event: &mut PaintingEvent, cell: &CellInfo);
+}
+
+impl<F> CellPainter for F where F: Fn(&mut PaintingEvent, &CellInfo) {
+    fn paint_cell(&self, event: &mut PaintingEvent, cell: &CellInfo) {
+        self(event, cell)
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SortDirection {
+    Ascending,
+    Descending,
+}
+
+/// Sent to a table when a column header is clicked. The table doesn't sort the rows itself; the
+/// handler should sort the model, call `set_sort_indicator()`, and call `reload()`.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct SortRequestedEvent {
+    pub column: usize,
+    /// The opposite of the column's current sort indicator, or `Ascending` if it has none.
+    pub direction: SortDirection,
+}
+
+struct Column {
+    title: String,
+    width: f64,
+    sort: Option<SortDirection>,
+    painter: Option<Rc<dyn CellPainter>>,
+}
+
+// What a mouse button pressed on the header is doing
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum HeaderDrag {
+    // The right edge of a column is being dragged. `grab_x` is where the mouse was pressed, in
+    // the table's coordinates.
+    Resize { column: usize, grab_x: f64, start_width: f64 },
+    // A header was pressed. It is clicked if released without moving, or moved to a new position
+    // if dragged.
+    Press { column: usize, grab_x: f64, x: f64, moving: bool },
+}
+
+const CELL_PADDING_X: f64 = 6.0;
+const ROW_PADDING_Y: f64 = 2.0;
+const HEADER_PADDING_Y: f64 = 5.0;
+const MIN_COLUMN_WIDTH: f64 = 20.0;
+// The distance on each side of a header's right edge where pressing resizes the column
+const RESIZE_MARGIN: f64 = 4.0;
+// The distance a header has to be dragged before it starts moving
+const MOVE_THRESHOLD: f64 = 5.0;
+const DEFAULT_WIDTH: f64 = 300.0;
+const DEFAULT_HEIGHT: f64 = 150.0;
+
+/// A grid of cells with a header row, provided by a `TableModel`.
+///
+/// Columns can be resized by dragging the edges of their headers and reordered by dragging the
+/// headers. Clicking a header sends a `SortRequestedEvent`. Only the rows in view are painted, so
+/// a table can have any number of rows.
+//#[dynamin::control]
+pub struct TableData {
+    sub_control: SubControlData,
+    scroll_view: ScrollView,
+    // The content of the scroll view, which has the size of all the cells
+    panel: SubControl,
+    model: RefCell<Option<Rc<dyn TableModel>>>,
+    columns: RefCell<Vec<Column>>,
+    // The columns in the order they're shown
+    order: RefCell<Vec<usize>>,
+    // The row and column of the selected cell
+    selected: Cell<Option<(usize, usize)>>,
+    row_height: Cell<f64>,
+    header_height: Cell<f64>,
+    hot_header: Cell<Option<usize>>,
+    header_drag: Cell<Option<HeaderDrag>>,
+}
+
+impl SubControlRef for TableData {
+    fn sub_control_ref(&self) -> &SubControlData { &self.sub_control }
+
+    fn best_size(&self) -> Size2<f64> {
+        Size2::new(DEFAULT_WIDTH, DEFAULT_HEIGHT)
+    }
+}
+
+impl EventHandler for TableData {
+    fn on_event(&self, route: &mut EventRoute) {
+        if let Some(PanelMouseEvent { location, button, .. }) = route.event.downcast_mut() {
+            if let (Some(_), Some(handle)) = (button, control_handle(self)) {
+                set_focus(&handle);
+            }
+            // Dragging moves the selection with the mouse.
+            if let Some(cell) = self.cell_at(*location) {
+                self.set_selected_cell(Some(cell));
+            }
+            route.handled = true;
+        } else if let Some(MouseDownEvent { location, button: MouseButton::Left, .. }) =
+            route.event.downcast_mut()
+        {
+            self.on_header_pressed(location.x);
+        } else if let Some(MouseDraggedEvent { location }) = route.event.downcast_mut() {
+            self.on_header_dragged(location.x);
+        } else if let Some(MouseUpEvent { button: MouseButton::Left, .. }) =
+            route.event.downcast_mut()
+        {
+            self.on_header_released();
+        } else if let Some(MouseCaptureLostEvent) = route.event.downcast_mut() {
+            self.header_drag.set(None);
+            self.repaint_later();
+        } else if let Some(MouseMovedEvent { location }) = route.event.downcast_mut() {
+            let hot = if location.y < self.header_height.get() {
+                self.header_at(location.x)
+            } else {
+                None
+            };
+            if self.hot_header.replace(hot) != hot {
+                self.repaint_later();
+            }
+        } else if let Some(MouseLeftEvent) = route.event.downcast_mut() {
+            self.hot_header.set(None);
+            self.repaint_later();
+        } else if let Some(event) = route.event.downcast_mut::<KeyDownEvent>() {
+            if self.on_key_down(event.key, event.modifiers) {
+                route.handled = true;
+            }
+        } else if let Some(FocusGainedEvent) = route.event.downcast_mut() {
+            self.repaint_later();
+        } else if let Some(FocusLostEvent) = route.event.downcast_mut() {
+            self.repaint_later();
+        } else if let Some(SizeChangedEvent) = route.event.downcast_mut() {
+            self.layout();
+        } else if let Some(ScrollOffsetChangedEvent) = route.event.downcast_mut() {
+            // The header scrolls with the cells but is outside the scroll view.
+            self.repaint_later();
+        } else if let Some(event) = route.event.downcast_mut::<PaintingEvent>() {
+            self.paint(event);
+        }
+    }
+}
+
+impl TableData {
+    pub fn new() -> Self {
+        let sub_control = SubControlData::new();
+        sub_control.set_focusable(true);
+        let line = theme::layout_line(theme::formatted_string("Xg", theme::text_color(true)));
+        let text_height = theme::text_size(&line).height;
+        TableData {
+            sub_control,
+            scroll_view: ScrollView::new(),
+            panel: SubControl::new(),
+            model: RefCell::new(None),
+            columns: RefCell::new(vec![]),
+            order: RefCell::new(vec![]),
+            selected: Cell::new(None),
+            row_height: Cell::new((text_height + ROW_PADDING_Y * 2.0).ceil()),
+            header_height: Cell::new((text_height + HEADER_PADDING_Y * 2.0).ceil()),
+            hot_header: Cell::new(None),
+            header_drag: Cell::new(None),
+        }
+    }
+
+    pub fn model(&self) -> Option<Rc<dyn TableModel>> {
+        self.model.borrow().clone()
+    }
+
+    /// Sets where the cells come from and clears the selection.
+    pub fn set_model(&self, model: Option<Rc<dyn TableModel>>) {
+        self.add_children();
+        *self.model.borrow_mut() = model;
+        self.scroll_view.set_scroll_offset(Point2::new(0.0, 0.0));
+        self.set_selected_cell(None);
+        self.reload();
+    }
+
+    /// Paints the cells again after the model changes. The selection is cleared if its row no
+    /// longer exists.
+    pub fn reload(&self) {
+        if let Some((row, _)) = self.selected.get() {
+            if row >= self.row_count() {
+                self.set_selected_cell(None);
+            }
+        }
+        self.layout();
+        self.repaint_later();
+    }
+
+    /// Adds a column at the right end and returns its index.
+    pub fn add_column(&self, title: &str, width: f64) -> usize {
+        let index = {
+            let mut columns = self.columns.borrow_mut();
+            columns.push(Column {
+                title: title.to_owned(),
+                width: width.max(MIN_COLUMN_WIDTH),
+                sort: None,
+                painter: None,
+            });
+            columns.len() - 1
+        };
+        self.order.borrow_mut().push(index);
+        self.layout();
+        self.repaint_later();
+        index
+    }
+
+    pub fn column_count(&self) -> usize {
+        self.columns.borrow().len()
+    }
+
+    pub fn column_title(&self, column: usize) -> String {
+        self.columns.borrow()[column].title.clone()
+    }
+
+    pub fn set_column_title(&self, column: usize, title: &str) {
+        self.columns.borrow_mut()[column].title = title.to_owned();
+        self.repaint_later();
+    }
+
+    pub fn column_width(&self, column: usize) -> f64 {
+        self.columns.borrow()[column].width
+    }
+
+    pub fn set_column_width(&self, column: usize, width: f64) {
+        self.columns.borrow_mut()[column].width = width.max(MIN_COLUMN_WIDTH);
+        self.layout();
+        self.repaint_later();
+    }
+
+    /// Returns the indexes of the columns in the order they're shown from left to right.
+    pub fn column_order(&self) -> Vec<usize> {
+        self.order.borrow().clone()
+    }
+
+    /// Sets the order the columns are shown in from left to right.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `order` doesn't contain the index of each column exactly once.
+    pub fn set_column_order(&self, order: &[usize]) {
+        let mut sorted = order.to_vec();
+        sorted.sort();
+        assert!(sorted.iter().cloned().eq(0..self.column_count()),
+            "column order must contain each column once");
+        *self.order.borrow_mut() = order.to_vec();
+        self.repaint_later();
+    }
+
+    pub fn sort_indicator(&self, column: usize) -> Option<SortDirection> {
+        self.columns.borrow()[column].sort
+    }
+
+    /// Shows an arrow in the column's header for the direction the rows are sorted in. Only one
+    /// column has an indicator, so any other column's indicator is removed.
+    pub fn set_sort_indicator(&self, column: usize, direction: Option<SortDirection>) {
+        for (i, c) in self.columns.borrow_mut().iter_mut().enumerate() {
+            c.sort = if i == column { direction } else { None };
+        }
+        self.repaint_later();
+    }
+
+    /// Sets what paints the cells of the column. If it is `None`, the model's text is painted.
+    pub fn set_cell_painter(&self, column: usize, painter: Option<Rc<dyn CellPainter>>) {
+        self.columns.borrow_mut()[column].painter = painter;
+        self.repaint_later();
+    }
+
+    /// Returns the row and column of the selected cell.
+    pub fn selected_cell(&self) -> Option<(usize, usize)> {
+        self.selected.get()
+    }
+
+    /// Selects the cell at the row and column and scrolls it into view.
+    pub fn set_selected_cell(&self, cell: Option<(usize, usize)>) {
+        let cell = cell.filter(|&(row, column)| {
+            row < self.row_count() && column < self.column_count()
+        });
+        if let Some(cell) = cell {
+            self.scroll_to_cell(cell.0, cell.1);
+        }
+        if self.selected.replace(cell) != cell {
+            self.event_handlers().send(&mut SelectionChangedEvent);
+            self.repaint_later();
+        }
+    }
+
+    /// Scrolls as little as possible to make the cell entirely visible.
+    pub fn scroll_to_cell(&self, row: usize, column: usize) {
+        let height = self.row_height.get();
+        let (left, width) = match self.column_bounds(column) {
+            Some(bounds) => bounds,
+            None => return,
+        };
+        let top = row as f64 * height;
+        let offset = self.scroll_view.scroll_offset();
+        let viewport_size = self.scroll_view.viewport_size();
+        let scroll_distance = |start: f64, len: f64, offset: f64, available: f64| {
+            if start < offset || len > available {
+                start
+            } else if start + len > offset + available {
+                start + len - available
+            } else {
+                offset
+            }
+        };
+        self.scroll_view.set_scroll_offset(Point2::new(
+            scroll_distance(left, width, offset.x, viewport_size.width),
+            scroll_distance(top, height, offset.y, viewport_size.height),
+        ));
+    }
+
+    // Convenience method to add an event handler that is called for `SortRequestedEvent`s.
+    pub fn on_sort_requested_event<F>(&self, mut handler: F)
+        where F: for<'a> FnMut(&'a mut EventRoute) + 'static
+    {
+        self.event_handlers().add(move |mut route| {
+            if let Some(_) = route.event.downcast_mut::<SortRequestedEvent>() {
+                handler(&mut route);
+            }
+        });
+    }
+
+    // Convenience method to add an event handler that is called for `SelectionChangedEvent`s.
+    pub fn on_selection_changed_event<F>(&self, mut handler: F)
+        where F: for<'a> FnMut(&'a mut EventRoute) + 'static
+    {
+        self.event_handlers().add(move |mut route| {
+            if let Some(_) = route.event.downcast_mut::<SelectionChangedEvent>() {
+                handler(&mut route);
+            }
+        });
+    }
+
+    /// Sets the size of the scroll view below the header and of the panel in it to fit all the
+    /// cells. This is done automatically when the table's size or columns change and by
+    /// `reload()`.
+    pub fn layout(&self) {
+        let size = self.size();
+        let header_height = self.header_height.get();
+        self.scroll_view.set_location(&Point2::new(0.0, header_height));
+        self.scroll_view.set_size(&Size2::new(size.width, (size.height - header_height).max(0.0)));
+        let width = self.columns.borrow().iter().map(|c| c.width).sum();
+        let height = self.row_count() as f64 * self.row_height.get();
+        self.panel.set_size(&Size2::new(width, height));
+        self.scroll_view.layout();
+    }
+
+    fn row_count(&self) -> usize {
+        self.model().map_or(0, |m| m.row_count())
+    }
+
+    // Children can't be added in `new()`, before the handle is registered.
+    fn add_children(&self) {
+        if self.scroll_view.parent().is_some() {
+            return;
+        }
+        self.children().borrow_mut().push(self.scroll_view.clone());
+        self.scroll_view.set_content(Some(self.panel.clone().into()));
+        forward_panel_mouse_events(&self.panel, self);
+        forward_scroll_events(&self.scroll_view, self);
+    }
+
+    // Returns the left edge, in the panel's coordinates, and the width of the column.
+    fn column_bounds(&self, column: usize) -> Option<(f64, f64)> {
+        let columns = self.columns.borrow();
+        let mut left = 0.0;
+        for &c in self.order.borrow().iter() {
+            if c == column {
+                return Some((left, columns[c].width));
+            }
+            left += columns[c].width;
+        }
+        None
+    }
+
+    // Returns the column at the x coordinate in the panel's coordinates.
+    fn column_at(&self, x: f64) -> Option<usize> {
+        let columns = self.columns.borrow();
+        let mut left = 0.0;
+        for &c in self.order.borrow().iter() {
+            left += columns[c].width;
+            if x >= 0.0 && x < left {
+                return Some(c);
+            }
+        }
+        None
+    }
+
+    fn cell_at(&self, pt: Point2<f64>) -> Option<(usize, usize)> {
+        if pt.y < 0.0 {
+            return None;
+        }
+        let row = (pt.y / self.row_height.get()) as usize;
+        if row >= self.row_count() {
+            return None;
+        }
+        self.column_at(pt.x).map(|column| (row, column))
+    }
+
+    // Returns the column whose header is at the x coordinate in the table's coordinates.
+    fn header_at(&self, x: f64) -> Option<usize> {
+        self.column_at(x + self.scroll_view.scroll_offset().x)
+    }
+
+    // Returns the column whose header's right edge is near the x coordinate in the table's
+    // coordinates. When edges are close together, the rightmost wins so that columns shrunk to
+    // the minimum width can still be widened.
+    fn resize_edge_at(&self, x: f64) -> Option<usize> {
+        let x = x + self.scroll_view.scroll_offset().x;
+        let columns = self.columns.borrow();
+        let mut right = 0.0;
+        let mut found = None;
+        for &c in self.order.borrow().iter() {
+            right += columns[c].width;
+            if (x - right).abs() <= RESIZE_MARGIN {
+                found = Some(c);
+            }
+        }
+        found
+    }
+
+    // Returns the position in the column order that a header dragged to the x coordinate, in the
+    // table's coordinates, would be moved to.
+    fn drop_position(&self, x: f64) -> usize {
+        let x = x + self.scroll_view.scroll_offset().x;
+        let columns = self.columns.borrow();
+        let mut left = 0.0;
+        for (i, &c) in self.order.borrow().iter().enumerate() {
+            let width = columns[c].width;
+            if x < left + width / 2.0 {
+                return i;
+            }
+            left += width;
+        }
+        self.order.borrow().len()
+    }
+
+    fn on_header_pressed(&self, x: f64) {
+        if let Some(column) = self.resize_edge_at(x) {
+            let start_width = self.column_width(column);
+            self.header_drag.set(Some(HeaderDrag::Resize { column, grab_x: x, start_width }));
+        } else if let Some(column) = self.header_at(x) {
+            self.header_drag.set(Some(HeaderDrag::Press { column, grab_x: x, x, moving: false }));
+        }
+        self.repaint_later();
+    }
+
+    fn on_header_dragged(&self, x: f64) {
+        match self.header_drag.get() {
+            Some(HeaderDrag::Resize { column, grab_x, start_width }) => {
+                self.set_column_width(column, start_width + x - grab_x);
+            }
+            Some(HeaderDrag::Press { column, grab_x, moving, .. }) => {
+                let moving = moving || (x - grab_x).abs() >= MOVE_THRESHOLD;
+                self.header_drag.set(Some(HeaderDrag::Press { column, grab_x, x, moving }));
+                self.repaint_later();
+            }
+            None => {}
+        }
+    }
+
+    fn on_header_released(&self) {
+        match self.header_drag.take() {
+            Some(HeaderDrag::Press { column, x, moving: true, .. }) => {
+                let position = self.drop_position(x);
+                let mut order = self.column_order();
+                let old_position = order.iter().position(|&c| c == column).unwrap();
+                order.remove(old_position);
+                let position = if position > old_position { position - 1 } else { position };
+                order.insert(position, column);
+                self.set_column_order(&order);
+            }
+            Some(HeaderDrag::Press { column, moving: false, .. }) => {
+                let direction = match self.sort_indicator(column) {
+                    Some(SortDirection::Ascending) => SortDirection::Descending,
+                    _ => SortDirection::Ascending,
+                };
+                self.event_handlers().send(&mut SortRequestedEvent { column, direction });
+            }
+            _ => {}
+        }
+        self.repaint_later();
+    }
+
+    // Returns whether the key was handled.
+    fn on_key_down(&self, key: Key, modifiers: Modifiers) -> bool {
+        let Modifiers { shift, control, alt, logo } = modifiers;
+        let (row_count, order) = (self.row_count(), self.column_order());
+        if shift || alt || logo || row_count == 0 || order.is_empty() {
+            return false;
+        }
+        let (row, column) = match self.selected.get() {
+            Some(cell) => cell,
+            // Without a selection, moving selects the first cell.
+            None => match key {
+                Key::Up | Key::Down | Key::Left | Key::Right | Key::PageUp | Key::PageDown |
+                Key::Home | Key::End => {
+                    self.set_selected_cell(Some((0, order[0])));
+                    return true;
+                }
+                _ => return false,
+            },
+        };
+        let position = order.iter().position(|&c| c == column).unwrap_or(0);
+        let page_len = ((self.scroll_view.viewport_size().height / self.row_height.get()) as usize)
+            .max(1);
+        let (row, position) = match key {
+            Key::Up => (row.saturating_sub(1), position),
+            Key::Down => ((row + 1).min(row_count - 1), position),
+            Key::Left => (row, position.saturating_sub(1)),
+            Key::Right => (row, (position + 1).min(order.len() - 1)),
+            Key::PageUp => (row.saturating_sub(page_len), position),
+            Key::PageDown => ((row + page_len).min(row_count - 1), position),
+            Key::Home if control => (0, position),
+            Key::End if control => (row_count - 1, position),
+            Key::Home => (row, 0),
+            Key::End => (row, order.len() - 1),
+            _ => return false,
+        };
+        self.set_selected_cell(Some((row, order[position])));
+        true
+    }
+
+    // Returns the range of rows that are at least partly in view.
+    fn visible_rows(&self) -> Range<usize> {
+        let height = self.row_height.get();
+        let top = self.scroll_view.scroll_offset().y;
+        let bottom = top + self.scroll_view.viewport_size().height;
+        let end = ((bottom / height).ceil() as usize).min(self.row_count());
+        ((top / height).floor() as usize).min(end)..end
+    }
+
+    fn paint(&self, event: &mut PaintingEvent) {
+        let size = self.size();
+        let painter = &mut *event.painter;
+        painter.clip_rect(Rect::new(0.0, 0.0, size.width as f32, size.height as f32));
+        painter.fill_path(
+            &mut theme::rect_path(0.0, 0.0, size.width as f32, size.height as f32).path_iter(),
+            &Brush::Solid(Color::from_rgba(255, 255, 255, 255)),
+        );
+
+        let header_height = self.header_height.get();
+        let viewport_size = self.scroll_view.viewport_size();
+        event.painter.save();
+        event.painter.clip_rect(Rect::new(
+            0.0, header_height as f32, viewport_size.width as f32, viewport_size.height as f32));
+        self.paint_cells(event);
+        event.painter.restore();
+        self.paint_header(&mut *event.painter);
+    }
+
+    fn paint_cells(&self, event: &mut PaintingEvent) {
+        let model = match self.model() {
+            Some(model) => model,
+            None => return,
+        };
+        let enabled = self.is_effectively_enabled();
+        let focused = self.focused();
+        let height = self.row_height.get();
+        let offset = self.scroll_view.scroll_offset();
+        let viewport_width = self.scroll_view.viewport_size().width;
+        let top = self.header_height.get() - offset.y;
+        let rows = self.visible_rows();
+
+        let mut left = -offset.x;
+        for &column in self.order.borrow().iter() {
+            let (width, painter) = {
+                let c = &self.columns.borrow()[column];
+                (c.width, c.painter.clone())
+            };
+            // Columns scrolled out of view horizontally are skipped.
+            if left + width <= 0.0 || left >= viewport_width {
+                left += width;
+                continue;
+            }
+            for row in rows.clone() {
+                let cell_top = top + row as f64 * height;
+                let selected = self.selected.get() == Some((row, column));
+                let cell = CellInfo {
+                    row,
+                    column,
+                    size: Size2::new(width, height),
+                    selected,
+                    enabled,
+                };
+                event.painter.save();
+                event.painter.translate(left, cell_top);
+                event.painter.clip_rect(Rect::new(0.0, 0.0, width as f32, height as f32));
+                if selected {
+                    event.painter.fill_path(
+                        &mut theme::rect_path(0.0, 0.0, width as f32, height as f32).path_iter(),
+                        &Brush::Solid(theme::item_selection_color(focused)),
+                    );
+                }
+                match painter {
+                    Some(ref painter) => painter.paint_cell(event, &cell),
+                    None => {
+                        let text = model.cell_text(row, column);
+                        paint_cell_text(&mut *event.painter, &text, &cell);
+                    }
+                }
+                if selected && focused {
+                    event.painter.stroke_path(
+                        &mut theme::rect_path(0.5, 0.5, width as f32 - 0.5, height as f32 - 0.5)
+                            .path_iter(),
+                        &Brush::Solid(theme::item_focus_color()),
+                        &StrokeStyle::with_width(1.0),
+                    );
+                }
+                event.painter.restore();
+            }
+            left += width;
+        }
+    }
+
+    fn paint_header(&self, painter: &mut dyn Painter) {
+        let enabled = self.is_effectively_enabled();
+        let height = self.header_height.get();
+        let width = self.size().width;
+        let border_color = Color::from_rgba(229, 229, 229, 255);
+        let drag = self.header_drag.get();
+        let pressed = match drag {
+            Some(HeaderDrag::Press { column, .. }) => Some(column),
+            _ => None,
+        };
+
+        let columns = self.columns.borrow();
+        let mut left = -self.scroll_view.scroll_offset().x;
+        for &c in self.order.borrow().iter() {
+            let column = &columns[c];
+            let right = left + column.width;
+            let background = if pressed == Some(c) {
+                Color::from_rgba(188, 220, 244, 255)
+            } else if self.hot_header.get() == Some(c) && enabled {
+                Color::from_rgba(217, 235, 249, 255)
+            } else {
+                Color::from_rgba(255, 255, 255, 255)
+            };
+            painter.fill_path(
+                &mut theme::rect_path(left as f32, 0.0, right as f32, height as f32).path_iter(),
+                &Brush::Solid(background),
+            );
+            painter.fill_path(
+                &mut theme::rect_path(right as f32 - 1.0, 0.0, right as f32, height as f32)
+                    .path_iter(),
+                &Brush::Solid(border_color),
+            );
+
+            painter.save();
+            painter.translate(left, 0.0);
+            painter.clip_rect(Rect::new(0.0, 0.0, column.width as f32 - 1.0, height as f32));
+            let layout = theme::layout_line(
+                theme::formatted_string(&column.title, theme::text_color(enabled)));
+            let text_top = ((height - theme::text_size(&layout).height) / 2.0).round();
+            painter.save();
+            painter.translate(CELL_PADDING_X, text_top);
+            layout.draw(painter);
+            painter.restore();
+            if let Some(direction) = column.sort {
+                paint_sort_indicator(painter, (column.width / 2.0) as f32, direction);
+            }
+            painter.restore();
+            left = right;
+        }
+        painter.fill_path(
+            &mut theme::rect_path(0.0, height as f32 - 1.0, width as f32, height as f32)
+                .path_iter(),
+            &Brush::Solid(border_color),
+        );
+
+        // Shows where a dragged header will be dropped.
+        if let Some(HeaderDrag::Press { x, moving: true, .. }) = drag {
+            let position = self.drop_position(x);
+            let order = self.order.borrow();
+            let x = order[..position].iter().map(|&c| columns[c].width).sum::<f64>()
+                - self.scroll_view.scroll_offset().x;
+            painter.fill_path(
+                &mut theme::rect_path(x as f32 - 1.0, 0.0, x as f32 + 1.0, height as f32)
+                    .path_iter(),
+                &Brush::Solid(Color::from_rgba(0, 120, 215, 255)),
+            );
+        }
+    }
+}
+
+// Paints the text of a cell, vertically centered and cut off at the right edge.
+fn paint_cell_text(painter: &mut dyn Painter, text: &str, cell: &CellInfo) {
+    let layout = theme::layout_line(theme::formatted_string(text, theme::text_color(cell.enabled)));
+    let top = ((cell.size.height - theme::text_size(&layout).height) / 2.0).round();
+    painter.save();
+    painter.translate(CELL_PADDING_X, top);
+    layout.draw(painter);
+    painter.restore();
+}
+
+// Paints a small chevron at the top center of a header, like Windows does.
+fn paint_sort_indicator(painter: &mut dyn Painter, center_x: f32, direction: SortDirection) {
+    let (tip_y, base_y) = match direction {
+        SortDirection::Ascending => (1.0, 4.0),
+        SortDirection::Descending => (4.0, 1.0),
+    };
+    let mut path = PathBuf::new();
+    path.move_to(Point2::new(center_x - 4.0, base_y));
+    path.line_to(Point2::new(center_x, tip_y));
+    path.line_to(Point2::new(center_x + 4.0, base_y));
+    painter.stroke_path(
+        &mut path.path_iter(),
+        &Brush::Solid(Color::from_rgba(96, 96, 96, 255)),
+        &StrokeStyle::with_width(1.0),
+    );
+}
+
+impl Default for TableData {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[test]
+fn test_table_header() {
+    struct Model;
+    impl TableModel for Model {
+        fn row_count(&self) -> usize { 1000 }
+        fn cell_text(&self, row: usize, column: usize) -> String { format!("{}, {}", row, column) }
+    }
+
+    let table = Table::new();
+    table.set_size(&Size2::new(300.0, 200.0));
+    for title in ["Name", "Size", "Date"].iter() {
+        table.add_column(title, 100.0);
+    }
+    table.set_model(Some(Rc::new(Model)));
+    let requests = Rc::new(RefCell::new(vec![]));
+    let requests2 = requests.clone();
+    table.on_sort_requested_event(move |route| {
+        let event = route.event.downcast_mut::<SortRequestedEvent>().unwrap();
+        requests2.borrow_mut().push((event.column, event.direction));
+    });
+
+    let drag = |from: f64, to: f64| {
+        let y = 5.0;
+        let button = MouseButton::Left;
+        let (location, modifiers) = (Point2::new(from, y), Modifiers::none());
+        table.event_handlers().send(&mut MouseDownEvent { location, button, modifiers });
+        table.event_handlers().send(&mut MouseDraggedEvent { location: Point2::new(to, y) });
+        table.event_handlers().send(&mut MouseUpEvent { location: Point2::new(to, y), button });
+    };
+    // Dragging the edge between the first two columns resizes the first.
+    drag(101.0, 131.0);
+    assert_eq!(table.column_width(0), 130.0);
+    // Clicking a header requests sorting, in the opposite direction after it is sorted.
+    drag(150.0, 150.0);
+    table.set_sort_indicator(1, Some(SortDirection::Ascending));
+    drag(150.0, 151.0);
+    assert_eq!(
+        *requests.borrow(),
+        vec![(1, SortDirection::Ascending), (1, SortDirection::Descending)],
+    );
+    // Dragging the first header past the middle of the last one moves it to the end.
+    drag(50.0, 290.0);
+    assert_eq!(table.column_order(), vec![1, 2, 0]);
+
+    let key = |key| {
+        let modifiers = Modifiers::none();
+        table.event_handlers().send(&mut KeyDownEvent { key, modifiers, repeat: false });
+    };
+    key(Key::Down);
+    key(Key::Down);
+    key(Key::End);
+    assert_eq!(table.selected_cell(), Some((1, 0)));
+    assert_eq!(table.visible_rows().start, 0);
+    table.set_selected_cell(Some((500, 2)));
+    assert!(table.visible_rows().contains(&500));
+}
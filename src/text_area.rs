@@ -0,0 +1,1111 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ *
+ */
+
+use std::cell::{Cell, RefCell};
+use std::ops::{Deref, Range};
+use std::rc::Rc;
+use std::time::Duration;
+
+use zaffre::{Brush, Color, Painter, PathBuf, Point2, Rect, Size2, StrokeStyle};
+
+use crate::clipboard::Clipboard;
+use crate::control::{
+    control_handle, Control, DetachedFromWindowEvent, EnabledChangedEvent, FocusGainedEvent,
+    FocusLostEvent, MouseButton, MouseCaptureLostEvent, MouseDownEvent, MouseDraggedEvent,
+    MouseUpEvent, PaintingEvent, set_focus, SubControl, SubControlData, SubControlRef,
+};
+use crate::event_vec::{EventHandler, EventRoute};
+use crate::immrope::ImmRope;
+use crate::keyboard::{Key, KeyDownEvent, Modifiers, TextInputEvent};
+use crate::text_box::{TextChangedEvent, word_boundary};
+use crate::theme;
+use crate::timer::Timer;
+
+// TODO: generate with a proc macro
+// start proc macro generated
+#[derive(Clone)]
+pub struct TextArea(Rc<TextAreaData>);
+
+impl TextArea {
+    pub fn new() -> Self {
+        SubControl::register_handle(TextArea(Rc::new(TextAreaData::new())))
+    }
+}
+
+impl Default for TextArea {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deref for TextArea {
+    type Target = Rc<TextAreaData>;
+    fn deref(&self) -> &Rc<TextAreaData> {
+        &self.0
+    }
+}
+
+impl From<TextArea> for Rc<dyn Control> {
+    fn from(self_: TextArea) -> Self {
+        self_.0 as Rc<dyn Control>
+    }
+}
+
+impl From<TextArea> for Rc<dyn EventHandler> {
+    fn from(self_: TextArea) -> Self {
+        self_.0 as Rc<dyn EventHandler>
+    }
+}
+// end proc macro generated
+
+// Sent to the text area by its caret blink timer
+struct BlinkTimerEvent;
+
+// The space between the border and the text
+const PADDING: f64 = 4.0;
+// The space on each side of the line numbers
+const GUTTER_PADDING: f64 = 6.0;
+const DEFAULT_WIDTH: f64 = 240.0;
+const DEFAULT_HEIGHT: f64 = 120.0;
+// This matches the Windows default caret blink time.
+const BLINK_INTERVAL: Duration = Duration::from_millis(530);
+// How far a selection is extended past the end of a line to show it includes the newline
+const NEWLINE_SELECTION_WIDTH: f64 = 4.0;
+
+// The kind of the last edit, so that consecutive typing or deleting is undone in one step
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum EditKind {
+    Typing,
+    Deleting,
+    Other,
+}
+
+// The text and selection saved on the undo and redo stacks. Ropes share their trees, so this is
+// cheap even for large documents.
+struct EditState {
+    rope: ImmRope,
+    caret: usize,
+    anchor: usize,
+    // Where the text differs from the text that replaced it. In this state's rope, the changes
+    // are between `start` and `end`, and in the other text, between `start` and `other_end`.
+    start: usize,
+    end: usize,
+    other_end: usize,
+}
+
+impl EditState {
+    // Extends the changes to include replacing a range of the other text with `len` bytes.
+    fn extend(&mut self, range: Range<usize>, len: usize) {
+        let other_end = self.other_end.max(range.end);
+        self.end += other_end - self.other_end;
+        self.start = self.start.min(range.start);
+        self.other_end = other_end + len - range.len();
+    }
+}
+
+// How a paragraph is broken into lines. Paragraphs are only wrapped when they are shown, and
+// edits only discard the wrapping of the paragraphs they touch, so large documents stay fast.
+#[derive(Default)]
+struct Paragraph {
+    // The wrap width and the byte range of each line in the paragraph, or `None` if it hasn't
+    // been wrapped since it last changed
+    lines: Option<(Option<f64>, Vec<Range<usize>>)>,
+}
+
+impl Paragraph {
+    fn line_count(&self) -> usize {
+        self.lines.as_ref().map_or(1, |(_, lines)| lines.len())
+    }
+}
+
+// The paragraphs of the text, with an index of how many lines they are wrapped onto. The index
+// is a Fenwick tree, so the line a paragraph starts on and the paragraph shown on a line are found
+// in O(log n) time. Inserting or removing paragraphs rebuilds it in O(n) time, which is no slower
+// than moving the paragraphs after them.
+struct Paragraphs {
+    list: Vec<Paragraph>,
+    // Each element holds the sum of the line counts of a range of paragraphs ending at it.
+    tree: Vec<usize>,
+}
+
+impl Paragraphs {
+    fn new(count: usize) -> Self {
+        let mut paragraphs = Paragraphs { list: vec![], tree: vec![] };
+        paragraphs.splice(0..0, count);
+        paragraphs
+    }
+
+    fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    fn get(&self, paragraph: usize) -> &Paragraph {
+        &self.list[paragraph]
+    }
+
+    fn set_lines(&mut self, paragraph: usize, wrap_width: Option<f64>, lines: Vec<Range<usize>>) {
+        let old_count = self.list[paragraph].line_count();
+        self.list[paragraph].lines = Some((wrap_width, lines));
+        let new_count = self.list[paragraph].line_count();
+        let mut i = paragraph + 1;
+        while i <= self.tree.len() {
+            self.tree[i - 1] = self.tree[i - 1] - old_count + new_count;
+            i += i & i.wrapping_neg();
+        }
+    }
+
+    // Replaces a range of paragraphs with a number of paragraphs that haven't been wrapped.
+    fn splice(&mut self, range: Range<usize>, count: usize) {
+        self.list.splice(range, (0..count).map(|_| Paragraph::default()));
+        self.tree = self.list.iter().map(Paragraph::line_count).collect();
+        for i in 1..=self.tree.len() {
+            let parent = i + (i & i.wrapping_neg());
+            if parent <= self.tree.len() {
+                self.tree[parent - 1] += self.tree[i - 1];
+            }
+        }
+    }
+
+    // Returns the number of lines before a paragraph.
+    fn lines_before(&self, paragraph: usize) -> usize {
+        let mut lines = 0;
+        let mut i = paragraph;
+        while i > 0 {
+            lines += self.tree[i - 1];
+            i &= i - 1;
+        }
+        lines
+    }
+
+    fn total_lines(&self) -> usize {
+        self.lines_before(self.len())
+    }
+
+    // Returns the paragraph that a line is in. Lines past the end are in the last paragraph.
+    fn paragraph_at_line(&self, line: usize) -> usize {
+        let mut paragraph = 0;
+        let mut remaining = line;
+        let mut step = (self.tree.len() + 1).next_power_of_two() / 2;
+        while step > 0 {
+            let next = paragraph + step;
+            if next <= self.tree.len() && self.tree[next - 1] <= remaining {
+                paragraph = next;
+                remaining -= self.tree[next - 1];
+            }
+            step /= 2;
+        }
+        paragraph.min(self.len() - 1)
+    }
+}
+
+/// A box for editing multiple lines of text, which can be large.
+///
+/// The text is stored in a rope, so edits don't copy the whole document. Positions in the text,
+/// such as the selection, are byte indexes that are always on character boundaries. Each line of
+/// the text is called a paragraph here, since it can be wrapped onto multiple lines.
+//#[dynamin::control]
+pub struct TextAreaData {
+    sub_control: SubControlData,
+    rope: RefCell<ImmRope>,
+    paragraphs: RefCell<Paragraphs>,
+    // The end of the selection that moves. The caret is drawn here.
+    caret: Cell<usize>,
+    // The end of the selection that stays put when extending it with Shift or the mouse
+    anchor: Cell<usize>,
+    // The x coordinate that Up and Down try to keep the caret at
+    preferred_x: Cell<Option<f64>>,
+    scroll_x: Cell<f64>,
+    scroll_y: Cell<f64>,
+    line_height: Cell<Option<f64>>,
+    word_wrap: Cell<bool>,
+    line_numbers: Cell<bool>,
+    undo_stack: RefCell<Vec<EditState>>,
+    redo_stack: RefCell<Vec<EditState>>,
+    last_edit: Cell<Option<EditKind>>,
+    selecting: Cell<bool>,
+    caret_visible: Cell<bool>,
+    blink_timer: RefCell<Option<Timer>>,
+}
+
+impl SubControlRef for TextAreaData {
+    fn sub_control_ref(&self) -> &SubControlData { &self.sub_control }
+
+    fn best_size(&self) -> Size2<f64> {
+        Size2::new(DEFAULT_WIDTH, DEFAULT_HEIGHT)
+    }
+}
+
+impl EventHandler for TextAreaData {
+    fn on_event(&self, route: &mut EventRoute) {
+        if let Some(MouseDownEvent { button: MouseButton::Left, location, .. }) =
+            route.event.downcast_mut()
+        {
+            if let Some(handle) = control_handle(self) {
+                set_focus(&handle);
+            }
+            let index = self.index_at_point(*location);
+            self.move_caret(index, false);
+            self.selecting.set(true);
+            route.handled = true;
+        }
+
+        if let Some(MouseDraggedEvent { location }) = route.event.downcast_mut() {
+            if self.selecting.get() {
+                let index = self.index_at_point(*location);
+                self.move_caret(index, true);
+            }
+        }
+
+        if let Some(MouseUpEvent { button: MouseButton::Left, .. }) = route.event.downcast_mut() {
+            self.selecting.set(false);
+            route.handled = true;
+        }
+
+        if let Some(MouseCaptureLostEvent) = route.event.downcast_mut() {
+            self.selecting.set(false);
+        }
+
+        if let Some(event) = route.event.downcast_mut::<KeyDownEvent>() {
+            if self.on_key_down(event.key, event.modifiers) {
+                route.handled = true;
+            }
+        }
+
+        if let Some(TextInputEvent { text }) = route.event.downcast_mut() {
+            if self.is_effectively_enabled() {
+                self.replace_selection(text, EditKind::Typing);
+            }
+            route.handled = true;
+        }
+
+        if let Some(FocusGainedEvent) = route.event.downcast_mut() {
+            self.restart_blinking();
+            self.repaint_later();
+        }
+
+        let stop_blinking = route.event.is::<FocusLostEvent>() ||
+            route.event.is::<DetachedFromWindowEvent>();
+        if stop_blinking {
+            if let Some(timer) = self.blink_timer.borrow().as_ref() {
+                timer.stop();
+            }
+            self.selecting.set(false);
+            self.last_edit.set(None);
+            self.repaint_later();
+        }
+
+        if let Some(BlinkTimerEvent) = route.event.downcast_mut() {
+            self.caret_visible.set(!self.caret_visible.get());
+            self.repaint_later();
+        }
+
+        if let Some(EnabledChangedEvent { .. }) = route.event.downcast_mut() {
+            self.repaint_later();
+        }
+
+        if let Some(PaintingEvent { painter }) = route.event.downcast_mut() {
+            self.paint(&mut **painter);
+        }
+    }
+}
+
+impl TextAreaData {
+    pub fn new() -> Self {
+        let sub_control = SubControlData::new();
+        sub_control.set_focusable(true);
+        TextAreaData {
+            sub_control,
+            rope: RefCell::new(ImmRope::new()),
+            paragraphs: RefCell::new(Paragraphs::new(1)),
+            caret: Cell::new(0),
+            anchor: Cell::new(0),
+            preferred_x: Cell::new(None),
+            scroll_x: Cell::new(0.0),
+            scroll_y: Cell::new(0.0),
+            line_height: Cell::new(None),
+            word_wrap: Cell::new(true),
+            line_numbers: Cell::new(false),
+            undo_stack: RefCell::new(vec![]),
+            redo_stack: RefCell::new(vec![]),
+            last_edit: Cell::new(None),
+            selecting: Cell::new(false),
+            caret_visible: Cell::new(true),
+            blink_timer: RefCell::new(None),
+        }
+    }
+
+    /// Returns the text. For large documents, this copies the whole document.
+    pub fn text(&self) -> String {
+        self.rope.borrow().to_string()
+    }
+
+    /// Replaces the text, moves the caret to the start and clears the undo history.
+    pub fn set_text(&self, text: &str) {
+        self.set_rope(ImmRope::from(text));
+    }
+
+    /// Returns the text as a rope. This is cheap, since the rope shares its tree with the text
+    /// area's, so it can be used to take snapshots of a large document.
+    pub fn rope(&self) -> ImmRope {
+        self.rope.borrow().clone()
+    }
+
+    /// Replaces the text with a rope, moves the caret to the start and clears the undo history.
+    pub fn set_rope(&self, rope: ImmRope) {
+        self.undo_stack.borrow_mut().clear();
+        self.redo_stack.borrow_mut().clear();
+        self.replace_rope(rope, 0, 0);
+        self.scroll_x.set(0.0);
+        self.scroll_y.set(0.0);
+    }
+
+    /// Returns the number of paragraphs, which is one more than the number of newlines.
+    pub fn paragraph_count(&self) -> usize {
+        self.rope.borrow().len_lines()
+    }
+
+    /// Returns the selected range of the text. The range is empty if nothing is selected, and its
+    /// start is then the caret position.
+    pub fn selection(&self) -> Range<usize> {
+        let (caret, anchor) = (self.caret.get(), self.anchor.get());
+        caret.min(anchor)..caret.max(anchor)
+    }
+
+    /// Selects a range of the text, putting the caret at its end. The range is clamped to the
+    /// text and moved back to character boundaries.
+    pub fn select(&self, range: Range<usize>) {
+        let start = self.floor_boundary(range.start);
+        let end = self.floor_boundary(range.end);
+        self.anchor.set(start);
+        self.move_caret(end, true);
+    }
+
+    pub fn select_all(&self) {
+        let len = self.rope.borrow().len();
+        self.select(0..len);
+    }
+
+    pub fn selected_text(&self) -> String {
+        self.rope.borrow().slice(self.selection()).to_string()
+    }
+
+    /// Returns the byte index in the text where the caret is.
+    pub fn caret(&self) -> usize {
+        self.caret.get()
+    }
+
+    /// Returns whether paragraphs wider than the text area are wrapped onto multiple lines.
+    /// It is on by default. When it is off, the text scrolls horizontally instead.
+    pub fn word_wrap(&self) -> bool {
+        self.word_wrap.get()
+    }
+
+    pub fn set_word_wrap(&self, word_wrap: bool) {
+        self.word_wrap.set(word_wrap);
+        self.scroll_x.set(0.0);
+        self.scroll_to_caret();
+        self.repaint_later();
+    }
+
+    /// Returns whether paragraph numbers are shown in a margin on the left.
+    pub fn line_numbers(&self) -> bool {
+        self.line_numbers.get()
+    }
+
+    pub fn set_line_numbers(&self, line_numbers: bool) {
+        self.line_numbers.set(line_numbers);
+        self.scroll_to_caret();
+        self.repaint_later();
+    }
+
+    /// Copies the selected text to the clipboard and deletes it.
+    pub fn cut(&self) {
+        if self.selection().is_empty() {
+            return;
+        }
+        self.copy();
+        self.replace_selection("", EditKind::Other);
+    }
+
+    /// Copies the selected text to the clipboard.
+    pub fn copy(&self) {
+        if !self.selection().is_empty() {
+            Clipboard::set_text(&self.selected_text());
+        }
+    }
+
+    /// Replaces the selection with the text on the clipboard.
+    pub fn paste(&self) {
+        if let Some(text) = Clipboard::text() {
+            self.replace_selection(&text, EditKind::Other);
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.borrow().is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.borrow().is_empty()
+    }
+
+    /// Undoes the last edit. Consecutive typing or deleting is undone together.
+    pub fn undo(&self) {
+        let state = self.undo_stack.borrow_mut().pop();
+        if let Some(state) = state {
+            let current = self.restore(state);
+            self.redo_stack.borrow_mut().push(current);
+        }
+    }
+
+    pub fn redo(&self) {
+        let state = self.redo_stack.borrow_mut().pop();
+        if let Some(state) = state {
+            let current = self.restore(state);
+            self.undo_stack.borrow_mut().push(current);
+        }
+    }
+
+    // Convenience method to add an event handler that is called for `TextChangedEvent`s.
+    pub fn on_text_changed_event<F>(&self, mut handler: F)
+        where F: for<'a> FnMut(&'a mut EventRoute) + 'static
+    {
+        self.event_handlers().add(move |mut route| {
+            if let Some(_) = route.event.downcast_mut::<TextChangedEvent>() {
+                handler(&mut route);
+            }
+        });
+    }
+
+    // Returns whether the key was handled.
+    fn on_key_down(&self, key: Key, modifiers: Modifiers) -> bool {
+        let Modifiers { shift, control, alt, logo } = modifiers;
+        if alt || logo {
+            return false;
+        }
+        let enabled = self.is_effectively_enabled();
+        let caret = self.caret.get();
+        match key {
+            Key::Left | Key::Right => {
+                let forward = key == Key::Right;
+                let selection = self.selection();
+                let index = if control {
+                    self.word_boundary(caret, forward)
+                } else if !shift && !selection.is_empty() {
+                    // Without Shift, the arrows collapse the selection to the side they point to.
+                    if forward { selection.end } else { selection.start }
+                } else if forward {
+                    self.next_boundary(caret)
+                } else {
+                    self.prev_boundary(caret)
+                };
+                self.move_caret(index, shift);
+            }
+            Key::Up | Key::Down if !control => {
+                self.move_caret_vertically(if key == Key::Down { 1 } else { -1 }, shift);
+            }
+            Key::PageUp | Key::PageDown if !control => {
+                let lines = (self.visible_size().height / self.line_height()).floor().max(1.0);
+                let lines = lines as isize * if key == Key::PageDown { 1 } else { -1 };
+                self.move_caret_vertically(lines, shift);
+            }
+            Key::Home | Key::End => {
+                let to_end = key == Key::End;
+                let index = if control {
+                    if to_end { self.rope.borrow().len() } else { 0 }
+                } else {
+                    let (paragraph, line) = self.locate(caret);
+                    let start = self.rope.borrow().line_to_byte(paragraph);
+                    let lines = self.visual_lines(paragraph);
+                    let range = &lines[line];
+                    if !to_end {
+                        start + range.start
+                    } else if line + 1 < lines.len() {
+                        // The end of a wrapped line is the start of the next, so stop before the
+                        // space it was wrapped at.
+                        self.prev_boundary(start + range.end)
+                    } else {
+                        start + range.end
+                    }
+                };
+                self.move_caret(index, shift);
+            }
+            Key::Enter if enabled && !control && !shift => {
+                self.replace_selection("\n", EditKind::Typing);
+            }
+            Key::Backspace | Key::Delete if enabled && !shift => {
+                if self.selection().is_empty() {
+                    let forward = key == Key::Delete;
+                    let end = match (control, forward) {
+                        (true, _) => self.word_boundary(caret, forward),
+                        (false, true) => self.next_boundary(caret),
+                        (false, false) => self.prev_boundary(caret),
+                    };
+                    self.anchor.set(end);
+                }
+                self.replace_selection("", EditKind::Deleting);
+            }
+            Key::A if control && !shift => self.select_all(),
+            Key::C if control && !shift => self.copy(),
+            Key::Insert if control && !shift => self.copy(),
+            Key::X if control && !shift && enabled => self.cut(),
+            Key::Delete if shift && !control && enabled => self.cut(),
+            Key::V if control && !shift && enabled => self.paste(),
+            Key::Insert if shift && !control && enabled => self.paste(),
+            Key::Z if control && !shift && enabled => self.undo(),
+            Key::Y if control && !shift && enabled => self.redo(),
+            Key::Z if control && shift && enabled => self.redo(),
+            _ => return false,
+        }
+        true
+    }
+
+    // Replaces the selection with text, like typing or pasting does, and puts the caret after
+    // it. Returns whether the text changed.
+    fn replace_selection(&self, new_text: &str, kind: EditKind) -> bool {
+        let selection = self.selection();
+        let new_text = new_text.replace("\r\n", "\n").replace('\r', "\n");
+        if new_text.is_empty() && selection.is_empty() {
+            return false;
+        }
+
+        // Consecutive typing or deleting is one undo step.
+        let new_end = selection.start + new_text.len();
+        let mut undo_stack = self.undo_stack.borrow_mut();
+        match undo_stack.last_mut() {
+            Some(state) if kind != EditKind::Other && self.last_edit.get() == Some(kind) => {
+                state.extend(selection.clone(), new_text.len());
+            }
+            _ => undo_stack.push(self.current_state(selection.clone(), new_end)),
+        }
+        drop(undo_stack);
+        self.redo_stack.borrow_mut().clear();
+
+        let rope = self.rope.borrow().replace(selection.clone(), &new_text);
+        self.splice_rope(rope, selection.start, selection.end, new_end);
+
+        let caret = new_end;
+        self.caret.set(caret);
+        self.anchor.set(caret);
+        self.text_changed();
+        self.last_edit.set(Some(kind));
+        true
+    }
+
+    // Saves the text and selection, before the range `start..end` of the text is changed into
+    // `start..other_end`.
+    fn current_state(&self, changed: Range<usize>, other_end: usize) -> EditState {
+        EditState {
+            rope: self.rope.borrow().clone(),
+            caret: self.caret.get(),
+            anchor: self.anchor.get(),
+            start: changed.start,
+            end: changed.end,
+            other_end,
+        }
+    }
+
+    // Puts back the text and selection of an undo or redo state, and returns the state to go back
+    // to the current text.
+    fn restore(&self, state: EditState) -> EditState {
+        let current = self.current_state(state.start..state.other_end, state.end);
+        self.splice_rope(state.rope, state.start, state.other_end, state.end);
+        self.caret.set(state.caret);
+        self.anchor.set(state.anchor);
+        self.last_edit.set(None);
+        self.text_changed();
+        current
+    }
+
+    // Replaces the text with a rope that only differs from it between `start` and `old_end` in
+    // the current text and between `start` and `new_end` in the rope. Only the paragraphs in
+    // between need to be wrapped again.
+    fn splice_rope(&self, rope: ImmRope, start: usize, old_end: usize, new_end: usize) {
+        let (first, last) = {
+            let old_rope = self.rope.borrow();
+            (old_rope.byte_to_line(start), old_rope.byte_to_line(old_end))
+        };
+        let count = rope.byte_to_line(new_end) - rope.byte_to_line(start) + 1;
+        self.paragraphs.borrow_mut().splice(first..last + 1, count);
+        *self.rope.borrow_mut() = rope;
+    }
+
+    // Replaces the whole text, discarding the wrapping of every paragraph.
+    fn replace_rope(&self, rope: ImmRope, caret: usize, anchor: usize) {
+        *self.paragraphs.borrow_mut() = Paragraphs::new(rope.len_lines());
+        *self.rope.borrow_mut() = rope;
+        self.caret.set(caret);
+        self.anchor.set(anchor);
+        self.last_edit.set(None);
+        self.text_changed();
+    }
+
+    fn text_changed(&self) {
+        self.preferred_x.set(None);
+        self.event_handlers().send(&mut TextChangedEvent);
+        self.scroll_to_caret();
+        self.restart_blinking();
+        self.repaint_later();
+    }
+
+    // Moves the caret, extending the selection from the anchor if `extend` is true or collapsing
+    // it otherwise.
+    fn move_caret(&self, index: usize, extend: bool) {
+        self.caret.set(index);
+        if !extend {
+            self.anchor.set(index);
+        }
+        self.preferred_x.set(None);
+        // Typing after moving the caret starts a new undo step.
+        self.last_edit.set(None);
+        self.scroll_to_caret();
+        self.restart_blinking();
+        self.repaint_later();
+    }
+
+    // Moves the caret up or down a number of lines, keeping it as close as possible to the x
+    // coordinate it started at.
+    fn move_caret_vertically(&self, lines: isize, extend: bool) {
+        let caret_point = self.caret_point(self.caret.get());
+        let x = self.preferred_x.get().unwrap_or(caret_point.x);
+        let y = caret_point.y + self.line_height() * (lines as f64 + 0.5);
+        let index = if y < 0.0 {
+            0
+        } else if y >= self.content_height() {
+            self.rope.borrow().len()
+        } else {
+            self.index_at_content_point(Point2::new(x, y))
+        };
+        self.move_caret(index, extend);
+        self.preferred_x.set(Some(x));
+    }
+
+    fn floor_boundary(&self, index: usize) -> usize {
+        let rope = self.rope.borrow();
+        let index = index.min(rope.len());
+        rope.char_to_byte(rope.byte_to_char(index))
+    }
+
+    fn next_boundary(&self, index: usize) -> usize {
+        let rope = self.rope.borrow();
+        if index < rope.len() { index + rope.char_at(index).len_utf8() } else { index }
+    }
+
+    fn prev_boundary(&self, index: usize) -> usize {
+        let rope = self.rope.borrow();
+        match rope.byte_to_char(index) {
+            0 => 0,
+            char_index => rope.char_to_byte(char_index - 1),
+        }
+    }
+
+    // Returns the start of the next or previous word in the paragraph, or the other side of the
+    // newline if the index is at the end or start of the paragraph.
+    fn word_boundary(&self, index: usize, forward: bool) -> usize {
+        let rope = self.rope.borrow();
+        let range = rope.line_range(rope.byte_to_line(index));
+        if (forward && index == range.end) || (!forward && index == range.start) {
+            drop(rope);
+            return if forward { self.next_boundary(index) } else { self.prev_boundary(index) };
+        }
+        let text = rope.slice(range.clone()).to_string();
+        range.start + word_boundary(&text, index - range.start, forward)
+    }
+
+    fn line_height(&self) -> f64 {
+        if let Some(line_height) = self.line_height.get() {
+            return line_height;
+        }
+        let line = theme::layout_line(theme::formatted_string("Xg", theme::text_color(true)));
+        let line_height = theme::text_size(&line).height.ceil();
+        self.line_height.set(Some(line_height));
+        line_height
+    }
+
+    fn gutter_width(&self) -> f64 {
+        if !self.line_numbers.get() {
+            return 0.0;
+        }
+        let digits = self.paragraph_count().to_string().len().max(2);
+        theme::line_width(&"9".repeat(digits)) + GUTTER_PADDING * 2.0
+    }
+
+    // Returns the location of the text's origin in the control, without scrolling.
+    fn text_origin(&self) -> Point2<f64> {
+        Point2::new(self.gutter_width() + PADDING, PADDING)
+    }
+
+    // Returns the size of the area the text is shown in.
+    fn visible_size(&self) -> Size2<f64> {
+        let size = self.size();
+        let origin = self.text_origin();
+        Size2::new(
+            (size.width - origin.x - PADDING).max(0.0),
+            (size.height - origin.y - PADDING).max(0.0),
+        )
+    }
+
+    // Returns the width to wrap paragraphs at, or `None` if they shouldn't be wrapped.
+    fn wrap_width(&self) -> Option<f64> {
+        let width = self.visible_size().width;
+        if self.word_wrap.get() && width > 0.0 {
+            Some(width)
+        } else {
+            None
+        }
+    }
+
+    // Returns the byte ranges of the lines a paragraph is wrapped onto, relative to the start of
+    // the paragraph. The wrapping is cached until the paragraph changes.
+    fn visual_lines(&self, paragraph: usize) -> Vec<Range<usize>> {
+        let wrap_width = self.wrap_width();
+        if let Some((width, lines)) = &self.paragraphs.borrow().get(paragraph).lines {
+            if *width == wrap_width {
+                return lines.clone();
+            }
+        }
+        let text = self.rope.borrow().line(paragraph);
+        let lines = theme::wrap_paragraph(&text, wrap_width);
+        self.paragraphs.borrow_mut().set_lines(paragraph, wrap_width, lines.clone());
+        lines
+    }
+
+    // Returns the height of the paragraphs before one. Paragraphs that haven't been wrapped are
+    // assumed to be one line.
+    fn paragraph_top(&self, paragraph: usize) -> f64 {
+        self.paragraphs.borrow().lines_before(paragraph) as f64 * self.line_height()
+    }
+
+    fn content_height(&self) -> f64 {
+        self.paragraphs.borrow().total_lines() as f64 * self.line_height()
+    }
+
+    // Returns the paragraph and the line in it that a position is shown on.
+    fn locate(&self, index: usize) -> (usize, usize) {
+        let (paragraph, start) = {
+            let rope = self.rope.borrow();
+            let paragraph = rope.byte_to_line(index);
+            (paragraph, rope.line_to_byte(paragraph))
+        };
+        let offset = index - start;
+        // At a wrap point, the caret is shown at the start of the next line.
+        let line = self.visual_lines(paragraph).iter().rposition(|l| l.start <= offset);
+        (paragraph, line.unwrap_or(0))
+    }
+
+    // Returns the location of the caret at a position, relative to the text's origin.
+    fn caret_point(&self, index: usize) -> Point2<f64> {
+        let (paragraph, line) = self.locate(index);
+        let lines = self.visual_lines(paragraph);
+        let rope = self.rope.borrow();
+        let start = rope.line_to_byte(paragraph) + lines[line].start;
+        let x = theme::line_width(&rope.slice(start..index).to_string());
+        drop(rope);
+        let y = self.paragraph_top(paragraph) + line as f64 * self.line_height();
+        Point2::new(x, y)
+    }
+
+    fn index_at_point(&self, location: Point2<f64>) -> usize {
+        let origin = self.text_origin();
+        let x = location.x - origin.x + self.scroll_x.get();
+        let y = (location.y - origin.y + self.scroll_y.get()).max(0.0);
+        self.index_at_content_point(Point2::new(x, y))
+    }
+
+    // Returns the position closest to a point relative to the text's origin.
+    fn index_at_content_point(&self, point: Point2<f64>) -> usize {
+        let line = (point.y / self.line_height()).max(0.0) as usize;
+        let paragraph = self.paragraphs.borrow().paragraph_at_line(line);
+        // Wrapping the paragraph doesn't move it, only the paragraphs after it.
+        let lines = self.visual_lines(paragraph);
+        let line = line.saturating_sub(self.paragraphs.borrow().lines_before(paragraph))
+            .min(lines.len() - 1);
+        let range = lines[line].clone();
+        let start = self.rope.borrow().line_to_byte(paragraph);
+        let text = self.rope.borrow().slice(start + range.start..start + range.end).to_string();
+        let mut offset = theme::index_at_x(&text, point.x);
+        // Stay on this line instead of moving to the start of the next one.
+        if line + 1 < lines.len() && offset == text.len() {
+            offset = text[..offset].char_indices().next_back().map_or(0, |(i, _)| i);
+        }
+        start + range.start + offset
+    }
+
+    // Scrolls just enough to show the caret, and no further than the end of the text.
+    fn scroll_to_caret(&self) {
+        let visible = self.visible_size();
+        let line_height = self.line_height();
+        let caret = self.caret_point(self.caret.get());
+
+        let mut scroll_y = self.scroll_y.get();
+        if caret.y < scroll_y {
+            scroll_y = caret.y;
+        } else if caret.y + line_height > scroll_y + visible.height {
+            scroll_y = caret.y + line_height - visible.height;
+        }
+        let max_scroll_y = (self.content_height() - visible.height).max(0.0);
+        self.scroll_y.set(scroll_y.min(max_scroll_y).max(0.0));
+
+        let mut scroll_x = if self.word_wrap.get() { 0.0 } else { self.scroll_x.get() };
+        if caret.x < scroll_x {
+            scroll_x = caret.x;
+        } else if caret.x > scroll_x + visible.width {
+            scroll_x = caret.x - visible.width;
+        }
+        self.scroll_x.set(scroll_x.max(0.0));
+    }
+
+    // Shows the caret and starts the blink over, so that it doesn't disappear while typing.
+    fn restart_blinking(&self) {
+        self.caret_visible.set(true);
+        if !self.focused() {
+            return;
+        }
+        let mut timer = self.blink_timer.borrow_mut();
+        if timer.is_none() {
+            let handle = match control_handle(self) {
+                Some(handle) => Rc::downgrade(&handle),
+                None => return,
+            };
+            *timer = Some(Timer::new(BLINK_INTERVAL, move || {
+                if let Some(handle) = handle.upgrade() {
+                    handle.event_handlers().send(&mut BlinkTimerEvent);
+                }
+            }));
+        }
+        timer.as_ref().unwrap().start();
+    }
+
+    fn paint(&self, painter: &mut dyn Painter) {
+        let enabled = self.is_effectively_enabled();
+        let focused = self.focused();
+        let size = self.size();
+        let (width, height) = (size.width as f32, size.height as f32);
+
+        let background_color = if enabled {
+            Color::from_rgba(255, 255, 255, 255)
+        } else {
+            Color::from_rgba(240, 240, 240, 255)
+        };
+        let border_color = if !enabled {
+            Color::from_rgba(204, 204, 204, 255)
+        } else if focused {
+            Color::from_rgba(0, 120, 215, 255)
+        } else {
+            Color::from_rgba(122, 122, 122, 255)
+        };
+        painter.fill_path(
+            &mut theme::rect_path(0.0, 0.0, width, height).path_iter(),
+            &Brush::Solid(background_color),
+        );
+
+        let gutter_width = self.gutter_width();
+        if gutter_width > 0.0 {
+            painter.fill_path(
+                &mut theme::rect_path(0.0, 0.0, gutter_width as f32, height).path_iter(),
+                &Brush::Solid(Color::from_rgba(240, 240, 240, 255)),
+            );
+        }
+        painter.stroke_path(
+            &mut theme::rect_path(0.5, 0.5, width - 0.5, height - 0.5).path_iter(),
+            &Brush::Solid(border_color),
+            &StrokeStyle::with_width(1.0),
+        );
+
+        let origin = self.text_origin();
+        let visible = self.visible_size();
+        let line_height = self.line_height();
+        let (scroll_x, scroll_y) = (self.scroll_x.get(), self.scroll_y.get());
+        let selection = if focused { self.selection() } else { 0..0 };
+        let text_color = theme::text_color(enabled);
+        let selected_color = Color::from_rgba(255, 255, 255, 255);
+
+        // Paragraphs above the visible area are skipped without wrapping them. Lines that are
+        // partly scrolled out of view are drawn and cut off at the padding.
+        let mut visible_lines = vec![];
+        let paragraph_count = self.paragraphs.borrow().len();
+        let first_line = (scroll_y / line_height) as usize;
+        let first_paragraph = self.paragraphs.borrow().paragraph_at_line(first_line);
+        for paragraph in first_paragraph..paragraph_count {
+            let top = self.paragraph_top(paragraph) - scroll_y;
+            if top >= visible.height {
+                break;
+            }
+            let lines = self.visual_lines(paragraph);
+            let line_count = lines.len();
+            for (i, range) in lines.into_iter().enumerate() {
+                let y = top + i as f64 * line_height;
+                if y + line_height > 0.0 && y < visible.height {
+                    visible_lines.push((paragraph, i, line_count, range, origin.y + y));
+                }
+            }
+        }
+        let (clip_top, clip_height) = (origin.y as f32, visible.height as f32);
+
+        if gutter_width > 0.0 {
+            painter.save();
+            painter.clip_rect(Rect::new(0.0, clip_top, gutter_width as f32, clip_height));
+            // Paragraph numbers are shown on the first line of each paragraph.
+            for &(paragraph, _, _, _, y) in visible_lines.iter().filter(|line| line.1 == 0) {
+                let number = (paragraph + 1).to_string();
+                let x = gutter_width - GUTTER_PADDING - theme::line_width(&number);
+                let string =
+                    theme::formatted_string(&number, Color::from_rgba(128, 128, 128, 255));
+                painter.save();
+                painter.translate(x, y);
+                theme::layout_line(string).draw(painter);
+                painter.restore();
+            }
+            painter.restore();
+        }
+
+        painter.save();
+        painter.clip_rect(
+            Rect::new(origin.x as f32, clip_top, visible.width as f32, clip_height));
+        let to_screen = |x: f64| origin.x + x - scroll_x;
+        for (paragraph, i, line_count, range, y) in visible_lines {
+            let line_start = self.rope.borrow().line_to_byte(paragraph) + range.start;
+            let line_text =
+                self.rope.borrow().slice(line_start..line_start + range.len()).to_string();
+            let line_end = line_start + line_text.len();
+            // Characters that are partly in view are drawn too.
+            let first = theme::floor_index_at_x(&line_text, scroll_x);
+            let last = theme::ceil_index_at_x(&line_text, scroll_x + visible.width).max(first);
+            let layout = theme::measured_line(&line_text);
+            let x_of = |index: usize| theme::layout_x_of_index(&layout, index);
+
+            // Selection, relative to the line
+            let sel_start = selection.start.max(line_start).min(line_end) - line_start;
+            let sel_end = selection.end.max(line_start).min(line_end) - line_start;
+            let includes_newline = i + 1 == line_count &&
+                selection.end > line_end && selection.start <= line_end;
+            if sel_end > sel_start || includes_newline {
+                let left = x_of(sel_start);
+                let mut right = x_of(sel_end);
+                if includes_newline {
+                    right += NEWLINE_SELECTION_WIDTH;
+                }
+                if right > left {
+                    painter.fill_path(
+                        &mut theme::rect_path(
+                            to_screen(left) as f32, y as f32,
+                            to_screen(right) as f32, (y + line_height) as f32,
+                        ).path_iter(),
+                        &Brush::Solid(Color::from_rgba(0, 120, 215, 255)),
+                    );
+                }
+            }
+
+            if last > first {
+                let mut string = theme::formatted_string(&line_text[first..last], text_color);
+                let start = sel_start.max(first).min(last);
+                let end = sel_end.max(first).min(last);
+                if end > start {
+                    string.set_brush(start - first..end - first, Brush::Solid(selected_color));
+                }
+                painter.save();
+                painter.translate(to_screen(x_of(first)), y);
+                theme::layout_line(string).draw(painter);
+                painter.restore();
+            }
+        }
+        painter.restore();
+
+        if focused && enabled && self.caret_visible.get() {
+            let caret = self.caret_point(self.caret.get());
+            let (x, y) = (caret.x - scroll_x, caret.y - scroll_y);
+            // The caret is cut off at the top and bottom like the line it is on.
+            let top = y.max(0.0);
+            let bottom = (y + line_height).min(visible.height);
+            if x >= 0.0 && x <= visible.width && bottom > top {
+                let x = (origin.x + x).round() as f32 + 0.5;
+                let mut path = PathBuf::new();
+                path.move_to(Point2::new(x, (origin.y + top) as f32));
+                path.line_to(Point2::new(x, (origin.y + bottom) as f32));
+                painter.stroke_path(
+                    &mut path.path_iter(),
+                    &Brush::Solid(Color::from_rgba(0, 0, 0, 255)),
+                    &StrokeStyle::with_width(1.0),
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_text_area_editing() {
+    let text_area = TextArea::new();
+    let key = |key, control, shift| {
+        let modifiers = Modifiers { control, shift, ..Modifiers::none() };
+        text_area.event_handlers().send(&mut KeyDownEvent { key, modifiers, repeat: false });
+    };
+    let type_text = |text: &str| {
+        text_area.event_handlers().send(&mut TextInputEvent { text: text.to_owned() });
+    };
+
+    let document: String = (0..10_000).map(|i| format!("paragraph {}\n", i)).collect();
+    text_area.set_text(&document);
+    assert_eq!(text_area.paragraph_count(), 10_001);
+
+    key(Key::End, true, false);
+    type_text("last");
+    key(Key::Enter, false, false);
+    type_text("line");
+    assert_eq!(text_area.paragraph_count(), 10_002);
+    assert!(text_area.text().ends_with("paragraph 9999\nlast\nline"));
+
+    key(Key::Home, true, true);
+    assert_eq!(text_area.selection(), 0..text_area.text().len());
+    key(Key::Delete, false, false);
+    assert_eq!(text_area.text(), "");
+    assert_eq!(text_area.paragraph_count(), 1);
+
+    key(Key::Z, true, false);
+    assert!(text_area.text().ends_with("last\nline"));
+    key(Key::Z, true, false);
+    assert_eq!(text_area.text(), document);
+    key(Key::Y, true, false);
+    key(Key::Y, true, false);
+    assert_eq!(text_area.text(), "");
+
+    // Undoing and redoing only discard the wrapping of the paragraphs they change.
+    text_area.set_text("one\ntwo\nthree");
+    text_area.select(8..8);
+    type_text("x\ny");
+    text_area.visual_lines(0);
+    text_area.undo();
+    assert_eq!(text_area.text(), "one\ntwo\nthree");
+    assert!(text_area.paragraphs.borrow().get(0).lines.is_some());
+    assert!(text_area.paragraphs.borrow().get(2).lines.is_none());
+    text_area.redo();
+    assert_eq!(text_area.text(), "one\ntwo\nx\nythree");
+    assert!(text_area.paragraphs.borrow().get(0).lines.is_some());
+
+    // With word wrap on, lines are found through the index of how many lines each paragraph is
+    // wrapped onto.
+    text_area.set_size(&Size2::new(120.0, 100.0));
+    let document: String =
+        (0..1000).map(|i| format!("paragraph {} is long enough to wrap\n", i)).collect();
+    text_area.set_text(&document);
+    key(Key::End, true, false);
+    for paragraph in (0..1000).step_by(7) {
+        text_area.visual_lines(paragraph);
+    }
+    let paragraphs = text_area.paragraphs.borrow();
+    let mut lines = 0;
+    for paragraph in 0..paragraphs.len() {
+        assert_eq!(paragraphs.lines_before(paragraph), lines);
+        assert_eq!(paragraphs.paragraph_at_line(lines), paragraph);
+        lines += paragraphs.get(paragraph).line_count();
+    }
+    assert_eq!(paragraphs.total_lines(), lines);
+    assert!(lines > paragraphs.len());
+    drop(paragraphs);
+    let index = text_area.rope().line_to_byte(700) + 20;
+    let point = text_area.caret_point(index);
+    assert_eq!(text_area.index_at_content_point(Point2::new(point.x, point.y + 1.0)), index);
+}
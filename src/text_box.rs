@@ -0,0 +1,908 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ *
+ */
+
+use std::cell::{Cell, Ref, RefCell};
+use std::ops::{Deref, Range};
+use std::rc::Rc;
+use std::time::Duration;
+
+use unicode_segmentation::UnicodeSegmentation;
+use zaffre::text::TextLayout;
+use zaffre::{Brush, Color, Painter, PathBuf, Point2, Rect, Size2, StrokeStyle};
+
+use crate::clipboard::Clipboard;
+use crate::control::{
+    control_handle, Control, DetachedFromWindowEvent, EnabledChangedEvent, FocusGainedEvent,
+    FocusLostEvent, MouseButton, MouseCaptureLostEvent, MouseDownEvent, MouseDraggedEvent,
+    MouseUpEvent, PaintingEvent, set_focus, SizeChangedEvent, SubControl, SubControlData,
+    SubControlRef,
+};
+use crate::event_vec::{EventHandler, EventRoute};
+use crate::keyboard::{Key, KeyDownEvent, Modifiers, TextInputEvent};
+use crate::theme;
+use crate::timer::Timer;
+
+// TODO: generate with a proc macro
+// start proc macro generated
+#[derive(Clone)]
+pub struct TextBox(Rc<TextBoxData>);
+
+impl TextBox {
+    pub fn new() -> Self {
+        SubControl::register_handle(TextBox(Rc::new(TextBoxData::new())))
+    }
+}
+
+impl Default for TextBox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deref for TextBox {
+    type Target = Rc<TextBoxData>;
+    fn deref(&self) -> &Rc<TextBoxData> {
+        &self.0
+    }
+}
+
+impl From<TextBox> for Rc<dyn Control> {
+    fn from(self_: TextBox) -> Self {
+        self_.0 as Rc<dyn Control>
+    }
+}
+
+impl From<TextBox> for Rc<dyn EventHandler> {
+    fn from(self_: TextBox) -> Self {
+        self_.0 as Rc<dyn EventHandler>
+    }
+}
+// end proc macro generated
+
+/// Sent to a text box after its text changes, whether by the user or by code.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct TextChangedEvent;
+
+// Sent to the text box by its caret blink timer
+struct BlinkTimerEvent;
+
+// The space between the border and the text
+const PADDING_X: f64 = 3.0;
+const PADDING_Y: f64 = 3.0;
+const DEFAULT_WIDTH: f64 = 120.0;
+const MIN_HEIGHT: f64 = 23.0;
+// This matches the Windows default caret blink time.
+const BLINK_INTERVAL: Duration = Duration::from_millis(530);
+// Shown in place of each character of a password
+const PASSWORD_CHAR: char = '\u{25CF}';
+
+// The kind of the last edit, so that consecutive typing or deleting is undone in one step
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum EditKind {
+    Typing,
+    Deleting,
+    Other,
+}
+
+// An edit saved on the undo and redo stacks. Only the changed part of the text is kept.
+struct Edit {
+    // Where the replaced text started
+    start: usize,
+    removed: String,
+    inserted: String,
+    // The selection before the edit, which undoing it restores
+    caret: usize,
+    anchor: usize,
+}
+
+impl Edit {
+    // Adds a replacement of `range` with `text`, made right after this edit, to it if the two
+    // are next to each other, like when typing or deleting one character after another. Returns
+    // whether it was added.
+    fn extend(&mut self, range: Range<usize>, removed: &str, text: &str) -> bool {
+        let end = self.start + self.inserted.len();
+        if range.is_empty() && range.start == end {
+            // Typing
+            self.inserted.push_str(text);
+        } else if !self.inserted.is_empty() || !text.is_empty() {
+            return false;
+        } else if range.end == self.start {
+            // Backspace
+            self.removed.insert_str(0, removed);
+            self.start = range.start;
+        } else if range.start == self.start {
+            // Delete
+            self.removed.push_str(removed);
+        } else {
+            return false;
+        }
+        true
+    }
+}
+
+/// A box for editing a single line of text.
+///
+/// Positions in the text, such as the selection, are byte indexes that are always on character
+/// boundaries.
+//#[dynamin::control]
+pub struct TextBoxData {
+    sub_control: SubControlData,
+    text: RefCell<String>,
+    // The end of the selection that moves. The caret is drawn here.
+    caret: Cell<usize>,
+    // The end of the selection that stays put when extending it with Shift or the mouse
+    anchor: Cell<usize>,
+    // How far the text is scrolled left to keep the caret in view
+    scroll_x: Cell<f64>,
+    // The text as it is shown, laid out on one line when needed after it changes. Carets are
+    // placed and clicks hit tested with it.
+    layout: RefCell<Option<TextLayout>>,
+    // The same layout in the color of selected text
+    selected_layout: RefCell<Option<TextLayout>>,
+    undo_stack: RefCell<Vec<Edit>>,
+    redo_stack: RefCell<Vec<Edit>>,
+    last_edit: Cell<Option<EditKind>>,
+    password: Cell<bool>,
+    max_length: Cell<Option<usize>>,
+    placeholder: RefCell<String>,
+    selecting: Cell<bool>,
+    caret_visible: Cell<bool>,
+    blink_timer: RefCell<Option<Timer>>,
+}
+
+impl SubControlRef for TextBoxData {
+    fn sub_control_ref(&self) -> &SubControlData { &self.sub_control }
+
+    fn best_size(&self) -> Size2<f64> {
+        let line = theme::layout_line(theme::formatted_string("Xg", theme::text_color(true)));
+        let height = theme::text_size(&line).height + PADDING_Y * 2.0 + 2.0;
+        Size2::new(DEFAULT_WIDTH, height.max(MIN_HEIGHT).ceil())
+    }
+}
+
+impl EventHandler for TextBoxData {
+    fn on_event(&self, route: &mut EventRoute) {
+        if let Some(MouseDownEvent { button: MouseButton::Left, location, .. }) =
+            route.event.downcast_mut()
+        {
+            if let Some(handle) = control_handle(self) {
+                set_focus(&handle);
+            }
+            let index = self.index_at_x(location.x);
+            self.move_caret(index, false);
+            self.selecting.set(true);
+            route.handled = true;
+        }
+
+        if let Some(MouseDraggedEvent { location }) = route.event.downcast_mut() {
+            if self.selecting.get() {
+                let index = self.index_at_x(location.x);
+                self.move_caret(index, true);
+            }
+        }
+
+        if let Some(MouseUpEvent { button: MouseButton::Left, .. }) = route.event.downcast_mut() {
+            self.selecting.set(false);
+            route.handled = true;
+        }
+
+        if let Some(MouseCaptureLostEvent) = route.event.downcast_mut() {
+            self.selecting.set(false);
+        }
+
+        if let Some(event) = route.event.downcast_mut::<KeyDownEvent>() {
+            if self.on_key_down(event.key, event.modifiers) {
+                route.handled = true;
+            }
+        }
+
+        if let Some(TextInputEvent { text }) = route.event.downcast_mut() {
+            if self.is_effectively_enabled() {
+                self.replace_selection(text, EditKind::Typing);
+            }
+            route.handled = true;
+        }
+
+        if let Some(FocusGainedEvent) = route.event.downcast_mut() {
+            self.restart_blinking();
+            self.repaint_later();
+        }
+
+        let stop_blinking = route.event.is::<FocusLostEvent>() ||
+            route.event.is::<DetachedFromWindowEvent>();
+        if stop_blinking {
+            if let Some(timer) = self.blink_timer.borrow().as_ref() {
+                timer.stop();
+            }
+            self.selecting.set(false);
+            self.last_edit.set(None);
+            self.repaint_later();
+        }
+
+        if let Some(BlinkTimerEvent) = route.event.downcast_mut() {
+            self.caret_visible.set(!self.caret_visible.get());
+            self.repaint_later();
+        }
+
+        if let Some(EnabledChangedEvent { .. }) = route.event.downcast_mut() {
+            // The text color depends on whether the text box is enabled.
+            self.clear_layout();
+            self.repaint_later();
+        }
+
+        if let Some(SizeChangedEvent) = route.event.downcast_mut() {
+            self.scroll_to_caret();
+        }
+
+        if let Some(PaintingEvent { painter }) = route.event.downcast_mut() {
+            self.paint(&mut **painter);
+        }
+    }
+}
+
+impl TextBoxData {
+    pub fn new() -> Self {
+        let sub_control = SubControlData::new();
+        sub_control.set_focusable(true);
+        TextBoxData {
+            sub_control,
+            text: RefCell::new(String::new()),
+            caret: Cell::new(0),
+            anchor: Cell::new(0),
+            scroll_x: Cell::new(0.0),
+            layout: RefCell::new(None),
+            selected_layout: RefCell::new(None),
+            undo_stack: RefCell::new(vec![]),
+            redo_stack: RefCell::new(vec![]),
+            last_edit: Cell::new(None),
+            password: Cell::new(false),
+            max_length: Cell::new(None),
+            placeholder: RefCell::new(String::new()),
+            selecting: Cell::new(false),
+            caret_visible: Cell::new(true),
+            blink_timer: RefCell::new(None),
+        }
+    }
+
+    pub fn text(&self) -> String {
+        self.text.borrow().clone()
+    }
+
+    /// Replaces the text, moves the caret to the end and clears the undo history. Line breaks are
+    /// replaced with spaces, and the text is truncated to the max length.
+    pub fn set_text(&self, text: &str) {
+        let text = self.truncate_to_max_length(&single_line(text), 0);
+        let len = text.len();
+        *self.text.borrow_mut() = text;
+        self.undo_stack.borrow_mut().clear();
+        self.redo_stack.borrow_mut().clear();
+        self.last_edit.set(None);
+        self.caret.set(len);
+        self.anchor.set(len);
+        self.text_changed();
+    }
+
+    /// Returns the selected range of the text. The range is empty if nothing is selected, and its
+    /// start is then the caret position.
+    pub fn selection(&self) -> Range<usize> {
+        let (caret, anchor) = (self.caret.get(), self.anchor.get());
+        caret.min(anchor)..caret.max(anchor)
+    }
+
+    /// Selects a range of the text, putting the caret at its end. The range is clamped to the
+    /// text and moved back to character boundaries.
+    pub fn select(&self, range: Range<usize>) {
+        let start = self.floor_boundary(range.start);
+        let end = self.floor_boundary(range.end);
+        self.anchor.set(start);
+        self.move_caret(end, true);
+    }
+
+    pub fn select_all(&self) {
+        let len = self.text.borrow().len();
+        self.select(0..len);
+    }
+
+    pub fn selected_text(&self) -> String {
+        self.text.borrow()[self.selection()].to_owned()
+    }
+
+    /// Returns the byte index in the text where the caret is.
+    pub fn caret(&self) -> usize {
+        self.caret.get()
+    }
+
+    /// Returns whether the characters are masked when shown.
+    pub fn password(&self) -> bool {
+        self.password.get()
+    }
+
+    /// Sets whether the text is shown as a row of dots, for entering passwords. Copying and
+    /// cutting are disabled in password mode.
+    pub fn set_password(&self, password: bool) {
+        self.password.set(password);
+        self.clear_layout();
+        self.scroll_to_caret();
+        self.repaint_later();
+    }
+
+    /// Returns the maximum number of characters the user can enter, if any.
+    pub fn max_length(&self) -> Option<usize> {
+        self.max_length.get()
+    }
+
+    /// Sets the maximum number of characters the user can enter. Existing text longer than the
+    /// maximum is left alone.
+    pub fn set_max_length(&self, max_length: Option<usize>) {
+        self.max_length.set(max_length);
+    }
+
+    pub fn placeholder(&self) -> String {
+        self.placeholder.borrow().clone()
+    }
+
+    /// Sets the hint shown in gray while the text box is empty and not focused.
+    pub fn set_placeholder(&self, placeholder: &str) {
+        *self.placeholder.borrow_mut() = placeholder.to_owned();
+        self.repaint_later();
+    }
+
+    /// Copies the selected text to the clipboard and deletes it.
+    pub fn cut(&self) {
+        if self.password.get() || self.selection().is_empty() {
+            return;
+        }
+        self.copy();
+        self.replace_selection("", EditKind::Other);
+    }
+
+    /// Copies the selected text to the clipboard.
+    pub fn copy(&self) {
+        if self.password.get() || self.selection().is_empty() {
+            return;
+        }
+        Clipboard::set_text(&self.selected_text());
+    }
+
+    /// Replaces the selection with the text on the clipboard.
+    pub fn paste(&self) {
+        if let Some(text) = Clipboard::text() {
+            self.replace_selection(&text, EditKind::Other);
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.borrow().is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.borrow().is_empty()
+    }
+
+    /// Undoes the last edit. Consecutive typing or deleting is undone together.
+    pub fn undo(&self) {
+        let edit = self.undo_stack.borrow_mut().pop();
+        if let Some(edit) = edit {
+            let end = edit.start + edit.inserted.len();
+            self.text.borrow_mut().replace_range(edit.start..end, &edit.removed);
+            self.caret.set(edit.caret);
+            self.anchor.set(edit.anchor);
+            self.last_edit.set(None);
+            self.text_changed();
+            self.redo_stack.borrow_mut().push(edit);
+        }
+    }
+
+    pub fn redo(&self) {
+        let edit = self.redo_stack.borrow_mut().pop();
+        if let Some(edit) = edit {
+            let end = edit.start + edit.removed.len();
+            self.text.borrow_mut().replace_range(edit.start..end, &edit.inserted);
+            let caret = edit.start + edit.inserted.len();
+            self.caret.set(caret);
+            self.anchor.set(caret);
+            self.last_edit.set(None);
+            self.text_changed();
+            self.undo_stack.borrow_mut().push(edit);
+        }
+    }
+
+    // Convenience method to add an event handler that is called for `TextChangedEvent`s.
+    pub fn on_text_changed_event<F>(&self, mut handler: F)
+        where F: for<'a> FnMut(&'a mut EventRoute) + 'static
+    {
+        self.event_handlers().add(move |mut route| {
+            if let Some(_) = route.event.downcast_mut::<TextChangedEvent>() {
+                handler(&mut route);
+            }
+        });
+    }
+
+    // Returns whether the key was handled.
+    fn on_key_down(&self, key: Key, modifiers: Modifiers) -> bool {
+        let Modifiers { shift, control, alt, logo } = modifiers;
+        if alt || logo {
+            return false;
+        }
+        let enabled = self.is_effectively_enabled();
+        let caret = self.caret.get();
+        match key {
+            Key::Left | Key::Right => {
+                let forward = key == Key::Right;
+                let selection = self.selection();
+                let index = if control {
+                    self.word_boundary(caret, forward)
+                } else if !shift && !selection.is_empty() {
+                    // Without Shift, the arrows collapse the selection to the side they point to.
+                    if forward { selection.end } else { selection.start }
+                } else if forward {
+                    self.next_boundary(caret)
+                } else {
+                    self.prev_boundary(caret)
+                };
+                self.move_caret(index, shift);
+            }
+            Key::Home => self.move_caret(0, shift),
+            Key::End => self.move_caret(self.text.borrow().len(), shift),
+            Key::Backspace | Key::Delete if enabled && !shift => {
+                if self.selection().is_empty() {
+                    let forward = key == Key::Delete;
+                    let end = match (control, forward) {
+                        (true, _) => self.word_boundary(caret, forward),
+                        (false, true) => self.next_boundary(caret),
+                        (false, false) => self.prev_boundary(caret),
+                    };
+                    self.anchor.set(end);
+                }
+                self.replace_selection("", EditKind::Deleting);
+            }
+            Key::A if control && !shift => self.select_all(),
+            Key::C if control && !shift => self.copy(),
+            Key::Insert if control && !shift => self.copy(),
+            Key::X if control && !shift && enabled => self.cut(),
+            Key::Delete if shift && !control && enabled => self.cut(),
+            Key::V if control && !shift && enabled => self.paste(),
+            Key::Insert if shift && !control && enabled => self.paste(),
+            Key::Z if control && !shift && enabled => self.undo(),
+            Key::Y if control && !shift && enabled => self.redo(),
+            Key::Z if control && shift && enabled => self.redo(),
+            _ => return false,
+        }
+        true
+    }
+
+    // Replaces the selection with text, like typing or pasting does, and puts the caret after
+    // it. Returns whether the text changed.
+    fn replace_selection(&self, new_text: &str, kind: EditKind) -> bool {
+        let selection = self.selection();
+        let selected_chars = self.text.borrow()[selection.clone()].chars().count();
+        let new_text = self.truncate_to_max_length(&single_line(new_text), selected_chars);
+        if new_text.is_empty() && selection.is_empty() {
+            return false;
+        }
+
+        // Consecutive typing or deleting is one undo step.
+        let removed = self.text.borrow()[selection.clone()].to_owned();
+        let mut undo_stack = self.undo_stack.borrow_mut();
+        let extended = kind != EditKind::Other && self.last_edit.get() == Some(kind) &&
+            undo_stack.last_mut().map_or(false, |edit| {
+                edit.extend(selection.clone(), &removed, &new_text)
+            });
+        if !extended {
+            undo_stack.push(Edit {
+                start: selection.start,
+                removed,
+                inserted: new_text.clone(),
+                caret: self.caret.get(),
+                anchor: self.anchor.get(),
+            });
+        }
+        drop(undo_stack);
+        self.redo_stack.borrow_mut().clear();
+
+        self.text.borrow_mut().replace_range(selection.clone(), &new_text);
+        let caret = selection.start + new_text.len();
+        self.caret.set(caret);
+        self.anchor.set(caret);
+        self.text_changed();
+        self.last_edit.set(Some(kind));
+        true
+    }
+
+    // Truncates text so that it fits in the max length after replacing `replaced_chars`
+    // characters.
+    fn truncate_to_max_length(&self, text: &str, replaced_chars: usize) -> String {
+        match self.max_length.get() {
+            Some(max_length) => {
+                let current_chars = self.text.borrow().chars().count();
+                let available = (max_length + replaced_chars).saturating_sub(current_chars);
+                text.chars().take(available).collect()
+            }
+            None => text.to_owned(),
+        }
+    }
+
+    fn text_changed(&self) {
+        self.clear_layout();
+        self.event_handlers().send(&mut TextChangedEvent);
+        self.scroll_to_caret();
+        self.restart_blinking();
+        self.repaint_later();
+    }
+
+    // Moves the caret, extending the selection from the anchor if `extend` is true or collapsing
+    // it otherwise.
+    fn move_caret(&self, index: usize, extend: bool) {
+        self.caret.set(index);
+        if !extend {
+            self.anchor.set(index);
+        }
+        // Typing after moving the caret starts a new undo step.
+        self.last_edit.set(None);
+        self.scroll_to_caret();
+        self.restart_blinking();
+        self.repaint_later();
+    }
+
+    // Moves an index back to the start of the grapheme cluster it is in.
+    fn floor_boundary(&self, index: usize) -> usize {
+        let text = self.text.borrow();
+        if index >= text.len() {
+            return text.len();
+        }
+        text.grapheme_indices(true).map(|(i, _)| i).take_while(|&i| i <= index).last().unwrap_or(0)
+    }
+
+    // The caret moves over a whole grapheme cluster at a time, such as a letter and its accents.
+    fn next_boundary(&self, index: usize) -> usize {
+        let text = self.text.borrow();
+        text[index..].graphemes(true).next().map_or(index, |g| index + g.len())
+    }
+
+    fn prev_boundary(&self, index: usize) -> usize {
+        let text = self.text.borrow();
+        text[..index].graphemes(true).next_back().map_or(index, |g| index - g.len())
+    }
+
+    // In password mode, the whole text is one word so that word lengths aren't revealed.
+    fn word_boundary(&self, index: usize, forward: bool) -> usize {
+        let text = self.text.borrow();
+        if self.password.get() {
+            return if forward { text.len() } else { 0 };
+        }
+        word_boundary(&text, index, forward)
+    }
+
+    // Returns the text as it is shown, with each grapheme cluster masked in password mode.
+    fn display_text(&self) -> String {
+        let text = self.text.borrow();
+        if self.password.get() {
+            text.graphemes(true).map(|_| PASSWORD_CHAR).collect()
+        } else {
+            text.clone()
+        }
+    }
+
+    // Converts an index in the text to the same position in the display text.
+    fn to_display_index(&self, index: usize) -> usize {
+        if self.password.get() {
+            self.text.borrow()[..index].graphemes(true).count() * PASSWORD_CHAR.len_utf8()
+        } else {
+            index
+        }
+    }
+
+    // Converts an index in the display text to the same position in the text.
+    fn from_display_index(&self, index: usize) -> usize {
+        let text = self.text.borrow();
+        if self.password.get() {
+            let count = index / PASSWORD_CHAR.len_utf8();
+            text.grapheme_indices(true).nth(count).map_or(text.len(), |(i, _)| i)
+        } else {
+            index
+        }
+    }
+
+    fn clear_layout(&self) {
+        self.layout.replace(None);
+        self.selected_layout.replace(None);
+    }
+
+    fn layout(&self) -> Ref<TextLayout> {
+        self.cached_layout(&self.layout, theme::text_color(self.is_effectively_enabled()))
+    }
+
+    fn selected_layout(&self) -> Ref<TextLayout> {
+        self.cached_layout(&self.selected_layout, Color::from_rgba(255, 255, 255, 255))
+    }
+
+    fn cached_layout<'a>(&self, cache: &'a RefCell<Option<TextLayout>>, color: Color)
+        -> Ref<'a, TextLayout>
+    {
+        if cache.borrow().is_none() {
+            let string = theme::formatted_string(&self.display_text(), color);
+            *cache.borrow_mut() = Some(theme::layout_line(string));
+        }
+        Ref::map(cache.borrow(), |layout| layout.as_ref().unwrap())
+    }
+
+    // Returns the x coordinate of the caret at an index, relative to the start of the text.
+    fn x_of_index(&self, index: usize) -> f64 {
+        theme::layout_x_of_index(&self.layout(), self.to_display_index(index))
+    }
+
+    // Returns the caret position closest to an x coordinate in the control.
+    fn index_at_x(&self, x: f64) -> usize {
+        let x = x - PADDING_X + self.scroll_x.get();
+        let index = theme::layout_index_at_x(&self.layout(), x);
+        self.from_display_index(index)
+    }
+
+    fn text_width(&self) -> f64 {
+        let len = self.text.borrow().len();
+        self.x_of_index(len)
+    }
+
+    fn visible_width(&self) -> f64 {
+        (self.size().width - PADDING_X * 2.0).max(0.0)
+    }
+
+    // Scrolls horizontally just enough to show the caret, and no further than the end of the
+    // text.
+    fn scroll_to_caret(&self) {
+        let caret_x = self.x_of_index(self.caret.get());
+        let visible_width = self.visible_width();
+        let mut scroll_x = self.scroll_x.get();
+        if caret_x < scroll_x {
+            scroll_x = caret_x;
+        } else if caret_x > scroll_x + visible_width {
+            scroll_x = caret_x - visible_width;
+        }
+        let max_scroll = (self.text_width() - visible_width).max(0.0);
+        self.scroll_x.set(scroll_x.min(max_scroll).max(0.0));
+    }
+
+    // Shows the caret and starts the blink over, so that it doesn't disappear while typing.
+    fn restart_blinking(&self) {
+        self.caret_visible.set(true);
+        if !self.focused() {
+            return;
+        }
+        let mut timer = self.blink_timer.borrow_mut();
+        if timer.is_none() {
+            let handle = match control_handle(self) {
+                Some(handle) => Rc::downgrade(&handle),
+                None => return,
+            };
+            *timer = Some(Timer::new(BLINK_INTERVAL, move || {
+                if let Some(handle) = handle.upgrade() {
+                    handle.event_handlers().send(&mut BlinkTimerEvent);
+                }
+            }));
+        }
+        timer.as_ref().unwrap().start();
+    }
+
+    fn paint(&self, painter: &mut dyn Painter) {
+        let enabled = self.is_effectively_enabled();
+        let focused = self.focused();
+        let size = self.size();
+        let (width, height) = (size.width as f32, size.height as f32);
+
+        let background_color = if enabled {
+            Color::from_rgba(255, 255, 255, 255)
+        } else {
+            Color::from_rgba(240, 240, 240, 255)
+        };
+        let border_color = if !enabled {
+            Color::from_rgba(204, 204, 204, 255)
+        } else if focused {
+            Color::from_rgba(0, 120, 215, 255)
+        } else {
+            Color::from_rgba(122, 122, 122, 255)
+        };
+        painter.fill_path(
+            &mut theme::rect_path(0.0, 0.0, width, height).path_iter(),
+            &Brush::Solid(background_color),
+        );
+        painter.stroke_path(
+            &mut theme::rect_path(0.5, 0.5, width - 0.5, height - 0.5).path_iter(),
+            &Brush::Solid(border_color),
+            &StrokeStyle::with_width(1.0),
+        );
+
+        let line = theme::layout_line(theme::formatted_string("Xg", theme::text_color(true)));
+        let line_height = theme::text_size(&line).height;
+        let top = ((size.height - line_height) / 2.0).round();
+
+        // Characters that are partly scrolled out of view are cut off at the padding.
+        let visible_width = self.visible_width();
+        painter.save();
+        painter.clip_rect(Rect::new(
+            PADDING_X as f32, 1.0, visible_width as f32, (size.height - 2.0).max(0.0) as f32));
+
+        if self.text.borrow().is_empty() {
+            let placeholder = self.placeholder.borrow();
+            if !focused && !placeholder.is_empty() {
+                let string =
+                    theme::formatted_string(&placeholder, Color::from_rgba(109, 109, 109, 255));
+                painter.save();
+                painter.translate(PADDING_X, top);
+                theme::layout_line(string).draw(painter);
+                painter.restore();
+            }
+        }
+
+        let scroll_x = self.scroll_x.get();
+        let to_screen = |x: f64| (PADDING_X + x - scroll_x) as f32;
+
+        let selection = self.selection();
+        let selection_x = if focused && !selection.is_empty() {
+            Some((to_screen(self.x_of_index(selection.start)),
+                  to_screen(self.x_of_index(selection.end))))
+        } else {
+            None
+        };
+        if let Some((start, end)) = selection_x {
+            painter.fill_path(
+                &mut theme::rect_path(start, top as f32, end, (top + line_height) as f32)
+                    .path_iter(),
+                &Brush::Solid(Color::from_rgba(0, 120, 215, 255)),
+            );
+        }
+
+        painter.save();
+        painter.translate(to_screen(0.0) as f64, top);
+        self.layout().draw(painter);
+        painter.restore();
+
+        // The selected text is drawn again on top, in its own color.
+        if let Some((start, end)) = selection_x {
+            painter.save();
+            painter.clip_rect(Rect::new(start, top as f32, end - start, line_height as f32));
+            painter.translate(to_screen(0.0) as f64, top);
+            self.selected_layout().draw(painter);
+            painter.restore();
+        }
+        painter.restore();
+
+        if focused && enabled && self.caret_visible.get() {
+            let x = to_screen(self.x_of_index(self.caret.get())).round() + 0.5;
+            let mut path = PathBuf::new();
+            path.move_to(Point2::new(x, top as f32));
+            path.line_to(Point2::new(x, (top + line_height) as f32));
+            painter.stroke_path(
+                &mut path.path_iter(),
+                &Brush::Solid(Color::from_rgba(0, 0, 0, 255)),
+                &StrokeStyle::with_width(1.0),
+            );
+        }
+    }
+}
+
+// Returns the start of the next or previous word, skipping whitespace and punctuation.
+pub(crate) fn word_boundary(text: &str, index: usize, forward: bool) -> usize {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    if forward {
+        let mut chars = text[index..].char_indices().peekable();
+        while let Some(&(_, c)) = chars.peek() {
+            if !is_word(c) { break; }
+            chars.next();
+        }
+        while let Some(&(_, c)) = chars.peek() {
+            if is_word(c) { break; }
+            chars.next();
+        }
+        chars.peek().map_or(text.len(), |&(i, _)| index + i)
+    } else {
+        let mut chars = text[..index].char_indices().rev().peekable();
+        while let Some(&(_, c)) = chars.peek() {
+            if is_word(c) { break; }
+            chars.next();
+        }
+        let mut start = chars.peek().map_or(0, |&(i, _)| i);
+        while let Some(&(i, c)) = chars.peek() {
+            if !is_word(c) { break; }
+            start = i;
+            chars.next();
+        }
+        start
+    }
+}
+
+// Replaces line breaks with spaces, since a text box only has one line.
+fn single_line(text: &str) -> String {
+    text.replace("\r\n", " ").replace(|c| c == '\r' || c == '\n', " ")
+}
+
+#[test]
+fn test_text_box_editing() {
+    let text_box = TextBox::new();
+    let key = |key, control, shift| {
+        let modifiers = Modifiers { control, shift, ..Modifiers::none() };
+        text_box.event_handlers().send(&mut KeyDownEvent { key, modifiers, repeat: false });
+    };
+    let type_text = |text: &str| {
+        text_box.event_handlers().send(&mut TextInputEvent { text: text.to_owned() });
+    };
+
+    type_text("hello");
+    type_text(" ");
+    type_text("wörld");
+    assert_eq!(text_box.text(), "hello wörld");
+
+    key(Key::Left, true, true);
+    assert_eq!(text_box.selected_text(), "wörld");
+    key(Key::Backspace, false, false);
+    assert_eq!(text_box.text(), "hello ");
+    key(Key::Left, false, false);
+    key(Key::Home, false, true);
+    assert_eq!(text_box.selection(), 0..5);
+
+    // typing is undone together, but separately from deleting
+    key(Key::Z, true, false);
+    assert_eq!(text_box.text(), "hello wörld");
+    key(Key::Z, true, false);
+    assert_eq!(text_box.text(), "");
+    assert!(!text_box.can_undo());
+    key(Key::Y, true, false);
+    assert_eq!(text_box.text(), "hello wörld");
+
+    text_box.set_max_length(Some(13));
+    text_box.select(6..13);
+    type_text("there\nfriend");
+    assert_eq!(text_box.text(), "hello there f");
+    type_text("x");
+    assert_eq!(text_box.text(), "hello there f");
+
+    text_box.set_password(true);
+    key(Key::Left, true, false);
+    assert_eq!(text_box.caret(), 0);
+
+    // The caret moves over an accented letter as a whole.
+    text_box.set_password(false);
+    text_box.set_text("e\u{301}x");
+    text_box.select(1..1);
+    assert_eq!(text_box.caret(), 0);
+    key(Key::Right, false, false);
+    assert_eq!(text_box.caret(), 3);
+    key(Key::Backspace, false, false);
+    assert_eq!(text_box.text(), "x");
+
+    // Resizing scrolls the caret into view without waiting for a paint.
+    text_box.set_size(&Size2::new(0.0, 0.0));
+    text_box.set_text("hello there");
+    assert!(text_box.scroll_x.get() > 0.0);
+    text_box.set_size(&Size2::new(500.0, 30.0));
+    assert_eq!(text_box.scroll_x.get(), 0.0);
+}
+
+#[test]
+fn test_text_box_undo_deleting() {
+    let text_box = TextBox::new();
+    let key = |key| {
+        let modifiers = Modifiers::none();
+        text_box.event_handlers().send(&mut KeyDownEvent { key, modifiers, repeat: false });
+    };
+    text_box.set_text("one two");
+
+    // Consecutive backspaces or deletes are undone together.
+    text_box.select(3..3);
+    key(Key::Backspace);
+    key(Key::Backspace);
+    assert_eq!(text_box.text(), "o two");
+    text_box.undo();
+    assert_eq!((text_box.text(), text_box.caret()), ("one two".to_owned(), 3));
+    key(Key::Delete);
+    key(Key::Delete);
+    assert_eq!(text_box.text(), "onewo");
+    text_box.undo();
+    assert_eq!(text_box.text(), "one two");
+    text_box.redo();
+    assert_eq!((text_box.text(), text_box.caret()), ("onewo".to_owned(), 3));
+}
@@ -0,0 +1,254 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ *
+ */
+
+use zaffre::font::{self, Font};
+use zaffre::text::{FormattedString, TextLayout, TextRectFramer};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::f32::consts::PI;
+use std::ops::Range;
+use std::rc::Rc;
+
+use zaffre::{Brush, Color, PathBuf, Point2, Rect, Size2};
+
+// The size of the default UI font in pixels. This matches 9 pt Segoe UI on Windows at 96 DPI.
+pub(crate) const DEFAULT_FONT_SIZE: f32 = 12.0;
+
+// A width to lay out text in when it shouldn't wrap
+pub(crate) const UNBOUNDED: f32 = 50_000.0;
+
+/// Returns the color of text in a control.
+pub(crate) fn text_color(enabled: bool) -> Color {
+    if enabled {
+        Color::from_rgba(0, 0, 0, 255)
+    } else {
+        Color::from_rgba(131, 131, 131, 255)
+    }
+}
+
+/// Returns the color behind selected items in lists and trees. The selection is less prominent
+/// when the control doesn't have the focus.
+pub(crate) fn item_selection_color(focused: bool) -> Color {
+    if focused {
+        Color::from_rgba(204, 232, 255, 255)
+    } else {
+        Color::from_rgba(217, 217, 217, 255)
+    }
+}
+
+/// Returns the color of the rectangle around the item that has the keyboard focus in a list or
+/// tree.
+pub(crate) fn item_focus_color() -> Color {
+    Color::from_rgba(153, 209, 255, 255)
+}
+
+fn load_default_font() -> Font {
+    let font_family = font::get_family("Segoe UI")
+        .or_else(|| font::get_family("DejaVu Sans"))
+        .or_else(|| font::get_family("Helvetica"))
+        .or_else(|| font::get_family("Arial"))
+        .expect("couldn't find font");
+    font_family.get_styles()[0].get_font(DEFAULT_FONT_SIZE)
+}
+
+thread_local! {
+    // Looking up the font family is slow, so it is only done once.
+    static DEFAULT_FONT: Font = load_default_font();
+}
+
+/// Returns the font that controls use for their text by default.
+pub(crate) fn default_font() -> Font {
+    DEFAULT_FONT.with(|font| font.clone())
+}
+
+/// Returns a `FormattedString` of the text in the default font and the specified color.
+pub(crate) fn formatted_string(text: &str, color: Color) -> FormattedString {
+    let mut string = FormattedString::new();
+    string.clear_and_set_text_from(&text.to_owned());
+    string.set_initial_font(default_font());
+    string.set_initial_brush(Brush::Solid(color));
+    string
+}
+
+/// Lays out text on one line starting at the origin.
+pub(crate) fn layout_line(text: FormattedString) -> TextLayout {
+    layout_in_width(text, UNBOUNDED)
+}
+
+/// Lays out text starting at the origin, wrapping it at the specified width.
+pub(crate) fn layout_in_width(text: FormattedString, width: f32) -> TextLayout {
+    let mut layout = TextLayout::new();
+    layout.set_text(text);
+    layout.layout(&mut TextRectFramer::new(Rect::new(0.0, 0.0, width, UNBOUNDED)));
+    layout
+}
+
+/// Returns the size of the text in a layout.
+pub(crate) fn text_size(layout: &TextLayout) -> Size2<f64> {
+    let size = layout.size();
+    Size2::new(size.width as f64, size.height as f64)
+}
+
+/// Returns a path around a rectangle.
+pub(crate) fn rect_path(left: f32, top: f32, right: f32, bottom: f32) -> PathBuf {
+    let mut path = PathBuf::new();
+    path.move_to(Point2::new(left, top));
+    path.line_to(Point2::new(right, top));
+    path.line_to(Point2::new(right, bottom));
+    path.line_to(Point2::new(left, bottom));
+    path.close();
+    path
+}
+
+/// Returns a path around a circle, approximated with line segments. At the sizes controls draw
+/// circles, the segments are too short to see.
+pub(crate) fn circle_path(center: Point2<f32>, radius: f32) -> PathBuf {
+    const SEGMENTS: u32 = 32;
+    let mut path = PathBuf::new();
+    path.move_to(Point2::new(center.x + radius, center.y));
+    for i in 1..SEGMENTS {
+        let angle = i as f32 * 2.0 * PI / SEGMENTS as f32;
+        path.line_to(Point2::new(center.x + radius * angle.cos(), center.y + radius * angle.sin()));
+    }
+    path.close();
+    path
+}
+
+// The most lines whose layouts are kept for measuring. When there are more, the cache starts over.
+const MAX_CACHED_LINES: usize = 1000;
+
+thread_local! {
+    // Lines of text in the default font that have been laid out for measuring, by their text
+    static LINE_LAYOUTS: RefCell<HashMap<String, Rc<TextLayout>>> = RefCell::new(HashMap::new());
+}
+
+// Returns a layout of text on one line in the default font, for measuring it. Laying out the
+// whole run includes kerning and shaping between characters. The same text is usually measured
+// over and over while painting, so layouts are cached by their text.
+pub(crate) fn measured_line(text: &str) -> Rc<TextLayout> {
+    if let Some(layout) = LINE_LAYOUTS.with(|layouts| layouts.borrow().get(text).cloned()) {
+        return layout;
+    }
+    let layout = Rc::new(layout_line(formatted_string(text, Color::from_rgba(0, 0, 0, 255))));
+    LINE_LAYOUTS.with(|layouts| {
+        let mut layouts = layouts.borrow_mut();
+        if layouts.len() >= MAX_CACHED_LINES {
+            layouts.clear();
+        }
+        layouts.insert(text.to_owned(), layout.clone());
+    });
+    layout
+}
+
+// Returns the x coordinate of the caret before a byte index in a layout of one line.
+pub(crate) fn layout_x_of_index(layout: &TextLayout, index: usize) -> f64 {
+    layout.position_of_index(index).x as f64
+}
+
+// Returns the caret position in a layout of one line that is closest to an x coordinate.
+pub(crate) fn layout_index_at_x(layout: &TextLayout, x: f64) -> usize {
+    layout.index_at_point(Point2::new(x as f32, 0.0))
+}
+
+// Returns the width of text laid out on one line. Unlike the layout's size, this includes
+// trailing whitespace.
+pub(crate) fn line_width(text: &str) -> f64 {
+    layout_x_of_index(&measured_line(text), text.len())
+}
+
+// Returns the last character boundary in the text that is at or before an x coordinate.
+pub(crate) fn floor_index_at_x(text: &str, x: f64) -> usize {
+    layout_floor_index_at_x(text, &measured_line(text), x)
+}
+
+// Returns the last character boundary at or before an x coordinate in a layout of the text on one
+// line.
+pub(crate) fn layout_floor_index_at_x(text: &str, layout: &TextLayout, x: f64) -> usize {
+    let index = layout_index_at_x(layout, x);
+    if layout_x_of_index(layout, index) <= x {
+        return index;
+    }
+    text[..index].chars().next_back().map_or(index, |c| index - c.len_utf8())
+}
+
+// Returns the first character boundary in the text that is at or after an x coordinate.
+pub(crate) fn ceil_index_at_x(text: &str, x: f64) -> usize {
+    let layout = measured_line(text);
+    let index = layout_index_at_x(&layout, x);
+    if layout_x_of_index(&layout, index) >= x {
+        return index;
+    }
+    text[index..].chars().next().map_or(index, |c| index + c.len_utf8())
+}
+
+// Returns the character boundary in the text closest to an x coordinate.
+pub(crate) fn index_at_x(text: &str, x: f64) -> usize {
+    layout_index_at_x(&measured_line(text), x)
+}
+
+// Breaks a paragraph into lines that fit in a width, at spaces where possible. A word wider than
+// the width is broken between characters.
+pub(crate) fn wrap_paragraph(text: &str, width: Option<f64>) -> Vec<Range<usize>> {
+    wrap_layout(text, &measured_line(text), width)
+}
+
+// Like `wrap_paragraph()`, but measures the paragraph with a layout of it on one line, which can
+// be in any font.
+pub(crate) fn wrap_layout(text: &str, layout: &TextLayout, width: Option<f64>)
+    -> Vec<Range<usize>>
+{
+    let width = match width {
+        Some(width) if !text.is_empty() => width,
+        _ => return vec![0..text.len()],
+    };
+    // A line can break after any run of whitespace.
+    let mut breaks: Vec<usize> = text.char_indices()
+        .zip(text.chars().skip(1))
+        .filter(|&((_, c), next)| c.is_whitespace() && !next.is_whitespace())
+        .map(|((i, c), _)| i + c.len_utf8())
+        .collect();
+    breaks.push(text.len());
+
+    // The paragraph is laid out once, and each line's width is the difference of two offsets.
+    let x_of = |index: usize| layout_x_of_index(layout, index);
+    let mut lines = vec![];
+    let mut start = 0;
+    while start < text.len() {
+        let mut end = start;
+        for &b in breaks.iter().filter(|&&b| b > start) {
+            // Trailing whitespace can hang past the edge.
+            let trimmed_end = start + text[start..b].trim_end().len();
+            if x_of(trimmed_end) - x_of(start) <= width {
+                end = b;
+            } else {
+                break;
+            }
+        }
+        if end == start {
+            // The first word doesn't fit, so put as much of it as fits on the line.
+            end = layout_floor_index_at_x(text, layout, x_of(start) + width);
+            if end <= start {
+                end = start + text[start..].chars().next().unwrap().len_utf8();
+            }
+        }
+        lines.push(start..end);
+        start = end;
+    }
+    lines
+}
+
+#[test]
+fn test_wrap_paragraph() {
+    let text = "the quick brown fox jumps over the lazy dog";
+    let lines = wrap_paragraph(text, Some(line_width("the quick brown ")));
+    assert_eq!(text[lines[0].clone()].trim_end(), "the quick brown");
+    assert_eq!(lines.last().unwrap().end, text.len());
+    for pair in lines.windows(2) {
+        assert_eq!(pair[0].end, pair[1].start);
+    }
+    assert_eq!(wrap_paragraph(text, None), vec![0..text.len()]);
+}
@@ -0,0 +1,128 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ *
+ */
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use crate::backend::TimerBackend;
+use crate::control::{control_handle, Control};
+use crate::generic_backend::GenericTimerBackend;
+
+/// Calls a function repeatedly on the UI thread.
+///
+/// A timer isn't running until `start()` is called. Dropping the timer stops it.
+pub struct Timer {
+    backend: TimerBackend,
+    interval: Cell<Duration>,
+    handler: Rc<RefCell<dyn FnMut()>>,
+}
+
+impl Timer {
+    pub fn new<F>(interval: Duration, handler: F) -> Self
+    where
+        F: FnMut() + 'static,
+    {
+        Timer {
+            backend: TimerBackend::new(),
+            interval: Cell::new(interval),
+            handler: Rc::new(RefCell::new(handler)),
+        }
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval.get()
+    }
+
+    /// Sets how often the handler is called. If the timer is running, it is restarted.
+    pub fn set_interval(&self, interval: Duration) {
+        self.interval.set(interval);
+        if self.is_running() {
+            self.start();
+        }
+    }
+
+    /// Starts calling the handler every interval. If the timer is already running, it is
+    /// restarted, so the next call is a full interval away.
+    pub fn start(&self) {
+        let handler = self.handler.clone();
+        self.backend.start(self.interval.get(), Rc::new(move || {
+            // If the handler is already running, it is blocked in a nested event loop, and it
+            // doesn't make sense to call it again.
+            if let Ok(mut handler) = handler.try_borrow_mut() {
+                (&mut *handler)();
+            }
+        }));
+    }
+
+    pub fn stop(&self) {
+        self.backend.stop();
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.backend.is_running()
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+// About 60 frames per second
+const FRAME_INTERVAL: Duration = Duration::from_millis(16);
+
+// Repaints a control every frame while it is running, so that what it paints can depend on the
+// time since the animation started.
+pub(crate) struct Animation {
+    timer: RefCell<Option<Timer>>,
+    start: Cell<Instant>,
+}
+
+impl Animation {
+    pub(crate) fn new() -> Self {
+        Animation { timer: RefCell::new(None), start: Cell::new(Instant::now()) }
+    }
+
+    // Starts repainting the control if the animation isn't already running. The control must be
+    // registered with a handle.
+    pub(crate) fn start(&self, control: &dyn Control) {
+        if self.is_running() {
+            return;
+        }
+        let mut timer = self.timer.borrow_mut();
+        if timer.is_none() {
+            let handle = match control_handle(control) {
+                Some(handle) => Rc::downgrade(&handle),
+                None => return,
+            };
+            *timer = Some(Timer::new(FRAME_INTERVAL, move || {
+                if let Some(handle) = handle.upgrade() {
+                    handle.repaint_later();
+                }
+            }));
+        }
+        self.start.set(Instant::now());
+        timer.as_ref().unwrap().start();
+    }
+
+    pub(crate) fn stop(&self) {
+        if let Some(timer) = self.timer.borrow().as_ref() {
+            timer.stop();
+        }
+    }
+
+    pub(crate) fn is_running(&self) -> bool {
+        self.timer.borrow().as_ref().map_or(false, |t| t.is_running())
+    }
+
+    // Returns the time since the animation was last started.
+    pub(crate) fn elapsed(&self) -> Duration {
+        self.start.get().elapsed()
+    }
+}
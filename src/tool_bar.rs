@@ -0,0 +1,288 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ *
+ */
+
+use std::cell::{Cell, RefCell};
+use std::ops::Deref;
+use std::rc::Rc;
+
+use zaffre::{Brush, Color, Painter, Rect, Size2, StrokeStyle};
+
+use crate::action::{notify_when_changed, Action, AttachedActionChangedEvent, Subscription};
+use crate::control::{
+    Control, MouseButton, MouseDownEvent, MouseDraggedEvent, MouseLeftEvent, MouseMovedEvent,
+    MouseUpEvent, PaintingEvent, SubControl, SubControlData, SubControlRef,
+};
+use crate::event_vec::{EventHandler, EventRoute};
+use crate::mnemonic::MnemonicText;
+use crate::theme;
+
+// TODO: generate with a proc macro
+// start proc macro generated
+#[derive(Clone)]
+pub struct ToolBar(Rc<ToolBarData>);
+
+impl ToolBar {
+    pub fn new() -> Self {
+        SubControl::register_handle(ToolBar(Rc::new(ToolBarData::new())))
+    }
+}
+
+impl Default for ToolBar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deref for ToolBar {
+    type Target = Rc<ToolBarData>;
+    fn deref(&self) -> &Rc<ToolBarData> {
+        &self.0
+    }
+}
+
+impl From<ToolBar> for Rc<dyn Control> {
+    fn from(self_: ToolBar) -> Self {
+        self_.0 as Rc<dyn Control>
+    }
+}
+
+impl From<ToolBar> for Rc<dyn EventHandler> {
+    fn from(self_: ToolBar) -> Self {
+        self_.0 as Rc<dyn EventHandler>
+    }
+}
+// end proc macro generated
+
+#[derive(Clone)]
+enum ToolBarItem {
+    Action(Action),
+    Separator,
+}
+
+const ITEM_PADDING: f64 = 4.0;
+const MIN_ITEM_SIZE: f64 = 24.0;
+const SEPARATOR_WIDTH: f64 = 9.0;
+
+/// A row of flat buttons that trigger actions. An item shows its action's icon, or its text if
+/// it doesn't have one, and is painted pushed in while the action is checked.
+//#[dynamin::control]
+pub struct ToolBarData {
+    sub_control: SubControlData,
+    items: RefCell<Vec<ToolBarItem>>,
+    // One for each distinct action of the items
+    subscriptions: RefCell<Vec<Subscription>>,
+    hot: Cell<Option<usize>>,
+    // The item the mouse was pressed on
+    pressed: Cell<Option<usize>>,
+}
+
+impl SubControlRef for ToolBarData {
+    fn sub_control_ref(&self) -> &SubControlData { &self.sub_control }
+
+    fn best_size(&self) -> Size2<f64> {
+        let items = self.items.borrow();
+        let width = items.iter().map(|item| self.item_size(item).width).sum::<f64>();
+        let height = items.iter().map(|item| self.item_size(item).height).fold(0.0, f64::max);
+        Size2::new(width.ceil(), height.max(MIN_ITEM_SIZE).ceil())
+    }
+}
+
+impl EventHandler for ToolBarData {
+    fn on_event(&self, route: &mut EventRoute) {
+        if let Some(MouseDownEvent { location, button: MouseButton::Left, .. }) =
+            route.event.downcast_mut()
+        {
+            let index = self.item_at(location.x).filter(|&i| self.is_item_enabled(i));
+            self.pressed.set(index);
+            self.repaint_later();
+            route.handled = true;
+        } else if let Some(MouseUpEvent { location, button: MouseButton::Left }) =
+            route.event.downcast_mut()
+        {
+            let pressed = self.pressed.take();
+            self.repaint_later();
+            // Releasing off the pressed item cancels the click, like a button.
+            if pressed.is_some() && pressed == self.item_at(location.x) {
+                let item = self.items.borrow()[pressed.unwrap()].clone();
+                if let ToolBarItem::Action(action) = item {
+                    action.trigger();
+                }
+            }
+        } else if let Some(MouseMovedEvent { location }) = route.event.downcast_mut() {
+            self.set_hot(self.item_at(location.x));
+        } else if let Some(MouseDraggedEvent { location }) = route.event.downcast_mut() {
+            self.set_hot(self.item_at(location.x));
+        } else if let Some(MouseLeftEvent) = route.event.downcast_mut() {
+            self.set_hot(None);
+        } else if let Some(AttachedActionChangedEvent { .. }) = route.event.downcast_mut() {
+            self.repaint_later();
+        } else if let Some(PaintingEvent { painter }) = route.event.downcast_mut() {
+            self.paint(&mut **painter);
+        }
+    }
+}
+
+impl ToolBarData {
+    pub fn new() -> Self {
+        ToolBarData {
+            sub_control: SubControlData::new(),
+            items: RefCell::new(vec![]),
+            subscriptions: RefCell::new(vec![]),
+            hot: Cell::new(None),
+            pressed: Cell::new(None),
+        }
+    }
+
+    /// Adds a button at the end of the tool bar that triggers the action.
+    pub fn add_action(&self, action: &Action) {
+        self.items.borrow_mut().push(ToolBarItem::Action(action.clone()));
+        let subscribed = self.subscriptions.borrow().iter().any(|s| s.is_to(action));
+        if !subscribed {
+            self.subscriptions.borrow_mut().extend(notify_when_changed(action, self));
+        }
+        self.repaint_later();
+    }
+
+    /// Adds a line separating groups of buttons at the end of the tool bar.
+    pub fn add_separator(&self) {
+        self.items.borrow_mut().push(ToolBarItem::Separator);
+        self.repaint_later();
+    }
+
+    /// Returns the actions of the tool bar's buttons, in order.
+    pub fn actions(&self) -> Vec<Action> {
+        self.items.borrow().iter().filter_map(|item| match item {
+            ToolBarItem::Action(action) => Some(action.clone()),
+            ToolBarItem::Separator => None,
+        }).collect()
+    }
+
+    /// Removes all buttons and separators.
+    pub fn clear(&self) {
+        self.subscriptions.borrow_mut().clear();
+        self.items.borrow_mut().clear();
+        self.hot.set(None);
+        self.pressed.set(None);
+        self.repaint_later();
+    }
+
+    fn item_size(&self, item: &ToolBarItem) -> Size2<f64> {
+        let action = match item {
+            ToolBarItem::Action(action) => action,
+            ToolBarItem::Separator => return Size2::new(SEPARATOR_WIDTH, 0.0),
+        };
+        let content = match action.icon() {
+            Some(icon) => Size2::new(icon.width() as f64, icon.height() as f64),
+            None => {
+                let text = MnemonicText::parse(&action.text()).text;
+                let layout = theme::layout_line(
+                    theme::formatted_string(&text, theme::text_color(true)));
+                theme::text_size(&layout)
+            }
+        };
+        Size2::new(
+            (content.width + ITEM_PADDING * 2.0).max(MIN_ITEM_SIZE),
+            (content.height + ITEM_PADDING * 2.0).max(MIN_ITEM_SIZE),
+        )
+    }
+
+    // Returns the left edge and width of each item.
+    fn item_bounds(&self) -> Vec<(f64, f64)> {
+        let mut left = 0.0;
+        self.items.borrow().iter().map(|item| {
+            let width = self.item_size(item).width;
+            left += width;
+            (left - width, width)
+        }).collect()
+    }
+
+    fn item_at(&self, x: f64) -> Option<usize> {
+        self.item_bounds().iter().position(|&(left, width)| x >= left && x < left + width)
+            .filter(|&i| match self.items.borrow()[i] {
+                ToolBarItem::Action(_) => true,
+                ToolBarItem::Separator => false,
+            })
+    }
+
+    fn is_item_enabled(&self, index: usize) -> bool {
+        match &self.items.borrow()[index] {
+            ToolBarItem::Action(action) => action.enabled() && self.is_effectively_enabled(),
+            ToolBarItem::Separator => false,
+        }
+    }
+
+    fn set_hot(&self, index: Option<usize>) {
+        if self.hot.replace(index) != index {
+            self.repaint_later();
+        }
+    }
+
+    fn paint(&self, painter: &mut dyn Painter) {
+        let height = self.size().height;
+        let items = self.items.borrow().clone();
+        for (i, (item, &(left, width))) in items.iter().zip(self.item_bounds().iter()).enumerate() {
+            let (l, r) = (left as f32, (left + width) as f32);
+            let action = match item {
+                ToolBarItem::Action(action) => action,
+                ToolBarItem::Separator => {
+                    let x = (left + width / 2.0).floor() as f32;
+                    painter.fill_path(
+                        &mut theme::rect_path(x, 3.0, x + 1.0, height as f32 - 3.0).path_iter(),
+                        &Brush::Solid(Color::from_rgba(215, 215, 215, 255)),
+                    );
+                    continue;
+                }
+            };
+            let enabled = self.is_item_enabled(i);
+            let pushed = enabled && self.pressed.get() == Some(i) && self.hot.get() == Some(i);
+            let colors = if pushed || action.checked() {
+                Some((Color::from_rgba(204, 232, 255, 255), Color::from_rgba(153, 209, 255, 255)))
+            } else if enabled && self.hot.get() == Some(i) {
+                Some((Color::from_rgba(229, 243, 255, 255), Color::from_rgba(204, 232, 255, 255)))
+            } else {
+                None
+            };
+            if let Some((fill, border)) = colors {
+                painter.fill_path(
+                    &mut theme::rect_path(l, 0.0, r, height as f32).path_iter(),
+                    &Brush::Solid(fill),
+                );
+                painter.stroke_path(
+                    &mut theme::rect_path(l + 0.5, 0.5, r - 0.5, height as f32 - 0.5).path_iter(),
+                    &Brush::Solid(border),
+                    &StrokeStyle::with_width(1.0),
+                );
+            }
+
+            // TODO: paint disabled icons grayed out
+            if let Some(icon) = action.icon() {
+                let (w, h) = (icon.width() as f64, icon.height() as f64);
+                let x = (left + (width - w) / 2.0).round();
+                let y = ((height - h) / 2.0).round();
+                painter.draw_image(&icon, Rect::new(x as f32, y as f32, w as f32, h as f32));
+            } else {
+                let text = MnemonicText::parse(&action.text()).text;
+                let layout = theme::layout_line(
+                    theme::formatted_string(&text, theme::text_color(enabled)));
+                let size = theme::text_size(&layout);
+                painter.save();
+                painter.translate(
+                    (left + (width - size.width) / 2.0).round(),
+                    ((height - size.height) / 2.0).round(),
+                );
+                layout.draw(painter);
+                painter.restore();
+            }
+        }
+    }
+}
+
+impl Default for ToolBarData {
+    fn default() -> Self {
+        Self::new()
+    }
+}
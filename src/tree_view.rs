@@ -0,0 +1,630 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ *
+ */
+
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeSet;
+use std::ops::{Deref, Range};
+use std::rc::Rc;
+
+use zaffre::{Brush, Color, Painter, PathBuf, Point2, Rect, Size2, StrokeStyle};
+
+use crate::control::{
+    control_handle, set_focus, Control, FocusGainedEvent, FocusLostEvent, MouseButton,
+    PaintingEvent, SizeChangedEvent, SubControl, SubControlData, SubControlRef,
+};
+use crate::event_vec::{EventHandler, EventRoute};
+use crate::keyboard::{Key, KeyDownEvent, Modifiers};
+use crate::list_view::{forward_panel_mouse_events, PanelMouseEvent, SelectionChangedEvent};
+use crate::scroll_view::{ScrollBarVisibility, ScrollView};
+use crate::theme;
+
+// TODO: generate with a proc macro
+// start proc macro generated
+#[derive(Clone)]
+pub struct TreeView(Rc<TreeViewData>);
+
+impl TreeView {
+    pub fn new() -> Self {
+        SubControl::register_handle(TreeView(Rc::new(TreeViewData::new())))
+    }
+}
+
+impl Default for TreeView {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deref for TreeView {
+    type Target = Rc<TreeViewData>;
+    fn deref(&self) -> &Rc<TreeViewData> {
+        &self.0
+    }
+}
+
+impl From<TreeView> for Rc<dyn Control> {
+    fn from(self_: TreeView) -> Self {
+        self_.0 as Rc<dyn Control>
+    }
+}
+
+impl From<TreeView> for Rc<dyn EventHandler> {
+    fn from(self_: TreeView) -> Self {
+        self_.0 as Rc<dyn EventHandler>
+    }
+}
+// end proc macro generated
+
+/// Provides the nodes shown in a `TreeView`.
+///
+/// A node is identified by its path: the index of its top-level ancestor followed by the index of
+/// each descendant under it down to the node. The empty path is the invisible root that the
+/// top-level nodes are children of.
+pub trait TreeModel {
+    /// Returns the number of children of the node. It is only called for the root and for nodes
+    /// that are expanded, so children can be loaded the first time they're shown.
+    fn child_count(&self, path: &[usize]) -> usize;
+
+    /// Returns whether the node has any children, which decides whether it has an expander. The
+    /// default implementation calls `child_count()`. Models that load children lazily should
+    /// override it with something that doesn't load them.
+    fn has_children(&self, path: &[usize]) -> bool {
+        self.child_count(path) > 0
+    }
+
+    fn text(&self, path: &[usize]) -> String;
+}
+
+/// Sent to a tree view after a node is expanded or collapsed, whether by the user or by code.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct ExpandedChangedEvent {
+    pub path: Vec<usize>,
+    pub expanded: bool,
+}
+
+// A node that is shown because all of its ancestors are expanded
+struct Row {
+    path: Vec<usize>,
+    has_children: bool,
+}
+
+// The distance each level is indented, and the width of the space at the start of each row that
+// toggles the node when clicked
+const INDENT: f64 = 19.0;
+const EXPANDER_WIDTH: f64 = 16.0;
+const TEXT_GAP: f64 = 3.0;
+const ROW_PADDING_Y: f64 = 2.0;
+const DEFAULT_WIDTH: f64 = 200.0;
+const DEFAULT_HEIGHT: f64 = 150.0;
+
+/// A scrolling tree of nodes provided by a `TreeModel`, which can be expanded to show their
+/// children.
+///
+/// Only one node can be selected. The nodes in view are painted directly rather than having a
+/// control each, so large trees don't use memory for every node.
+//#[dynamin::control]
+pub struct TreeViewData {
+    sub_control: SubControlData,
+    scroll_view: ScrollView,
+    // The content of the scroll view, which is as tall as all the rows
+    panel: SubControl,
+    model: RefCell<Option<Rc<dyn TreeModel>>>,
+    rows: RefCell<Vec<Row>>,
+    // Nodes stay in this set when an ancestor is collapsed, so that they are expanded again when
+    // it is.
+    expanded: RefCell<BTreeSet<Vec<usize>>>,
+    selected: RefCell<Option<Vec<usize>>>,
+    row_height: Cell<f64>,
+}
+
+impl SubControlRef for TreeViewData {
+    fn sub_control_ref(&self) -> &SubControlData { &self.sub_control }
+
+    fn best_size(&self) -> Size2<f64> {
+        Size2::new(DEFAULT_WIDTH, DEFAULT_HEIGHT)
+    }
+}
+
+impl EventHandler for TreeViewData {
+    fn on_event(&self, route: &mut EventRoute) {
+        if let Some(PanelMouseEvent { location, button: Some(button), .. }) =
+            route.event.downcast_mut()
+        {
+            self.on_mouse_down(*location, *button);
+            route.handled = true;
+        } else if let Some(event) = route.event.downcast_mut::<KeyDownEvent>() {
+            if self.on_key_down(event.key, event.modifiers) {
+                route.handled = true;
+            }
+        } else if let Some(FocusGainedEvent) = route.event.downcast_mut() {
+            self.repaint_later();
+        } else if let Some(FocusLostEvent) = route.event.downcast_mut() {
+            self.repaint_later();
+        } else if let Some(SizeChangedEvent) = route.event.downcast_mut() {
+            self.layout();
+        } else if let Some(PaintingEvent { painter }) = route.event.downcast_mut() {
+            self.paint(&mut **painter);
+        }
+    }
+}
+
+impl TreeViewData {
+    pub fn new() -> Self {
+        let sub_control = SubControlData::new();
+        sub_control.set_focusable(true);
+        let line = theme::layout_line(theme::formatted_string("Xg", theme::text_color(true)));
+        TreeViewData {
+            sub_control,
+            scroll_view: ScrollView::new(),
+            panel: SubControl::new(),
+            model: RefCell::new(None),
+            rows: RefCell::new(vec![]),
+            expanded: RefCell::new(BTreeSet::new()),
+            selected: RefCell::new(None),
+            row_height: Cell::new((theme::text_size(&line).height + ROW_PADDING_Y * 2.0).ceil()),
+        }
+    }
+
+    pub fn model(&self) -> Option<Rc<dyn TreeModel>> {
+        self.model.borrow().clone()
+    }
+
+    /// Sets where the nodes come from. All nodes are collapsed and the selection is cleared.
+    pub fn set_model(&self, model: Option<Rc<dyn TreeModel>>) {
+        self.add_children();
+        *self.model.borrow_mut() = model;
+        self.expanded.borrow_mut().clear();
+        self.scroll_view.set_scroll_offset(Point2::new(0.0, 0.0));
+        self.reload();
+    }
+
+    /// Asks the model for the nodes again after they change. Nodes that were expanded stay
+    /// expanded if they still have children, and the selection is cleared if the selected node
+    /// no longer exists.
+    pub fn reload(&self) {
+        let mut rows = vec![];
+        if let Some(model) = self.model() {
+            self.add_child_rows(&*model, &[], &mut rows);
+        }
+        *self.rows.borrow_mut() = rows;
+        let selected = self.selected.borrow().clone();
+        if let Some(selected) = selected {
+            if self.row_index(&selected).is_none() {
+                self.set_selected_path(None);
+            }
+        }
+        self.layout();
+        self.repaint_later();
+    }
+
+    pub fn is_expanded(&self, path: &[usize]) -> bool {
+        self.expanded.borrow().contains(path)
+    }
+
+    /// Shows the children of the node. If an ancestor of the node is collapsed, the node's
+    /// children are shown when the ancestor is expanded.
+    pub fn expand(&self, path: &[usize]) {
+        let model = match self.model() {
+            Some(model) => model,
+            None => return,
+        };
+        if path.is_empty() || !self.expanded.borrow_mut().insert(path.to_vec()) {
+            return;
+        }
+        if let Some(index) = self.row_index(path) {
+            let mut child_rows = vec![];
+            self.add_child_rows(&*model, path, &mut child_rows);
+            let mut rows = self.rows.borrow_mut();
+            rows.splice(index + 1..index + 1, child_rows);
+        }
+        self.event_handlers().send(&mut ExpandedChangedEvent {
+            path: path.to_vec(),
+            expanded: true,
+        });
+        self.layout();
+        self.repaint_later();
+    }
+
+    /// Hides the children of the node. If the selected node is one of them, the node is selected
+    /// instead.
+    pub fn collapse(&self, path: &[usize]) {
+        if !self.expanded.borrow_mut().remove(path) {
+            return;
+        }
+        if let Some(index) = self.row_index(path) {
+            let range = self.descendant_rows(index);
+            self.rows.borrow_mut().drain(range);
+        }
+        let selected_inside = self.selected.borrow().as_ref()
+            .map_or(false, |s| s.len() > path.len() && s.starts_with(path));
+        if selected_inside {
+            self.set_selected_path(Some(path));
+        }
+        self.event_handlers().send(&mut ExpandedChangedEvent {
+            path: path.to_vec(),
+            expanded: false,
+        });
+        self.layout();
+        self.repaint_later();
+    }
+
+    /// Expands the node if it is collapsed and collapses it if it is expanded.
+    pub fn toggle(&self, path: &[usize]) {
+        if self.is_expanded(path) {
+            self.collapse(path);
+        } else {
+            self.expand(path);
+        }
+    }
+
+    pub fn selected_path(&self) -> Option<Vec<usize>> {
+        self.selected.borrow().clone()
+    }
+
+    /// Selects the node, expanding its ancestors so that it is shown, and scrolls it into view.
+    pub fn set_selected_path(&self, path: Option<&[usize]>) {
+        if let Some(path) = path {
+            for len in 1..path.len() {
+                self.expand(&path[..len]);
+            }
+            if self.row_index(path).is_none() {
+                return;
+            }
+        }
+        let path = path.map(|p| p.to_vec());
+        if *self.selected.borrow() == path {
+            return;
+        }
+        *self.selected.borrow_mut() = path.clone();
+        if let Some(path) = path {
+            self.scroll_to_node(&path);
+        }
+        self.event_handlers().send(&mut SelectionChangedEvent);
+        self.repaint_later();
+    }
+
+    /// Scrolls as little as possible to make the node's row entirely visible, if it is shown.
+    pub fn scroll_to_node(&self, path: &[usize]) {
+        let index = match self.row_index(path) {
+            Some(index) => index,
+            None => return,
+        };
+        let height = self.row_height.get();
+        let top = index as f64 * height;
+        let offset = self.scroll_view.scroll_offset();
+        let viewport_height = self.scroll_view.viewport_size().height;
+        if top < offset.y {
+            self.scroll_view.set_scroll_offset(Point2::new(offset.x, top));
+        } else if top + height > offset.y + viewport_height {
+            let bottom_offset = top + height - viewport_height;
+            self.scroll_view.set_scroll_offset(Point2::new(offset.x, bottom_offset));
+        }
+    }
+
+    // Convenience method to add an event handler that is called for `SelectionChangedEvent`s.
+    pub fn on_selection_changed_event<F>(&self, mut handler: F)
+        where F: for<'a> FnMut(&'a mut EventRoute) + 'static
+    {
+        self.event_handlers().add(move |mut route| {
+            if let Some(_) = route.event.downcast_mut::<SelectionChangedEvent>() {
+                handler(&mut route);
+            }
+        });
+    }
+
+    // Convenience method to add an event handler that is called for `ExpandedChangedEvent`s.
+    pub fn on_expanded_changed_event<F>(&self, mut handler: F)
+        where F: for<'a> FnMut(&'a mut EventRoute) + 'static
+    {
+        self.event_handlers().add(move |mut route| {
+            if let Some(_) = route.event.downcast_mut::<ExpandedChangedEvent>() {
+                handler(&mut route);
+            }
+        });
+    }
+
+    /// Sets the size of the scroll view and of the panel in it to fit the shown nodes. This is
+    /// done automatically when the tree view's size changes and when nodes are shown or hidden.
+    pub fn layout(&self) {
+        self.scroll_view.set_location(&Point2::new(0.0, 0.0));
+        self.scroll_view.set_size(&self.size());
+        let height = self.rows.borrow().len() as f64 * self.row_height.get();
+        // The scroll view stretches the panel to the width of the view.
+        self.panel.set_size(&Size2::new(0.0, height));
+        self.scroll_view.layout();
+    }
+
+    // Children can't be added in `new()`, before the handle is registered.
+    fn add_children(&self) {
+        if self.scroll_view.parent().is_some() {
+            return;
+        }
+        self.children().borrow_mut().push(self.scroll_view.clone());
+        self.scroll_view.set_horizontal_scroll_bar(ScrollBarVisibility::Never);
+        self.scroll_view.set_content(Some(self.panel.clone().into()));
+        forward_panel_mouse_events(&self.panel, self);
+    }
+
+    // Adds rows for the children of the node, and for their children if they are expanded.
+    fn add_child_rows(&self, model: &dyn TreeModel, path: &[usize], rows: &mut Vec<Row>) {
+        for i in 0..model.child_count(path) {
+            let mut child_path = path.to_vec();
+            child_path.push(i);
+            let has_children = model.has_children(&child_path);
+            let expanded = has_children && self.expanded.borrow().contains(&child_path);
+            rows.push(Row { path: child_path.clone(), has_children });
+            if expanded {
+                self.add_child_rows(model, &child_path, rows);
+            }
+        }
+    }
+
+    fn row_index(&self, path: &[usize]) -> Option<usize> {
+        self.rows.borrow().iter().position(|row| row.path == path)
+    }
+
+    // Returns the range of rows after the row at `index` that are its descendants.
+    fn descendant_rows(&self, index: usize) -> Range<usize> {
+        let rows = self.rows.borrow();
+        let depth = rows[index].path.len();
+        let end = rows[index + 1..].iter().position(|row| row.path.len() <= depth)
+            .map_or(rows.len(), |i| index + 1 + i);
+        index + 1..end
+    }
+
+    // Returns the indexes of the rows that are at least partly in view.
+    fn visible_rows(&self) -> Range<usize> {
+        let height = self.row_height.get();
+        let top = self.scroll_view.scroll_offset().y;
+        let bottom = top + self.scroll_view.viewport_size().height;
+        let len = self.rows.borrow().len();
+        let end = ((bottom / height).ceil() as usize).min(len);
+        ((top / height).floor() as usize).min(end)..end
+    }
+
+    fn on_mouse_down(&self, location: Point2<f64>, button: MouseButton) {
+        if let Some(handle) = control_handle(self) {
+            set_focus(&handle);
+        }
+        if location.y < 0.0 {
+            return;
+        }
+        let index = (location.y / self.row_height.get()) as usize;
+        let (path, has_children) = match self.rows.borrow().get(index) {
+            Some(row) => (row.path.clone(), row.has_children),
+            None => return,
+        };
+        let expander_left = (path.len() - 1) as f64 * INDENT;
+        let on_expander =
+            location.x >= expander_left && location.x < expander_left + EXPANDER_WIDTH;
+        if button == MouseButton::Left && on_expander && has_children {
+            self.toggle(&path);
+        } else {
+            self.set_selected_path(Some(&path));
+        }
+    }
+
+    // Returns whether the key was handled.
+    fn on_key_down(&self, key: Key, modifiers: Modifiers) -> bool {
+        if modifiers != Modifiers::none() {
+            return false;
+        }
+        let len = self.rows.borrow().len();
+        if len == 0 {
+            return false;
+        }
+        let selected = self.selected.borrow().clone();
+        let current = selected.as_ref().and_then(|s| self.row_index(s));
+        // Right expands the node, or moves to its first child if it is already expanded. Left
+        // collapses it, or moves to its parent if it is already collapsed.
+        if let (Some(index), Key::Right) | (Some(index), Key::Left) = (current, key) {
+            let (path, has_children) = {
+                let row = &self.rows.borrow()[index];
+                (row.path.clone(), row.has_children)
+            };
+            let expanded = self.is_expanded(&path);
+            if key == Key::Right && has_children && !expanded {
+                self.expand(&path);
+            } else if key == Key::Right && has_children {
+                let mut child = path;
+                child.push(0);
+                self.set_selected_path(Some(&child));
+            } else if key == Key::Left && has_children && expanded {
+                self.collapse(&path);
+            } else if key == Key::Left && path.len() > 1 {
+                self.set_selected_path(Some(&path[..path.len() - 1]));
+            }
+            return true;
+        }
+        let page_len = ((self.scroll_view.viewport_size().height / self.row_height.get()) as usize)
+            .max(1);
+        let target = match key {
+            Key::Up => current.map_or(0, |i| i.saturating_sub(1)),
+            Key::Down => current.map_or(0, |i| (i + 1).min(len - 1)),
+            Key::Home => 0,
+            Key::End => len - 1,
+            Key::PageUp => current.map_or(0, |i| i.saturating_sub(page_len)),
+            Key::PageDown => current.map_or(0, |i| (i + page_len).min(len - 1)),
+            _ => return false,
+        };
+        let path = self.rows.borrow()[target].path.clone();
+        self.set_selected_path(Some(&path));
+        true
+    }
+
+    fn paint(&self, painter: &mut dyn Painter) {
+        let size = self.size();
+        // Rows partly scrolled out of view would be painted outside the tree view.
+        painter.clip_rect(Rect::new(0.0, 0.0, size.width as f32, size.height as f32));
+        painter.fill_path(
+            &mut theme::rect_path(0.0, 0.0, size.width as f32, size.height as f32).path_iter(),
+            &Brush::Solid(Color::from_rgba(255, 255, 255, 255)),
+        );
+        let model = match self.model() {
+            Some(model) => model,
+            None => return,
+        };
+
+        let enabled = self.is_effectively_enabled();
+        let focused = self.focused();
+        let height = self.row_height.get();
+        let offset = self.scroll_view.scroll_offset().y;
+        let width = self.scroll_view.viewport_size().width as f32;
+        let selected = self.selected.borrow();
+        let rows = self.rows.borrow();
+        for index in self.visible_rows() {
+            let row = &rows[index];
+            let top = (index as f64 * height - offset) as f32;
+            if selected.as_ref() == Some(&row.path) {
+                painter.fill_path(
+                    &mut theme::rect_path(0.0, top, width, top + height as f32).path_iter(),
+                    &Brush::Solid(theme::item_selection_color(focused)),
+                );
+                if focused {
+                    painter.stroke_path(
+                        &mut theme::rect_path(
+                            0.5,
+                            top + 0.5,
+                            width - 0.5,
+                            top + height as f32 - 0.5,
+                        )
+                        .path_iter(),
+                        &Brush::Solid(theme::item_focus_color()),
+                        &StrokeStyle::with_width(1.0),
+                    );
+                }
+            }
+
+            let expander_left = (row.path.len() - 1) as f64 * INDENT;
+            if row.has_children {
+                let center = Point2::new(
+                    (expander_left + EXPANDER_WIDTH / 2.0) as f32,
+                    top + (height / 2.0) as f32,
+                );
+                paint_expander(painter, center, self.is_expanded(&row.path), enabled);
+            }
+
+            let layout = theme::layout_line(
+                theme::formatted_string(&model.text(&row.path), theme::text_color(enabled)));
+            let text_top = ((height - theme::text_size(&layout).height) / 2.0).round();
+            painter.save();
+            painter.translate(expander_left + EXPANDER_WIDTH + TEXT_GAP, top as f64 + text_top);
+            layout.draw(painter);
+            painter.restore();
+        }
+    }
+}
+
+// Windows shows a chevron pointing right or down, and GTK shows a filled triangle.
+#[cfg(windows)]
+fn paint_expander(painter: &mut dyn Painter, center: Point2<f32>, expanded: bool, enabled: bool) {
+    let color = if !enabled {
+        Color::from_rgba(191, 191, 191, 255)
+    } else if expanded {
+        Color::from_rgba(38, 38, 38, 255)
+    } else {
+        Color::from_rgba(166, 166, 166, 255)
+    };
+    let mut path = PathBuf::new();
+    if expanded {
+        path.move_to(Point2::new(center.x - 4.0, center.y - 2.0));
+        path.line_to(Point2::new(center.x, center.y + 2.0));
+        path.line_to(Point2::new(center.x + 4.0, center.y - 2.0));
+    } else {
+        path.move_to(Point2::new(center.x - 2.0, center.y - 4.0));
+        path.line_to(Point2::new(center.x + 2.0, center.y));
+        path.line_to(Point2::new(center.x - 2.0, center.y + 4.0));
+    }
+    painter.stroke_path(&mut path.path_iter(), &Brush::Solid(color), &StrokeStyle::with_width(1.0));
+}
+
+#[cfg(not(windows))]
+fn paint_expander(painter: &mut dyn Painter, center: Point2<f32>, expanded: bool, enabled: bool) {
+    let color = if enabled {
+        Color::from_rgba(46, 52, 54, 255)
+    } else {
+        Color::from_rgba(139, 142, 143, 255)
+    };
+    let mut path = PathBuf::new();
+    if expanded {
+        path.move_to(Point2::new(center.x - 4.0, center.y - 2.0));
+        path.line_to(Point2::new(center.x + 4.0, center.y - 2.0));
+        path.line_to(Point2::new(center.x, center.y + 2.0));
+    } else {
+        path.move_to(Point2::new(center.x - 2.0, center.y - 4.0));
+        path.line_to(Point2::new(center.x + 2.0, center.y));
+        path.line_to(Point2::new(center.x - 2.0, center.y + 4.0));
+    }
+    path.close();
+    painter.fill_path(&mut path.path_iter(), &Brush::Solid(color));
+}
+
+impl Default for TreeViewData {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[test]
+fn test_tree_view_lazy_expansion() {
+    // Each node has three children, down to a depth of three, and records which nodes it was
+    // asked to load the children of.
+    struct Model {
+        loaded: RefCell<Vec<Vec<usize>>>,
+    }
+    impl TreeModel for Model {
+        fn child_count(&self, path: &[usize]) -> usize {
+            self.loaded.borrow_mut().push(path.to_vec());
+            if path.len() < 3 { 3 } else { 0 }
+        }
+        fn has_children(&self, path: &[usize]) -> bool {
+            path.len() < 3
+        }
+        fn text(&self, path: &[usize]) -> String {
+            format!("{:?}", path)
+        }
+    }
+
+    let tree = TreeView::new();
+    tree.set_size(&Size2::new(200.0, 300.0));
+    let model = Rc::new(Model { loaded: RefCell::new(vec![]) });
+    tree.set_model(Some(model.clone()));
+    assert_eq!(*model.loaded.borrow(), vec![vec![]]);
+    assert_eq!(tree.rows.borrow().len(), 3);
+
+    let key = |key| {
+        let modifiers = Modifiers::none();
+        tree.event_handlers().send(&mut KeyDownEvent { key, modifiers, repeat: false });
+    };
+    key(Key::Down);
+    key(Key::Down);
+    key(Key::Right);
+    assert!(tree.is_expanded(&[1]));
+    assert_eq!(*model.loaded.borrow(), vec![vec![], vec![1]]);
+    key(Key::Right);
+    assert_eq!(tree.selected_path(), Some(vec![1, 0]));
+    key(Key::Right);
+    key(Key::Down);
+    assert_eq!(tree.selected_path(), Some(vec![1, 0, 0]));
+    assert_eq!(tree.rows.borrow().len(), 9);
+
+    // Collapsing an ancestor selects it, and expanding it again remembers the expanded child.
+    tree.collapse(&[1]);
+    assert_eq!(tree.selected_path(), Some(vec![1]));
+    assert_eq!(tree.rows.borrow().len(), 3);
+    key(Key::Right);
+    assert_eq!(tree.rows.borrow().len(), 9);
+    key(Key::Left);
+    key(Key::Left);
+    assert_eq!(tree.selected_path(), Some(vec![1]));
+    assert!(!tree.is_expanded(&[1]));
+
+    // The scroll view is laid out again as soon as the tree is resized.
+    tree.set_size(&Size2::new(150.0, 40.0));
+    assert_eq!(tree.scroll_view.size(), Size2::new(150.0, 40.0));
+}
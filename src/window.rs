@@ -11,10 +11,15 @@ use std::rc::{Weak, Rc};
 
 use zaffre::{Point2, Size2};
 
+use crate::action::Action;
 use crate::generic_backend::GenericWindowBackend;
 use crate::backend::WindowBackend;
-use crate::{ChildrenVec, Control, EventHandlerVec, Visibility};
-use crate::control::{PaintingEvent, PrivControl};
+use crate::{Children, Control, EventHandlerVec, Visibility};
+use crate::button::{Button, ButtonData};
+use crate::control::{
+    notify_enabled_changed, PaintingEvent, PrivControl, send_visibility_changed,
+    VisibilityChangedEvent,
+};
 
 // TODO: screenshots of border styles
 /// The style of border around a window.
@@ -44,24 +49,61 @@ impl Deref for Window {
 // Can't implement `Clone` without cloning the native handle.
 pub struct WindowData<B: GenericWindowBackend = WindowBackend> {
     pub(crate) backend: B,
-    children: RefCell<ChildrenVec>,
+    children: Children,
     event_handlers: EventHandlerVec,
+    default_button: RefCell<Option<Weak<ButtonData>>>,
+    cancel_button: RefCell<Option<Weak<ButtonData>>>,
+    owner: RefCell<Option<Weak<WindowData>>>,
+    popup: bool,
+    accelerators: RefCell<Vec<Action>>,
 }
 
 #[non_exhaustive]
 pub enum WindowEvent {
     // Triggered when the user clicks the close button on the window.
     Closing,
+    // Triggered when a popup is hidden because the user clicked outside it.
+    PopupDismissed,
+}
+
+thread_local! {
+    // The visible popups, in the order they were shown
+    static OPEN_POPUPS: RefCell<Vec<Weak<WindowData>>> = RefCell::new(vec![]);
 }
 
 impl Window {
     pub fn new() -> Window {
+        Self::create(false)
+    }
+
+    /// Creates a popup window, such as the dropdown of a combo box.
+    ///
+    /// A popup has no border, isn't shown in the taskbar, and doesn't take activation from the
+    /// window that was active when it was shown, so that window keeps getting keyboard input. Its
+    /// owner should be set with `set_owner()` so that it stays above the owner.
+    ///
+    /// It is hidden and sent `WindowEvent::PopupDismissed` when a mouse button is pressed outside
+    /// it, unless it is pressed in a popup shown after it. It is also dismissed when its owner is
+    /// hidden or another application is activated.
+    pub fn new_popup() -> Window {
+        Self::create(true)
+    }
+
+    fn create(popup: bool) -> Window {
         let handle = Window(Rc::new(WindowData {
             backend: WindowBackend::new(),
-            children: RefCell::new(ChildrenVec::new()),
+            children: Children::new(),
             event_handlers: EventHandlerVec::new(),
+            default_button: RefCell::new(None),
+            cancel_button: RefCell::new(None),
+            owner: RefCell::new(None),
+            popup,
+            accelerators: RefCell::new(vec![]),
         }));
         handle.0.backend.set_window(Rc::downgrade(&handle.0));
+        if popup {
+            handle.0.backend.set_popup(true);
+        }
         let control_handle = handle.0.clone() as Rc<dyn Control>;
         control_handle.children().borrow_mut().control = Some(Rc::downgrade(&control_handle));
         handle
@@ -90,12 +132,79 @@ impl<B: GenericWindowBackend> WindowData<B> {
     pub fn event_handlers(&self) -> &EventHandlerVec {
         &self.event_handlers
     }
+
+    pub fn owner(&self) -> Option<Window> {
+        self.owner.borrow().as_ref().and_then(|o| o.upgrade()).map(Window)
+    }
+
+    /// Sets the window that owns this one. An owned window always stays above its owner and isn't
+    /// shown in the taskbar. Dialogs, tool windows and popups are usually owned by the window they
+    /// were opened from.
+    pub fn set_owner(&self, owner: Option<&Window>) {
+        *self.owner.borrow_mut() = owner.map(|o| Rc::downgrade(&o.0));
+        self.backend.set_owner(owner);
+    }
+
+    /// Returns whether the window was created with `Window::new_popup()`.
+    pub fn is_popup(&self) -> bool {
+        self.popup
+    }
+
+    /// Returns the actions in the window's accelerator table.
+    pub fn accelerators(&self) -> Vec<Action> {
+        self.accelerators.borrow().clone()
+    }
+
+    /// Adds the action to the window's accelerator table. When a key is pressed in the window and
+    /// it matches the shortcut of an enabled action in the table, the action is triggered and the
+    /// focused control doesn't get the key. If several match, the first added is triggered.
+    pub fn add_accelerator(&self, action: &Action) {
+        self.accelerators.borrow_mut().push(action.clone());
+    }
+
+    pub fn remove_accelerator(&self, action: &Action) {
+        self.accelerators.borrow_mut().retain(|a| !a.ptr_eq(action));
+    }
+
+    /// Returns the button that is clicked when Enter is pressed and the focused control doesn't
+    /// handle it.
+    pub fn default_button(&self) -> Option<Button> {
+        self.default_button.borrow().as_ref().and_then(|b| b.upgrade()).map(Button::from_rc)
+    }
+
+    /// Sets the button that is clicked when Enter is pressed and the focused control doesn't
+    /// handle it. It is usually the OK button of a dialog and is painted with a thicker border.
+    pub fn set_default_button(&self, button: Option<&Button>) {
+        let old = self.default_button.replace(button.map(|b| Rc::downgrade(&**b)));
+        if let Some(old) = old.and_then(|b| b.upgrade()) {
+            old.repaint_later();
+        }
+        if let Some(button) = button {
+            button.repaint_later();
+        }
+    }
+
+    /// Returns the button that is clicked when Escape is pressed and the focused control doesn't
+    /// handle it.
+    pub fn cancel_button(&self) -> Option<Button> {
+        self.cancel_button.borrow().as_ref().and_then(|b| b.upgrade()).map(Button::from_rc)
+    }
+
+    /// Sets the button that is clicked when Escape is pressed and the focused control doesn't
+    /// handle it. It is usually the Cancel button of a dialog.
+    pub fn set_cancel_button(&self, button: Option<&Button>) {
+        *self.cancel_button.borrow_mut() = button.map(|b| Rc::downgrade(&**b));
+    }
 }
 
 impl PrivControl for WindowData {
-    fn set_parent(&self, _parent: Weak<dyn Control>) {
+    fn set_parent(&self, _parent: Option<Weak<dyn Control>>) {
         panic!("a window does not have a parent")
     }
+
+    fn set_focused(&self, _focused: bool) {
+        panic!("a window can't be the focused control")
+    }
 }
 
 impl Control for WindowData {
@@ -108,11 +217,41 @@ impl Control for WindowData {
     }
 
     fn set_visibility(&self, visibility: Visibility) {
-        self.backend.set_visibility(visibility)
+        let old_visibility = self.backend.visibility();
+        self.backend.set_visibility(visibility);
+        if old_visibility == visibility {
+            return;
+        }
+        let effectively_visible = visibility == Visibility::Visible;
+        if self.popup {
+            let window = Rc::downgrade(&self.backend.window().0);
+            OPEN_POPUPS.with(|popups| {
+                let mut popups = popups.borrow_mut();
+                popups.retain(|p| !p.ptr_eq(&window));
+                if effectively_visible {
+                    popups.push(window);
+                }
+            });
+        }
+        if !effectively_visible {
+            let window = self.backend.window();
+            let owned = open_popups().into_iter()
+                .filter(|p| p.owner().map_or(false, |o| Rc::ptr_eq(&o.0, &window.0)));
+            for popup in owned.collect::<Vec<_>>().iter().rev() {
+                dismiss_popup(popup);
+            }
+        }
+        self.event_handlers.send(&mut VisibilityChangedEvent { visibility, effectively_visible });
+        if (old_visibility == Visibility::Visible) != effectively_visible {
+            let children = self.children.borrow().to_vec();
+            for child in children.iter() {
+                send_visibility_changed(child, effectively_visible);
+            }
+        }
     }
 
     fn location(&self) -> Point2<f64> {
-        unimplemented!();
+        self.backend.location()
     }
 
     /// Depending on the platform, the location may not be updated immediately (getting the location
@@ -120,20 +259,56 @@ impl Control for WindowData {
     /// what it was set to. Most platforms have limits on where windows can be positioned. For
     /// example, the top edge of a window can't be off screen.
     fn set_location(&self, location: &Point2<f64>) {
+        self.backend.set_location(location);
     }
 
     fn size(&self) -> Size2<f64> {
-        unimplemented!();
+        self.backend.size()
     }
 
     fn tab_index(&self) -> u16 { panic!("a window does not have a tab index") }
 
     fn set_tab_index(&self, _tab_index: u16) { panic!("a window does not have a tab index") }
 
+    fn enabled(&self) -> bool {
+        self.backend.enabled()
+    }
+
+    /// Sets whether the window and its controls accept input. A disabled window can't be
+    /// activated or clicked, so an owner is disabled while a modal dialog is shown.
+    fn set_enabled(&self, enabled: bool) {
+        if self.backend.enabled() == enabled {
+            return;
+        }
+        self.backend.set_enabled(enabled);
+        notify_enabled_changed(self, enabled);
+    }
+
+    fn focusable(&self) -> bool {
+        false
+    }
+
+    fn set_focusable(&self, _focusable: bool) {
+        panic!("a window can't be the focused control")
+    }
+
+    fn focused(&self) -> bool {
+        false
+    }
+
+    fn input_transparent(&self) -> bool {
+        false
+    }
+
+    fn set_input_transparent(&self, _transparent: bool) {
+        panic!("a window can't be transparent to input")
+    }
+
     fn set_size(&self, size: &Size2<f64>) {
+        self.backend.set_size(size);
     }
 
-    fn children(&self) -> &RefCell<ChildrenVec> {
+    fn children(&self) -> &Children {
         &self.children
     }
 
@@ -150,6 +325,7 @@ impl Control for WindowData {
     }
 
     fn repaint_later(&self) {
+        self.backend.invalidate();
     }
 
     fn dispatch_painting(&self, event: &mut PaintingEvent) {
@@ -157,8 +333,171 @@ impl Control for WindowData {
         if let Some(child) = children.first() {
             event.painter.save();
             event.painter.translate(child.location().x, child.location().y);
-            child.event_handlers().send(event);
+            child.dispatch_painting(event);
             event.painter.restore();
         }
     }
 }
+
+// Returns the popups that are visible, from the first shown to the last.
+pub(crate) fn open_popups() -> Vec<Window> {
+    OPEN_POPUPS.with(|popups| {
+        popups.borrow().iter().filter_map(|p| p.upgrade()).map(Window).collect()
+    })
+}
+
+// Hides the popups in `popups` that were shown after the window clicked in, or all of them if it
+// isn't a popup. This is called after a mouse button is pressed, with the popups that were open
+// before, so that pressing the control that shows a popup doesn't immediately hide it.
+pub(crate) fn dismiss_popups(popups: &[Window], clicked: &dyn Control) {
+    let clicked = match clicked.as_window() {
+        Some(window) => window,
+        None => return,
+    };
+    let clicked_index = popups.iter().position(|p| Rc::ptr_eq(&p.0, &clicked.0));
+    let start = clicked_index.map_or(0, |i| i + 1);
+    // The last shown is hidden first, like closing nested menus.
+    for popup in popups[start..].iter().rev() {
+        dismiss_popup(popup);
+    }
+}
+
+// Hides all popups. The backends call this when the user interacts with something outside the
+// application's windows, such as activating another application or pressing a title bar.
+pub(crate) fn dismiss_all_popups() {
+    for popup in open_popups().iter().rev() {
+        dismiss_popup(popup);
+    }
+}
+
+fn dismiss_popup(popup: &Window) {
+    if popup.visibility() == Visibility::Visible {
+        popup.set_visibility(Visibility::Gone);
+        popup.event_handlers().send(&mut WindowEvent::PopupDismissed);
+    }
+}
+
+#[test]
+fn test_set_enabled() {
+    use std::cell::Cell;
+
+    use crate::{EnabledChangedEvent, SubControl};
+
+    let window = Window::new();
+    let child = SubControl::new();
+    child.set_focusable(true);
+    window.set_child(child.clone().into());
+    assert!(crate::set_focus(&child.clone().into()));
+    let enabled = Rc::new(Cell::new(None));
+    let enabled2 = enabled.clone();
+    child.event_handlers().add(move |route| {
+        if let Some(event) = route.event.downcast_mut::<EnabledChangedEvent>() {
+            enabled2.set(Some(event.enabled));
+        }
+    });
+
+    // Disabling the window disables its controls and takes the focus from them.
+    window.set_enabled(false);
+    assert_eq!(enabled.get(), Some(false));
+    assert!(!child.is_effectively_enabled());
+    assert!(crate::focused_control().is_none());
+    window.set_enabled(true);
+    assert_eq!(enabled.get(), Some(true));
+    assert!(child.is_effectively_enabled());
+}
+
+#[test]
+fn test_accelerators() {
+    use std::cell::Cell;
+
+    use crate::keyboard::dispatch_key_down;
+    use crate::{Key, KeyDownEvent, Modifiers, Shortcut, SubControl};
+
+    let window = Window::new();
+    let child = SubControl::new();
+    child.set_focusable(true);
+    window.set_child(child.clone().into());
+    assert!(crate::set_focus(&child.clone().into()));
+    let keys = Rc::new(Cell::new(0));
+    let keys2 = keys.clone();
+    child.event_handlers().add(move |route| {
+        if let Some(_) = route.event.downcast_mut::<KeyDownEvent>() {
+            keys2.set(keys2.get() + 1);
+            route.handled = true;
+        }
+    });
+
+    let save = Action::new("&Save");
+    let control = Modifiers { control: true, ..Modifiers::none() };
+    save.set_shortcut(Some(Shortcut::new(control, Key::S)));
+    let triggered = Rc::new(Cell::new(0));
+    let triggered2 = triggered.clone();
+    save.on_triggered_event(move |_| triggered2.set(triggered2.get() + 1));
+    window.add_accelerator(&save);
+    let key_down = |key, modifiers| {
+        dispatch_key_down(&window, KeyDownEvent { key, modifiers, repeat: false });
+    };
+
+    // The accelerator is matched before the focused control gets the key.
+    key_down(Key::S, control);
+    assert_eq!((triggered.get(), keys.get()), (1, 0));
+    key_down(Key::S, Modifiers::none());
+    assert_eq!((triggered.get(), keys.get()), (1, 1));
+    // A disabled action lets the key through.
+    save.set_enabled(false);
+    key_down(Key::S, control);
+    assert_eq!((triggered.get(), keys.get()), (1, 2));
+    save.set_enabled(true);
+    window.remove_accelerator(&save);
+    key_down(Key::S, control);
+    assert_eq!((triggered.get(), keys.get()), (1, 3));
+}
+
+// Showing windows isn't wanted outside the headless backend.
+#[cfg(feature = "headless")]
+#[test]
+fn test_popup_dismissal() {
+    use crate::control::dispatch_mouse_down;
+    use crate::{Modifiers, MouseButton};
+
+    let owner = Window::new();
+    owner.set_visibility(Visibility::Visible);
+    let popup = Window::new_popup();
+    popup.set_owner(Some(&owner));
+    let submenu = Window::new_popup();
+    submenu.set_owner(Some(&popup));
+    assert!(Rc::ptr_eq(&submenu.owner().unwrap().0, &popup.0));
+
+    let dismissed = Rc::new(RefCell::new(vec![]));
+    for (name, window) in [("popup", &popup), ("submenu", &submenu)].iter() {
+        let (name, dismissed) = (*name, dismissed.clone());
+        window.event_handlers().add(move |route| {
+            if let Some(WindowEvent::PopupDismissed) = route.event.downcast_mut() {
+                dismissed.borrow_mut().push(name);
+            }
+        });
+    }
+    let show = || {
+        popup.set_visibility(Visibility::Visible);
+        submenu.set_visibility(Visibility::Visible);
+    };
+
+    // Pressing in a popup only dismisses the popups shown after it.
+    show();
+    dispatch_mouse_down(&**submenu, Point2::new(1.0, 1.0), MouseButton::Left, Modifiers::none());
+    dispatch_mouse_down(&**popup, Point2::new(1.0, 1.0), MouseButton::Left, Modifiers::none());
+    assert_eq!(*dismissed.borrow(), vec!["submenu"]);
+    assert_eq!(popup.visibility(), Visibility::Visible);
+    // Pressing in the owner dismisses all of them, the last shown first.
+    submenu.set_visibility(Visibility::Visible);
+    dispatch_mouse_down(&**owner, Point2::new(1.0, 1.0), MouseButton::Left, Modifiers::none());
+    assert_eq!(*dismissed.borrow(), vec!["submenu", "submenu", "popup"]);
+
+    dismissed.borrow_mut().clear();
+    show();
+    // Like when another application is activated
+    dismiss_all_popups();
+    show();
+    owner.set_visibility(Visibility::Gone);
+    assert_eq!(*dismissed.borrow(), vec!["submenu", "popup", "submenu", "popup"]);
+}
@@ -0,0 +1,67 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ *
+ */
+
+use std::ptr;
+use std::slice;
+
+use windows::Win32::Foundation::{HANDLE, HWND};
+use windows::Win32::System::DataExchange::{CloseClipboard, EmptyClipboard, GetClipboardData, IsClipboardFormatAvailable, OpenClipboard, SetClipboardData};
+use windows::Win32::System::Memory::{GlobalAlloc, GlobalFree, GlobalLock, GlobalUnlock, GMEM_MOVEABLE};
+use windows::Win32::System::SystemServices::CF_UNICODETEXT;
+
+use crate::generic_backend::GenericClipboardBackend;
+
+pub struct ClipboardBackend;
+
+impl GenericClipboardBackend for ClipboardBackend {
+    fn text() -> Option<String> {
+        unsafe {
+            if !IsClipboardFormatAvailable(CF_UNICODETEXT.0).as_bool() {
+                return None;
+            }
+            if !OpenClipboard(HWND(0)).as_bool() {
+                return None;
+            }
+            let mut text = None;
+            if let Ok(handle) = GetClipboardData(CF_UNICODETEXT.0) {
+                let data = GlobalLock(handle.0) as *const u16;
+                if !data.is_null() {
+                    let mut len = 0;
+                    while *data.add(len) != 0 {
+                        len += 1;
+                    }
+                    text = Some(String::from_utf16_lossy(slice::from_raw_parts(data, len)));
+                    GlobalUnlock(handle.0);
+                }
+            }
+            CloseClipboard();
+            text
+        }
+    }
+
+    fn set_text(text: &str) {
+        let wide: Vec<u16> = text.encode_utf16().chain(Some(0)).collect();
+        unsafe {
+            if !OpenClipboard(HWND(0)).as_bool() {
+                return;
+            }
+            EmptyClipboard();
+            let size = wide.len() * 2;
+            let memory = GlobalAlloc(GMEM_MOVEABLE, size);
+            if memory != 0 {
+                let data = GlobalLock(memory) as *mut u16;
+                ptr::copy_nonoverlapping(wide.as_ptr(), data, wide.len());
+                GlobalUnlock(memory);
+                // The system owns the memory after SetClipboardData() succeeds.
+                if SetClipboardData(CF_UNICODETEXT.0, HANDLE(memory)).is_err() {
+                    GlobalFree(memory);
+                }
+            }
+            CloseClipboard();
+        }
+    }
+}
@@ -5,10 +5,14 @@
  *
  */
 
+pub use self::clipboard_backend::ClipboardBackend;
 pub use self::cursor_backend::CursorBackend;
+pub use self::timer_backend::TimerBackend;
 pub use self::window_backend::WindowBackend;
 
+pub mod clipboard_backend;
 pub mod cursor_backend;
+pub mod timer_backend;
 pub mod window_backend;
 
 use std::ffi::OsStr;
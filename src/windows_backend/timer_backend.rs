@@ -0,0 +1,65 @@
+/*
+ * This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ *
+ */
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::Duration;
+
+use windows::Win32::Foundation::HWND;
+use windows::Win32::UI::WindowsAndMessaging::{KillTimer, SetTimer};
+
+use crate::generic_backend::GenericTimerBackend;
+
+// Timers are created without a window, so the ID returned by SetTimer() is used to find the
+// callback.
+thread_local! {
+    static TIMERS: RefCell<HashMap<usize, Rc<dyn Fn()>>> = RefCell::new(HashMap::new());
+}
+
+#[allow(non_snake_case)]
+unsafe extern "system" fn timerProc(_hwnd: HWND, _uMsg: u32, idEvent: usize, _dwTime: u32) {
+    // Don't hold the borrow while calling the callback, since it may start or stop timers.
+    let callback = TIMERS.with(|timers| timers.borrow().get(&idEvent).cloned());
+    if let Some(callback) = callback {
+        callback();
+    }
+}
+
+pub struct TimerBackend {
+    id: Cell<usize>,
+}
+
+impl GenericTimerBackend for TimerBackend {
+    fn new() -> Self {
+        TimerBackend {
+            id: Cell::new(0),
+        }
+    }
+
+    fn start(&self, interval: Duration, callback: Rc<dyn Fn()>) {
+        self.stop();
+        // USER_TIMER_MINIMUM is 10 ms, and anything less is set to it.
+        let ms = interval.as_millis().min(u32::MAX as u128) as u32;
+        let id = unsafe { SetTimer(HWND(0), 0, ms, Some(timerProc)) };
+        assert!(id != 0, "SetTimer() failed");
+        TIMERS.with(|timers| timers.borrow_mut().insert(id, callback));
+        self.id.set(id);
+    }
+
+    fn stop(&self) {
+        let id = self.id.replace(0);
+        if id != 0 {
+            unsafe { KillTimer(HWND(0), id); }
+            TIMERS.with(|timers| timers.borrow_mut().remove(&id));
+        }
+    }
+
+    fn is_running(&self) -> bool {
+        self.id.get() != 0
+    }
+}
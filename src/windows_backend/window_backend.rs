@@ -15,18 +15,20 @@ use std::os::windows::ffi::OsStrExt;
 use std::rc::{Rc, Weak};
 use std::sync::{Once, ONCE_INIT};
 
-use crate::control::{PaintingEvent, set_hot_control};
-use crate::{Control, Visibility, Window, WindowBorderStyle, MouseDownEvent, MouseUpEvent, MouseMovedEvent, MouseDraggedEvent};
+use crate::control::{dispatch_mouse_capture_lost, dispatch_mouse_down, dispatch_mouse_left_window, dispatch_mouse_moved, dispatch_mouse_up, dispatch_mouse_wheel, mouse_capture, PaintingEvent};
+use crate::keyboard::{dispatch_key_down, dispatch_key_up, dispatch_text_input, DIGIT_KEYS, LETTER_KEYS};
+use crate::{Control, Key, KeyDownEvent, KeyUpEvent, Modifiers, MouseButton, MouseWheelEvent, Visibility, Window, WindowBorderStyle};
 use crate::generic_backend::GenericWindowBackend;
 use crate::{WindowData, WindowEvent};
+use crate::window::dismiss_all_popups;
 
 use smallvec::SmallVec;
-use windows::Win32::Foundation::{HWND, WPARAM, LPARAM, LRESULT, HINSTANCE};
-use windows::Win32::Graphics::Gdi::{PAINTSTRUCT, BeginPaint, EndPaint};
+use windows::Win32::Foundation::{HWND, WPARAM, LPARAM, LRESULT, HINSTANCE, POINT, RECT};
+use windows::Win32::Graphics::Gdi::{PAINTSTRUCT, BeginPaint, ClientToScreen, EndPaint, InvalidateRect, ScreenToClient};
 use windows::Win32::System::LibraryLoader::GetModuleHandleW;
 use windows::Win32::UI::Controls::WM_MOUSELEAVE;
-use windows::Win32::UI::Input::KeyboardAndMouse::{SetCapture, ReleaseCapture, TRACKMOUSEEVENT, TME_LEAVE, TrackMouseEvent};
-use windows::Win32::UI::WindowsAndMessaging::{WM_LBUTTONDOWN, WM_MBUTTONDOWN, WM_RBUTTONDOWN, WM_LBUTTONUP, WM_MBUTTONUP, WM_RBUTTONUP, MK_LBUTTON, MK_MBUTTON, MK_RBUTTON, MK_XBUTTON1, MK_XBUTTON2, DefWindowProcW, DestroyWindow, GetWindowLongW, GWL_STYLE, GWL_EXSTYLE, WS_DLGFRAME, WS_BORDER, WS_THICKFRAME, WS_MINIMIZEBOX, WS_SYSMENU, WS_EX_TOOLWINDOW, SetWindowLongW, SetWindowPos, SWP_NOMOVE, SWP_NOSIZE, SWP_NOZORDER, SWP_FRAMECHANGED, WNDCLASSEXW, CS_DBLCLKS, RegisterClassExW, CreateWindowExW, HMENU, SetWindowTextW, ShowWindow, SW_SHOW, SW_HIDE, WM_CLOSE, WM_PAINT, WM_MOUSEMOVE, WINDOW_STYLE, WINDOW_EX_STYLE};
+use windows::Win32::UI::Input::KeyboardAndMouse::{EnableWindow, SetCapture, ReleaseCapture, TRACKMOUSEEVENT, TME_LEAVE, TrackMouseEvent, GetKeyState, VIRTUAL_KEY, VK_ESCAPE, VK_TAB, VK_BACK, VK_RETURN, VK_SPACE, VK_INSERT, VK_DELETE, VK_HOME, VK_END, VK_PRIOR, VK_NEXT, VK_LEFT, VK_UP, VK_RIGHT, VK_DOWN, VK_APPS, VK_SHIFT, VK_CONTROL, VK_MENU, VK_LWIN, VK_RWIN};
+use windows::Win32::UI::WindowsAndMessaging::{WM_LBUTTONDOWN, WM_MBUTTONDOWN, WM_RBUTTONDOWN, WM_LBUTTONUP, WM_MBUTTONUP, WM_RBUTTONUP, DefWindowProcW, DestroyWindow, GetWindowLongW, GWL_STYLE, GWL_EXSTYLE, WS_DLGFRAME, WS_BORDER, WS_THICKFRAME, WS_MINIMIZEBOX, WS_SYSMENU, WS_EX_TOOLWINDOW, WS_POPUP, WS_DISABLED, WS_EX_NOACTIVATE, SetWindowLongW, SetWindowLongPtrW, GWLP_HWNDPARENT, WM_ACTIVATEAPP, WM_CAPTURECHANGED, WM_NCLBUTTONDOWN, SetWindowPos, SWP_NOACTIVATE, SWP_NOMOVE, SWP_NOSIZE, SWP_NOZORDER, SWP_FRAMECHANGED, WM_MOUSEACTIVATE, MA_NOACTIVATE, SW_SHOWNOACTIVATE, WNDCLASSEXW, CS_DBLCLKS, RegisterClassExW, CreateWindowExW, HMENU, SetWindowTextW, ShowWindow, SW_SHOW, SW_HIDE, WM_CLOSE, WM_PAINT, WM_MOUSEMOVE, WM_MOUSEWHEEL, WM_MOUSEHWHEEL, WHEEL_DELTA, SystemParametersInfoW, SPI_GETWHEELSCROLLLINES, SPI_GETWHEELSCROLLCHARS, SYSTEM_PARAMETERS_INFO_ACTION, SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS, WM_KEYDOWN, WM_KEYUP, WM_CHAR, WINDOW_STYLE, WINDOW_EX_STYLE};
 use windows::core::{PWSTR, PCWSTR};
 use zaffre::{Brush, Color, PainterExt, PathBuf, Point2, RenderingBackend, Size2, StrokeStyle, SwapchainSurface};
 use zaffre::AsPathIter;
@@ -75,8 +77,12 @@ pub struct WindowBackend {
     location: Cell<Point2<f64>>,
     size: Cell<Size2<f64>>,
     border_style: Cell<WindowBorderStyle>,
+    enabled: Cell<bool>,
     resizable: Cell<bool>,
+    popup: Cell<bool>,
     tracking_mouse_leave: Cell<bool>,
+    // The first half of a surrogate pair from WM_CHAR, or 0
+    high_surrogate: Cell<u16>,
 }
 
 trait ToWide {
@@ -95,6 +101,8 @@ impl ToWide for str {
 // than a system call.
 thread_local! {
     static WINDOWS: RefCell<HashMap<isize, Weak<WindowData>>> = RefCell::new(HashMap::new());
+    // Whether this application is releasing the native capture itself
+    static RELEASING_CAPTURE: Cell<bool> = Cell::new(false);
 }
 
 fn get_window(hwnd: HWND) -> Rc<WindowData> {
@@ -148,36 +156,52 @@ fn windowProc(hwnd: HWND, uMsg: u32, wParam: WPARAM, lParam: LPARAM) -> LRESULT
             LRESULT(0)
         }
         WM_LBUTTONDOWN | WM_MBUTTONDOWN | WM_RBUTTONDOWN => {
+            // Capture the native mouse so that events outside the window are received. Which
+            // control gets them is decided by the core capture.
             SetCapture(hwnd);
             let (x, y) = (GET_X_LPARAM(lParam), GET_Y_LPARAM(lParam));
 
             let window = get_window(hwnd);
-
-            if let Some(child) = window.children().borrow().first() {
-                let descendant = child.descendant_at_point(x as f64, y as f64)
-                    .unwrap_or_else(|| child.clone());
-                descendant.event_handlers().send(&mut MouseDownEvent {
-                });
-            }
+            let location = Point2::new(x as f64, y as f64);
+            dispatch_mouse_down(&*window, location, mouse_button(uMsg), current_modifiers());
+            sync_native_capture(hwnd);
 
             LRESULT(0)
         }
         WM_LBUTTONUP | WM_MBUTTONUP | WM_RBUTTONUP => {
-            ReleaseCapture();
-
             let (x, y) = (GET_X_LPARAM(lParam), GET_Y_LPARAM(lParam));
 
             let window = get_window(hwnd);
-
-            if let Some(child) = window.children().borrow().first() {
-                let descendant = child.descendant_at_point(x as f64, y as f64)
-                    .unwrap_or_else(|| child.clone());
-                descendant.event_handlers().send(&mut MouseUpEvent {
-                });
-            }
+            dispatch_mouse_up(&*window, Point2::new(x as f64, y as f64), mouse_button(uMsg));
+            sync_native_capture(hwnd);
 
             LRESULT(0)
         }
+        // Popups are dismissed when the user switches to another application or presses the
+        // title bar or border of a window, as those clicks aren't sent as mouse down events.
+        WM_ACTIVATEAPP if wParam.0 == 0 => {
+            dismiss_all_popups();
+            dispatch_mouse_capture_lost();
+            DefWindowProcW(hwnd, uMsg, wParam, lParam)
+        }
+        // Another application or the system took the capture, such as with Alt+Tab during a
+        // drag. The capture moving between this application's windows is handled by the core.
+        WM_CAPTURECHANGED => {
+            let new_owner = lParam.0;
+            let ours = WINDOWS.with(|windows| windows.borrow().contains_key(&new_owner));
+            if !RELEASING_CAPTURE.with(|r| r.get()) && !ours {
+                dispatch_mouse_capture_lost();
+            }
+            DefWindowProcW(hwnd, uMsg, wParam, lParam)
+        }
+        WM_NCLBUTTONDOWN => {
+            dismiss_all_popups();
+            DefWindowProcW(hwnd, uMsg, wParam, lParam)
+        }
+        // Clicking a popup would activate it and deactivate the window it belongs to.
+        WM_MOUSEACTIVATE if get_window(hwnd).backend.popup.get() => {
+            LRESULT(MA_NOACTIVATE as isize)
+        }
         WM_MOUSEMOVE => {
             let (x, y) = (GET_X_LPARAM(lParam), GET_Y_LPARAM(lParam));
 
@@ -194,21 +218,62 @@ fn windowProc(hwnd: HWND, uMsg: u32, wParam: WPARAM, lParam: LPARAM) -> LRESULT
                 TrackMouseEvent(&mut tme);
             }
 
-            if let Some(child) = window.children().borrow().first() {
-                let descendant = child.descendant_at_point(x as f64, y as f64)
-                    .unwrap_or_else(|| child.clone());
-                set_hot_control(Some(&descendant));
-                let wParam = wParam.0 as u32;
-                if wParam & (MK_LBUTTON | MK_MBUTTON | MK_RBUTTON | MK_XBUTTON1 | MK_XBUTTON2) != 0
-                {
-                    descendant.event_handlers().send(&mut MouseDraggedEvent {
-                    });
+            dispatch_mouse_moved(&*window, Point2::new(x as f64, y as f64));
+
+            LRESULT(0)
+        }
+        WM_MOUSEWHEEL | WM_MOUSEHWHEEL => {
+            // Unlike other mouse messages, the location is in screen coordinates.
+            let mut pt = POINT { x: GET_X_LPARAM(lParam), y: GET_Y_LPARAM(lParam) };
+            ScreenToClient(hwnd, &mut pt);
+            // A positive delta means the wheel was rotated away from the user or tilted right.
+            let notches = HIWORD(wParam.0 as u32) as c_short as f64 / WHEEL_DELTA as f64;
+
+            // Precision touchpads send deltas that aren't multiples of WHEEL_DELTA, but no phases.
+            let location = Point2::new(pt.x as f64, pt.y as f64);
+            let mut event = if uMsg == WM_MOUSEWHEEL {
+                let lines = notches * wheel_scroll_setting(SPI_GETWHEELSCROLLLINES);
+                MouseWheelEvent::new(location, 0.0, -lines)
+            } else {
+                let chars = notches * wheel_scroll_setting(SPI_GETWHEELSCROLLCHARS);
+                MouseWheelEvent::new(location, chars, 0.0)
+            };
+            event.modifiers = current_modifiers();
+
+            let window = get_window(hwnd);
+            dispatch_mouse_wheel(&*window, event);
+
+            LRESULT(0)
+        }
+        WM_KEYDOWN | WM_KEYUP => {
+            let window = get_window(hwnd);
+            if let Some(key) = key_from_vk(VIRTUAL_KEY(wParam.0 as u16)) {
+                let modifiers = current_modifiers();
+                if uMsg == WM_KEYDOWN {
+                    // Bit 30 is the previous key state.
+                    let repeat = lParam.0 & (1 << 30) != 0;
+                    dispatch_key_down(&window, KeyDownEvent { key, modifiers, repeat });
                 } else {
-                    descendant.event_handlers().send(&mut MouseMovedEvent {
-                    });
+                    dispatch_key_up(&window, KeyUpEvent { key, modifiers });
                 }
             }
-
+            LRESULT(0)
+        }
+        WM_CHAR => {
+            let window = get_window(hwnd);
+            let unit = wParam.0 as u16;
+            // Characters outside the BMP arrive as two messages, one for each surrogate.
+            if (0xD800..0xDC00).contains(&unit) {
+                window.backend.high_surrogate.set(unit);
+            } else {
+                let high = window.backend.high_surrogate.replace(0);
+                let units: SmallVec<[u16; 2]> = if high != 0 {
+                    SmallVec::from_slice(&[high, unit])
+                } else {
+                    SmallVec::from_slice(&[unit])
+                };
+                dispatch_text_input(&window, &String::from_utf16_lossy(&units));
+            }
             LRESULT(0)
         }
         WM_MOUSELEAVE => {
@@ -217,7 +282,7 @@ fn windowProc(hwnd: HWND, uMsg: u32, wParam: WPARAM, lParam: LPARAM) -> LRESULT
 
             backend.tracking_mouse_leave.set(false);
 
-            set_hot_control(None);
+            dispatch_mouse_left_window();
 
             LRESULT(0)
         }
@@ -225,6 +290,88 @@ fn windowProc(hwnd: HWND, uMsg: u32, wParam: WPARAM, lParam: LPARAM) -> LRESULT
     }
 }
 
+fn key_from_vk(vk: VIRTUAL_KEY) -> Option<Key> {
+    Some(match vk {
+        VK_ESCAPE => Key::Escape,
+        VK_TAB => Key::Tab,
+        VK_BACK => Key::Backspace,
+        VK_RETURN => Key::Enter,
+        VK_SPACE => Key::Space,
+        VK_INSERT => Key::Insert,
+        VK_DELETE => Key::Delete,
+        VK_HOME => Key::Home,
+        VK_END => Key::End,
+        VK_PRIOR => Key::PageUp,
+        VK_NEXT => Key::PageDown,
+        VK_LEFT => Key::Left,
+        VK_UP => Key::Up,
+        VK_RIGHT => Key::Right,
+        VK_DOWN => Key::Down,
+        VK_APPS => Key::Menu,
+        VK_SHIFT => Key::Shift,
+        VK_CONTROL => Key::Control,
+        VK_MENU => Key::Alt,
+        VIRTUAL_KEY(vk @ 0x30..=0x39) => DIGIT_KEYS[(vk - 0x30) as usize],
+        VIRTUAL_KEY(vk @ 0x41..=0x5A) => LETTER_KEYS[(vk - 0x41) as usize],
+        VIRTUAL_KEY(vk @ 0x70..=0x7B) => [
+            Key::F1, Key::F2, Key::F3, Key::F4, Key::F5, Key::F6,
+            Key::F7, Key::F8, Key::F9, Key::F10, Key::F11, Key::F12,
+        ][(vk - 0x70) as usize],
+        _ => return None,
+    })
+}
+
+fn current_modifiers() -> Modifiers {
+    // The high bit is set if the key is down.
+    let is_down = |vk: VIRTUAL_KEY| unsafe { GetKeyState(vk.0 as i32) < 0 };
+    Modifiers {
+        shift: is_down(VK_SHIFT),
+        control: is_down(VK_CONTROL),
+        alt: is_down(VK_MENU),
+        logo: is_down(VK_LWIN) || is_down(VK_RWIN),
+    }
+}
+
+// Returns the number of lines or characters that a notch of the mouse wheel scrolls.
+unsafe fn wheel_scroll_setting(action: SYSTEM_PARAMETERS_INFO_ACTION) -> f64 {
+    let mut setting: u32 = 3;
+    SystemParametersInfoW(
+        action,
+        0,
+        &mut setting as *mut u32 as *mut _,
+        SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+    );
+    // WHEEL_PAGESCROLL means a notch scrolls a page, which isn't supported yet.
+    if setting == u32::MAX { 3.0 } else { setting as f64 }
+}
+
+fn mouse_button(msg: u32) -> MouseButton {
+    match msg {
+        WM_LBUTTONDOWN | WM_LBUTTONUP => MouseButton::Left,
+        WM_MBUTTONDOWN | WM_MBUTTONUP => MouseButton::Middle,
+        WM_RBUTTONDOWN | WM_RBUTTONUP => MouseButton::Right,
+        _ => panic!("not a mouse button message"),
+    }
+}
+
+// Keeps the native capture while a control has captured the mouse, even after the buttons are
+// released.
+unsafe fn sync_native_capture(hwnd: HWND) {
+    if mouse_capture().is_some() {
+        SetCapture(hwnd);
+    } else {
+        release_native_capture();
+    }
+}
+
+// Releases the native capture without treating the WM_CAPTURECHANGED it sends as the capture
+// being taken away.
+unsafe fn release_native_capture() {
+    RELEASING_CAPTURE.with(|r| r.set(true));
+    ReleaseCapture();
+    RELEASING_CAPTURE.with(|r| r.set(false));
+}
+
 impl WindowBackend {
     fn delete_handle(&self) {
         if self.handle.get() != HWND(0) {
@@ -259,11 +406,19 @@ impl WindowBackend {
             //    self.border_style == WindowBorderStyle::Normal && self.resizable &&
             //    content.max_width == 0 && content.max_height == 0);
             set_if(WS_SYSMENU, self.border_style.get() != WindowBorderStyle::None);
-            if self.border_style.get() == WindowBorderStyle::Tool {
+            set_if(WS_POPUP, self.popup.get());
+            set_if(WS_DISABLED, !self.enabled.get());
+            // Tool windows and popups aren't shown in the taskbar.
+            if self.border_style.get() == WindowBorderStyle::Tool || self.popup.get() {
                 ex_style |= WS_EX_TOOLWINDOW.0;
             } else {
                 ex_style &= !WS_EX_TOOLWINDOW.0;
             }
+            if self.popup.get() {
+                ex_style |= WS_EX_NOACTIVATE.0;
+            } else {
+                ex_style &= !WS_EX_NOACTIVATE.0;
+            }
         }
         (style, ex_style)
     }
@@ -369,8 +524,11 @@ impl GenericWindowBackend for WindowBackend {
             location: Cell::new(Point2::new(0.0, 0.0)),
             size: Cell::new(Size2::new(400.0, 300.0)),
             border_style: Cell::new(WindowBorderStyle::Normal),
+            enabled: Cell::new(true),
             resizable: Cell::new(true),
+            popup: Cell::new(false),
             tracking_mouse_leave: Cell::new(false),
+            high_surrogate: Cell::new(0),
         }
     }
 
@@ -406,7 +564,8 @@ impl GenericWindowBackend for WindowBackend {
         self.visibility.set(visibility);
         if self.visibility.get() == Visibility::Visible {
             // TODO: this isn't how I did it in D
-            unsafe { ShowWindow(self.handle(), SW_SHOW); }
+            let show = if self.popup.get() { SW_SHOWNOACTIVATE } else { SW_SHOW };
+            unsafe { ShowWindow(self.handle(), show); }
         } else {
             if self.is_handle_created() {
                 unsafe { ShowWindow(self.handle.get(), SW_HIDE); }
@@ -414,9 +573,55 @@ impl GenericWindowBackend for WindowBackend {
         }
     }
 
+    // TODO: update the location and size from WM_MOVE and WM_SIZE when the user moves the window
+    fn location(&self) -> Point2<f64> {
+        self.location.get()
+    }
+
     fn set_location(&self, location: &Point2<f64>) {
-        // Don't set the backend fields, only the native window location.
-        // The struct fields should be updated by the window procedure.
+        self.location.set(*location);
+        if self.is_handle_created() {
+            unsafe {
+                SetWindowPos(self.handle.get(), HWND(0), location.x as c_int, location.y as c_int,
+                    0, 0, SWP_NOSIZE | SWP_NOZORDER | SWP_NOACTIVATE);
+            }
+        }
+    }
+
+    fn size(&self) -> Size2<f64> {
+        self.size.get()
+    }
+
+    fn set_size(&self, size: &Size2<f64>) {
+        self.size.set(*size);
+        if self.is_handle_created() {
+            unsafe {
+                SetWindowPos(self.handle.get(), HWND(0), 0, 0,
+                    size.width as c_int, size.height as c_int,
+                    SWP_NOMOVE | SWP_NOZORDER | SWP_NOACTIVATE);
+            }
+        }
+    }
+
+    fn client_origin(&self) -> Option<Point2<f64>> {
+        if !self.is_handle_created() {
+            return None;
+        }
+        let mut pt = POINT { x: 0, y: 0 };
+        unsafe { ClientToScreen(self.handle.get(), &mut pt); }
+        Some(Point2::new(pt.x as f64, pt.y as f64))
+    }
+
+    fn enabled(&self) -> bool {
+        self.enabled.get()
+    }
+
+    fn set_enabled(&self, enabled: bool) {
+        self.enabled.set(enabled);
+        // WS_DISABLED is passed to CreateWindowEx() when the handle is created.
+        if self.is_handle_created() {
+            unsafe { EnableWindow(self.handle.get(), enabled); }
+        }
     }
 
     fn resizable(&self) -> bool {
@@ -427,6 +632,47 @@ impl GenericWindowBackend for WindowBackend {
         self.resizable.set(resizable);
         self.update_window_styles();
     }
+
+    fn set_owner(&self, owner: Option<&Window>) {
+        let owner = owner.map_or(HWND(0), |o| o.backend.handle());
+        self.owner.set(owner);
+        // The owner is passed to CreateWindowEx() when the handle is created.
+        if self.is_handle_created() {
+            unsafe { SetWindowLongPtrW(self.handle.get(), GWLP_HWNDPARENT, owner.0); }
+        }
+    }
+
+    fn set_popup(&self, popup: bool) {
+        self.popup.set(popup);
+        if popup {
+            self.border_style.set(WindowBorderStyle::None);
+        }
+        self.update_window_styles();
+    }
+
+    fn release_mouse_capture() {
+        unsafe { release_native_capture(); }
+    }
+
+    fn invalidate(&self) {
+        if self.is_handle_created() {
+            unsafe { InvalidateRect(self.handle.get(), ptr::null(), false); }
+        }
+    }
+
+    fn invalidate_area(&self, location: Point2<f64>, size: Size2<f64>) {
+        if self.is_handle_created() {
+            // Rounding outward covers any pixel the control paints part of.
+            let rect = RECT {
+                left: location.x.floor() as i32,
+                top: location.y.floor() as i32,
+                right: (location.x + size.width).ceil() as i32,
+                bottom: (location.y + size.height).ceil() as i32,
+            };
+            unsafe { InvalidateRect(self.handle.get(), &rect, false); }
+        }
+    }
+
     // enabling and disabling the close button can be done dynamically by enabling or disabling
     // the close menu item: http://blogs.msdn.com/b/oldnewthing/archive/2010/06/04/10019758.aspx
 }